@@ -1,6 +1,12 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use eddo::gen_keypair;
+#[cfg(feature = "hazmat")]
+use eddo::{
+    hazmat::{EdwardsPoint, Scalar},
+    PippengerParams,
+};
 use rand::rngs::OsRng;
+use std::thread;
 
 const KB: usize = 1024;
 
@@ -35,6 +41,86 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         }
         group.finish();
     }
+
+    {
+        let mut group = c.benchmark_group("chunked_verification");
+        let size = 1024 * KB;
+        let data = vec![0; size];
+        let signature = private.sign(&data);
+        group.throughput(Throughput::Bytes(size as u64));
+        for &chunk_size in &[256, KB, 4 * KB, 64 * KB] {
+            group.bench_with_input(
+                BenchmarkId::from_parameter(chunk_size),
+                &chunk_size,
+                |b, &chunk_size| {
+                    b.iter(|| {
+                        public
+                            .verify_chunked(black_box(&data[..]), black_box(signature), chunk_size)
+                            .unwrap()
+                    });
+                },
+            );
+        }
+        group.finish();
+    }
+
+    {
+        // Verification shares no state between calls, so this should scale with cores.
+        let mut group = c.benchmark_group("concurrent_verification");
+        let batch: usize = 256;
+        let messages: Vec<_> = (0..batch as u32).map(|i| i.to_le_bytes()).collect();
+        let signatures: Vec<_> = messages.iter().map(|m| private.sign(m)).collect();
+        group.throughput(Throughput::Elements(batch as u64));
+        for &threads in &[1, 2, 4, 8, 16] {
+            group.bench_with_input(
+                BenchmarkId::from_parameter(threads),
+                &threads,
+                |b, &threads| {
+                    let per_thread = batch / threads;
+                    b.iter(|| {
+                        thread::scope(|s| {
+                            for t in 0..threads {
+                                let range = t * per_thread..(t + 1) * per_thread;
+                                let (messages, signatures) =
+                                    (&messages[range.clone()], &signatures[range]);
+                                s.spawn(move || {
+                                    for (m, sig) in messages.iter().zip(signatures) {
+                                        assert!(public.verify(m, *sig));
+                                    }
+                                });
+                            }
+                        })
+                    });
+                },
+            );
+        }
+        group.finish();
+    }
+
+    #[cfg(feature = "hazmat")]
+    {
+        let mut group = c.benchmark_group("multiscalar");
+        group.sample_size(10);
+        for &size in &[64, KB, 16 * KB] {
+            let scalars: Vec<_> = (0..size)
+                .map(|i| Scalar::from_bytes_mod_order_wide([i as u8; 64]) + Scalar::from(i as u64))
+                .collect();
+            let points: Vec<_> = scalars
+                .iter()
+                .map(|s| EdwardsPoint::BASEPOINT * *s)
+                .collect();
+            let params = PippengerParams::for_size(size);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(BenchmarkId::new("pippenger", size), &size, |b, _| {
+                b.iter(|| EdwardsPoint::vartime_pippenger_mul(&scalars, &points, params));
+            });
+            #[cfg(feature = "parallel")]
+            group.bench_with_input(BenchmarkId::new("par_pippenger", size), &size, |b, _| {
+                b.iter(|| EdwardsPoint::par_vartime_pippenger_mul(&scalars, &points, params));
+            });
+        }
+        group.finish();
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,16 @@
+//! Decode arbitrary bytes as a field element.
+//!
+//! Decoding rejects anything at least P, so an element we get back should encode
+//! to the same bytes.
+#![no_main]
+use std::convert::TryFrom;
+
+use eddo::hazmat::Z25519;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(x) = Z25519::try_from(data) {
+        let bytes: [u8; 32] = x.into();
+        assert_eq!(bytes[..], data[..32]);
+    }
+});
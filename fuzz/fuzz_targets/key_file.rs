@@ -0,0 +1,18 @@
+//! Parse arbitrary text as the CLI's key files.
+//!
+//! This covers the versioned format, with its fields, and the original format,
+//! without decrypting anything.
+#![no_main]
+use eddo::keyfile::{self, KeyFile};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(contents) = std::str::from_utf8(data) {
+        if let Ok(key_file) = KeyFile::parse(contents) {
+            let _ = key_file.format(false);
+        }
+        let _ = keyfile::parse_private_key(contents, None);
+        let _ = keyfile::parse_public_key(contents, None);
+        let _ = keyfile::decode_public_key(contents);
+    }
+});
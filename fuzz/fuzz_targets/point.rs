@@ -0,0 +1,22 @@
+//! Decompress arbitrary bytes as a point.
+//!
+//! Decompression rejects non-canonical encodings, so any point we get back should
+//! compress to exactly the bytes we started with.
+#![no_main]
+use std::convert::TryInto;
+
+use eddo::hazmat::CompressedEdwardsY;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let bytes: [u8; 32] = match data.try_into() {
+        Ok(bytes) => bytes,
+        Err(_) => return,
+    };
+    if let Some(point) = (CompressedEdwardsY { bytes }).decompress() {
+        assert_eq!(point.compress().bytes, bytes);
+        assert!(point.compress().decompress() == Some(point));
+        let _ = point.is_small_order();
+        let _ = point.is_torsion_free();
+    }
+});
@@ -0,0 +1,19 @@
+//! Decode arbitrary bytes as a scalar, both strictly, and reducing them.
+//!
+//! A scalar which decodes strictly should encode back to the same bytes, and reducing
+//! any bytes should produce a scalar which decodes strictly.
+#![no_main]
+use std::convert::{TryFrom, TryInto};
+
+use eddo::hazmat::Scalar;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(scalar) = Scalar::try_from(data) {
+        assert_eq!(scalar.to_bytes()[..], data[..32]);
+    }
+    if let Ok(bytes) = data.try_into() {
+        let reduced = Scalar::from_bytes_mod_order(bytes);
+        assert!(Scalar::from_canonical_bytes(reduced.to_bytes()).is_some());
+    }
+});
@@ -0,0 +1,32 @@
+//! Verify arbitrary signatures, and parse arbitrary text as the CLI's signatures.
+//!
+//! The input is split into a public key, a signature, and a message. Neither
+//! verification method should panic, and strict verification should never accept
+//! something which plain verification rejects.
+#![no_main]
+use std::convert::TryInto;
+
+use eddo::{PublicKey, Signature};
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../src/bin.rs"]
+#[allow(dead_code)]
+mod cli;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        cli::fuzz::parse_signature(input);
+    }
+    if data.len() < 96 {
+        return;
+    }
+    let public = PublicKey {
+        bytes: data[..32].try_into().unwrap(),
+    };
+    let signature = Signature {
+        bytes: data[32..96].try_into().unwrap(),
+    };
+    let message = &data[96..];
+    let valid = public.verify(message, signature);
+    assert!(valid || !public.verify_strict(message, signature));
+});
@@ -0,0 +1,348 @@
+//! This module implements the ChaCha20-Poly1305 AEAD, as described in RFC 8439:
+//! https://datatracker.ietf.org/doc/html/rfc8439
+//!
+//! This encrypts and authenticates data in place, along with some associated data,
+//! which is authenticated, but not encrypted. A key must never be used twice with
+//! the same nonce, so keys used only once, like those derived for a sealed box,
+//! can use a fixed nonce, but long lived keys need a fresh random nonce every time.
+use core::convert::TryInto;
+
+use subtle::ConstantTimeEq;
+
+/// The number of bytes in a key.
+pub const KEY_SIZE: usize = 32;
+
+/// The number of bytes in a nonce.
+pub const NONCE_SIZE: usize = 12;
+
+/// The number of bytes in an authentication tag.
+pub const TAG_SIZE: usize = 16;
+
+/// The number of bytes in a ChaCha20 block.
+const CHACHA_BLOCK_SIZE: usize = 64;
+
+/// The number of bytes in a Poly1305 block.
+const POLY_BLOCK_SIZE: usize = 16;
+
+/// The ChaCha20 block function, as described in Section 2.3.
+fn chacha20_block(
+    key: &[u8; KEY_SIZE],
+    counter: u32,
+    nonce: &[u8; NONCE_SIZE],
+) -> [u8; CHACHA_BLOCK_SIZE] {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+    for (s, chunk) in state[4..12].iter_mut().zip(key.chunks_exact(4)) {
+        *s = u32::from_le_bytes(chunk.try_into().unwrap());
+    }
+    state[12] = counter;
+    for (s, chunk) in state[13..].iter_mut().zip(nonce.chunks_exact(4)) {
+        *s = u32::from_le_bytes(chunk.try_into().unwrap());
+    }
+
+    let mut x = state;
+    macro_rules! quarter {
+        ($a:expr, $b:expr, $c:expr, $d:expr) => {
+            x[$a] = x[$a].wrapping_add(x[$b]);
+            x[$d] = (x[$d] ^ x[$a]).rotate_left(16);
+            x[$c] = x[$c].wrapping_add(x[$d]);
+            x[$b] = (x[$b] ^ x[$c]).rotate_left(12);
+            x[$a] = x[$a].wrapping_add(x[$b]);
+            x[$d] = (x[$d] ^ x[$a]).rotate_left(8);
+            x[$c] = x[$c].wrapping_add(x[$d]);
+            x[$b] = (x[$b] ^ x[$c]).rotate_left(7);
+        };
+    }
+    for _ in 0..10 {
+        // Operate on columns
+        quarter!(0, 4, 8, 12);
+        quarter!(1, 5, 9, 13);
+        quarter!(2, 6, 10, 14);
+        quarter!(3, 7, 11, 15);
+        // Operate on diagonals
+        quarter!(0, 5, 10, 15);
+        quarter!(1, 6, 11, 12);
+        quarter!(2, 7, 8, 13);
+        quarter!(3, 4, 9, 14);
+    }
+
+    let mut out = [0u8; CHACHA_BLOCK_SIZE];
+    for ((chunk, x_i), s_i) in out.chunks_exact_mut(4).zip(x.iter()).zip(state.iter()) {
+        chunk.copy_from_slice(&x_i.wrapping_add(*s_i).to_le_bytes());
+    }
+    out
+}
+
+/// XOR data with the ChaCha20 keystream, starting at a given block, as in Section 2.4.
+fn chacha20_xor(key: &[u8; KEY_SIZE], counter: u32, nonce: &[u8; NONCE_SIZE], data: &mut [u8]) {
+    for (i, chunk) in data.chunks_mut(CHACHA_BLOCK_SIZE).enumerate() {
+        let block = chacha20_block(key, counter.wrapping_add(i as u32), nonce);
+        for (x, k) in chunk.iter_mut().zip(block.iter()) {
+            *x ^= k;
+        }
+    }
+}
+
+/// The Poly1305 one time authenticator, as described in Section 2.5.
+///
+/// This works with 26 bit limbs, so that products fit comfortably in 64 bits.
+struct Poly1305 {
+    r: [u32; 5],
+    h: [u32; 5],
+    pad: [u32; 4],
+}
+
+impl Poly1305 {
+    fn new(key: &[u8; 32]) -> Self {
+        let word = |i: usize| u32::from_le_bytes(key[i..i + 4].try_into().unwrap());
+        // This includes clamping r, as in Section 2.5.1.
+        let r = [
+            word(0) & 0x3ffffff,
+            (word(3) >> 2) & 0x3ffff03,
+            (word(6) >> 4) & 0x3ffc0ff,
+            (word(9) >> 6) & 0x3f03fff,
+            (word(12) >> 8) & 0x00fffff,
+        ];
+        Poly1305 {
+            r,
+            h: [0; 5],
+            pad: [word(16), word(20), word(24), word(28)],
+        }
+    }
+
+    fn block(&mut self, block: &[u8; POLY_BLOCK_SIZE]) {
+        let word = |i: usize| u32::from_le_bytes(block[i..i + 4].try_into().unwrap());
+        let [r0, r1, r2, r3, r4] = self.r.map(u64::from);
+        let [s1, s2, s3, s4] = [r1 * 5, r2 * 5, r3 * 5, r4 * 5];
+
+        let h = &mut self.h;
+        // Add the block, with a 1 bit just past its end.
+        h[0] += word(0) & 0x3ffffff;
+        h[1] += (word(3) >> 2) & 0x3ffffff;
+        h[2] += (word(6) >> 4) & 0x3ffffff;
+        h[3] += (word(9) >> 6) & 0x3ffffff;
+        h[4] += (word(12) >> 8) | (1 << 24);
+
+        // Multiply by r, reducing modulo 2^130 - 5 by folding the top limbs times 5.
+        let [h0, h1, h2, h3, h4] = h.map(u64::from);
+        let d0 = h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3;
+        let mut d3 = h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4;
+        let mut d4 = h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0;
+
+        d1 += d0 >> 26;
+        d2 += d1 >> 26;
+        d3 += d2 >> 26;
+        d4 += d3 >> 26;
+        h[0] = (d0 as u32) & 0x3ffffff;
+        h[1] = (d1 as u32) & 0x3ffffff;
+        h[2] = (d2 as u32) & 0x3ffffff;
+        h[3] = (d3 as u32) & 0x3ffffff;
+        h[4] = (d4 as u32) & 0x3ffffff;
+        h[0] += ((d4 >> 26) as u32) * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= 0x3ffffff;
+    }
+
+    /// Feed data in, padding it with zeros to a multiple of 16 bytes.
+    ///
+    /// This is the padding the AEAD construction in Section 2.8 needs.
+    fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(POLY_BLOCK_SIZE) {
+            let mut block = [0u8; POLY_BLOCK_SIZE];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.block(&block);
+        }
+    }
+
+    fn finalize(self) -> [u8; TAG_SIZE] {
+        let mut h = self.h;
+        // Fully carry h.
+        for i in 1..5 {
+            h[i] += h[i - 1] >> 26;
+            h[i - 1] &= 0x3ffffff;
+        }
+        h[0] += (h[4] >> 26) * 5;
+        h[4] &= 0x3ffffff;
+        h[1] += h[0] >> 26;
+        h[0] &= 0x3ffffff;
+
+        // Compute g = h + 5 - 2^130, and use it if it didn't underflow, in constant time.
+        let mut g = [0u32; 5];
+        let mut carry = 5;
+        for i in 0..4 {
+            g[i] = h[i] + carry;
+            carry = g[i] >> 26;
+            g[i] &= 0x3ffffff;
+        }
+        g[4] = (h[4] + carry).wrapping_sub(1 << 26);
+        let use_g = ((g[4] >> 31) ^ 1).wrapping_neg();
+        for (h_i, g_i) in h.iter_mut().zip(g.iter()) {
+            *h_i = (*h_i & !use_g) | (g_i & use_g);
+        }
+
+        // Pack h into 128 bits, and add the pad.
+        let packed = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8),
+        ];
+        let mut out = [0u8; TAG_SIZE];
+        let mut carry = 0u64;
+        for ((chunk, x), p) in out
+            .chunks_exact_mut(4)
+            .zip(packed.iter())
+            .zip(self.pad.iter())
+        {
+            let sum = u64::from(*x) + u64::from(*p) + carry;
+            chunk.copy_from_slice(&(sum as u32).to_le_bytes());
+            carry = sum >> 32;
+        }
+        out
+    }
+}
+
+/// Calculate the tag for some associated data, and ciphertext, as in Section 2.8.
+fn tag(
+    key: &[u8; KEY_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> [u8; TAG_SIZE] {
+    // The one time key comes from the first block of the keystream, as in Section 2.6.
+    let block = chacha20_block(key, 0, nonce);
+    let mut poly = Poly1305::new(block[..32].try_into().unwrap());
+    poly.update_padded(aad);
+    poly.update_padded(ciphertext);
+    let mut lengths = [0u8; POLY_BLOCK_SIZE];
+    lengths[..8].copy_from_slice(&(aad.len() as u64).to_le_bytes());
+    lengths[8..].copy_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+    poly.block(&lengths);
+    poly.finalize()
+}
+
+/// Encrypt data in place, returning a tag authenticating it along with `aad`.
+pub fn encrypt(
+    key: &[u8; KEY_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    data: &mut [u8],
+) -> [u8; TAG_SIZE] {
+    chacha20_xor(key, 1, nonce, data);
+    tag(key, nonce, aad, data)
+}
+
+/// Decrypt data in place, after checking its tag.
+///
+/// This returns false, leaving the data untouched, if the tag is invalid.
+#[must_use]
+pub fn decrypt(
+    key: &[u8; KEY_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    data: &mut [u8],
+    tag_bytes: &[u8; TAG_SIZE],
+) -> bool {
+    if !bool::from(tag(key, nonce, aad, data).ct_eq(tag_bytes)) {
+        return false;
+    }
+    chacha20_xor(key, 1, nonce, data);
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key() -> [u8; KEY_SIZE] {
+        let mut key = [0u8; KEY_SIZE];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = 0x80 + i as u8;
+        }
+        key
+    }
+
+    #[test]
+    fn test_chacha20_block_vector() {
+        // This is the test vector from Section 2.3.2
+        let mut key = [0u8; KEY_SIZE];
+        for (i, k) in key.iter_mut().enumerate() {
+            *k = i as u8;
+        }
+        let nonce = [0, 0, 0, 9, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        assert_eq!(
+            hex::encode(chacha20_block(&key, 1, &nonce)),
+            "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4ed2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+        );
+    }
+
+    #[test]
+    fn test_poly1305_edge_cases() {
+        // These are test vectors 5, 6, 8, and 9 from Appendix A.3, which stress
+        // the final reduction modulo 2^130 - 5.
+        let key = |r: u8, s: u8| {
+            let mut key = [s; 32];
+            key[..16].copy_from_slice(&[0; 16]);
+            key[0] = r;
+            key
+        };
+        let mut limbs = [0u8; 48];
+        limbs[..16].copy_from_slice(&[0xff; 16]);
+        limbs[16] = 0xfb;
+        limbs[17..32].copy_from_slice(&[0xfe; 15]);
+        limbs[32..].copy_from_slice(&[0x01; 16]);
+        let mut almost_p = [0xff; 16];
+        almost_p[0] = 0xfd;
+        let mut two = [0; 16];
+        two[0] = 2;
+        let vectors: [(u8, u8, &[u8], &str); 4] = [
+            (2, 0, &[0xff; 16], "03000000000000000000000000000000"),
+            (2, 0xff, &two, "03000000000000000000000000000000"),
+            (1, 0, &limbs, "00000000000000000000000000000000"),
+            (2, 0, &almost_p, "faffffffffffffffffffffffffffffff"),
+        ];
+        for (r, s, data, expected) in vectors.iter() {
+            let mut poly = Poly1305::new(&key(*r, *s));
+            poly.update_padded(data);
+            assert_eq!(hex::encode(poly.finalize()), *expected);
+        }
+    }
+
+    #[test]
+    fn test_aead_vector() {
+        // This is the test vector from Section 2.8.2
+        let nonce = [
+            0x07, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+        ];
+        let aad = hex::decode("50515253c0c1c2c3c4c5c6c7").unwrap();
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let mut data = *plaintext;
+        let t = encrypt(&key(), &nonce, &aad, &mut data);
+        assert_eq!(
+            hex::encode(data),
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc3ff4def08e4b7a9de576d26586cec64b6116"
+        );
+        assert_eq!(hex::encode(t), "1ae10b594f09e26a7e902ecbd0600691");
+        assert!(decrypt(&key(), &nonce, &aad, &mut data, &t));
+        assert_eq!(&data[..], &plaintext[..]);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let nonce = [0; NONCE_SIZE];
+        let mut data = *b"attack at dawn";
+        let t = encrypt(&key(), &nonce, b"header", &mut data);
+        let ciphertext = data;
+        let mut tampered = data;
+        tampered[0] ^= 1;
+        assert!(!decrypt(&key(), &nonce, b"header", &mut tampered, &t));
+        assert!(!decrypt(&key(), &nonce, b"other", &mut data, &t));
+        let mut other_tag = t;
+        other_tag[0] ^= 1;
+        assert!(!decrypt(&key(), &nonce, b"header", &mut data, &other_tag));
+        // Failed decryptions leave the data alone.
+        assert_eq!(data, ciphertext);
+    }
+}
@@ -0,0 +1,170 @@
+//! This module implements base64, and the armor we wrap keys and signatures in.
+//!
+//! Armor is base64, split into lines, between BEGIN and END markers naming what
+//! the data is, in the style of PEM:
+//!
+//! ```text
+//! -----BEGIN EDDO SIGNATURE-----
+//! ...
+//! -----END EDDO SIGNATURE-----
+//! ```
+
+use alloc::{format, string::String, vec::Vec};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// How many base64 characters go on each line of armor.
+const ARMOR_WIDTH: usize = 64;
+
+/// Represents the reasons decoding armor, or base64, might fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmorError {
+    /// The input wasn't valid, padded, base64.
+    InvalidBase64,
+    /// The armor didn't have the label we expected.
+    WrongLabel,
+    /// The END marker was missing, or wasn't the last thing in the input.
+    Malformed,
+}
+
+/// Encode data as standard base64, with padding.
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard base64, ignoring any whitespace, like the line breaks in armor.
+pub fn base64_decode(input: &str) -> Result<Vec<u8>, ArmorError> {
+    let chars: Vec<u8> = input.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !chars.len().is_multiple_of(4) {
+        return Err(ArmorError::InvalidBase64);
+    }
+    let mut out = Vec::with_capacity(chars.len() / 4 * 3);
+    for (i, chunk) in chars.chunks(4).enumerate() {
+        let last = i == chars.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err(ArmorError::InvalidBase64);
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or(ArmorError::InvalidBase64)?;
+            n = n << 6 | value as u32;
+        }
+        n <<= 6 * padding as u32;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(out)
+}
+
+/// Wrap data in armor: base64 lines, between BEGIN and END markers naming what it is.
+pub fn armor(label: &str, data: &[u8]) -> String {
+    let encoded = base64_encode(data);
+    let mut out = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(ARMOR_WIDTH) {
+        // The base64 alphabet is ASCII, so any split of it is valid UTF-8
+        out.push_str(core::str::from_utf8(line).unwrap());
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {}-----", label));
+    out
+}
+
+/// The label of some armor, if that's what the input is.
+pub fn armor_label(input: &str) -> Option<&str> {
+    input
+        .trim_start()
+        .lines()
+        .next()?
+        .trim_end()
+        .strip_prefix("-----BEGIN ")?
+        .strip_suffix("-----")
+}
+
+/// Remove the armor around some data, checking that it has the label we expect.
+pub fn dearmor(label: &str, input: &str) -> Result<Vec<u8>, ArmorError> {
+    if armor_label(input) != Some(label) {
+        return Err(ArmorError::WrongLabel);
+    }
+    let mut lines = input.trim().lines().skip(1);
+    let end = format!("-----END {}-----", label);
+    let body: Vec<&str> = lines
+        .by_ref()
+        .take_while(|line| line.trim() != end)
+        .collect();
+    // The END marker has to be there, and be the last thing
+    if lines.next().is_some() || !input.trim_end().ends_with(&end) {
+        return Err(ArmorError::Malformed);
+    }
+    base64_decode(&body.concat())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64_rfc4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (data, encoded) in vectors {
+            assert_eq!(base64_encode(data.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_base64_rejects_malformed() {
+        for input in ["Zg=", "Zg===", "Z===", "Zg==Zg==", "Zm9*", "Zm9v\u{e9}"] {
+            assert_eq!(base64_decode(input), Err(ArmorError::InvalidBase64));
+        }
+    }
+
+    #[test]
+    fn test_armor_round_trip() {
+        let data: Vec<u8> = (0..=255).collect();
+        let armored = armor("EDDO TEST", &data);
+        assert_eq!(armor_label(&armored), Some("EDDO TEST"));
+        assert!(armored.lines().all(|line| line.len() <= ARMOR_WIDTH));
+        assert_eq!(dearmor("EDDO TEST", &armored).unwrap(), data);
+        assert_eq!(
+            dearmor("EDDO TEST", &format!("\n{}\n", armored)).unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_dearmor_rejects_malformed() {
+        let armored = armor("EDDO TEST", b"data");
+        assert_eq!(dearmor("EDDO OTHER", &armored), Err(ArmorError::WrongLabel));
+        let truncated = armored.trim_end_matches("-----END EDDO TEST-----");
+        assert_eq!(dearmor("EDDO TEST", truncated), Err(ArmorError::Malformed));
+        let trailing = format!("{}\nmore", armored);
+        assert_eq!(dearmor("EDDO TEST", &trailing), Err(ArmorError::Malformed));
+        assert_eq!(armor_label("not armor"), None);
+    }
+}
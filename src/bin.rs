@@ -1,12 +1,34 @@
-use eddo::{gen_keypair, PrivateKey, PublicKey, Signature};
+use eddo::armor::{self, armor, armor_label, base64_encode, ArmorError};
+use eddo::keyfile::{
+    self, format_public_key, KeyFile, KeyFileError, ENCRYPTED_PRIVATE_KEY_ARMOR,
+    ENCRYPTED_PRIVATE_KEY_PREFIX, PRIVATE_KEY_ARMOR, PRIVATE_KEY_PREFIX, PUBLIC_KEY_ARMOR,
+    PUBLIC_KEY_PREFIX,
+};
+use eddo::recovery::recover_private_scalar;
+use eddo::selftest;
+use eddo::sha256;
+use eddo::sha512::{self, Sha512Writer};
+use eddo::vectors::Rfc8032Vector;
+use eddo::{
+    gen_keypair, prehash, verify_batch, verify_batch_chunked, ExpandedPrivateKey, PassphraseParams,
+    PrivateKey, PublicKey, Signature, DIGEST_SIZE, SIGNATURE_SIZE,
+};
 use rand::rngs::OsRng;
-use std::fs::{self, File};
-use std::io::{self, BufReader};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
 use std::io::{BufRead, Write};
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use structopt::StructOpt;
 
 extern crate hex;
+extern crate serde;
+extern crate serde_json;
 extern crate structopt;
 
 #[derive(StructOpt, Debug)]
@@ -19,27 +41,686 @@ enum Args {
         /// The file to write the private key into
         #[structopt(short = "o", long = "out", parse(from_os_str))]
         out_file: PathBuf,
+        /// Derive the key from a passphrase read from stdin, instead of randomly
+        ///
+        /// This is much weaker than a random key, unless the passphrase is very strong.
+        #[structopt(long = "from-passphrase", requires = "salt")]
+        from_passphrase: bool,
+        /// The salt used when deriving a key from a passphrase, unique to you
+        #[structopt(long = "salt")]
+        salt: Option<String>,
+        /// The number of iterations used when deriving a key from a passphrase
+        #[structopt(long = "iterations")]
+        iterations: Option<u32>,
+        /// Encrypt the private key file under a passphrase
+        ///
+        /// The passphrase is read from --passphrase-file, or the EDDO_PASSPHRASE
+        /// environment variable, or else prompted for.
+        #[structopt(long = "encrypt")]
+        encrypt: bool,
+        /// A file whose first line is the passphrase for the key file
+        #[structopt(long = "passphrase-file", parse(from_os_str), requires = "encrypt")]
+        passphrase_file: Option<PathBuf>,
+        /// Print 24 words encoding the private key, as a backup for use with recover
+        ///
+        /// The words come from the BIP39 English word list, and end with a checksum.
+        /// Anyone who sees them can recreate your private key.
+        #[structopt(long = "mnemonic")]
+        mnemonic: bool,
+        /// Write the private key as base64 armor, instead of hex
+        #[structopt(long = "armor")]
+        armor: bool,
+        /// A comment to keep in the key file, like what the key is for
+        #[structopt(long = "comment")]
+        comment: Option<String>,
+    },
+    /// Recreate a private key file from the words printed by generate --mnemonic
+    ///
+    /// The words are read from stdin, and the public key is printed out.
+    Recover {
+        /// The file to write the private key into
+        #[structopt(short = "o", long = "out", parse(from_os_str))]
+        out_file: PathBuf,
+        /// Encrypt the private key file under a passphrase
+        #[structopt(long = "encrypt")]
+        encrypt: bool,
+        /// A file whose first line is the passphrase for the key file
+        #[structopt(long = "passphrase-file", parse(from_os_str), requires = "encrypt")]
+        passphrase_file: Option<PathBuf>,
     },
     /// Verify a signature for a file, by a given public key
     Verify {
-        /// The public key used to sign this file
-        #[structopt(short = "p", long = "public")]
-        public: String,
-        /// The signature for this file
-        #[structopt(short = "s", long = "signature")]
-        signature: String,
+        #[structopt(flatten)]
+        allowed: AllowedKeys,
+        /// A signature for this file, which can be given several times
+        ///
+        /// This includes any lines following the signature, like its trusted comment.
+        #[structopt(
+            short = "s",
+            long = "signature",
+            env = "EDDO_SIGNATURE",
+            hide_env_values = true,
+            number_of_values = 1,
+            allow_hyphen_values = true,
+            required_unless_one = &["embedded", "recursive", "bundle"]
+        )]
+        signature: Vec<String>,
+        /// Verify every file in a directory, against the .sig file next to it
+        ///
+        /// This prints a table of which files passed, failed, or are missing a signature.
+        #[structopt(
+            long = "recursive",
+            conflicts_with_all = &["signature", "embedded", "digest", "template"]
+        )]
+        recursive: bool,
+        /// Read the signature from the input file, as produced by sign --embed
+        #[structopt(long = "embedded", conflicts_with_all = &["signature", "digest"])]
+        embedded: bool,
+        /// Read the signature from a JSON bundle, as produced by sign --bundle
+        ///
+        /// The public key in the bundle isn't trusted: the signer still needs to be
+        /// one of the keys given to verify. Prehashed bundles are checked as such.
+        #[structopt(
+            long = "bundle",
+            parse(from_os_str),
+            conflicts_with_all = &["signature", "embedded", "recursive"]
+        )]
+        bundle: Option<PathBuf>,
+        /// Write the original content of an embedded file here, if the signature is valid
+        #[structopt(long = "extract", parse(from_os_str), requires = "embedded")]
+        extract: Option<PathBuf>,
+        /// The digest of the file, as sha512:<hex>, checked against a prehashed signature
+        #[structopt(long = "digest", conflicts_with = "INPUT_FILE")]
+        digest: Option<String>,
+        /// Check a prehashed signature, as made by sign --prehash, against the file
+        #[structopt(long = "prehashed", conflicts_with_all = &["digest", "embedded", "recursive"])]
+        prehashed: bool,
+        /// The context the signatures were made with, as given to sign --context
+        #[structopt(long = "context", parse(try_from_str = parse_context))]
+        context: Option<String>,
         /// The file whose signature needs to be verified
-        #[structopt(name = "INPUT_FILE", parse(from_os_str))]
-        in_file: PathBuf,
+        #[structopt(name = "INPUT_FILE", parse(from_os_str), required_unless = "digest")]
+        in_file: Option<PathBuf>,
+        /// Format the result using a template, e.g. '{fingerprint} {status} {file}'
+        ///
+        /// The available fields are fingerprint, status, file, and comment.
+        #[structopt(long = "template")]
+        template: Option<String>,
     },
     /// Sign a file using your private key
     Sign {
-        /// A path to your private key file
+        #[structopt(flatten)]
+        key: SigningKey,
+        /// Ask the ssh-agent at $SSH_AUTH_SOCK to sign, instead of using a key file
+        ///
+        /// The private key never enters this process, so it can stay in the agent,
+        /// or in hardware behind it. The agent limits messages to 256 KiB.
+        #[structopt(
+            long = "ssh-agent",
+            conflicts_with_all = &["key-file", "key-name", "prehash", "context"]
+        )]
+        ssh_agent: bool,
+        /// Which of the agent's Ed25519 keys to use, needed if it has more than one
+        ///
+        /// This can be an eddo fingerprint or public key, or a SHA256: fingerprint,
+        /// as printed by ssh-add -l.
+        #[structopt(long = "key-fingerprint", requires = "ssh-agent")]
+        key_fingerprint: Option<String>,
+        /// Sign the SHA-512 digest of the file, producing a prehashed signature
+        ///
+        /// This is Ed25519ph, from RFC 8032. These signatures can be verified with
+        /// just the digest, using --digest, or against the file, using --prehashed.
+        #[structopt(long = "prehash", alias = "prehashed")]
+        prehash: bool,
+        /// Bind a context, like myapp-v1, into the signature, using Ed25519ctx
+        ///
+        /// The signature only verifies with the same --context, keeping signatures
+        /// made for one purpose from being used for another. With --prehash, the
+        /// context is passed to Ed25519ph instead. Contexts are at most 255 bytes.
+        #[structopt(long = "context", parse(try_from_str = parse_context))]
+        context: Option<String>,
+        /// Output the file with the signature appended, instead of just the signature
+        ///
+        /// This can be checked with verify --embedded.
+        #[structopt(long = "embed", conflicts_with = "prehash")]
+        embed: bool,
+        /// Write the signature as base64 armor, which survives email and the like
+        ///
+        /// Armor is detected automatically when reading signatures and keys.
+        #[structopt(long = "armor", conflicts_with = "embed")]
+        armor: bool,
+        /// Output a JSON bundle, with the signature, public key, digest, and metadata
+        ///
+        /// This packages everything about the signature into a single file, to attach
+        /// to a release, for instance. It can be checked with verify --bundle.
+        /// With several files, each bundle is written next to its file, as <file>.bundle.
+        #[structopt(long = "bundle", conflicts_with_all = &["embed", "armor"])]
+        bundle: bool,
+        /// Where to write the output of --embed, instead of stdout
+        #[structopt(short = "o", long = "out", parse(from_os_str), requires = "embed")]
+        out_file: Option<PathBuf>,
+        /// A trusted comment, covered by the signature, and shown by verify if it's valid
+        ///
+        /// The comment has to fit on a single line, without any line breaks.
+        #[structopt(long = "comment")]
+        comment: Option<String>,
+        /// Include the current time in the signature
+        #[structopt(long = "timestamp")]
+        timestamp: bool,
+        /// Make the signature expire after a duration, like 30d, implying --timestamp
+        ///
+        /// The units are s, m, h, d, and w, for seconds, minutes, hours, days, and weeks.
+        #[structopt(long = "expires", parse(try_from_str = parse_duration))]
+        expires: Option<u64>,
+        /// Show the name, size, and digest of each file, and the key id, and ask before signing
+        ///
+        /// Every answer is appended to the audit log, along with each signature made.
+        #[structopt(long = "confirm")]
+        confirm: bool,
+        /// Record each use of the key in this file, instead of audit.log in the configuration directory
+        ///
+        /// This also works without --confirm, to keep a record of unattended signing.
+        #[structopt(long = "audit-log", parse(from_os_str))]
+        audit_log: Option<PathBuf>,
+        /// The files containing the data to sign
+        ///
+        /// With a single file, the output goes to stdout. With several, each
+        /// signature is written next to its file, as <file>.sig, or <file>.signed
+        /// with --embed. Patterns using * and ? are expanded, for shells that don't.
+        #[structopt(name = "INPUT_FILE", parse(from_os_str), required = true)]
+        in_files: Vec<PathBuf>,
+    },
+    /// Sign or verify a manifest of every file in a directory
+    Manifest(ManifestArgs),
+    /// Let another key sign manifests for the paths matching a pattern
+    ///
+    /// This prints a delegation statement, signed by your key, which manifest verify
+    /// accepts with --delegations. A key you delegate to can delegate further, but
+    /// only for paths which its own delegation covers.
+    Delegate {
+        #[structopt(flatten)]
+        key: SigningKey,
+        /// The public key to delegate to
+        #[structopt(long = "to", allow_hyphen_values = true)]
+        to: String,
+        /// The paths the key can sign for, like docs/*, relative to the manifest's directory
+        ///
+        /// This uses * and ?, where * also matches /, so docs/* covers everything under docs.
+        #[structopt(name = "PATTERN")]
+        pattern: String,
+    },
+    /// Write a SHA512SUMS file for some files, and optionally sign it
+    ///
+    /// This uses the same format as sha512sum, so the file can also be checked with sha512sum -c.
+    Checksum {
+        /// Where to write the checksums, instead of stdout
+        #[structopt(short = "o", long = "out", parse(from_os_str))]
+        out_file: Option<PathBuf>,
+        /// Sign the checksum file too, writing the signature to <out>.sig
+        #[structopt(long = "sign", requires = "out-file")]
+        sign: bool,
+        #[structopt(flatten)]
+        key: SigningKey,
+        /// The files to list in the checksum file
+        #[structopt(name = "INPUT_FILE", parse(from_os_str), required = true)]
+        in_files: Vec<PathBuf>,
+    },
+    /// Verify a signed SHA512SUMS file, and then every file it lists
+    ///
+    /// Paths are relative to the directory containing the checksum file.
+    VerifyChecksums {
+        #[structopt(flatten)]
+        allowed: AllowedKeys,
+        /// The signature of the checksum file, instead of reading it from <CHECKSUMS>.sig
+        #[structopt(short = "s", long = "signature", allow_hyphen_values = true)]
+        signature: Option<String>,
+        /// Don't fail because of files which are listed, but not present
+        #[structopt(long = "ignore-missing")]
+        ignore_missing: bool,
+        /// The checksum file, like SHA512SUMS
+        #[structopt(name = "CHECKSUMS", parse(from_os_str))]
+        checksums: PathBuf,
+    },
+    /// Verify many signatures at once, much faster than checking them one by one
+    ///
+    /// Each line of the list names a file, its signature file, and the public key
+    /// that should have signed it, separated by whitespace. The key can be given
+    /// directly, or as a path to a file containing it. Empty lines, and lines
+    /// starting with #, are ignored. Every entry that fails is reported by line.
+    ///
+    /// Only plain signatures over the files can be checked this way. Signatures
+    /// made with --prehash or --context have to be checked with verify.
+    VerifyBatch {
+        /// Accept signatures past their expiry time
+        #[structopt(long = "ignore-expiry")]
+        ignore_expiry: bool,
+        /// The list of entries to check, with - meaning stdin
+        #[structopt(name = "LIST", parse(from_os_str))]
+        list: PathBuf,
+    },
+    /// Print the digests of some files, using the same hash functions as signing does
+    ///
+    /// The output is in the format of sha512sum, and a SHA-512 digest can be passed to
+    /// verify --digest, after a sha512: prefix.
+    Hash {
+        /// The hash function to use
+        #[structopt(
+            short = "a",
+            long = "algorithm",
+            default_value = "sha512",
+            possible_values = &["sha512", "sha384", "sha256"]
+        )]
+        algorithm: HashAlgorithm,
+        /// The files to hash, with - meaning stdin
+        #[structopt(name = "INPUT_FILE", parse(from_os_str), required = true)]
+        in_files: Vec<PathBuf>,
+    },
+    /// Manage named keys, stored in the configuration directory
+    ///
+    /// This is $EDDO_CONFIG_DIR, or else $XDG_CONFIG_HOME/eddo, or ~/.config/eddo.
+    Key(KeyArgs),
+    /// Sign and verify git commits and tags, acting like gpg for git
+    ///
+    /// Git needs a single program, so link eddo to a file named eddo-git-sign,
+    /// which acts like this command, and then configure git with:
+    ///
+    /// git config gpg.format x509
+    ///
+    /// git config gpg.x509.program eddo-git-sign
+    ///
+    /// The signing key is user.signingkey, as a key name or a path, or the default key.
+    /// Signatures are verified against every key added with key add.
+    GitSign(GitSignArgs),
+    /// Audit a corpus of signatures, producing a CSV report
+    ///
+    /// Each line of the corpus should be a JSON object, with hex encoded "public",
+    /// "message", and "signature" fields. A "digest" field, holding the SHA-512
+    /// digest of the message, can replace "message", for prehashed signatures.
+    ///
+    /// Each signature is checked with verify, verify_strict, and verify_zip215, which
+    /// disagree on edge cases, like small order points. The status is valid or invalid
+    /// when they all agree, and mixed otherwise. Prehashed signatures only get verify.
+    Audit {
+        /// The file containing one JSON record per line
+        #[structopt(name = "CORPUS", parse(from_os_str))]
+        corpus: PathBuf,
+        /// Format each result using a template, instead of as CSV
+        ///
+        /// The available fields are line, fingerprint, status, detail, verify, strict, and zip215.
+        #[structopt(long = "template")]
+        template: Option<String>,
+    },
+    /// Look for nonces reused across different messages in a corpus of signatures
+    ///
+    /// The corpus uses the same format as the audit command. Two valid signatures by
+    /// the same key, sharing the same R value, but over different messages, reveal
+    /// the private key. Each such reuse is reported as a line of CSV.
+    AuditNonces {
+        /// The file containing one JSON record per line
+        #[structopt(name = "CORPUS", parse(from_os_str))]
+        corpus: PathBuf,
+        /// Recover the secret scalar of each affected key, to demonstrate the exposure
+        #[structopt(long = "recover")]
+        recover: bool,
+    },
+    /// Check that this build computes signatures correctly, exiting with an error if not
+    ///
+    /// This runs the RFC 8032 test vectors, along with vectors we generate, and checks
+    /// of the underlying arithmetic, which differs between architectures.
+    Selftest {
+        /// Print this many generated vectors instead, in the format of sign.input
+        ///
+        /// The vectors are deterministic, so the output of different builds can be compared.
+        #[structopt(long = "generate")]
+        generate: Option<u32>,
+    },
+}
+
+/// The private key to sign with, from a file, by name, or the default key
+#[derive(StructOpt, Debug)]
+struct SigningKey {
+    /// A path to your private key file
+    #[structopt(short = "k", long = "key", parse(from_os_str))]
+    key_file: Option<PathBuf>,
+    /// The name of a key added with key add, instead of a path
+    ///
+    /// Without either this or --key, the key in $EDDO_PRIVATE_KEY is used, if set,
+    /// or else the default key.
+    #[structopt(long = "key-name", conflicts_with = "key-file")]
+    key_name: Option<String>,
+    /// A file whose first line is the passphrase, if the key file is encrypted
+    ///
+    /// Otherwise, the EDDO_PASSPHRASE environment variable is used, or we prompt for it.
+    #[structopt(long = "passphrase-file", parse(from_os_str))]
+    passphrase_file: Option<PathBuf>,
+}
+
+/// The public keys allowed to sign something, and how many of them need to
+#[derive(StructOpt, Debug)]
+struct AllowedKeys {
+    /// A public key allowed to sign, which can be given several times
+    #[structopt(
+        short = "p",
+        long = "public",
+        env = "EDDO_PUBLIC_KEY",
+        hide_env_values = true,
+        number_of_values = 1,
+        allow_hyphen_values = true,
+        required_unless_one = &["keyring", "signer", "public-url", "public-dns"]
+    )]
+    public: Vec<String>,
+    /// An https URL to fetch the public key from, pinning it the first time
+    ///
+    /// Later verifications use the pinned key, without fetching it again. Pins are
+    /// kept in the pins file of the configuration directory, and deleting a line
+    /// there lets the key be fetched again.
+    #[structopt(long = "public-url")]
+    public_url: Option<String>,
+    /// A domain whose _eddo TXT records hold the public key, pinning it the first time
+    ///
+    /// Each record should look like eddo-public-key=<hex>. DNS isn't authenticated,
+    /// so this is only as trustworthy as the network the first lookup is made on.
+    #[structopt(long = "public-dns")]
+    public_dns: Option<String>,
+    /// The name of a pinned public key, added with key add, which can be given several times
+    #[structopt(long = "signer", number_of_values = 1)]
+    signer: Vec<String>,
+    /// A file listing allowed public keys, one per line
+    ///
+    /// Empty lines, and lines starting with #, are ignored. The file can also contain
+    /// statements made by key rotate, which allow the new key in place of the old one.
+    #[structopt(long = "keyring", parse(from_os_str))]
+    keyring: Option<PathBuf>,
+    /// How many of the allowed keys need to have signed
+    ///
+    /// Keys linked by a rotation only count once.
+    #[structopt(long = "threshold", default_value = "1")]
+    threshold: usize,
+    /// Accept signatures past their expiry time
+    #[structopt(long = "ignore-expiry")]
+    ignore_expiry: bool,
+}
+
+impl AllowedKeys {
+    /// Collect the keys given directly, from a keyring file, and by name
+    ///
+    /// Rotations in the keyring file are followed, adding the new keys they allow.
+    fn public_keys(&self) -> AppResult<Vec<TrustedSigner>> {
+        let mut publics = self
+            .public
+            .iter()
+            .map(|public| decode_public_key(public))
+            .collect::<AppResult<Vec<_>>>()?;
+        let mut rotations = Vec::new();
+        if let Some(keyring) = &self.keyring {
+            let (keys, keyring_rotations) = read_keyring(keyring)?;
+            publics.extend(keys);
+            rotations = keyring_rotations;
+        }
+        if !self.signer.is_empty() {
+            let named = Keyring::open()?;
+            for name in &self.signer {
+                publics.push(named.public_key(name)?);
+            }
+        }
+        if let Some(url) = &self.public_url {
+            publics.extend(pinned_keys(&format!("url:{}", url), || {
+                Ok(parse_keyring(&fetch_url(url)?)?.0)
+            })?);
+        }
+        if let Some(domain) = &self.public_dns {
+            publics.extend(pinned_keys(&format!("dns:{}", domain), || {
+                dns_public_keys(domain)
+            })?);
+        }
+        if publics.is_empty() {
+            return Err(AppError::ParseError("no public keys".into()));
+        }
+        Ok(follow_rotations(publics, &rotations))
+    }
+
+    /// The time to check expiry against, if we're checking it at all
+    fn now(&self) -> Option<u64> {
+        if self.ignore_expiry {
+            None
+        } else {
+            Some(unix_now())
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+enum KeyArgs {
+    /// Add a named key, from a private key file, or just a public key
+    Add {
+        /// The name to refer to this key by
+        #[structopt(name = "NAME")]
+        name: String,
+        /// A private key file to copy, for signing
         #[structopt(short = "k", long = "key", parse(from_os_str))]
-        key_file: PathBuf,
-        /// The file contained the data to sign
-        #[structopt(name = "INPUT_FILE", parse(from_os_str))]
-        in_file: PathBuf,
+        key_file: Option<PathBuf>,
+        /// A public key to pin, for verification only
+        #[structopt(
+            short = "p",
+            long = "public",
+            allow_hyphen_values = true,
+            required_unless = "key-file",
+            conflicts_with = "key-file"
+        )]
+        public: Option<String>,
+    },
+    /// List the named keys, marking the default with *
+    List,
+    /// Remove a named key, deleting its copy of the private key
+    Remove {
+        #[structopt(name = "NAME")]
+        name: String,
+    },
+    /// Use a named key when sign isn't given one
+    Default {
+        #[structopt(name = "NAME")]
+        name: String,
+    },
+    /// Replace a private key with a new one, printing a statement endorsing the new key
+    ///
+    /// The statement is signed by both keys. Adding it to a keyring file used with
+    /// verify --keyring allows the new key wherever the old one was.
+    Rotate {
+        /// The current private key, which endorses the new one
+        #[structopt(flatten)]
+        key: SigningKey,
+        /// The file to write the new private key into
+        #[structopt(short = "o", long = "out", parse(from_os_str))]
+        out_file: PathBuf,
+        /// Encrypt the new private key file under a passphrase
+        #[structopt(long = "encrypt")]
+        encrypt: bool,
+    },
+    /// Print the public key for a private key, deriving it again from the private key
+    Public {
+        #[structopt(flatten)]
+        key: SigningKey,
+        /// How to print the public key
+        #[structopt(
+            long = "format",
+            default_value = "eddo",
+            possible_values = &["eddo", "hex", "fingerprint", "comment", "armor"]
+        )]
+        format: PublicKeyFormat,
+    },
+}
+
+/// The subset of gpg's arguments which git uses
+///
+/// Some of these are only accepted, since they ask for what we always do.
+#[allow(dead_code)]
+#[derive(StructOpt, Debug)]
+struct GitSignArgs {
+    /// Where to write gpg style status lines, as a file descriptor, either 1 or 2
+    #[structopt(long = "status-fd", default_value = "2")]
+    status_fd: i32,
+    /// Make a detached signature, which is the only kind we make
+    #[structopt(short = "b", long = "detach-sign")]
+    detach_sign: bool,
+    /// Sign the data on stdin, writing the signature to stdout
+    #[structopt(short = "s", long = "sign", required_unless = "verify")]
+    sign: bool,
+    /// Armor the signature, which is the only way we write it
+    #[structopt(short = "a", long = "armor")]
+    armor: bool,
+    /// The key to sign with, as a key name or a path, using the default key otherwise
+    ///
+    /// Git passes the committer's identity when user.signingkey isn't set, which
+    /// doesn't name a key, and also leads to using the default key.
+    #[structopt(short = "u", long = "local-user")]
+    local_user: Option<String>,
+    /// Verify the signature in this file, against the data on stdin
+    #[structopt(long = "verify", parse(from_os_str), conflicts_with = "sign")]
+    verify: Option<PathBuf>,
+    /// Ignored, since fingerprints are always shown in full
+    #[structopt(long = "keyid-format")]
+    keyid_format: Option<String>,
+    /// The data to verify, which git always passes as - for stdin
+    #[structopt(name = "DATA")]
+    data: Option<String>,
+}
+
+/// The hash functions the hash command offers
+#[derive(Debug, Clone, Copy)]
+enum HashAlgorithm {
+    Sha512,
+    Sha384,
+    Sha256,
+}
+
+impl std::str::FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            "sha384" => Ok(HashAlgorithm::Sha384),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            _ => Err(format!("unknown hash algorithm: {}", s)),
+        }
+    }
+}
+
+impl HashAlgorithm {
+    /// Hash everything a reader produces, a chunk at a time
+    fn hash_reader(self, reader: impl Read) -> io::Result<Vec<u8>> {
+        match self {
+            HashAlgorithm::Sha512 | HashAlgorithm::Sha384 => {
+                let (mut hasher, size) = match self {
+                    HashAlgorithm::Sha384 => (sha512::Hasher::sha384(), sha512::SHA384_SIZE),
+                    _ => (sha512::Hasher::new(), sha512::HASH_SIZE),
+                };
+                for_each_chunk(reader, |data| hasher.update(data))?;
+                Ok(hasher.finalize()[..size].to_vec())
+            }
+            HashAlgorithm::Sha256 => {
+                let mut hasher = sha256::Hasher::new();
+                for_each_chunk(reader, |data| hasher.update(data))?;
+                Ok(hasher.finalize().to_vec())
+            }
+        }
+    }
+}
+
+/// Pass everything a reader produces to a function, a chunk at a time
+fn for_each_chunk(mut reader: impl Read, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(read) => f(&buf[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// The ways of printing a public key
+#[derive(Debug, Clone, Copy)]
+enum PublicKeyFormat {
+    /// The same prefixed format verify takes
+    Eddo,
+    /// Just the hex encoded bytes
+    Hex,
+    /// The short fingerprint shown by verify and key list
+    Fingerprint,
+    /// The comment line at the start of a key file
+    Comment,
+    /// Base64 armor, which verify also accepts
+    Armor,
+}
+
+impl std::str::FromStr for PublicKeyFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eddo" => Ok(PublicKeyFormat::Eddo),
+            "hex" => Ok(PublicKeyFormat::Hex),
+            "fingerprint" => Ok(PublicKeyFormat::Fingerprint),
+            "comment" => Ok(PublicKeyFormat::Comment),
+            "armor" => Ok(PublicKeyFormat::Armor),
+            _ => Err(format!("unknown public key format: {}", s)),
+        }
+    }
+}
+
+impl PublicKeyFormat {
+    fn format(self, public: &PublicKey) -> String {
+        match self {
+            PublicKeyFormat::Eddo => format_public_key(*public),
+            PublicKeyFormat::Hex => hex::encode(public.bytes),
+            PublicKeyFormat::Fingerprint => fingerprint(public),
+            PublicKeyFormat::Comment => format!("# Public Key: {}", format_public_key(*public)),
+            PublicKeyFormat::Armor => armor(PUBLIC_KEY_ARMOR, &public.bytes),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+enum ManifestArgs {
+    /// Hash every file under a directory, and sign the resulting manifest
+    ///
+    /// The manifest lists the path, size, and SHA-512 digest of each file, and
+    /// has its signature embedded, like sign --embed.
+    Sign {
+        #[structopt(flatten)]
+        key: SigningKey,
+        /// Where to write the signed manifest, instead of stdout
+        #[structopt(short = "o", long = "out", parse(from_os_str))]
+        out_file: Option<PathBuf>,
+        /// Only list the files matching a pattern, like docs/*, for a key delegated to sign them
+        #[structopt(long = "only")]
+        only: Option<String>,
+        /// The directory to sign
+        #[structopt(name = "DIR", parse(from_os_str))]
+        dir: PathBuf,
+    },
+    /// Check a signed manifest, and then every file it lists, reporting mismatches
+    Verify {
+        /// The public key used to sign the manifest
+        #[structopt(short = "p", long = "public", allow_hyphen_values = true)]
+        public: String,
+        /// A file of statements made by delegate, which can be given several times
+        #[structopt(long = "delegations", parse(from_os_str), number_of_values = 1)]
+        delegations: Vec<PathBuf>,
+        /// Another signed manifest, for part of the directory, which can be given several times
+        ///
+        /// Each manifest can be signed by the public key, or by a key it has delegated
+        /// to, as long as that key is allowed to sign for every path the manifest lists.
+        #[structopt(long = "delegated", parse(from_os_str), number_of_values = 1)]
+        delegated: Vec<PathBuf>,
+        /// The signed manifest
+        #[structopt(name = "MANIFEST", parse(from_os_str))]
+        manifest: PathBuf,
+        /// The directory the manifest describes
+        #[structopt(name = "DIR", parse(from_os_str))]
+        dir: PathBuf,
     },
 }
 
@@ -52,6 +733,8 @@ enum AppError {
     ParseError(String),
     /// An error that occurrs when a signature check fails
     FailedSignature,
+    /// The signatures were valid, but expired, at the time given
+    ExpiredSignature(String),
     /// An error that happened while doing IO of some kind
     IO(io::Error),
     /// An error that happened while doing hex decoding
@@ -70,46 +753,64 @@ impl From<hex::FromHexError> for AppError {
     }
 }
 
-/// The type of result produced our application
-type AppResult<T> = Result<T, AppError>;
-
-fn decode_prefixed_hex<const N: usize>(prefix: &str, input: &str) -> AppResult<[u8; N]> {
-    let just_hex = input
-        .strip_prefix(prefix)
-        .ok_or(AppError::ParseError("incorrect prefix".into()))?;
-    if just_hex.len() != 2 * N {
-        return Err(AppError::ParseError("incorrect size".into()));
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::ParseError(what) => write!(f, "{}", what),
+            AppError::FailedSignature => write!(f, "invalid signature"),
+            AppError::ExpiredSignature(when) => write!(f, "signature expired at {}", when),
+            AppError::IO(err) => write!(f, "{}", err),
+            AppError::HexError(err) => write!(f, "invalid hex: {}", err),
+        }
     }
-    let mut bytes = [0; N];
-    hex::decode_to_slice(just_hex, &mut bytes)?;
-    Ok(bytes)
 }
 
-const PUBLIC_KEY_PREFIX: &'static str = "エッドの公開鍵";
+/// The type of result produced our application
+type AppResult<T> = Result<T, AppError>;
 
-fn format_public_key(public: PublicKey) -> String {
-    format!("{}{}", PUBLIC_KEY_PREFIX, hex::encode(public.bytes))
+impl From<KeyFileError> for AppError {
+    fn from(err: KeyFileError) -> Self {
+        AppError::ParseError(err.to_string())
+    }
 }
 
-fn decode_public_key(input: &str) -> AppResult<PublicKey> {
-    Ok(PublicKey {
-        bytes: decode_prefixed_hex(PUBLIC_KEY_PREFIX, input)?,
+/// Remove the armor around some data, checking that it has the label we expect
+fn dearmor(label: &str, input: &str) -> AppResult<Vec<u8>> {
+    armor::dearmor(label, input).map_err(|e| match e {
+        ArmorError::WrongLabel => AppError::ParseError(format!("expected {}", label)),
+        ArmorError::Malformed => AppError::ParseError(format!("malformed {}", label)),
+        ArmorError::InvalidBase64 => AppError::ParseError("invalid base64".into()),
     })
 }
 
-const PRIVATE_KEY_PREFIX: &'static str = "エッドの秘密鍵";
+/// The armor label used for each kind of prefixed value
+fn prefix_armor_label(prefix: &str) -> Option<&'static str> {
+    match prefix {
+        PUBLIC_KEY_PREFIX => Some(PUBLIC_KEY_ARMOR),
+        PRIVATE_KEY_PREFIX => Some(PRIVATE_KEY_ARMOR),
+        ENCRYPTED_PRIVATE_KEY_PREFIX => Some(ENCRYPTED_PRIVATE_KEY_ARMOR),
+        SIGNATURE_PREFIX => Some(SIGNATURE_ARMOR),
+        ROTATION_PREFIX => Some(ROTATION_ARMOR),
+        _ => None,
+    }
+}
 
-fn format_private_key(private: PrivateKey) -> String {
-    format!("{}{}", PRIVATE_KEY_PREFIX, hex::encode(private.bytes))
+/// Decode a value written with a prefix, followed by hex, or in the matching armor
+fn decode_prefixed_hex<const N: usize>(prefix: &str, input: &str) -> AppResult<[u8; N]> {
+    if let (Some(label), Some(_)) = (prefix_armor_label(prefix), armor_label(input)) {
+        return dearmor(label, input)?
+            .try_into()
+            .map_err(|_| AppError::ParseError("incorrect size".into()));
+    }
+    Ok(keyfile::decode_prefixed(prefix, None, input)?)
 }
 
-fn decode_private_key(input: &str) -> AppResult<PrivateKey> {
-    Ok(PrivateKey {
-        bytes: decode_prefixed_hex(PRIVATE_KEY_PREFIX, input)?,
-    })
+fn decode_public_key(input: &str) -> AppResult<PublicKey> {
+    Ok(keyfile::decode_public_key(input)?)
 }
 
-const SIGNATURE_PREFIX: &'static str = "エッドの署名";
+const SIGNATURE_PREFIX: &str = "エッドの署名";
+const SIGNATURE_ARMOR: &str = "EDDO SIGNATURE";
 
 fn format_signature(signature: Signature) -> String {
     format!("{}{}", SIGNATURE_PREFIX, hex::encode(signature.bytes))
@@ -121,57 +822,3997 @@ fn decode_signature(input: &str) -> AppResult<Signature> {
     })
 }
 
-fn generate(out_path: &Path) -> AppResult<()> {
-    let (public, private) = gen_keypair(&mut OsRng);
-    let formatted_public = format_public_key(public);
-    let formatted_private = format_private_key(private);
-    let mut out_file = File::create(out_path)?;
-    writeln!(out_file, "# Public Key: {}", formatted_public)?;
-    writeln!(out_file, "{}", formatted_private)?;
-    Ok(())
+const TRUSTED_COMMENT_PREFIX: &str = "trusted comment: ";
+const VERSION_PREFIX: &str = "version: ";
+const TIMESTAMP_PREFIX: &str = "timestamp: ";
+const EXPIRES_PREFIX: &str = "expires: ";
+
+/// The version of the metadata format, written before any other metadata
+const METADATA_VERSION: u32 = 1;
+
+/// Information carried along with a signature, and covered by it
+#[derive(Debug, Clone, Default)]
+struct Metadata {
+    /// When the signature was made, in seconds since the Unix epoch
+    timestamp: Option<u64>,
+    /// When the signature stops being valid, in seconds since the Unix epoch
+    expires: Option<u64>,
+    /// A comment, like minisign's trusted comments, shown only if the signature is valid
+    comment: Option<String>,
 }
 
-fn sign(key_path: &Path, in_path: &Path) -> AppResult<()> {
-    let key_file = File::open(key_path)?;
-    let key_reader = BufReader::new(key_file);
-    let mut maybe_private = None;
-    for maybe_line in key_reader.lines() {
-        let line = maybe_line?;
-        if line.starts_with("#") {
-            continue;
+/// Signatures with metadata sign this, followed by the metadata, and then the message
+const METADATA_DOMAIN: &[u8] = b"eddo signature metadata v1\0";
+
+impl Metadata {
+    fn is_empty(&self) -> bool {
+        self.timestamp.is_none() && self.expires.is_none() && self.comment.is_none()
+    }
+
+    /// The lines following a signature, in the same order they're signed in
+    fn lines(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        if self.is_empty() {
+            return out;
+        }
+        out.push(format!("{}{}", VERSION_PREFIX, METADATA_VERSION));
+        if let Some(timestamp) = self.timestamp {
+            out.push(format!("{}{}", TIMESTAMP_PREFIX, timestamp));
+        }
+        if let Some(expires) = self.expires {
+            out.push(format!("{}{}", EXPIRES_PREFIX, expires));
+        }
+        if let Some(comment) = &self.comment {
+            out.push(format!("{}{}", TRUSTED_COMMENT_PREFIX, comment));
         }
-        maybe_private = Some(decode_private_key(&line)?);
-        break;
+        out
+    }
+
+    fn parse<'a>(mut lines: impl Iterator<Item = &'a str>) -> AppResult<Self> {
+        let mut out = Metadata::default();
+        let version = match lines.next() {
+            None => return Ok(out),
+            Some(line) => line.strip_prefix(VERSION_PREFIX),
+        };
+        if version != Some(&METADATA_VERSION.to_string()) {
+            return Err(AppError::ParseError(
+                "unsupported signature metadata version".into(),
+            ));
+        }
+        let parse_time = |time: &str| {
+            time.parse()
+                .map_err(|_| AppError::ParseError(format!("malformed time: {}", time)))
+        };
+        for line in lines {
+            if let Some(timestamp) = line.strip_prefix(TIMESTAMP_PREFIX) {
+                out.timestamp = Some(parse_time(timestamp)?);
+            } else if let Some(expires) = line.strip_prefix(EXPIRES_PREFIX) {
+                out.expires = Some(parse_time(expires)?);
+            } else if let Some(comment) = line.strip_prefix(TRUSTED_COMMENT_PREFIX) {
+                out.comment = Some(comment.to_string());
+            } else {
+                return Err(AppError::ParseError(format!(
+                    "unknown signature metadata: {}",
+                    line
+                )));
+            }
+        }
+        Ok(out)
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| now >= expires)
+    }
+
+    /// The message a signature with this metadata actually signs
+    ///
+    /// Without metadata, this is just the message, so that plain signatures stay the same.
+    fn signed_message<'a>(&self, message: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.is_empty() {
+            return Cow::Borrowed(message);
+        }
+        let mut out = self.prefix();
+        out.extend_from_slice(message);
+        Cow::Owned(out)
+    }
+
+    /// The digest a prehashed signature with this metadata actually signs
+    ///
+    /// The metadata is bound to the digest of the message, so that verify can still
+    /// work from just that digest. Without metadata, this is the digest itself.
+    fn prehashed_digest(&self, digest: &[u8; DIGEST_SIZE]) -> [u8; DIGEST_SIZE] {
+        match self.signed_message(digest) {
+            Cow::Borrowed(_) => *digest,
+            Cow::Owned(message) => prehash::digest(&message),
+        }
+    }
+
+    /// What comes before the message in signed_message, which is empty without metadata
+    fn prefix(&self) -> Vec<u8> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let lines = self.lines().join("\n");
+        let mut out = Vec::with_capacity(METADATA_DOMAIN.len() + 8 + lines.len());
+        out.extend_from_slice(METADATA_DOMAIN);
+        out.extend_from_slice(&(lines.len() as u64).to_be_bytes());
+        out.extend_from_slice(lines.as_bytes());
+        out
+    }
+
+    /// Read the whole signed message into memory, for when it can't be streamed
+    fn read_signed(&self, message: &Message) -> io::Result<Vec<u8>> {
+        match message {
+            Message::File(path, len) => {
+                let mut out = Vec::new();
+                self.open_signed(path, *len)?.read_to_end(&mut out)?;
+                Ok(out)
+            }
+            Message::Memory(data) => Ok(self.signed_message(data).into_owned()),
+        }
+    }
+
+    /// Read the signed message, in chunks, if it's in a file
+    fn open_message<'a>(&self, message: &'a Message) -> io::Result<Box<dyn Read + 'a>> {
+        match message {
+            Message::File(path, len) => Ok(Box::new(self.open_signed(path, *len)?)),
+            Message::Memory(data) => Ok(Box::new(io::Cursor::new(self.prefix()).chain(&data[..]))),
+        }
+    }
+
+    /// Read the signed message for the first `len` bytes of a file, without loading it all
+    fn open_signed(&self, path: &Path, len: u64) -> io::Result<impl Read> {
+        let file = File::open(path)?.take(len);
+        Ok(io::Cursor::new(self.prefix()).chain(file))
     }
-    let private = maybe_private.ok_or(AppError::ParseError("no private key in file".into()))?;
-    let in_data = fs::read(in_path)?;
-    let sig = private.sign(&in_data);
-    println!("{}", format_signature(sig));
-    Ok(())
 }
 
-fn verify(public: PublicKey, signature: Signature, in_path: &Path) -> AppResult<()> {
-    let in_data = fs::read(in_path)?;
-    if !public.verify(&in_data, signature) {
-        return Err(AppError::FailedSignature);
+/// How many bytes of a file we read at once, when signing or verifying it
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// A message to sign or verify, read in chunks from a file, or held in memory
+enum Message {
+    /// The first bytes of a regular file, which we can read as many times as we need
+    File(PathBuf, u64),
+    /// Data from somewhere we can only read once, like a pipe
+    Memory(Vec<u8>),
+}
+
+impl Message {
+    fn open(path: &Path) -> AppResult<Self> {
+        let metadata = fs::metadata(path)?;
+        if metadata.is_file() {
+            Ok(Message::File(path.to_path_buf(), metadata.len()))
+        } else {
+            Ok(Message::Memory(fs::read(path)?))
+        }
+    }
+
+    /// Open a file produced by sign --embed, separating its content from its signature
+    fn open_embedded(path: &Path) -> AppResult<(Self, SignatureBlock)> {
+        if fs::metadata(path)?.is_file() {
+            let (len, block) = split_embedded_file(path)?;
+            return Ok((Message::File(path.to_path_buf(), len), block));
+        }
+        let data = fs::read(path)?;
+        let (content, block) = split_embedded(&data)?;
+        Ok((Message::Memory(content.to_vec()), block))
+    }
+
+    fn digest(&self) -> AppResult<[u8; DIGEST_SIZE]> {
+        match self {
+            Message::File(path, _) => Ok(hash_file_contents(path)?.1),
+            Message::Memory(data) => Ok(prehash::digest(data)),
+        }
+    }
+
+    /// The number of bytes in this message
+    fn len(&self) -> u64 {
+        match self {
+            Message::File(_, len) => *len,
+            Message::Memory(data) => data.len() as u64,
+        }
+    }
+
+    fn copy_to(&self, out: &mut impl Write) -> AppResult<()> {
+        match self {
+            Message::File(path, len) => {
+                io::copy(&mut File::open(path)?.take(*len), out)?;
+            }
+            Message::Memory(data) => out.write_all(data)?,
+        }
+        Ok(())
     }
-    println!("Ok!");
-    Ok(())
 }
 
-fn main() -> AppResult<()> {
-    let args = Args::from_args();
-    match args {
-        Args::Generate { out_file } => generate(&out_file),
-        Args::Sign { key_file, in_file } => sign(&key_file, &&in_file),
-        Args::Verify {
-            public,
+/// The current time, in seconds since the Unix epoch
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The error for a context which RFC 8032 doesn't allow
+const CONTEXT_TOO_LONG: &str = "contexts must be at most 255 bytes";
+
+/// Check a context given to --context, which has to fit into a single length byte
+fn parse_context(input: &str) -> Result<String, String> {
+    if input.is_empty() {
+        return Err("contexts must not be empty".into());
+    }
+    if input.len() > 255 {
+        return Err(CONTEXT_TOO_LONG.into());
+    }
+    Ok(input.to_string())
+}
+
+/// Parse a duration like 90s, 30m, 12h, 7d, or 2w, with plain numbers being seconds
+fn parse_duration(input: &str) -> Result<u64, String> {
+    let (number, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => input.split_at(i),
+        None => (input, "s"),
+    };
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown duration unit: {}", unit)),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(scale))
+        .ok_or_else(|| format!("invalid duration: {}", input))
+}
+
+/// Format a Unix timestamp as an RFC 3339 date, in UTC
+fn format_time(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let secs = timestamp % 86400;
+    // This is the civil_from_days algorithm, by Howard Hinnant
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// A signature, along with the metadata it covers
+#[derive(Debug, Clone)]
+struct SignatureBlock {
+    signature: Signature,
+    metadata: Metadata,
+}
+
+impl SignatureBlock {
+    /// Format the signature, followed by a line for each piece of metadata
+    fn format(&self) -> String {
+        let mut lines = vec![format_signature(self.signature)];
+        lines.extend(self.metadata.lines());
+        lines.join("\n")
+    }
+
+    /// Format the signature as armor, holding its bytes, and then each line of metadata
+    fn armor(&self) -> String {
+        let mut data = self.signature.bytes.to_vec();
+        for line in self.metadata.lines() {
+            data.extend_from_slice(line.as_bytes());
+            data.push(b'\n');
+        }
+        armor(SIGNATURE_ARMOR, &data)
+    }
+
+    /// Decode a signature in either format, detecting armor
+    fn decode(input: &str) -> AppResult<Self> {
+        if armor_label(input).is_some() {
+            let data = dearmor(SIGNATURE_ARMOR, input)?;
+            if data.len() < SIGNATURE_SIZE {
+                return Err(AppError::ParseError("incorrect size".into()));
+            }
+            let (signature, metadata) = data.split_at(SIGNATURE_SIZE);
+            let metadata = std::str::from_utf8(metadata)
+                .map_err(|_| AppError::ParseError("malformed signature metadata".into()))?;
+            return Ok(SignatureBlock {
+                signature: Signature {
+                    bytes: signature.try_into().unwrap(),
+                },
+                metadata: Metadata::parse(metadata.lines())?,
+            });
+        }
+        let mut lines = input.trim_end().lines();
+        let signature = decode_signature(lines.next().unwrap_or(""))?;
+        let metadata = Metadata::parse(lines)?;
+        Ok(SignatureBlock {
             signature,
-            in_file,
-        } => {
-            let public_key = decode_public_key(&public)?;
-            let decoded_signature = decode_signature(&signature)?;
-            verify(public_key, decoded_signature, &in_file)
+            metadata,
+        })
+    }
+
+    /// Check this signature over a message
+    fn verify(&self, public: &PublicKey, message: &[u8]) -> bool {
+        public.verify(&self.metadata.signed_message(message), self.signature)
+    }
+
+    /// Check this signature over a message, reading it in chunks if it's in a file
+    ///
+    /// With a context, this checks an Ed25519ctx signature, which needs the whole
+    /// message in memory. A file we can't read just counts as a failed signature.
+    fn verify_message(&self, public: &PublicKey, message: &Message, context: &[u8]) -> bool {
+        match message {
+            _ if !context.is_empty() => self
+                .metadata
+                .read_signed(message)
+                .map(|signed| public.verify_with_context(&signed, context, self.signature))
+                .unwrap_or(false),
+            Message::File(path, len) => self
+                .metadata
+                .open_signed(path, *len)
+                .and_then(|reader| public.verify_chunked(reader, self.signature, CHUNK_SIZE))
+                .unwrap_or(false),
+            Message::Memory(data) => self.verify(public, data),
+        }
+    }
+
+    /// Check this signature over the digest of a message, as made by sign --prehash
+    fn verify_prehashed(
+        &self,
+        public: &PublicKey,
+        digest: &[u8; DIGEST_SIZE],
+        context: &[u8],
+    ) -> bool {
+        let digest = self.metadata.prehashed_digest(digest);
+        public.verify_prehashed(&digest, context, self.signature)
+    }
+}
+
+const ROTATION_PREFIX: &str = "エッドの鍵更新";
+const ROTATION_ARMOR: &str = "EDDO KEY ROTATION";
+
+/// Separates rotation statements from anything else a key might sign
+const ROTATION_DOMAIN: &[u8] = b"eddo key rotation v1\0";
+
+/// A statement that one key has been replaced by another
+///
+/// Both keys sign it: the old key endorses the new one, and the new key shows
+/// that whoever made the statement also holds it.
+struct Rotation {
+    old: PublicKey,
+    new: PublicKey,
+    timestamp: u64,
+    old_signature: Signature,
+    new_signature: Signature,
+}
+
+impl Rotation {
+    const SIZE: usize = 32 + 32 + 8 + 64 + 64;
+
+    fn message(old: &PublicKey, new: &PublicKey, timestamp: u64) -> Vec<u8> {
+        let mut message = ROTATION_DOMAIN.to_vec();
+        message.extend_from_slice(&old.bytes);
+        message.extend_from_slice(&new.bytes);
+        message.extend_from_slice(&timestamp.to_be_bytes());
+        message
+    }
+
+    fn create(old: &PrivateKey, new: &PrivateKey, timestamp: u64) -> Self {
+        let (old_public, new_public) = (old.public_key(), new.public_key());
+        let message = Self::message(&old_public, &new_public, timestamp);
+        Rotation {
+            old: old_public,
+            new: new_public,
+            timestamp,
+            old_signature: old.sign(&message),
+            new_signature: new.sign(&message),
+        }
+    }
+
+    fn format(&self) -> String {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend_from_slice(&self.old.bytes);
+        bytes.extend_from_slice(&self.new.bytes);
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&self.old_signature.bytes);
+        bytes.extend_from_slice(&self.new_signature.bytes);
+        format!("{}{}", ROTATION_PREFIX, hex::encode(bytes))
+    }
+
+    /// Decode a rotation, checking both of its signatures
+    fn decode(input: &str) -> AppResult<Self> {
+        let bytes: [u8; Self::SIZE] = decode_prefixed_hex(ROTATION_PREFIX, input)?;
+        let rotation = Rotation {
+            old: PublicKey {
+                bytes: bytes[..32].try_into().unwrap(),
+            },
+            new: PublicKey {
+                bytes: bytes[32..64].try_into().unwrap(),
+            },
+            timestamp: u64::from_be_bytes(bytes[64..72].try_into().unwrap()),
+            old_signature: Signature {
+                bytes: bytes[72..136].try_into().unwrap(),
+            },
+            new_signature: Signature {
+                bytes: bytes[136..].try_into().unwrap(),
+            },
+        };
+        let message = Self::message(&rotation.old, &rotation.new, rotation.timestamp);
+        let valid = rotation.old.verify(&message, rotation.old_signature)
+            && rotation.new.verify(&message, rotation.new_signature);
+        if !valid {
+            return Err(AppError::ParseError(format!(
+                "invalid key rotation from {}",
+                fingerprint(&rotation.old)
+            )));
+        }
+        Ok(rotation)
+    }
+}
+
+const DELEGATION_PREFIX: &str = "エッドの委任";
+
+/// Separates delegation statements from anything else a key might sign
+const DELEGATION_DOMAIN: &[u8] = b"eddo delegation v1\0";
+
+/// How many delegations can separate a key from the root key, before we stop looking
+const MAX_DELEGATION_DEPTH: usize = 8;
+
+/// A statement that one key lets another sign manifests for the paths matching a pattern
+///
+/// This is formatted on a single line, with the pattern last, since it can contain spaces.
+struct Delegation {
+    from: PublicKey,
+    to: PublicKey,
+    timestamp: u64,
+    pattern: String,
+    signature: Signature,
+}
+
+impl Delegation {
+    const SIZE: usize = 32 + 32 + 8 + 64;
+
+    fn message(from: &PublicKey, to: &PublicKey, timestamp: u64, pattern: &str) -> Vec<u8> {
+        let mut message = DELEGATION_DOMAIN.to_vec();
+        message.extend_from_slice(&from.bytes);
+        message.extend_from_slice(&to.bytes);
+        message.extend_from_slice(&timestamp.to_be_bytes());
+        message.extend_from_slice(pattern.as_bytes());
+        message
+    }
+
+    fn create(
+        from: &PrivateKey,
+        to: PublicKey,
+        pattern: String,
+        timestamp: u64,
+    ) -> AppResult<Self> {
+        if pattern.is_empty() || pattern.contains(&['\n', '\r'][..]) {
+            return Err(AppError::ParseError(
+                "patterns must be a single, non-empty line".into(),
+            ));
+        }
+        let from_public = from.public_key();
+        let message = Self::message(&from_public, &to, timestamp, &pattern);
+        Ok(Delegation {
+            from: from_public,
+            to,
+            timestamp,
+            signature: from.sign(&message),
+            pattern,
+        })
+    }
+
+    fn format(&self) -> String {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend_from_slice(&self.from.bytes);
+        bytes.extend_from_slice(&self.to.bytes);
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&self.signature.bytes);
+        format!(
+            "{}{} {}",
+            DELEGATION_PREFIX,
+            hex::encode(bytes),
+            self.pattern
+        )
+    }
+
+    /// Decode a delegation, checking its signature
+    fn decode(input: &str) -> AppResult<Self> {
+        let (statement, pattern) = input
+            .split_once(' ')
+            .ok_or_else(|| AppError::ParseError("delegation without a pattern".into()))?;
+        let bytes: [u8; Self::SIZE] = decode_prefixed_hex(DELEGATION_PREFIX, statement)?;
+        let delegation = Delegation {
+            from: PublicKey {
+                bytes: bytes[..32].try_into().unwrap(),
+            },
+            to: PublicKey {
+                bytes: bytes[32..64].try_into().unwrap(),
+            },
+            timestamp: u64::from_be_bytes(bytes[64..72].try_into().unwrap()),
+            pattern: pattern.to_string(),
+            signature: Signature {
+                bytes: bytes[72..].try_into().unwrap(),
+            },
+        };
+        let message = Self::message(
+            &delegation.from,
+            &delegation.to,
+            delegation.timestamp,
+            &delegation.pattern,
+        );
+        if !delegation.from.verify(&message, delegation.signature) {
+            return Err(AppError::ParseError(format!(
+                "invalid delegation from {}",
+                fingerprint(&delegation.from)
+            )));
         }
+        Ok(delegation)
+    }
+
+    fn covers(&self, path: &str) -> bool {
+        let pattern: Vec<char> = self.pattern.chars().collect();
+        wildcard_matches(&pattern, &path.chars().collect::<Vec<_>>())
+    }
+}
+
+/// Read a file of delegation statements, one per line
+///
+/// Empty lines, and lines starting with #, are ignored.
+fn read_delegations(path: &Path) -> AppResult<Vec<Delegation>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Delegation::decode)
+        .collect()
+}
+
+/// Check whether a key can sign for a path, as the root key, or through delegations from it
+///
+/// Every delegation in the chain has to cover the path, so keys can only pass on
+/// authority they have themselves.
+fn authorized(
+    root: &PublicKey,
+    delegations: &[Delegation],
+    key: &PublicKey,
+    path: &str,
+    depth: usize,
+) -> bool {
+    if key == root {
+        return true;
+    }
+    depth < MAX_DELEGATION_DEPTH
+        && delegations.iter().any(|delegation| {
+            delegation.to == *key
+                && delegation.covers(path)
+                && authorized(root, delegations, &delegation.from, path, depth + 1)
+        })
+}
+
+/// What sign uses to make signatures
+enum Signer {
+    /// A private key we've loaded
+    Key(ExpandedPrivateKey),
+    /// A key held by an ssh-agent, which only it can use
+    #[cfg(unix)]
+    Agent(SshAgent, PublicKey),
+}
+
+impl Signer {
+    fn public_key(&self) -> PublicKey {
+        match self {
+            Signer::Key(private) => private.public_key(),
+            #[cfg(unix)]
+            Signer::Agent(_, public) => *public,
+        }
+    }
+
+    /// Sign a message, along with metadata, as a normal or prehashed signature
+    ///
+    /// A non-empty context makes an Ed25519ctx signature, or is passed along to
+    /// Ed25519ph. With a private key, files are read in chunks, rather than all at
+    /// once, except with a context, and without prehashing.
+    fn sign_message(
+        &self,
+        message: &Message,
+        metadata: Metadata,
+        prehashed: bool,
+        context: &[u8],
+    ) -> AppResult<SignatureBlock> {
+        let signature = match self {
+            Signer::Key(private) if prehashed => {
+                let digest = metadata.prehashed_digest(&message.digest()?);
+                private
+                    .sign_prehashed(&digest, context)
+                    .ok_or_else(|| AppError::ParseError(CONTEXT_TOO_LONG.into()))?
+            }
+            Signer::Key(private) if !context.is_empty() => private
+                .sign_with_context(&metadata.read_signed(message)?, context)
+                .ok_or_else(|| AppError::ParseError(CONTEXT_TOO_LONG.into()))?,
+            Signer::Key(private) => match message {
+                Message::File(path, len) => {
+                    private.sign_chunked(|| metadata.open_signed(path, *len), CHUNK_SIZE)?
+                }
+                Message::Memory(data) => private.sign(&metadata.signed_message(data)),
+            },
+            #[cfg(unix)]
+            Signer::Agent(agent, public) => {
+                // The agent only makes pure Ed25519 signatures, so the message has to be sent whole
+                let signed = match message {
+                    Message::File(path, len) => {
+                        let mut signed = Vec::new();
+                        let limit = SSH_AGENT_MAX_DATA as u64;
+                        metadata
+                            .open_signed(path, *len)?
+                            .take(limit + 1)
+                            .read_to_end(&mut signed)?;
+                        signed
+                    }
+                    Message::Memory(data) => metadata.signed_message(data).into_owned(),
+                };
+                if prehashed || !context.is_empty() {
+                    return Err(AppError::ParseError(
+                        "ssh-agent can only make plain Ed25519 signatures".into(),
+                    ));
+                }
+                if signed.len() > SSH_AGENT_MAX_DATA {
+                    return Err(AppError::ParseError(
+                        "message too large to sign with ssh-agent".into(),
+                    ));
+                }
+                agent.sign(public, &signed)?
+            }
+        };
+        Ok(SignatureBlock {
+            signature,
+            metadata,
+        })
+    }
+}
+
+fn generate(
+    out_path: &Path,
+    passphrase: Option<PassphraseOptions>,
+    encryption: Option<PassphraseSource>,
+    mnemonic: bool,
+    armor: bool,
+    comment: Option<String>,
+) -> AppResult<()> {
+    let private = match passphrase {
+        None => gen_keypair(&mut OsRng).1,
+        Some(options) => passphrase_key(options)?,
+    };
+    if mnemonic {
+        eprintln!("Write these words down, and keep them secret:");
+        println!("{}", encode_mnemonic(&private).join(" "));
+    }
+    write_key_file(out_path, private, encryption, armor, comment)
+}
+
+/// Read the words printed by generate --mnemonic, and write the key they encode
+fn recover(out_path: &Path, encryption: Option<PassphraseSource>) -> AppResult<()> {
+    eprintln!("Enter your {} recovery words:", MNEMONIC_WORDS);
+    let mut words = String::new();
+    // The words can be split across several lines
+    while words.split_whitespace().count() < MNEMONIC_WORDS {
+        if io::stdin().read_line(&mut words)? == 0 {
+            break;
+        }
+    }
+    let private = decode_mnemonic(&words)?;
+    let public = private.public_key();
+    println!("{}", format_public_key(public));
+    write_key_file(out_path, private, encryption, false, None)
+}
+
+fn write_key_file(
+    out_path: &Path,
+    private: PrivateKey,
+    encryption: Option<PassphraseSource>,
+    armored: bool,
+    comment: Option<String>,
+) -> AppResult<()> {
+    let key_file = match encryption {
+        None => KeyFile::new(private, Some(unix_now()), comment)?,
+        Some(source) => {
+            let passphrase = source.read(true)?;
+            KeyFile::encrypted(
+                &mut OsRng,
+                private,
+                Some(unix_now()),
+                comment,
+                &passphrase,
+                keyfile::DEFAULT_SCRYPT,
+            )?
+        }
+    };
+    write_private_file(out_path, key_file.format(armored).as_bytes())?;
+    Ok(())
+}
+
+/// Write a file which only its owner can read or write, for private keys
+///
+/// On unix, the file gets created with mode 0600, rather than the umask's default,
+/// and an existing file gets its permissions narrowed before being overwritten.
+fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents)
+}
+
+/// The BIP39 English word list, one word per line
+const WORDLIST: &str = include_str!("bip39-english.txt");
+
+/// The number of words encoding a private key
+const MNEMONIC_WORDS: usize = 24;
+
+fn wordlist() -> Vec<&'static str> {
+    WORDLIST.lines().collect()
+}
+
+/// Encode a private key as words, in the same way as BIP39 encodes entropy
+///
+/// The 256 bits of the key are followed by the first 8 bits of their SHA-256 hash,
+/// and each group of 11 bits then picks one of the 2048 words.
+fn encode_mnemonic(private: &PrivateKey) -> Vec<&'static str> {
+    let words = wordlist();
+    let mut bits = private.bytes.to_vec();
+    bits.push(sha256::hash(&private.bytes)[0]);
+    (0..MNEMONIC_WORDS)
+        .map(|i| {
+            let index = (0..11).fold(0, |acc, j| {
+                let bit = i * 11 + j;
+                (acc << 1) | usize::from((bits[bit / 8] >> (7 - bit % 8)) & 1)
+            });
+            words[index]
+        })
+        .collect()
+}
+
+/// Decode the words made by encode_mnemonic, checking the checksum they end with
+///
+/// Since the first 4 letters of each word are unique, these are enough on their own.
+fn decode_mnemonic(input: &str) -> AppResult<PrivateKey> {
+    let words = wordlist();
+    let input: Vec<String> = input
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect();
+    if input.len() != MNEMONIC_WORDS {
+        return Err(AppError::ParseError(format!(
+            "expected {} words, found {}",
+            MNEMONIC_WORDS,
+            input.len()
+        )));
+    }
+    let mut bits = [0u8; 33];
+    for (i, word) in input.iter().enumerate() {
+        let index = words
+            .iter()
+            .position(|w| w == word || (word.len() >= 4 && w.starts_with(word.as_str())))
+            .ok_or_else(|| AppError::ParseError(format!("unknown word: {}", word)))?;
+        for j in 0..11 {
+            let bit = i * 11 + j;
+            bits[bit / 8] |= (((index >> (10 - j)) & 1) as u8) << (7 - bit % 8);
+        }
+    }
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(&bits[..32]);
+    if sha256::hash(&bytes)[0] != bits[32] {
+        return Err(AppError::ParseError(
+            "invalid checksum, some words may be wrong".into(),
+        ));
+    }
+    Ok(PrivateKey { bytes })
+}
+
+/// The options needed to derive a private key from a passphrase
+struct PassphraseOptions {
+    salt: String,
+    iterations: Option<u32>,
+}
+
+fn passphrase_key(options: PassphraseOptions) -> AppResult<PrivateKey> {
+    eprintln!("WARNING: keys derived from passphrases are only as strong as the passphrase.");
+    eprintln!("Anyone who guesses your passphrase and salt can recreate your private key.");
+    // Unlike a key file, there's no way to notice a typo later, so we always ask twice
+    let passphrase = prompt_passphrase("Enter passphrase: ", true)?;
+    let mut params = PassphraseParams::default();
+    if let Some(iterations) = options.iterations {
+        params.iterations = iterations;
+    }
+    PrivateKey::from_passphrase(passphrase.as_bytes(), options.salt.as_bytes(), params)
+        .map_err(|e| AppError::ParseError(format!("refusing to derive key: {:?}", e)))
+}
+
+/// The environment variable holding the passphrase for a key file, for automation
+const PASSPHRASE_ENV: &str = "EDDO_PASSPHRASE";
+
+/// Where to get the passphrase for an encrypted key file
+struct PassphraseSource {
+    file: Option<PathBuf>,
+}
+
+impl PassphraseSource {
+    /// Read the passphrase, from a file, the environment, or by prompting
+    ///
+    /// When prompting for a new passphrase, we ask twice, to catch typos.
+    fn read(&self, confirm: bool) -> AppResult<String> {
+        let passphrase = if let Some(path) = &self.file {
+            let contents = fs::read_to_string(path)?;
+            contents.lines().next().unwrap_or("").to_string()
+        } else if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+            passphrase
+        } else {
+            return prompt_passphrase("Enter passphrase for key file: ", confirm);
+        };
+        if passphrase.is_empty() {
+            return Err(AppError::ParseError("empty passphrase".into()));
+        }
+        Ok(passphrase)
+    }
+}
+
+/// Prompt for a passphrase, asking twice if confirm is set
+fn prompt_passphrase(prompt: &str, confirm: bool) -> AppResult<String> {
+    eprint!("{}", prompt);
+    let passphrase = read_hidden_line()?;
+    if confirm {
+        eprint!("Enter the same passphrase again: ");
+        if read_hidden_line()? != passphrase {
+            return Err(AppError::ParseError("passphrases do not match".into()));
+        }
+    }
+    if passphrase.is_empty() {
+        return Err(AppError::ParseError("empty passphrase".into()));
+    }
+    Ok(passphrase)
+}
+
+fn read_line() -> io::Result<String> {
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+/// Read a line from stdin, without echoing it, if stdin is a terminal
+#[cfg(unix)]
+fn read_hidden_line() -> io::Result<String> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = io::stdin().as_raw_fd();
+    let mut original = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // This fails if stdin isn't a terminal, in which case there's no echo to disable
+    if unsafe { libc::tcgetattr(fd, original.as_mut_ptr()) } != 0 {
+        return read_line();
+    }
+    let original = unsafe { original.assume_init() };
+    let mut hidden = original;
+    hidden.c_lflag &= !libc::ECHO;
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &hidden) };
+    let result = read_line();
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    // The newline the user typed wasn't echoed either
+    eprintln!();
+    result
+}
+
+#[cfg(not(unix))]
+fn read_hidden_line() -> io::Result<String> {
+    read_line()
+}
+
+/// Run a parser which may need the passphrase of a key file, only asking for it if so
+fn with_passphrase<T>(
+    passphrase: &PassphraseSource,
+    parse: impl Fn(Option<&str>) -> Result<T, KeyFileError>,
+) -> AppResult<T> {
+    match parse(None) {
+        Err(KeyFileError::PassphraseRequired) => Ok(parse(Some(&passphrase.read(false)?))?),
+        result => Ok(result?),
+    }
+}
+
+/// Read the private key from a key file, decrypting it if necessary
+fn load_private_key(key_path: &Path, passphrase: &PassphraseSource) -> AppResult<PrivateKey> {
+    parse_private_key(&fs::read_to_string(key_path)?, passphrase)
+}
+
+/// Parse the contents of a key file, decrypting the key if necessary
+///
+/// This reads both the versioned format, and the original one.
+fn parse_private_key(contents: &str, passphrase: &PassphraseSource) -> AppResult<PrivateKey> {
+    with_passphrase(passphrase, |p| keyfile::parse_private_key(contents, p))
+}
+
+/// The environment variable holding a private key, as the contents of a key file
+///
+/// This is how CI systems usually provide secrets, rather than as files.
+const PRIVATE_KEY_ENV: &str = "EDDO_PRIVATE_KEY";
+
+/// The environment variable overriding where named keys are stored
+const CONFIG_DIR_ENV: &str = "EDDO_CONFIG_DIR";
+
+/// The directory holding our configuration, following the XDG base directory spec
+fn config_dir() -> AppResult<PathBuf> {
+    let non_empty = |var| std::env::var_os(var).filter(|value| !value.is_empty());
+    if let Some(dir) = non_empty(CONFIG_DIR_ENV) {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = non_empty("XDG_CONFIG_HOME") {
+        return Ok(Path::new(&dir).join("eddo"));
+    }
+    if let Some(dir) = non_empty("HOME") {
+        return Ok(Path::new(&dir).join(".config").join("eddo"));
+    }
+    if let Some(dir) = non_empty("APPDATA") {
+        return Ok(Path::new(&dir).join("eddo"));
+    }
+    Err(AppError::ParseError(format!(
+        "no configuration directory, set {}",
+        CONFIG_DIR_ENV
+    )))
+}
+
+/// Use the keys pinned for a source, or else fetch them, and pin them for next time
+///
+/// Pinning means that if the source is later compromised, it can't substitute its own key.
+fn pinned_keys(
+    source: &str,
+    fetch: impl FnOnce() -> AppResult<Vec<PublicKey>>,
+) -> AppResult<Vec<PublicKey>> {
+    let pins_path = config_dir()?.join("pins");
+    let contents = match fs::read_to_string(&pins_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    // Each line is a source, and then one of its keys, so a source can have several
+    let pinned = contents
+        .lines()
+        .filter_map(|line| line.rsplit_once(' '))
+        .filter(|(pinned_source, _)| *pinned_source == source)
+        .map(|(_, public)| decode_public_key(public))
+        .collect::<AppResult<Vec<_>>>()?;
+    if !pinned.is_empty() {
+        return Ok(pinned);
+    }
+    let keys = fetch()?;
+    if keys.is_empty() {
+        return Err(AppError::ParseError(format!("no public key at {}", source)));
+    }
+    if let Some(dir) = pins_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut pins = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&pins_path)?;
+    for public in &keys {
+        writeln!(pins, "{} {}", source, format_public_key(*public))?;
+        eprintln!("Pinned {} from {}", fingerprint(public), source);
+    }
+    Ok(keys)
+}
+
+/// The most we'll download when fetching a public key
+const MAX_FETCH_SIZE: usize = 64 * 1024;
+
+/// Fetch the contents of an https URL, using curl
+fn fetch_url(url: &str) -> AppResult<String> {
+    if !url.starts_with("https://") {
+        return Err(AppError::ParseError(
+            "public keys can only be fetched over https".into(),
+        ));
+    }
+    let output = std::process::Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        // Redirects have to stay on https too
+        .args(["--proto", "=https", "--proto-redir", "=https"])
+        .args(["--max-filesize", &MAX_FETCH_SIZE.to_string()])
+        .arg("--")
+        .arg(url)
+        .output()
+        .map_err(|e| AppError::ParseError(format!("failed to run curl: {}", e)))?;
+    if !output.status.success() {
+        return Err(AppError::ParseError(format!(
+            "failed to fetch {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| AppError::ParseError(format!("{} isn't valid UTF-8", url)))
+}
+
+/// What a TXT record holding a public key starts with
+const DNS_KEY_PREFIX: &str = "eddo-public-key=";
+
+/// Look up the public keys in the _eddo TXT records of a domain
+fn dns_public_keys(domain: &str) -> AppResult<Vec<PublicKey>> {
+    let records = lookup_txt(&format!("_eddo.{}", domain.trim_end_matches('.')))?;
+    let mut keys = Vec::new();
+    for record in records {
+        if let Some(encoded) = record.strip_prefix(DNS_KEY_PREFIX) {
+            let mut bytes = [0; 32];
+            hex::decode_to_slice(encoded.trim(), &mut bytes)?;
+            keys.push(PublicKey { bytes });
+        }
+    }
+    Ok(keys)
+}
+
+/// The first nameserver in /etc/resolv.conf
+fn dns_server() -> AppResult<std::net::SocketAddr> {
+    let resolv = fs::read_to_string("/etc/resolv.conf")?;
+    resolv
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|server| server.trim().parse::<std::net::IpAddr>().ok())
+        .map(|ip| std::net::SocketAddr::new(ip, 53))
+        .next()
+        .ok_or_else(|| AppError::ParseError("no nameserver in /etc/resolv.conf".into()))
+}
+
+const DNS_TYPE_TXT: u16 = 16;
+const DNS_CLASS_IN: u16 = 1;
+
+/// Skip over a possibly compressed name in a DNS message, returning where it ends
+fn skip_dns_name(message: &[u8], mut at: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(at)? as usize;
+        match len {
+            0 => return Some(at + 1),
+            // A pointer ends the name, wherever it points
+            _ if len & 0xc0 == 0xc0 => return Some(at + 2),
+            _ => at += 1 + len,
+        }
+    }
+}
+
+/// Build a query for the TXT records of a name, with a given ID
+fn dns_query(id: [u8; 2], name: &str) -> AppResult<Vec<u8>> {
+    // The header asks for recursion, with one question
+    let mut query = vec![id[0], id[1], 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(AppError::ParseError(format!("invalid domain: {}", name)));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&DNS_TYPE_TXT.to_be_bytes());
+    query.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Parse the TXT records out of the response to a query
+///
+/// The response has to answer our query: it needs the same ID, and to repeat our
+/// question, so that a stray, or spoofed, packet for some other lookup gets rejected.
+fn parse_txt_response(query: &[u8], response: &[u8]) -> AppResult<Vec<String>> {
+    let malformed = || AppError::ParseError("malformed DNS response".into());
+    if response.len() < 12 {
+        return Err(malformed());
+    }
+    let flags = u16::from_be_bytes([response[2], response[3]]);
+    let question = &query[12..];
+    // Names are case insensitive, and some servers randomize the case of queries
+    let answers_query = response[..2] == query[..2]
+        && flags & 0x8000 != 0
+        && response[4..6] == [0, 1]
+        && response
+            .get(12..12 + question.len())
+            .is_some_and(|q| q.eq_ignore_ascii_case(question));
+    if !answers_query {
+        return Err(AppError::ParseError(
+            "DNS response doesn't answer our query".into(),
+        ));
+    }
+    if flags & 0x0200 != 0 {
+        return Err(AppError::ParseError("DNS response was truncated".into()));
+    }
+    match flags & 0x000f {
+        0 => {}
+        3 => return Ok(Vec::new()),
+        rcode => {
+            return Err(AppError::ParseError(format!(
+                "DNS lookup failed, with code {}",
+                rcode
+            )))
+        }
+    }
+    let answers = u16::from_be_bytes([response[6], response[7]]);
+    let mut at = 12 + question.len();
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        at = skip_dns_name(response, at).ok_or_else(malformed)?;
+        let header = response.get(at..at + 10).ok_or_else(malformed)?;
+        let kind = u16::from_be_bytes([header[0], header[1]]);
+        let data_len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        at += 10;
+        let data = response.get(at..at + data_len).ok_or_else(malformed)?;
+        at += data_len;
+        // Answers can include CNAMEs, which we skip over, since the TXT records follow them
+        if kind != DNS_TYPE_TXT {
+            continue;
+        }
+        // The record is made of strings, each prefixed by its length, which we join
+        let mut record = Vec::new();
+        let mut rest = data;
+        while let Some((&len, tail)) = rest.split_first() {
+            let part = tail.get(..len as usize).ok_or_else(malformed)?;
+            record.extend_from_slice(part);
+            rest = &tail[len as usize..];
+        }
+        records.push(String::from_utf8_lossy(&record).into_owned());
+    }
+    Ok(records)
+}
+
+/// Look up the TXT records for a name, asking the system's nameserver over UDP
+fn lookup_txt(name: &str) -> AppResult<Vec<String>> {
+    let mut id = [0; 2];
+    OsRng.fill_bytes(&mut id);
+    let query = dns_query(id, name)?;
+    let socket = std::net::UdpSocket::bind(("0.0.0.0", 0))?;
+    socket.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+    socket.connect(dns_server()?)?;
+    socket.send(&query)?;
+    let mut response = [0; 4096];
+    let len = socket.recv(&mut response)?;
+    parse_txt_response(&query, &response[..len])
+}
+
+/// The named keys in our configuration, and which of them is the default
+///
+/// Each key has a public key file, NAME.pub, and keys which can sign also have
+/// a copy of their private key file, NAME.key.
+struct Keyring {
+    dir: PathBuf,
+}
+
+impl Keyring {
+    fn open() -> AppResult<Self> {
+        Ok(Keyring {
+            dir: config_dir()?.join("keys"),
+        })
+    }
+
+    /// Check that a name is usable as a file name, on any platform
+    fn check_name(name: &str) -> AppResult<()> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        if !valid {
+            return Err(AppError::ParseError(format!(
+                "invalid key name: {:?}",
+                name
+            )));
+        }
+        Ok(())
+    }
+
+    fn path(&self, name: &str, extension: &str) -> AppResult<PathBuf> {
+        Self::check_name(name)?;
+        Ok(self.dir.join(format!("{}.{}", name, extension)))
+    }
+
+    fn default_path(&self) -> PathBuf {
+        self.dir.join("default")
+    }
+
+    fn add(&self, name: &str, public: PublicKey, key_file: Option<&Path>) -> AppResult<()> {
+        let public_path = self.path(name, "pub")?;
+        if public_path.exists() {
+            return Err(AppError::ParseError(format!(
+                "a key named {} already exists",
+                name
+            )));
+        }
+        fs::create_dir_all(&self.dir)?;
+        if let Some(key_file) = key_file {
+            let private_path = self.path(name, "key")?;
+            write_private_file(&private_path, &fs::read(key_file)?)?;
+        }
+        fs::write(public_path, format!("{}\n", format_public_key(public)))?;
+        Ok(())
+    }
+
+    fn public_key(&self, name: &str) -> AppResult<PublicKey> {
+        let public_path = self.path(name, "pub")?;
+        if !public_path.exists() {
+            return Err(AppError::ParseError(format!("no key named {}", name)));
+        }
+        decode_public_key(fs::read_to_string(public_path)?.trim())
+    }
+
+    /// The path to the private key file for a name, or the default key
+    fn private_key_path(&self, name: Option<&str>) -> AppResult<PathBuf> {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => self.default_name()?.ok_or(AppError::ParseError(
+                "no key given, and no default key".into(),
+            ))?,
+        };
+        let private_path = self.path(&name, "key")?;
+        if !private_path.exists() {
+            return Err(AppError::ParseError(format!(
+                "no private key named {}",
+                name
+            )));
+        }
+        Ok(private_path)
+    }
+
+    fn default_name(&self) -> AppResult<Option<String>> {
+        match fs::read_to_string(self.default_path()) {
+            Ok(name) => Ok(Some(name.trim().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set_default(&self, name: &str) -> AppResult<()> {
+        self.private_key_path(Some(name))?;
+        fs::write(self.default_path(), format!("{}\n", name))?;
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> AppResult<()> {
+        let public_path = self.path(name, "pub")?;
+        if !public_path.exists() {
+            return Err(AppError::ParseError(format!("no key named {}", name)));
+        }
+        let private_path = self.path(name, "key")?;
+        if private_path.exists() {
+            fs::remove_file(private_path)?;
+        }
+        fs::remove_file(public_path)?;
+        if self.default_name()?.as_deref() == Some(name) {
+            fs::remove_file(self.default_path())?;
+        }
+        Ok(())
+    }
+
+    /// The names of every key, in sorted order
+    fn names(&self) -> AppResult<Vec<String>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut out = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("pub") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                out.push(name.to_string());
+            }
+        }
+        out.sort();
+        Ok(out)
+    }
+}
+
+/// Read the public key for a key file, from its comment, or by loading the private key
+fn key_file_public_key(key_path: &Path) -> AppResult<PublicKey> {
+    let passphrase = PassphraseSource { file: None };
+    parse_public_key(&fs::read_to_string(key_path)?, &passphrase)
+}
+
+/// Find the public key in the contents of a key file, only decrypting it if we have to
+fn parse_public_key(contents: &str, passphrase: &PassphraseSource) -> AppResult<PublicKey> {
+    with_passphrase(passphrase, |p| keyfile::parse_public_key(contents, p))
+}
+
+fn key_command(args: KeyArgs) -> AppResult<()> {
+    let keyring = Keyring::open()?;
+    match args {
+        KeyArgs::Add {
+            name,
+            key_file,
+            public,
+        } => {
+            let public = match (&key_file, public) {
+                (Some(key_file), _) => key_file_public_key(key_file)?,
+                (None, Some(public)) => decode_public_key(&public)?,
+                (None, None) => return Err(AppError::ParseError("no key given".into())),
+            };
+            keyring.add(&name, public, key_file.as_deref())?;
+            println!("Added {} {}", name, fingerprint(&public));
+        }
+        KeyArgs::List => {
+            let default = keyring.default_name()?;
+            for name in keyring.names()? {
+                let marker = if default.as_deref() == Some(&name) {
+                    "*"
+                } else {
+                    " "
+                };
+                let public = keyring.public_key(&name)?;
+                let kind = if keyring.path(&name, "key")?.exists() {
+                    "private"
+                } else {
+                    "public"
+                };
+                println!("{} {} {} {}", marker, name, fingerprint(&public), kind);
+            }
+        }
+        KeyArgs::Remove { name } => keyring.remove(&name)?,
+        KeyArgs::Default { name } => keyring.set_default(&name)?,
+        KeyArgs::Rotate {
+            key,
+            out_file,
+            encrypt,
+        } => {
+            let old = key.load()?;
+            let (_, new) = gen_keypair(&mut OsRng);
+            let encryption = if encrypt {
+                Some(PassphraseSource {
+                    file: key.passphrase_file,
+                })
+            } else {
+                None
+            };
+            let rotation = Rotation::create(&old, &new, unix_now());
+            write_key_file(&out_file, new, encryption, false, None)?;
+            println!(
+                "# Rotated from {} to {} at {}",
+                fingerprint(&rotation.old),
+                fingerprint(&rotation.new),
+                format_time(rotation.timestamp)
+            );
+            println!("{}", rotation.format());
+        }
+        KeyArgs::Public { key, format } => {
+            let public = key.public_key()?;
+            println!("{}", format.format(&public));
+        }
+    }
+    Ok(())
+}
+
+impl SigningKey {
+    /// Find the private key file to use, from a path, a key name, or the default key
+    fn path(&self) -> AppResult<PathBuf> {
+        match &self.key_file {
+            Some(key_file) => Ok(key_file.clone()),
+            None => Keyring::open()?.private_key_path(self.key_name.as_deref()),
+        }
+    }
+
+    /// Read the contents of the key file, along with where to get its passphrase
+    ///
+    /// When no key is given, $EDDO_PRIVATE_KEY comes before the default key.
+    fn read(&self) -> AppResult<(String, PassphraseSource)> {
+        let passphrase = PassphraseSource {
+            file: self.passphrase_file.clone(),
+        };
+        if self.key_file.is_none() && self.key_name.is_none() {
+            if let Ok(contents) = std::env::var(PRIVATE_KEY_ENV) {
+                return Ok((contents, passphrase));
+            }
+        }
+        Ok((fs::read_to_string(self.path()?)?, passphrase))
+    }
+
+    /// Load the private key, asking for its passphrase if it's encrypted
+    fn load(&self) -> AppResult<PrivateKey> {
+        let (contents, passphrase) = self.read()?;
+        parse_private_key(&contents, &passphrase)
+    }
+
+    /// Find the public key, without needing the passphrase if the key file records it
+    fn public_key(&self) -> AppResult<PublicKey> {
+        let (contents, passphrase) = self.read()?;
+        parse_public_key(&contents, &passphrase)
+    }
+}
+
+/// The media type of our bundles, which also identifies their version
+const BUNDLE_MEDIA_TYPE: &str = "application/vnd.eddo.bundle.v1+json";
+
+/// The digest of the signed file, as recorded in a bundle
+#[derive(Serialize, Deserialize)]
+struct BundleDigest {
+    algorithm: String,
+    digest: String,
+}
+
+/// A signature packaged as JSON, with the public key, and metadata, as made by sign --bundle
+///
+/// This is like a sigstore bundle, letting a single file be attached to a release.
+/// The public key and digest are only there for other tools: verify still needs to
+/// be told which keys to trust, and checks the signature against the file itself.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    media_type: String,
+    public_key: String,
+    signature: String,
+    /// Whether this is a signature over the digest, as made by sign --prehash
+    prehashed: bool,
+    message_digest: BundleDigest,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+impl Bundle {
+    fn new(
+        public: &PublicKey,
+        block: &SignatureBlock,
+        prehashed: bool,
+        digest: &[u8; DIGEST_SIZE],
+    ) -> Self {
+        Bundle {
+            media_type: BUNDLE_MEDIA_TYPE.to_string(),
+            public_key: format_public_key(*public),
+            signature: format_signature(block.signature),
+            prehashed,
+            message_digest: BundleDigest {
+                algorithm: "sha512".to_string(),
+                digest: hex::encode(digest),
+            },
+            timestamp: block.metadata.timestamp,
+            expires: block.metadata.expires,
+            comment: block.metadata.comment.clone(),
+        }
+    }
+
+    fn format(&self) -> String {
+        // Serializing our own struct into a string can't fail
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    fn decode(input: &str) -> AppResult<Self> {
+        let bundle: Bundle = serde_json::from_str(input)
+            .map_err(|e| AppError::ParseError(format!("malformed bundle: {}", e)))?;
+        if bundle.media_type != BUNDLE_MEDIA_TYPE {
+            return Err(AppError::ParseError(format!(
+                "unsupported bundle type: {}",
+                bundle.media_type
+            )));
+        }
+        Ok(bundle)
+    }
+
+    /// The signature inside this bundle, along with the metadata it covers
+    fn block(&self) -> AppResult<SignatureBlock> {
+        // Signatures only ever cover a single line of comment, which sign checks
+        single_line(self.comment.as_deref())?;
+        Ok(SignatureBlock {
+            signature: decode_signature(&self.signature)?,
+            metadata: Metadata {
+                timestamp: self.timestamp,
+                expires: self.expires,
+                comment: self.comment.clone(),
+            },
+        })
+    }
+}
+
+/// How the sign command should output its signature
+enum SignOutput {
+    /// Print just the signature, maybe as armor
+    Detached { armor: bool },
+    /// Print the signature prehashed, for use with --digest
+    Prehashed { armor: bool },
+    /// Print the signature as a JSON bundle, maybe prehashed
+    Bundle { prehashed: bool },
+    /// Output the file with its signature embedded, to a file, or stdout
+    Embedded(Option<PathBuf>),
+}
+
+impl SignOutput {
+    fn is_prehashed(&self) -> bool {
+        matches!(
+            self,
+            SignOutput::Prehashed { .. } | SignOutput::Bundle { prehashed: true }
+        )
+    }
+
+    /// Format a signature on its own, as this output asks for
+    fn format(
+        &self,
+        block: &SignatureBlock,
+        public: &PublicKey,
+        message: &Message,
+    ) -> AppResult<String> {
+        Ok(match self {
+            SignOutput::Detached { armor: true } | SignOutput::Prehashed { armor: true } => {
+                block.armor()
+            }
+            SignOutput::Bundle { prehashed } => {
+                Bundle::new(public, block, *prehashed, &message.digest()?).format()
+            }
+            _ => block.format(),
+        })
+    }
+}
+
+/// The file in the configuration directory where sign records each use of a key
+const AUDIT_LOG_FILE: &str = "audit.log";
+
+/// Whether sign should ask before signing each file, and where it records what it did
+struct KeyUseAudit {
+    confirm: bool,
+    log_path: PathBuf,
+}
+
+/// A file about to be signed, as shown when confirming, and written to the audit log
+struct AuditedFile {
+    /// The absolute path, if we can find it, so the log doesn't depend on where we ran
+    path: PathBuf,
+    size: u64,
+    digest: [u8; DIGEST_SIZE],
+}
+
+impl AuditedFile {
+    fn new(path: &Path, message: &Message) -> AppResult<Self> {
+        Ok(AuditedFile {
+            path: fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+            size: message.len(),
+            digest: message.digest()?,
+        })
+    }
+}
+
+impl KeyUseAudit {
+    /// Show what's about to be signed, and ask whether to go ahead, if we're confirming
+    ///
+    /// Declining is recorded in the log straight away, while signing is only recorded
+    /// once it has succeeded.
+    fn approve(&self, public: &PublicKey, file: &AuditedFile) -> AppResult<bool> {
+        if !self.confirm {
+            return Ok(true);
+        }
+        eprintln!("About to sign:");
+        eprintln!("  file:   {}", file.path.display());
+        eprintln!("  size:   {} bytes", file.size);
+        eprintln!("  sha512: {}", hex::encode(file.digest));
+        eprintln!("  key id: {}", fingerprint(public));
+        eprint!("Sign this file? [y/N] ");
+        let answer = read_line()?.trim().to_lowercase();
+        let approved = answer == "y" || answer == "yes";
+        if !approved {
+            self.record("declined", public, file)?;
+        }
+        Ok(approved)
+    }
+
+    /// Append a line to the audit log, saying what happened to a file
+    ///
+    /// Each line holds the time, the decision, the key id, the size, the digest, and
+    /// then the path, which comes last, since it can contain spaces.
+    fn record(&self, decision: &str, public: &PublicKey, file: &AuditedFile) -> AppResult<()> {
+        if let Some(dir) = self.log_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut log = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)?;
+        writeln!(
+            log,
+            "{} {} {} {} {}{} {}",
+            format_time(unix_now()),
+            decision,
+            fingerprint(public),
+            file.size,
+            DIGEST_PREFIX,
+            hex::encode(file.digest),
+            file.path.display()
+        )?;
+        Ok(())
+    }
+}
+
+/// Sign a single file, with an already expanded key
+fn sign_one(
+    signer: &Signer,
+    in_path: &Path,
+    output: &SignOutput,
+    metadata: &Metadata,
+    context: &[u8],
+    audit: Option<&KeyUseAudit>,
+) -> AppResult<()> {
+    let prehashed = output.is_prehashed();
+    let message = Message::open(in_path)?;
+    let public = signer.public_key();
+    let audited = match audit {
+        Some(audit) => {
+            let file = AuditedFile::new(in_path, &message)?;
+            if !audit.approve(&public, &file)? {
+                return Err(AppError::ParseError("declined to sign".into()));
+            }
+            Some((audit, file))
+        }
+        None => None,
+    };
+    let block = signer.sign_message(&message, metadata.clone(), prehashed, context)?;
+    if let Some((audit, file)) = &audited {
+        audit.record("signed", &public, file)?;
+    }
+    match output {
+        SignOutput::Embedded(out_path) => match out_path {
+            Some(out_path) => write_embedded(&mut File::create(out_path)?, &message, &block)?,
+            None => write_embedded(&mut io::stdout().lock(), &message, &block)?,
+        },
+        _ => println!("{}", output.format(&block, &public, &message)?),
+    }
+    Ok(())
+}
+
+/// Append an extension to a path, keeping its existing one, as in file.tar.gz.sig
+fn with_added_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut out = path.as_os_str().to_owned();
+    out.push(".");
+    out.push(extension);
+    PathBuf::from(out)
+}
+
+/// Sign several files, writing each output next to its input
+fn sign_many(
+    signer: &Signer,
+    in_paths: &[PathBuf],
+    output: &SignOutput,
+    metadata: &Metadata,
+    context: &[u8],
+    audit: Option<&KeyUseAudit>,
+) -> AppResult<()> {
+    let prehashed = output.is_prehashed();
+    let public = signer.public_key();
+    for in_path in in_paths {
+        let message = Message::open(in_path)?;
+        let audited = match audit {
+            Some(audit) => {
+                let file = AuditedFile::new(in_path, &message)?;
+                if !audit.approve(&public, &file)? {
+                    eprintln!("Skipped {}", in_path.display());
+                    continue;
+                }
+                Some((audit, file))
+            }
+            None => None,
+        };
+        let block = signer.sign_message(&message, metadata.clone(), prehashed, context)?;
+        if let Some((audit, file)) = &audited {
+            audit.record("signed", &public, file)?;
+        }
+        match output {
+            SignOutput::Embedded(_) => {
+                let out_path = with_added_extension(in_path, "signed");
+                write_embedded(&mut File::create(out_path)?, &message, &block)?;
+            }
+            _ => {
+                let extension = match output {
+                    SignOutput::Bundle { .. } => "bundle",
+                    _ => "sig",
+                };
+                let out_path = with_added_extension(in_path, extension);
+                let formatted = output.format(&block, &public, &message)?;
+                fs::write(out_path, format!("{}\n", formatted))?;
+            }
+        }
+        eprintln!("Signed {}", in_path.display());
+    }
+    Ok(())
+}
+
+fn sign(
+    signer: &Signer,
+    in_paths: &[PathBuf],
+    output: SignOutput,
+    metadata: Metadata,
+    context: &[u8],
+    audit: Option<&KeyUseAudit>,
+) -> AppResult<()> {
+    match in_paths {
+        [in_path] => sign_one(signer, in_path, &output, &metadata, context, audit),
+        _ => {
+            if let SignOutput::Embedded(Some(_)) = output {
+                return Err(AppError::ParseError(
+                    "--out can only be used with a single input file".into(),
+                ));
+            }
+            sign_many(signer, in_paths, &output, &metadata, context, audit)
+        }
+    }
+}
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const SSH_ED25519: &[u8] = b"ssh-ed25519";
+
+/// The most data we send to be signed, leaving room in OpenSSH's 256 KiB message limit
+const SSH_AGENT_MAX_DATA: usize = 256 * 1024 - 1024;
+
+/// Append a string, in the SSH wire format, prefixed by its length
+fn put_ssh_string(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// Read values in the SSH wire format, from the front of a buffer
+struct SshReader<'a>(&'a [u8]);
+
+impl<'a> SshReader<'a> {
+    fn take(&mut self, len: usize) -> AppResult<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(AppError::ParseError("truncated ssh-agent message".into()));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> AppResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> AppResult<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> AppResult<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// The key blob the SSH protocol uses for an Ed25519 public key
+fn ssh_key_blob(public: &PublicKey) -> Vec<u8> {
+    let mut blob = Vec::new();
+    put_ssh_string(&mut blob, SSH_ED25519);
+    put_ssh_string(&mut blob, &public.bytes);
+    blob
+}
+
+/// The fingerprint ssh-add -l shows, as SHA256: and then unpadded base64
+fn ssh_fingerprint(public: &PublicKey) -> String {
+    let hash = sha256::hash(&ssh_key_blob(public));
+    format!("SHA256:{}", base64_encode(&hash).trim_end_matches('='))
+}
+
+/// A connection to an ssh-agent, through its unix socket
+#[cfg(unix)]
+struct SshAgent {
+    stream: std::os::unix::net::UnixStream,
+}
+
+#[cfg(unix)]
+impl SshAgent {
+    fn connect() -> AppResult<Self> {
+        let path = std::env::var_os("SSH_AUTH_SOCK")
+            .ok_or_else(|| AppError::ParseError("SSH_AUTH_SOCK is not set".into()))?;
+        Ok(SshAgent {
+            stream: std::os::unix::net::UnixStream::connect(path)?,
+        })
+    }
+
+    /// Send a request, and read the agent's response, each framed by their length
+    fn request(&self, body: &[u8]) -> AppResult<Vec<u8>> {
+        let mut stream = &self.stream;
+        stream.write_all(&(body.len() as u32).to_be_bytes())?;
+        stream.write_all(body)?;
+        let mut len = [0; 4];
+        stream.read_exact(&mut len)?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 || len > 256 * 1024 {
+            return Err(AppError::ParseError("invalid ssh-agent response".into()));
+        }
+        let mut response = vec![0; len];
+        stream.read_exact(&mut response)?;
+        Ok(response)
+    }
+
+    /// List the Ed25519 keys the agent holds, along with their comments
+    fn identities(&self) -> AppResult<Vec<(PublicKey, String)>> {
+        let response = self.request(&[SSH_AGENTC_REQUEST_IDENTITIES])?;
+        let mut reader = SshReader(&response);
+        if reader.u8()? != SSH_AGENT_IDENTITIES_ANSWER {
+            return Err(AppError::ParseError(
+                "ssh-agent refused to list keys".into(),
+            ));
+        }
+        let mut out = Vec::new();
+        for _ in 0..reader.u32()? {
+            let mut blob = SshReader(reader.string()?);
+            let comment = String::from_utf8_lossy(reader.string()?).into_owned();
+            // Other kinds of keys can't make signatures we understand
+            if blob.string()? != SSH_ED25519 {
+                continue;
+            }
+            if let Ok(bytes) = blob.string()?.try_into() {
+                out.push((PublicKey { bytes }, comment));
+            }
+        }
+        Ok(out)
+    }
+
+    fn sign(&self, public: &PublicKey, data: &[u8]) -> AppResult<Signature> {
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_ssh_string(&mut request, &ssh_key_blob(public));
+        put_ssh_string(&mut request, data);
+        request.extend_from_slice(&0u32.to_be_bytes());
+        let response = self.request(&request)?;
+        let mut reader = SshReader(&response);
+        match reader.u8()? {
+            SSH_AGENT_SIGN_RESPONSE => {}
+            SSH_AGENT_FAILURE => return Err(AppError::FailedSignature),
+            _ => return Err(AppError::ParseError("invalid ssh-agent response".into())),
+        }
+        let mut signature = SshReader(reader.string()?);
+        if signature.string()? != SSH_ED25519 {
+            return Err(AppError::ParseError(
+                "ssh-agent made the wrong kind of signature".into(),
+            ));
+        }
+        let bytes = signature
+            .string()?
+            .try_into()
+            .map_err(|_| AppError::ParseError("invalid ssh-agent signature".into()))?;
+        Ok(Signature { bytes })
+    }
+}
+
+/// Pick the agent key to sign with, by fingerprint, or as the only Ed25519 key it has
+#[cfg(unix)]
+fn agent_signer(fingerprint_filter: Option<&str>) -> AppResult<Signer> {
+    let agent = SshAgent::connect()?;
+    let identities = agent.identities()?;
+    let matching: Vec<&(PublicKey, String)> = identities
+        .iter()
+        .filter(|(public, _)| {
+            fingerprint_filter.is_none_or(|filter| {
+                filter == fingerprint(public)
+                    || filter == format_public_key(*public)
+                    || filter == ssh_fingerprint(public)
+            })
+        })
+        .collect();
+    match matching[..] {
+        [(public, comment)] => {
+            eprintln!("Signing with {} {}", fingerprint(public), comment);
+            Ok(Signer::Agent(agent, *public))
+        }
+        [] => Err(AppError::ParseError(
+            "no matching Ed25519 key in ssh-agent".into(),
+        )),
+        _ => {
+            for (public, comment) in matching {
+                eprintln!(
+                    "{} {} {}",
+                    fingerprint(public),
+                    ssh_fingerprint(public),
+                    comment
+                );
+            }
+            Err(AppError::ParseError(
+                "several keys in ssh-agent, choose one with --key-fingerprint".into(),
+            ))
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn agent_signer(_fingerprint_filter: Option<&str>) -> AppResult<Signer> {
+    Err(AppError::ParseError(
+        "ssh-agent is only supported on unix".into(),
+    ))
+}
+
+/// Check if a file name matches a pattern, where * matches any run of characters, and ? any one
+fn wildcard_matches(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            wildcard_matches(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_matches(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => wildcard_matches(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) => p == n && wildcard_matches(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// Expand wildcards in the last component of paths, for shells that leave them alone
+///
+/// Paths which exist, or don't contain a wildcard, are left as is.
+fn expand_wildcards(paths: Vec<PathBuf>) -> AppResult<Vec<PathBuf>> {
+    let mut out = Vec::with_capacity(paths.len());
+    for path in paths {
+        let pattern = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if !path.exists() && name.contains(&['*', '?'][..]) => name,
+            _ => {
+                out.push(path);
+                continue;
+            }
+        };
+        let pattern: Vec<char> = pattern.chars().collect();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut matches = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name: Vec<char> = entry.file_name().to_string_lossy().chars().collect();
+            if entry.file_type()?.is_file() && wildcard_matches(&pattern, &name) {
+                matches.push(path.with_file_name(entry.file_name()));
+            }
+        }
+        if matches.is_empty() {
+            return Err(AppError::ParseError(format!(
+                "no files match {}",
+                path.display()
+            )));
+        }
+        matches.sort();
+        out.extend(matches);
+    }
+    Ok(out)
+}
+
+const EMBEDDED_BEGIN: &str = "\n-----BEGIN EDDO SIGNATURE-----\n";
+const EMBEDDED_END: &str = "\n-----END EDDO SIGNATURE-----\n";
+
+/// Append a signature to some content, clearsign style
+///
+/// The signature covers the content exactly, and the trailer always starts on a new line.
+fn embed_signature(content: &[u8], block: &SignatureBlock) -> Vec<u8> {
+    let mut out = content.to_vec();
+    out.extend_from_slice(embedded_trailer(block).as_bytes());
+    out
+}
+
+fn embedded_trailer(block: &SignatureBlock) -> String {
+    format!("{}{}{}", EMBEDDED_BEGIN, block.format(), EMBEDDED_END)
+}
+
+/// Like embed_signature, but writing the output as we go, copying files a chunk at a time
+fn write_embedded(
+    out: &mut impl Write,
+    message: &Message,
+    block: &SignatureBlock,
+) -> AppResult<()> {
+    message.copy_to(out)?;
+    out.write_all(embedded_trailer(block).as_bytes())?;
+    Ok(())
+}
+
+/// How far from the end of a file we look for an embedded signature
+const EMBEDDED_TAIL_SIZE: u64 = 1 << 16;
+
+/// Find the signature embedded at the end of a file, returning the length of its content
+///
+/// Only the end of the file is read, so this works for files of any size.
+fn split_embedded_file(path: &Path) -> AppResult<(u64, SignatureBlock)> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let tail_start = len.saturating_sub(EMBEDDED_TAIL_SIZE);
+    file.seek(SeekFrom::Start(tail_start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let (content, block) = split_embedded(&tail)?;
+    Ok((tail_start + content.len() as u64, block))
+}
+
+/// Split a file produced by embed_signature into its content, and signature
+fn split_embedded(data: &[u8]) -> AppResult<(&[u8], SignatureBlock)> {
+    let begin = EMBEDDED_BEGIN.as_bytes();
+    let start = data
+        .windows(begin.len())
+        .rposition(|window| window == begin)
+        .ok_or(AppError::ParseError("no embedded signature".into()))?;
+    let trailer = std::str::from_utf8(&data[start + begin.len()..])
+        .map_err(|_| AppError::ParseError("malformed embedded signature".into()))?;
+    let encoded = trailer
+        .strip_suffix(EMBEDDED_END.trim_start_matches('\n'))
+        .and_then(|t| t.strip_suffix('\n'))
+        .ok_or(AppError::ParseError("malformed embedded signature".into()))?;
+    Ok((&data[..start], SignatureBlock::decode(encoded)?))
+}
+
+/// Render a template, replacing each `{field}` with its value
+///
+/// Literal braces can be written as `{{` and `}}`.
+fn render_template(template: &str, fields: &[(&str, &str)]) -> AppResult<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(AppError::ParseError("unclosed template field".into())),
+                    }
+                }
+                let value = fields
+                    .iter()
+                    .find(|(field, _)| *field == name)
+                    .ok_or_else(|| {
+                        AppError::ParseError(format!("unknown template field: {}", name))
+                    })?
+                    .1;
+                out.push_str(value);
+            }
+            '}' => return Err(AppError::ParseError("unmatched } in template".into())),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// A short identifier for a public key, made of its first 8 bytes in hex
+fn fingerprint(public: &PublicKey) -> String {
+    keyfile::key_id(public)
+}
+
+const DIGEST_PREFIX: &str = "sha512:";
+
+/// What a signature should be checked against
+enum VerifyInput {
+    /// The contents of a file, for normal signatures
+    File(PathBuf),
+    /// The content of a file with its signature embedded, and where it came from
+    Embedded(Message, PathBuf),
+    /// The SHA-512 digest of some data, for prehashed signatures
+    Digest([u8; DIGEST_SIZE]),
+    /// A file to hash, checking its digest against prehashed signatures
+    PrehashedFile(PathBuf),
+}
+
+/// The result of verifying a signature
+struct VerifyOutcome {
+    fingerprint: String,
+    valid: bool,
+    file: String,
+}
+
+impl VerifyOutcome {
+    fn status(&self) -> &'static str {
+        if self.valid {
+            "ok"
+        } else {
+            "failed"
+        }
+    }
+}
+
+/// What happened when verifying a single file, as part of verify --recursive
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileStatus {
+    Passed,
+    Failed,
+    Expired,
+    /// There was no .sig file for this file
+    Missing,
+}
+
+impl FileStatus {
+    const ALL: [FileStatus; 4] = [
+        FileStatus::Passed,
+        FileStatus::Failed,
+        FileStatus::Expired,
+        FileStatus::Missing,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            FileStatus::Passed => "passed",
+            FileStatus::Failed => "failed",
+            FileStatus::Expired => "expired",
+            FileStatus::Missing => "missing",
+        }
+    }
+}
+
+/// Verify a single file against the signature next to it
+fn verify_file_status(
+    publics: &[TrustedSigner],
+    threshold: usize,
+    now: Option<u64>,
+    context: &[u8],
+    path: &Path,
+) -> FileStatus {
+    let sig_path = with_added_extension(path, "sig");
+    if !sig_path.exists() {
+        return FileStatus::Missing;
+    }
+    let read = || -> AppResult<(Message, SignatureBlock)> {
+        let block = SignatureBlock::decode(&fs::read_to_string(&sig_path)?)?;
+        Ok((Message::open(path)?, block))
+    };
+    // A signature we can't even read is as good as an invalid one
+    let (message, block) = match read() {
+        Ok(read) => read,
+        Err(_) => return FileStatus::Failed,
+    };
+    let signatures = [block];
+    let check = |public: &PublicKey, block: &SignatureBlock| {
+        block.verify_message(public, &message, context)
+    };
+    let signed_by = live_signers(publics, &signatures, now, check);
+    if !signed_by.is_empty() && signed_by.len() >= threshold {
+        FileStatus::Passed
+    } else if expired_at(publics, &signatures, threshold, check).is_some() {
+        FileStatus::Expired
+    } else {
+        FileStatus::Failed
+    }
+}
+
+/// Verify every file under a directory, against its .sig file, printing a summary table
+fn verify_recursive(
+    publics: &[TrustedSigner],
+    threshold: usize,
+    now: Option<u64>,
+    context: &[u8],
+    dir: &Path,
+) -> AppResult<()> {
+    use rayon::prelude::*;
+
+    let files: Vec<(String, PathBuf)> = list_files(dir)?
+        .into_iter()
+        .filter(|(relative, _)| !relative.ends_with(".sig"))
+        .collect();
+    // Each file is independent, so they can all be checked in parallel
+    let statuses: Vec<FileStatus> = files
+        .par_iter()
+        .map(|(_, path)| verify_file_status(publics, threshold, now, context, path))
+        .collect();
+    println!("{:<8} FILE", "STATUS");
+    for ((relative, _), status) in files.iter().zip(statuses.iter()) {
+        println!("{:<8} {}", status.name(), relative);
+    }
+    let counts: Vec<String> = FileStatus::ALL
+        .iter()
+        .map(|&kind| {
+            let count = statuses.iter().filter(|&&status| status == kind).count();
+            format!("{} {}", count, kind.name())
+        })
+        .collect();
+    println!("{}", counts.join(", "));
+    if statuses.iter().any(|&status| status != FileStatus::Passed) {
+        return Err(AppError::FailedSignature);
+    }
+    Ok(())
+}
+
+/// Read the public keys in a keyring file, skipping empty lines and comments
+///
+/// Key rotations are read as well, and checked, but not yet followed.
+fn read_keyring(path: &Path) -> AppResult<(Vec<PublicKey>, Vec<Rotation>)> {
+    parse_keyring(&fs::read_to_string(path)?)
+}
+
+/// Parse the contents of a keyring file, as read_keyring does
+fn parse_keyring(contents: &str) -> AppResult<(Vec<PublicKey>, Vec<Rotation>)> {
+    let mut keys = Vec::new();
+    let mut rotations = Vec::new();
+    let mut lines = contents.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Armor spans several lines, up to its END marker
+        let mut entry = line.to_string();
+        if armor_label(line).is_some() {
+            for line in lines.by_ref() {
+                entry.push('\n');
+                entry.push_str(line);
+                if line.starts_with("-----END ") {
+                    break;
+                }
+            }
+        }
+        if entry.starts_with(ROTATION_PREFIX) || armor_label(&entry) == Some(ROTATION_ARMOR) {
+            rotations.push(Rotation::decode(&entry)?);
+        } else {
+            keys.push(decode_public_key(&entry)?);
+        }
+    }
+    Ok((keys, rotations))
+}
+
+/// Someone allowed to sign, along with every key they've rotated through
+///
+/// A signature by any of these keys counts as a signature by them.
+#[derive(Debug, Clone)]
+struct TrustedSigner {
+    keys: Vec<PublicKey>,
+}
+
+/// Group allowed keys into signers, following rotations away from the keys we trust
+///
+/// A rotation starting from a key we don't trust is ignored, and rotations can be
+/// chained, going from one key, to another, and then to a third.
+fn follow_rotations(publics: Vec<PublicKey>, rotations: &[Rotation]) -> Vec<TrustedSigner> {
+    let mut signers: Vec<TrustedSigner> = Vec::new();
+    for public in publics {
+        if !signers.iter().any(|signer| signer.keys.contains(&public)) {
+            signers.push(TrustedSigner { keys: vec![public] });
+        }
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for rotation in rotations {
+            let from = match signers
+                .iter()
+                .position(|signer| signer.keys.contains(&rotation.old))
+            {
+                Some(from) => from,
+                None => continue,
+            };
+            if signers[from].keys.contains(&rotation.new) {
+                continue;
+            }
+            // If the new key was also listed on its own, it's the same signer
+            match signers
+                .iter()
+                .position(|signer| signer.keys.contains(&rotation.new))
+            {
+                Some(to) => {
+                    let merged = signers.remove(to);
+                    let from = if to < from { from - 1 } else { from };
+                    signers[from].keys.extend(merged.keys);
+                }
+                None => signers[from].keys.push(rotation.new),
+            }
+            changed = true;
+        }
+    }
+    signers
+}
+
+/// Find the distinct keys which made at least one of the signatures, and which signature that was
+fn signers<'a>(
+    publics: &[TrustedSigner],
+    signatures: &'a [SignatureBlock],
+    check: impl Fn(&PublicKey, &SignatureBlock) -> bool,
+) -> Vec<(PublicKey, &'a SignatureBlock)> {
+    let mut out: Vec<(PublicKey, &SignatureBlock)> = Vec::new();
+    for signer in publics {
+        let found = signer.keys.iter().find_map(|public| {
+            signatures
+                .iter()
+                .find(|block| check(public, block))
+                .map(|block| (*public, block))
+        });
+        if let Some(found) = found {
+            out.push(found);
+        }
+    }
+    out
+}
+
+/// Like signers, but skipping signatures which have expired at `now`, unless it's `None`
+fn live_signers<'a>(
+    publics: &[TrustedSigner],
+    signatures: &'a [SignatureBlock],
+    now: Option<u64>,
+    check: impl Fn(&PublicKey, &SignatureBlock) -> bool,
+) -> Vec<(PublicKey, &'a SignatureBlock)> {
+    signers(publics, signatures, |public, block| {
+        let live = now.is_none_or(|now| !block.metadata.is_expired(now));
+        live && check(public, block)
+    })
+}
+
+/// When enough signatures would be valid, if expiry were ignored, return when they expired
+///
+/// This lets us point out that signatures have expired, rather than just failing.
+fn expired_at(
+    publics: &[TrustedSigner],
+    signatures: &[SignatureBlock],
+    threshold: usize,
+    check: impl Fn(&PublicKey, &SignatureBlock) -> bool,
+) -> Option<u64> {
+    let signed_by = signers(publics, signatures, check);
+    if signed_by.is_empty() || signed_by.len() < threshold {
+        return None;
+    }
+    signed_by
+        .iter()
+        .filter_map(|(_, block)| block.metadata.expires)
+        .min()
+}
+
+/// Check signatures against a set of allowed keys
+///
+/// This succeeds if at least `threshold` distinct keys made one of the signatures.
+/// Signatures which have expired at `now` don't count, unless it's `None`.
+fn verify(
+    publics: &[TrustedSigner],
+    signatures: &[SignatureBlock],
+    threshold: usize,
+    now: Option<u64>,
+    input: &VerifyInput,
+    context: &[u8],
+    template: Option<&str>,
+) -> AppResult<()> {
+    type Check<'a> = Box<dyn Fn(&PublicKey, &SignatureBlock) -> bool + 'a>;
+    let (check, file): (Check, String) = match input {
+        VerifyInput::File(in_path) => {
+            let message = Message::open(in_path)?;
+            (
+                Box::new(move |public, block| block.verify_message(public, &message, context)),
+                in_path.display().to_string(),
+            )
+        }
+        VerifyInput::Embedded(message, in_path) => (
+            Box::new(move |public, block| block.verify_message(public, message, context)),
+            in_path.display().to_string(),
+        ),
+        VerifyInput::Digest(digest) => (
+            Box::new(move |public, block| block.verify_prehashed(public, digest, context)),
+            format!("{}{}", DIGEST_PREFIX, hex::encode(digest)),
+        ),
+        VerifyInput::PrehashedFile(in_path) => {
+            let digest = Message::open(in_path)?.digest()?;
+            (
+                Box::new(move |public, block| block.verify_prehashed(public, &digest, context)),
+                in_path.display().to_string(),
+            )
+        }
+    };
+    let signed_by = live_signers(publics, signatures, now, &check);
+    let valid = !signed_by.is_empty() && signed_by.len() >= threshold;
+    // When nothing matched, we list every key we tried instead
+    let fingerprints: Vec<String> = if signed_by.is_empty() {
+        publics
+            .iter()
+            .flat_map(|signer| signer.keys.iter())
+            .map(fingerprint)
+            .collect()
+    } else {
+        signed_by
+            .iter()
+            .map(|(public, _)| fingerprint(public))
+            .collect()
+    };
+    // Comments are only trustworthy once the signatures have been checked
+    let comments: Vec<&str> = if valid {
+        signed_by
+            .iter()
+            .filter_map(|(_, block)| block.metadata.comment.as_deref())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let outcome = VerifyOutcome {
+        fingerprint: fingerprints.join(","),
+        valid,
+        file,
+    };
+    if let Some(template) = template {
+        let fields = [
+            ("fingerprint", outcome.fingerprint.as_str()),
+            ("status", outcome.status()),
+            ("file", outcome.file.as_str()),
+            ("comment", &comments.join("\n")),
+        ];
+        println!("{}", render_template(template, &fields)?);
+    }
+    if !outcome.valid {
+        if let Some(expired) = expired_at(publics, signatures, threshold, &check) {
+            return Err(AppError::ExpiredSignature(format_time(expired)));
+        }
+        return Err(AppError::FailedSignature);
+    }
+    if template.is_none() {
+        println!("Ok!");
+        for (_, block) in &signed_by {
+            if let Some(timestamp) = block.metadata.timestamp {
+                println!("Signed at: {}", format_time(timestamp));
+            }
+        }
+        for comment in comments {
+            println!("Trusted comment: {}", comment);
+        }
+    }
+    Ok(())
+}
+
+const GIT_SIGNATURE_BEGIN: &str = "-----BEGIN SIGNED MESSAGE-----";
+const GIT_SIGNATURE_END: &str = "-----END SIGNED MESSAGE-----";
+
+/// Where gpg style status lines go, which git reads to learn how things went
+fn git_status_writer(fd: i32) -> AppResult<Box<dyn Write>> {
+    match fd {
+        1 => Ok(Box::new(io::stdout())),
+        2 => Ok(Box::new(io::stderr())),
+        _ => Err(AppError::ParseError(format!(
+            "unsupported status file descriptor: {}",
+            fd
+        ))),
+    }
+}
+
+/// Act like gpg, as git calls it, either signing, or verifying a signature
+fn git_sign(args: GitSignArgs) -> AppResult<()> {
+    let mut status = git_status_writer(args.status_fd)?;
+    // Git sends the data on stdin, so it needs to be read before any passphrase prompt
+    let mut data = Vec::new();
+    io::stdin().read_to_end(&mut data)?;
+    match args.verify {
+        Some(signature_path) => git_verify(&data, &signature_path, &mut status),
+        None => {
+            let keyring = Keyring::open()?;
+            let key_path = match args.local_user.as_deref() {
+                Some(user) if Path::new(user).is_file() => PathBuf::from(user),
+                Some(user) if keyring.path(user, "key").is_ok_and(|path| path.exists()) => {
+                    keyring.private_key_path(Some(user))?
+                }
+                _ => keyring.private_key_path(None)?,
+            };
+            let private = load_private_key(&key_path, &PassphraseSource { file: None })?;
+            let public = private.public_key();
+            let now = unix_now();
+            let metadata = Metadata {
+                timestamp: Some(now),
+                expires: None,
+                comment: None,
+            };
+            let block = SignatureBlock {
+                signature: private.sign(&metadata.signed_message(&data)),
+                metadata,
+            };
+            println!("{}", GIT_SIGNATURE_BEGIN);
+            println!("{}", block.format());
+            println!("{}", GIT_SIGNATURE_END);
+            let key_id = hex::encode(public.bytes);
+            // Git looks for SIG_CREATED at the start of a line, and not the first one
+            writeln!(status, "[GNUPG:] KEY_CONSIDERED {} 0", key_id)?;
+            writeln!(status, "[GNUPG:] BEGIN_SIGNING H10")?;
+            writeln!(status, "[GNUPG:] SIG_CREATED D 22 10 00 {} {}", now, key_id)?;
+            Ok(())
+        }
+    }
+}
+
+/// Verify a signature made by git_sign, against every key in the keyring
+fn git_verify(data: &[u8], signature_path: &Path, status: &mut dyn Write) -> AppResult<()> {
+    let contents = fs::read_to_string(signature_path)?;
+    let inner: Vec<&str> = contents
+        .lines()
+        .skip_while(|line| line.trim() != GIT_SIGNATURE_BEGIN)
+        .skip(1)
+        .take_while(|line| line.trim() != GIT_SIGNATURE_END)
+        .collect();
+    let block = SignatureBlock::decode(&inner.join("\n"))?;
+    let keyring = Keyring::open()?;
+    writeln!(status, "[GNUPG:] NEWSIG")?;
+    for name in keyring.names()? {
+        let public = keyring.public_key(&name)?;
+        if !block.verify(&public, data) {
+            continue;
+        }
+        let key_id = hex::encode(public.bytes);
+        let timestamp = block.metadata.timestamp.unwrap_or(0);
+        if block.metadata.is_expired(unix_now()) {
+            writeln!(status, "[GNUPG:] EXPSIG {} {}", key_id, name)?;
+            eprintln!("eddo: Expired signature from {} ({})", name, key_id);
+            return Err(AppError::ExpiredSignature(format_time(
+                block.metadata.expires.unwrap_or(0),
+            )));
+        }
+        writeln!(status, "[GNUPG:] GOODSIG {} {}", key_id, name)?;
+        writeln!(
+            status,
+            "[GNUPG:] VALIDSIG {} {} {} 0 4 0 22 10 00 {}",
+            key_id,
+            &format_time(timestamp)[..10],
+            timestamp,
+            key_id
+        )?;
+        writeln!(status, "[GNUPG:] TRUST_FULLY 0 pgp")?;
+        if let Some(timestamp) = block.metadata.timestamp {
+            eprintln!("eddo: Signature made {}", format_time(timestamp));
+        }
+        eprintln!("eddo: Good signature from {} ({})", name, key_id);
+        return Ok(());
+    }
+    writeln!(status, "[GNUPG:] ERRSIG - 22 10 00 0 9")?;
+    eprintln!("eddo: No key in the keyring made this signature");
+    Err(AppError::FailedSignature)
+}
+
+/// Print the digest of each file, in the format of sha512sum
+fn hash(algorithm: HashAlgorithm, in_paths: &[PathBuf]) -> AppResult<()> {
+    for in_path in in_paths {
+        let digest = if in_path == Path::new("-") {
+            algorithm.hash_reader(io::stdin().lock())?
+        } else {
+            algorithm.hash_reader(File::open(in_path)?)?
+        };
+        println!("{}  {}", hex::encode(digest), in_path.display());
+    }
+    Ok(())
+}
+
+/// Hash a file with SHA-512, a piece at a time, returning its size and digest
+fn hash_file_contents(path: &Path) -> AppResult<(u64, [u8; DIGEST_SIZE])> {
+    let mut writer = Sha512Writer::new(io::sink());
+    let size = io::copy(&mut File::open(path)?, &mut writer)?;
+    let (_, digest) = writer.finalize();
+    Ok((size, digest))
+}
+
+/// Format a line of a checksum file, as sha512sum does, in text mode
+fn format_checksum_line(digest: &[u8; DIGEST_SIZE], path: &str) -> String {
+    format!("{}  {}", hex::encode(digest), path)
+}
+
+/// Parse a line of a checksum file, in either text or binary mode
+fn parse_checksum_line(line: &str) -> AppResult<([u8; DIGEST_SIZE], &str)> {
+    let malformed = || AppError::ParseError(format!("malformed checksum line: {}", line));
+    let hex_len = 2 * DIGEST_SIZE;
+    if line.len() < hex_len + 3 || !line.is_char_boundary(hex_len) {
+        return Err(malformed());
+    }
+    let (encoded, rest) = line.split_at(hex_len);
+    let path = rest
+        .strip_prefix("  ")
+        .or_else(|| rest.strip_prefix(" *"))
+        .ok_or_else(malformed)?;
+    let mut digest = [0; DIGEST_SIZE];
+    hex::decode_to_slice(encoded, &mut digest)?;
+    Ok((digest, path))
+}
+
+fn checksum(
+    in_paths: &[PathBuf],
+    out_path: Option<&Path>,
+    key: Option<&SigningKey>,
+) -> AppResult<()> {
+    // Load the key first, so that a wrong passphrase doesn't waste time hashing
+    let private = key.map(SigningKey::load).transpose()?;
+    let mut out = String::new();
+    for in_path in in_paths {
+        let path = in_path
+            .to_str()
+            .ok_or_else(|| AppError::ParseError(format!("path is not UTF-8: {:?}", in_path)))?;
+        // sha512sum escapes these, which we don't bother with
+        if path.contains(&['\\', '\n', '\r'][..]) {
+            return Err(AppError::ParseError(format!(
+                "unsupported character in path: {:?}",
+                path
+            )));
+        }
+        let (_, digest) = hash_file_contents(in_path)?;
+        out.push_str(&format_checksum_line(&digest, path));
+        out.push('\n');
+    }
+    match out_path {
+        None => io::stdout().write_all(out.as_bytes())?,
+        Some(out_path) => {
+            fs::write(out_path, &out)?;
+            if let Some(private) = private {
+                let block = SignatureBlock {
+                    signature: private.sign(out.as_bytes()),
+                    metadata: Metadata::default(),
+                };
+                let sig_path = with_added_extension(out_path, "sig");
+                fs::write(sig_path, format!("{}\n", block.format()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check the signature on a checksum file, and then each of the files it lists
+fn verify_checksums(
+    publics: &[TrustedSigner],
+    threshold: usize,
+    now: Option<u64>,
+    checksums_path: &Path,
+    signature: Option<&str>,
+    ignore_missing: bool,
+) -> AppResult<()> {
+    let block = match signature {
+        Some(signature) => SignatureBlock::decode(signature)?,
+        None => SignatureBlock::decode(&fs::read_to_string(with_added_extension(
+            checksums_path,
+            "sig",
+        ))?)?,
+    };
+    let contents = fs::read_to_string(checksums_path)?;
+    let signatures = [block];
+    let check =
+        |public: &PublicKey, block: &SignatureBlock| block.verify(public, contents.as_bytes());
+    let signed_by = live_signers(publics, &signatures, now, check);
+    if signed_by.is_empty() || signed_by.len() < threshold {
+        if let Some(expired) = expired_at(publics, &signatures, threshold, check) {
+            return Err(AppError::ExpiredSignature(format_time(expired)));
+        }
+        return Err(AppError::FailedSignature);
+    }
+
+    // Only now that the signature is valid do the checksums mean anything
+    let base = checksums_path.parent().unwrap_or_else(|| Path::new(""));
+    let mut failures = 0;
+    for line in contents.lines().filter(|line| !line.is_empty()) {
+        let (expected, path) = parse_checksum_line(line)?;
+        let full_path = base.join(path);
+        if !full_path.exists() {
+            if !ignore_missing {
+                println!("{}: MISSING", path);
+                failures += 1;
+            }
+            continue;
+        }
+        let (_, digest) = hash_file_contents(&full_path)?;
+        if digest == expected {
+            println!("{}: OK", path);
+        } else {
+            println!("{}: FAILED", path);
+            failures += 1;
+        }
+    }
+    if failures > 0 {
+        return Err(AppError::ParseError(format!(
+            "{} file(s) don't match the checksums",
+            failures
+        )));
+    }
+    Ok(())
+}
+
+/// A line of the list given to verify-batch, once its files have been read
+struct BatchEntry {
+    line: usize,
+    file: String,
+    public: PublicKey,
+    block: SignatureBlock,
+    /// The file that was signed, which we read in chunks when checking the batch
+    message: Message,
+}
+
+/// Parse a line of the form <file> <signature file> <public key>, reading the files it names
+///
+/// The public key can be given directly, or as a file containing a single key.
+fn batch_entry(line_number: usize, line: &str) -> AppResult<BatchEntry> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (file, sig_path, public) = match fields[..] {
+        [file, sig_path, public] => (file, sig_path, public),
+        _ => {
+            return Err(AppError::ParseError(
+                "expected <file> <signature> <public key>".into(),
+            ))
+        }
+    };
+    let block = batch_signature(&fs::read_to_string(sig_path)?)?;
+    let public = match decode_public_key(public) {
+        Ok(public) => public,
+        Err(_) => match &parse_keyring(&fs::read_to_string(public)?)?.0[..] {
+            [public] => *public,
+            _ => return Err(AppError::ParseError("expected a single public key".into())),
+        },
+    };
+    Ok(BatchEntry {
+        line: line_number,
+        file: file.to_string(),
+        public,
+        block,
+        message: Message::open(Path::new(file))?,
+    })
+}
+
+/// Decode a signature for a batch, which has to be over the file itself
+///
+/// Batches only check plain Ed25519 signatures, so a bundle of a prehashed
+/// signature gets rejected here, rather than reported as an invalid signature.
+fn batch_signature(input: &str) -> AppResult<SignatureBlock> {
+    if !input.trim_start().starts_with('{') {
+        return SignatureBlock::decode(input);
+    }
+    let bundle = Bundle::decode(input)?;
+    if bundle.prehashed {
+        return Err(AppError::ParseError(
+            "prehashed signatures can't be checked in a batch, use verify --prehashed".into(),
+        ));
+    }
+    bundle.block()
+}
+
+/// Find the entries with invalid signatures, by splitting failing batches in half
+///
+/// Checking a batch is much cheaper than checking each of its signatures, so when
+/// only a few signatures are invalid, this is much faster than checking them all.
+///
+/// A file we can't read fails its batch, so it ends up reported like an invalid signature.
+fn failing_entries(entries: &[BatchEntry]) -> Vec<&BatchEntry> {
+    let signatures: Vec<Signature> = entries.iter().map(|entry| entry.block.signature).collect();
+    let publics: Vec<PublicKey> = entries.iter().map(|entry| entry.public).collect();
+    let valid = entries
+        .iter()
+        .map(|entry| entry.block.metadata.open_message(&entry.message))
+        .collect::<io::Result<Vec<_>>>()
+        .and_then(|mut readers| {
+            verify_batch_chunked(&mut OsRng, &mut readers, &signatures, &publics, CHUNK_SIZE)
+        })
+        .unwrap_or(false);
+    if valid {
+        return Vec::new();
+    }
+    if entries.len() == 1 {
+        return vec![&entries[0]];
+    }
+    let (left, right) = entries.split_at(entries.len() / 2);
+    let mut out = failing_entries(left);
+    out.extend(failing_entries(right));
+    out
+}
+
+/// Verify a list of files, signatures, and public keys, all at once
+///
+/// Every entry that can't be read, is invalid, or has expired gets reported.
+fn verify_batch_list(list_path: &Path, now: Option<u64>) -> AppResult<()> {
+    let list: Box<dyn BufRead> = if list_path == Path::new("-") {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(list_path)?))
+    };
+    let mut entries = Vec::new();
+    // Each problem, along with the line it's on, so they can be reported in order
+    let mut problems = Vec::new();
+    let mut total = 0;
+    for (i, maybe_line) in list.lines().enumerate() {
+        let line = maybe_line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        total += 1;
+        match batch_entry(i + 1, line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => problems.push((i + 1, format!("malformed: {:?}", e))),
+        }
+    }
+    let failing = failing_entries(&entries);
+    for entry in &entries {
+        if failing.iter().any(|failed| failed.line == entry.line) {
+            problems.push((entry.line, format!("{}: FAILED", entry.file)));
+        } else if now.is_some_and(|now| entry.block.metadata.is_expired(now)) {
+            problems.push((entry.line, format!("{}: EXPIRED", entry.file)));
+        }
+    }
+    if !problems.is_empty() {
+        problems.sort();
+        for (line, problem) in &problems {
+            println!("{}: {}", line, problem);
+        }
+        return Err(AppError::ParseError(format!(
+            "{} of {} signature(s) didn't verify",
+            problems.len(),
+            total
+        )));
+    }
+    println!("Ok! {} signature(s) verified", entries.len());
+    Ok(())
+}
+
+/// The first line of every manifest, identifying its format
+const MANIFEST_HEADER: &str = "eddo manifest v1";
+
+/// A single file in a manifest
+#[derive(Debug, PartialEq)]
+struct ManifestEntry {
+    /// The path, relative to the root directory, with / as the separator
+    path: String,
+    size: u64,
+    digest: [u8; DIGEST_SIZE],
+}
+
+impl ManifestEntry {
+    /// Read and hash a file, recording it under a given relative path
+    fn hash_file(path: String, full_path: &Path) -> AppResult<Self> {
+        let (size, digest) = hash_file_contents(full_path)?;
+        Ok(ManifestEntry { path, size, digest })
+    }
+
+    /// Format this entry as a single line, with the path last, since it may contain spaces
+    fn format(&self) -> String {
+        format!(
+            "{}{} {} {}",
+            DIGEST_PREFIX,
+            hex::encode(self.digest),
+            self.size,
+            self.path
+        )
+    }
+
+    fn parse(line: &str) -> AppResult<Self> {
+        let malformed = || AppError::ParseError(format!("malformed manifest line: {}", line));
+        let mut parts = line.splitn(3, ' ');
+        let digest = parts.next().ok_or_else(malformed)?;
+        let size = parts.next().ok_or_else(malformed)?;
+        let path = parts.next().ok_or_else(malformed)?;
+        Ok(ManifestEntry {
+            path: path.to_string(),
+            size: size.parse().map_err(|_| malformed())?,
+            digest: decode_prefixed_hex(DIGEST_PREFIX, digest)?,
+        })
+    }
+}
+
+/// List every file under a directory, as paths relative to it, in sorted order
+///
+/// Sorting by these relative paths makes the manifest canonical, so the same
+/// tree always produces the same manifest, on any platform.
+fn list_files(root: &Path) -> AppResult<Vec<(String, PathBuf)>> {
+    let mut out = Vec::new();
+    let mut pending = vec![(String::new(), root.to_path_buf())];
+    while let Some((prefix, dir)) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|name| AppError::ParseError(format!("path is not UTF-8: {:?}", name)))?;
+            if name.contains('\n') {
+                return Err(AppError::ParseError(format!(
+                    "path contains a newline: {:?}",
+                    name
+                )));
+            }
+            let relative = format!("{}{}", prefix, name);
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push((format!("{}/", relative), entry.path()));
+            } else if file_type.is_file() {
+                out.push((relative, entry.path()));
+            }
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+fn hash_tree(root: &Path) -> AppResult<Vec<ManifestEntry>> {
+    list_files(root)?
+        .into_iter()
+        .map(|(path, full_path)| ManifestEntry::hash_file(path, &full_path))
+        .collect()
+}
+
+fn format_manifest(entries: &[ManifestEntry]) -> String {
+    let mut out = String::from(MANIFEST_HEADER);
+    for entry in entries {
+        out.push('\n');
+        out.push_str(&entry.format());
+    }
+    out
+}
+
+fn parse_manifest(content: &[u8]) -> AppResult<Vec<ManifestEntry>> {
+    let content = std::str::from_utf8(content)
+        .map_err(|_| AppError::ParseError("manifest is not UTF-8".into()))?;
+    let mut lines = content.split('\n');
+    if lines.next() != Some(MANIFEST_HEADER) {
+        return Err(AppError::ParseError("unknown manifest format".into()));
+    }
+    lines.map(ManifestEntry::parse).collect()
+}
+
+fn manifest_sign(
+    private: &PrivateKey,
+    dir: &Path,
+    out_path: Option<&Path>,
+    only: Option<&str>,
+) -> AppResult<()> {
+    let mut entries = hash_tree(dir)?;
+    if let Some(only) = only {
+        let pattern: Vec<char> = only.chars().collect();
+        entries.retain(|entry| wildcard_matches(&pattern, &entry.path.chars().collect::<Vec<_>>()));
+    }
+    let manifest = format_manifest(&entries);
+    let block = SignatureBlock {
+        signature: private.sign(manifest.as_bytes()),
+        metadata: Metadata::default(),
+    };
+    let signed = embed_signature(manifest.as_bytes(), &block);
+    match out_path {
+        Some(out_path) => fs::write(out_path, signed)?,
+        None => io::stdout().write_all(&signed)?,
+    }
+    Ok(())
+}
+
+/// Read the entries of a signed manifest, along with the key which signed it
+///
+/// The signer can be the root key, or any key a delegation names.
+fn read_signed_manifest(
+    root: &PublicKey,
+    delegations: &[Delegation],
+    manifest_path: &Path,
+) -> AppResult<(PublicKey, Vec<ManifestEntry>)> {
+    let data = fs::read(manifest_path)?;
+    let (content, block) = split_embedded(&data)?;
+    let signer = std::iter::once(root)
+        .chain(delegations.iter().map(|delegation| &delegation.to))
+        .find(|public| block.verify(public, content))
+        .ok_or(AppError::FailedSignature)?;
+    Ok((*signer, parse_manifest(content)?))
+}
+
+/// Check signed manifests against a directory, printing each file that doesn't match
+///
+/// Files can be modified, missing, or extra, if they're present but not in any manifest.
+/// Files listed by a key which isn't allowed to sign for them are unauthorized, and
+/// files listed differently by two manifests are conflicting.
+fn manifest_verify(
+    root: PublicKey,
+    delegations: &[Delegation],
+    manifest_paths: &[PathBuf],
+    dir: &Path,
+) -> AppResult<()> {
+    let mut actual: HashMap<String, PathBuf> = list_files(dir)?.into_iter().collect();
+    let mut problems = 0;
+    let mut expected: Vec<ManifestEntry> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for manifest_path in manifest_paths {
+        let (signer, entries) = read_signed_manifest(&root, delegations, manifest_path)?;
+        for entry in entries {
+            if !authorized(&root, delegations, &signer, &entry.path, 0) {
+                println!("UNAUTHORIZED {} ({})", entry.path, fingerprint(&signer));
+                actual.remove(&entry.path);
+                problems += 1;
+                continue;
+            }
+            match seen.get(&entry.path) {
+                Some(&i) if expected[i] != entry => {
+                    println!("CONFLICTING {}", entry.path);
+                    problems += 1;
+                }
+                Some(_) => {}
+                None => {
+                    seen.insert(entry.path.clone(), expected.len());
+                    expected.push(entry);
+                }
+            }
+        }
+    }
+    for entry in &expected {
+        let full_path = match actual.remove(&entry.path) {
+            Some(full_path) => full_path,
+            None => {
+                println!("MISSING {}", entry.path);
+                problems += 1;
+                continue;
+            }
+        };
+        let found = ManifestEntry::hash_file(entry.path.clone(), &full_path)?;
+        if &found != entry {
+            println!("MODIFIED {}", entry.path);
+            problems += 1;
+        }
+    }
+    let mut extra: Vec<_> = actual.into_keys().collect();
+    extra.sort();
+    for path in extra {
+        println!("EXTRA {}", path);
+        problems += 1;
+    }
+    if problems > 0 {
+        return Err(AppError::ParseError(format!(
+            "{} file(s) don't match the manifest",
+            problems
+        )));
+    }
+    println!("Ok! {} files checked", expected.len());
+    Ok(())
+}
+
+/// A single record in a signature corpus, as read by the audit command.
+#[derive(Deserialize)]
+struct AuditRecord {
+    public: String,
+    message: Option<String>,
+    digest: Option<String>,
+    signature: String,
+}
+
+/// What a record in a corpus signs: a message, or the digest of one, for prehashed signatures
+enum AuditInput {
+    Message(Vec<u8>),
+    Digest([u8; DIGEST_SIZE]),
+}
+
+/// A record of a corpus, once decoded
+struct ParsedRecord {
+    public: PublicKey,
+    signature: Signature,
+    input: AuditInput,
+}
+
+/// The verification modes an audit reports on, in the order of its columns
+const AUDIT_MODES: [&str; 3] = ["verify", "strict", "zip215"];
+
+/// How many records get checked together, with a single batch verification
+const AUDIT_BATCH_SIZE: usize = 256;
+
+/// The outcome of auditing a single record.
+enum AuditStatus {
+    /// Whether each of the modes accepts the signature, or `None` if it doesn't apply
+    Checked([Option<bool>; 3]),
+    Malformed(String),
+}
+
+impl AuditStatus {
+    /// Summarize the modes: valid or invalid if they all agree, and mixed otherwise
+    fn summary(&self) -> &'static str {
+        match self {
+            AuditStatus::Malformed(_) => "malformed",
+            AuditStatus::Checked(modes) => {
+                let mut results = modes.iter().flatten();
+                let first = results.next().copied().unwrap_or(false);
+                match (results.all(|&r| r == first), first) {
+                    (true, true) => "valid",
+                    (true, false) => "invalid",
+                    (false, _) => "mixed",
+                }
+            }
+        }
+    }
+
+    /// The result of each mode, empty for modes which don't apply
+    fn modes(&self) -> [&'static str; 3] {
+        match self {
+            AuditStatus::Malformed(_) => [""; 3],
+            AuditStatus::Checked(modes) => modes.map(|mode| match mode {
+                Some(true) => "valid",
+                Some(false) => "invalid",
+                None => "",
+            }),
+        }
+    }
+}
+
+fn decode_hex<const N: usize>(input: &str) -> Option<[u8; N]> {
+    let mut bytes = [0; N];
+    hex::decode_to_slice(input, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Parse a single line, returning the public key, if one could be parsed
+fn parse_audit_record(line: &str) -> (Option<PublicKey>, Result<ParsedRecord, String>) {
+    let record: AuditRecord = match serde_json::from_str(line) {
+        Ok(record) => record,
+        Err(e) => return (None, Err(e.to_string())),
+    };
+    let public = match decode_hex(&record.public) {
+        Some(bytes) => PublicKey { bytes },
+        None => return (None, Err("bad public key".into())),
+    };
+    (Some(public), parse_record_input(public, record))
+}
+
+fn parse_record_input(public: PublicKey, record: AuditRecord) -> Result<ParsedRecord, String> {
+    let signature = Signature {
+        bytes: decode_hex(&record.signature).ok_or("bad signature")?,
+    };
+    let input = match (record.message, record.digest) {
+        (Some(message), _) => AuditInput::Message(hex::decode(message).map_err(|_| "bad message")?),
+        (None, Some(digest)) => AuditInput::Digest(decode_hex(&digest).ok_or("bad digest")?),
+        (None, None) => return Err("no message".into()),
+    };
+    Ok(ParsedRecord {
+        public,
+        signature,
+        input,
+    })
+}
+
+/// Check records under each mode, using a batch to check the cofactored equation at once
+///
+/// The batch uses the cofactor, like verify_zip215, and only accepts canonical encodings,
+/// which verify_zip215 also accepts, so a valid batch means every record passes
+/// verify_zip215. Otherwise, we fall back to checking each record, to find the failures.
+/// Prehashed signatures only have the one mode, so the others don't apply to them.
+fn audit_records(records: &[Result<ParsedRecord, String>]) -> Vec<AuditStatus> {
+    let plain: Vec<(&ParsedRecord, &[u8])> = records
+        .iter()
+        .flatten()
+        .filter_map(|record| match &record.input {
+            AuditInput::Message(message) => Some((record, &message[..])),
+            AuditInput::Digest(_) => None,
+        })
+        .collect();
+    let messages: Vec<&[u8]> = plain.iter().map(|(_, message)| *message).collect();
+    let signatures: Vec<Signature> = plain.iter().map(|(r, _)| r.signature).collect();
+    let public_keys: Vec<PublicKey> = plain.iter().map(|(r, _)| r.public).collect();
+    let batch_valid = verify_batch(&mut OsRng, &messages, &signatures, &public_keys);
+    records
+        .iter()
+        .map(|record| match record {
+            Err(why) => AuditStatus::Malformed(why.clone()),
+            Ok(record) => {
+                let (public, signature) = (&record.public, record.signature);
+                AuditStatus::Checked(match &record.input {
+                    AuditInput::Message(message) => [
+                        Some(public.verify(message, signature)),
+                        Some(public.verify_strict(message, signature)),
+                        Some(batch_valid || public.verify_zip215(message, signature)),
+                    ],
+                    AuditInput::Digest(digest) => [
+                        Some(public.verify_prehashed(digest, &[], signature)),
+                        None,
+                        None,
+                    ],
+                })
+            }
+        })
+        .collect()
+}
+
+/// Write the report for some records, given the line number of each
+fn write_audit_rows(
+    out: &mut impl Write,
+    template: Option<&str>,
+    records: Vec<(usize, Option<PublicKey>, Result<ParsedRecord, String>)>,
+) -> AppResult<()> {
+    let (lines, parsed): (Vec<_>, Vec<_>) = records
+        .into_iter()
+        .map(|(line, public, record)| ((line, public), record))
+        .unzip();
+    for ((line, public), status) in lines.into_iter().zip(audit_records(&parsed)) {
+        let modes = status.modes();
+        let detail = match &status {
+            AuditStatus::Malformed(why) => why.as_str(),
+            AuditStatus::Checked(_) => "",
+        };
+        if let Some(template) = template {
+            let line_number = line.to_string();
+            let fingerprint = public.as_ref().map(fingerprint).unwrap_or_default();
+            let mut fields = vec![
+                ("line", line_number.as_str()),
+                ("fingerprint", fingerprint.as_str()),
+                ("status", status.summary()),
+                ("detail", detail),
+            ];
+            fields.extend(AUDIT_MODES.iter().copied().zip(modes));
+            writeln!(out, "{}", render_template(template, &fields)?)?;
+            continue;
+        }
+        // The detail may contain commas, so we quote it, as per RFC 4180
+        writeln!(
+            out,
+            "{},{},{},\"{}\"",
+            line,
+            status.summary(),
+            modes.join(","),
+            detail.replace('"', "\"\"")
+        )?;
+    }
+    Ok(())
+}
+
+fn audit(corpus_path: &Path, template: Option<&str>) -> AppResult<()> {
+    let corpus = BufReader::new(File::open(corpus_path)?);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if template.is_none() {
+        writeln!(out, "line,status,{},detail", AUDIT_MODES.join(","))?;
+    }
+    let mut pending = Vec::with_capacity(AUDIT_BATCH_SIZE);
+    for (i, maybe_line) in corpus.lines().enumerate() {
+        let line = maybe_line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (public, record) = parse_audit_record(&line);
+        pending.push((i + 1, public, record));
+        if pending.len() == AUDIT_BATCH_SIZE {
+            write_audit_rows(&mut out, template, std::mem::take(&mut pending))?;
+        }
+    }
+    write_audit_rows(&mut out, template, pending)
+}
+
+/// A valid signature from a corpus, as tracked when looking for reused nonces.
+struct NonceUse {
+    line: usize,
+    signature: Signature,
+    /// The signed message, or `None` for prehashed signatures
+    message: Option<Vec<u8>>,
+    /// The bytes that were signed, to tell apart different messages
+    signed: Vec<u8>,
+}
+
+/// Parse a line of the corpus, returning its key and nonce use, if the signature is valid
+fn nonce_use(line_number: usize, line: &str) -> Option<(PublicKey, NonceUse)> {
+    let record: AuditRecord = serde_json::from_str(line).ok()?;
+    let public = PublicKey {
+        bytes: decode_hex(&record.public)?,
+    };
+    let signature = Signature {
+        bytes: decode_hex(&record.signature)?,
+    };
+    let (message, signed) = match (&record.message, &record.digest) {
+        (Some(message), _) => {
+            let message = hex::decode(message).ok()?;
+            (Some(message.clone()), message)
+        }
+        (None, Some(digest)) => (None, decode_hex::<DIGEST_SIZE>(digest)?.to_vec()),
+        (None, None) => return None,
+    };
+    let valid = match &message {
+        Some(message) => public.verify(message, signature),
+        None => public.verify_prehashed(signed[..].try_into().unwrap(), &[], signature),
+    };
+    if !valid {
+        return None;
+    }
+    let nonce_use = NonceUse {
+        line: line_number,
+        signature,
+        message,
+        signed,
+    };
+    Some((public, nonce_use))
+}
+
+fn selftest() -> AppResult<()> {
+    let mut failed = 0;
+    for check in selftest::CHECKS {
+        if check.run() {
+            println!("ok: {}", check.name);
+        } else {
+            println!("FAILED: {}", check.name);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(AppError::ParseError(format!(
+            "{} of {} check(s) failed on {}",
+            failed,
+            selftest::CHECKS.len(),
+            std::env::consts::ARCH
+        )));
+    }
+    println!("Ok! {} checks passed", selftest::CHECKS.len());
+    Ok(())
+}
+
+fn generate_vectors(count: u32) -> AppResult<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for index in 0..count {
+        writeln!(out, "{}", Rfc8032Vector::generate(index))?;
+    }
+    Ok(())
+}
+
+fn audit_nonces(corpus_path: &Path, recover: bool) -> AppResult<()> {
+    let corpus = BufReader::new(File::open(corpus_path)?);
+    // Uses are grouped by public key and R value, in the order they first appear
+    let mut groups: Vec<(PublicKey, Vec<NonceUse>)> = Vec::new();
+    let mut indices: HashMap<([u8; 32], [u8; 32]), usize> = HashMap::new();
+    for (i, maybe_line) in corpus.lines().enumerate() {
+        let line = maybe_line?;
+        let (public, nonce_use) = match nonce_use(i + 1, &line) {
+            Some(x) => x,
+            None => continue,
+        };
+        let mut r = [0; 32];
+        r.copy_from_slice(&nonce_use.signature.bytes[..32]);
+        let index = *indices.entry((public.bytes, r)).or_insert_with(|| {
+            groups.push((public, Vec::new()));
+            groups.len() - 1
+        });
+        let uses = &mut groups[index].1;
+        // Signing the same message twice with the same nonce leaks nothing
+        if uses.iter().all(|u| u.signed != nonce_use.signed) {
+            uses.push(nonce_use);
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "fingerprint,r,lines,detail")?;
+    for (public, uses) in groups.iter().filter(|(_, uses)| uses.len() > 1) {
+        let lines: Vec<String> = uses.iter().map(|u| u.line.to_string()).collect();
+        let detail = if !recover {
+            String::new()
+        } else {
+            // Prehashed signatures use a different challenge, so we only use normal ones
+            let normal: Vec<&NonceUse> = uses.iter().filter(|u| u.message.is_some()).collect();
+            match normal.as_slice() {
+                [first, second, ..] => match recover_private_scalar(
+                    &first.signature,
+                    first.message.as_ref().unwrap(),
+                    &second.signature,
+                    second.message.as_ref().unwrap(),
+                    public,
+                ) {
+                    Some(a) => format!("recovered secret scalar {}", hex::encode(a.to_bytes())),
+                    None => "recovery failed".into(),
+                },
+                _ => "recovery needs two signatures over raw messages".into(),
+            }
+        };
+        writeln!(
+            out,
+            "{},{},{},\"{}\"",
+            fingerprint(public),
+            hex::encode(&uses[0].signature.bytes[..32]),
+            lines.join(";"),
+            detail
+        )?;
+    }
+    Ok(())
+}
+
+/// Check that a comment fits on the single line it gets written to
+fn single_line(comment: Option<&str>) -> AppResult<()> {
+    if comment.is_some_and(|c| c.contains(&['\n', '\r'][..])) {
+        return Err(AppError::ParseError(
+            "comments must fit on a single line".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Entry points for the fuzz targets in fuzz/, which include this file as a module
+///
+/// These parse untrusted input the way the commands do, ignoring the results, since
+/// the targets only look for panics. Nothing here decrypts, so fuzzing never runs scrypt.
+#[cfg(fuzzing)]
+pub mod fuzz {
+    use super::*;
+
+    /// Parse a signature, a bundle, and the statements signed by keys
+    pub fn parse_signature(input: &str) {
+        if let Ok(block) = SignatureBlock::decode(input) {
+            let _ = block.armor();
+        }
+        let _ = Bundle::decode(input).and_then(|bundle| bundle.block());
+        let _ = Rotation::decode(input);
+        let _ = Delegation::decode(input);
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> AppResult<()> {
+    let mut raw_args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    // Git runs a single program, without arguments of our choosing, so we check our name instead
+    let invoked_as = raw_args
+        .first()
+        .and_then(|name| Path::new(name).file_stem())
+        .and_then(|name| name.to_str());
+    if invoked_as == Some("eddo-git-sign") {
+        raw_args.insert(1, "git-sign".into());
+    }
+    let args = Args::from_iter(raw_args);
+    match args {
+        Args::Generate {
+            out_file,
+            from_passphrase,
+            salt,
+            iterations,
+            encrypt,
+            passphrase_file,
+            mnemonic,
+            armor,
+            comment,
+        } => {
+            single_line(comment.as_deref())?;
+            let passphrase = if from_passphrase {
+                salt.map(|salt| PassphraseOptions { salt, iterations })
+            } else {
+                None
+            };
+            let encryption = if encrypt {
+                Some(PassphraseSource {
+                    file: passphrase_file,
+                })
+            } else {
+                None
+            };
+            generate(&out_file, passphrase, encryption, mnemonic, armor, comment)
+        }
+        Args::Recover {
+            out_file,
+            encrypt,
+            passphrase_file,
+        } => {
+            let encryption = if encrypt {
+                Some(PassphraseSource {
+                    file: passphrase_file,
+                })
+            } else {
+                None
+            };
+            recover(&out_file, encryption)
+        }
+        Args::Sign {
+            key,
+            ssh_agent,
+            key_fingerprint,
+            prehash,
+            context,
+            embed,
+            armor,
+            bundle,
+            out_file,
+            comment,
+            timestamp,
+            expires,
+            confirm,
+            audit_log,
+            in_files,
+        } => {
+            let output = match (prehash, embed, bundle) {
+                (prehashed, _, true) => SignOutput::Bundle { prehashed },
+                (true, _, _) => SignOutput::Prehashed { armor },
+                (_, true, _) => SignOutput::Embedded(out_file),
+                _ => SignOutput::Detached { armor },
+            };
+            single_line(comment.as_deref())?;
+            let now = unix_now();
+            let metadata = Metadata {
+                timestamp: if timestamp || expires.is_some() {
+                    Some(now)
+                } else {
+                    None
+                },
+                expires: expires.map(|duration| now.saturating_add(duration)),
+                comment,
+            };
+            let in_files = expand_wildcards(in_files)?;
+            let signer = if ssh_agent {
+                agent_signer(key_fingerprint.as_deref())?
+            } else {
+                // Hashing the private key happens once, no matter how many files we sign
+                Signer::Key(ExpandedPrivateKey::from_private_key(&key.load()?))
+            };
+            let context = context.unwrap_or_default();
+            let audit = match (confirm, audit_log) {
+                (false, None) => None,
+                (confirm, Some(log_path)) => Some(KeyUseAudit { confirm, log_path }),
+                (true, None) => Some(KeyUseAudit {
+                    confirm,
+                    log_path: config_dir()?.join(AUDIT_LOG_FILE),
+                }),
+            };
+            sign(
+                &signer,
+                &in_files,
+                output,
+                metadata,
+                context.as_bytes(),
+                audit.as_ref(),
+            )
+        }
+        Args::Verify {
+            allowed,
+            signature,
+            recursive,
+            embedded,
+            bundle,
+            extract,
+            digest,
+            prehashed,
+            context,
+            in_file,
+            template,
+        } => {
+            let publics = allowed.public_keys()?;
+            let threshold = allowed.threshold;
+            let now = allowed.now();
+            let context = context.unwrap_or_default();
+            let context = context.as_bytes();
+            if recursive {
+                let dir = in_file.ok_or(AppError::ParseError("no directory".into()))?;
+                return verify_recursive(&publics, threshold, now, context, &dir);
+            }
+            if embedded {
+                let in_file = in_file.ok_or(AppError::ParseError("no input file".into()))?;
+                let (message, block) = Message::open_embedded(&in_file)?;
+                let input = VerifyInput::Embedded(message, in_file);
+                verify(
+                    &publics,
+                    &[block],
+                    threshold,
+                    now,
+                    &input,
+                    context,
+                    template.as_deref(),
+                )?;
+                // We only get here if the signature was valid
+                if let (Some(extract), VerifyInput::Embedded(message, _)) = (extract, &input) {
+                    message.copy_to(&mut File::create(extract)?)?;
+                }
+                return Ok(());
+            }
+            let (signatures, prehashed) = match bundle {
+                Some(path) => {
+                    let bundle = Bundle::decode(&fs::read_to_string(path)?)?;
+                    (vec![bundle.block()?], prehashed || bundle.prehashed)
+                }
+                None => {
+                    let signatures = signature
+                        .iter()
+                        .map(|signature| SignatureBlock::decode(signature))
+                        .collect::<AppResult<Vec<_>>>()?;
+                    (signatures, prehashed)
+                }
+            };
+            let input = match (digest, in_file) {
+                (Some(digest), _) => {
+                    VerifyInput::Digest(decode_prefixed_hex(DIGEST_PREFIX, &digest)?)
+                }
+                (None, Some(in_file)) if prehashed => VerifyInput::PrehashedFile(in_file),
+                (None, Some(in_file)) => VerifyInput::File(in_file),
+                (None, None) => return Err(AppError::ParseError("no input file".into())),
+            };
+            verify(
+                &publics,
+                &signatures,
+                threshold,
+                now,
+                &input,
+                context,
+                template.as_deref(),
+            )
+        }
+        Args::Manifest(ManifestArgs::Sign {
+            key,
+            out_file,
+            only,
+            dir,
+        }) => manifest_sign(&key.load()?, &dir, out_file.as_deref(), only.as_deref()),
+        Args::Manifest(ManifestArgs::Verify {
+            public,
+            delegations,
+            delegated,
+            manifest,
+            dir,
+        }) => {
+            let mut statements = Vec::new();
+            for path in &delegations {
+                statements.extend(read_delegations(path)?);
+            }
+            let mut manifests = vec![manifest];
+            manifests.extend(delegated);
+            manifest_verify(decode_public_key(&public)?, &statements, &manifests, &dir)
+        }
+        Args::Delegate { key, to, pattern } => {
+            let delegation =
+                Delegation::create(&key.load()?, decode_public_key(&to)?, pattern, unix_now())?;
+            println!("{}", delegation.format());
+            Ok(())
+        }
+        Args::Checksum {
+            out_file,
+            sign,
+            key,
+            in_files,
+        } => {
+            let key = if sign { Some(&key) } else { None };
+            checksum(&expand_wildcards(in_files)?, out_file.as_deref(), key)
+        }
+        Args::VerifyChecksums {
+            allowed,
+            signature,
+            ignore_missing,
+            checksums,
+        } => verify_checksums(
+            &allowed.public_keys()?,
+            allowed.threshold,
+            allowed.now(),
+            &checksums,
+            signature.as_deref(),
+            ignore_missing,
+        ),
+        Args::VerifyBatch {
+            ignore_expiry,
+            list,
+        } => verify_batch_list(&list, Some(unix_now()).filter(|_| !ignore_expiry)),
+        Args::Hash {
+            algorithm,
+            in_files,
+        } => hash(algorithm, &expand_wildcards(in_files)?),
+        Args::Key(args) => key_command(args),
+        Args::GitSign(args) => git_sign(args),
+        Args::Audit { corpus, template } => audit(&corpus, template.as_deref()),
+        Args::AuditNonces { corpus, recover } => audit_nonces(&corpus, recover),
+        Args::Selftest { generate } => match generate {
+            Some(count) => generate_vectors(count),
+            None => selftest(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(byte: u8) -> PrivateKey {
+        PrivateKey { bytes: [byte; 32] }
+    }
+
+    /// Change the last hex digit of a formatted value
+    fn tamper(formatted: &str) -> String {
+        let mut out = formatted.to_string();
+        let last = if out.pop() == Some('0') { '1' } else { '0' };
+        out.push(last);
+        out
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let metadata = Metadata {
+            timestamp: Some(1000),
+            expires: Some(2000),
+            comment: Some("a comment: with a colon".into()),
+        };
+        let lines = metadata.lines();
+        assert_eq!(lines[0], "version: 1");
+        let parsed = Metadata::parse(lines.iter().map(String::as_str)).unwrap();
+        assert_eq!(parsed.lines(), lines);
+        assert!(!parsed.is_expired(1999));
+        assert!(parsed.is_expired(2000));
+        assert!(Metadata::default().lines().is_empty());
+        assert!(Metadata::parse(std::iter::empty()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_metadata_rejects_unknown_lines() {
+        for lines in [
+            &["version: 2"][..],
+            &["timestamp: 1000"],
+            &["version: 1", "timestamp: soon"],
+            &["version: 1", "untrusted comment: hello"],
+        ] {
+            assert!(Metadata::parse(lines.iter().copied()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_metadata_is_signed() {
+        let message = b"message";
+        assert_eq!(&Metadata::default().signed_message(message)[..], message);
+        let metadata = Metadata {
+            comment: Some("comment".into()),
+            ..Metadata::default()
+        };
+        let signed = metadata.signed_message(message);
+        assert!(signed.starts_with(METADATA_DOMAIN));
+        assert!(signed.ends_with(message));
+        assert_eq!(signed.len(), metadata.prefix().len() + message.len());
+
+        let mut read = Vec::new();
+        metadata
+            .open_message(&Message::Memory(message.to_vec()))
+            .unwrap()
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, &signed[..]);
+    }
+
+    #[test]
+    fn test_signature_block_formats() {
+        let private = key(1);
+        let metadata = Metadata {
+            timestamp: Some(1000),
+            expires: None,
+            comment: Some("hello".into()),
+        };
+        let block = SignatureBlock {
+            signature: private.sign(&metadata.signed_message(b"message")),
+            metadata,
+        };
+        for formatted in [block.format(), block.armor()] {
+            let decoded = SignatureBlock::decode(&formatted).unwrap();
+            assert_eq!(decoded.signature, block.signature);
+            assert_eq!(decoded.metadata.lines(), block.metadata.lines());
+            assert!(decoded.verify(&private.public_key(), b"message"));
+            assert!(!decoded.verify(&private.public_key(), b"other"));
+        }
+        // The comment is covered by the signature
+        let changed = block.format().replace("hello", "howdy");
+        let decoded = SignatureBlock::decode(&changed).unwrap();
+        assert!(!decoded.verify(&private.public_key(), b"message"));
+    }
+
+    #[test]
+    fn test_comments_fit_on_a_single_line() {
+        for comment in ["two\nlines", "two\rlines", "two\r\nlines"] {
+            assert!(single_line(Some(comment)).is_err());
+        }
+        assert!(single_line(Some("one line")).is_ok());
+        assert!(single_line(None).is_ok());
+
+        let private = key(1);
+        let block = SignatureBlock {
+            signature: private.sign(b"message"),
+            metadata: Metadata::default(),
+        };
+        let mut bundle = Bundle::new(&private.public_key(), &block, false, &[0; DIGEST_SIZE]);
+        assert!(bundle.block().is_ok());
+        bundle.comment = Some("trusted\r\ntrusted comment: forged".into());
+        assert!(bundle.block().is_err());
+    }
+
+    #[test]
+    fn test_decode_prefixed_hex_accepts_armor() {
+        let signature = key(1).sign(b"message");
+        assert_eq!(
+            decode_signature(&format_signature(signature)).unwrap(),
+            signature
+        );
+        let armored = armor(SIGNATURE_ARMOR, &signature.bytes);
+        assert_eq!(decode_signature(&armored).unwrap(), signature);
+        // The armor has to match the kind of value
+        let wrong_label = armor(ROTATION_ARMOR, &signature.bytes);
+        assert!(decode_signature(&wrong_label).is_err());
+        let wrong_size = armor(SIGNATURE_ARMOR, &signature.bytes[1..]);
+        assert!(decode_signature(&wrong_size).is_err());
+        assert_eq!(prefix_armor_label(DELEGATION_PREFIX), None);
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let expired = AppError::ExpiredSignature("2001-09-09 01:46:40 UTC".into());
+        assert_eq!(
+            expired.to_string(),
+            "signature expired at 2001-09-09 01:46:40 UTC"
+        );
+        let err = decode_signature("not a signature").unwrap_err();
+        assert!(!err.to_string().is_empty());
+        assert!(!format!("{}", AppError::from(hex::FromHexError::OddLength)).is_empty());
+    }
+
+    #[test]
+    fn test_dearmor_errors() {
+        let armored = armor(SIGNATURE_ARMOR, b"data");
+        assert_eq!(dearmor(SIGNATURE_ARMOR, &armored).unwrap(), b"data");
+        assert!(matches!(
+            dearmor(ROTATION_ARMOR, &armored),
+            Err(AppError::ParseError(e)) if e == "expected EDDO KEY ROTATION"
+        ));
+        let corrupted = armored.replace("ZGF0YQ==", "ZGF0YQ=");
+        assert!(matches!(
+            dearmor(SIGNATURE_ARMOR, &corrupted),
+            Err(AppError::ParseError(e)) if e == "invalid base64"
+        ));
+    }
+
+    #[test]
+    fn test_rotation_round_trip() {
+        let (old, new) = (key(1), key(2));
+        let rotation = Rotation::create(&old, &new, 1000);
+        let decoded = Rotation::decode(&rotation.format()).unwrap();
+        assert_eq!(decoded.old, old.public_key());
+        assert_eq!(decoded.new, new.public_key());
+        assert_eq!(decoded.timestamp, 1000);
+        let armored = armor(
+            ROTATION_ARMOR,
+            &hex::decode(rotation.format().trim_start_matches(ROTATION_PREFIX)).unwrap(),
+        );
+        assert!(Rotation::decode(&armored).is_ok());
+    }
+
+    #[test]
+    fn test_rotation_needs_both_signatures() {
+        let (old, new) = (key(1), key(2));
+        let rotation = Rotation::create(&old, &new, 1000);
+        assert!(Rotation::decode(&tamper(&rotation.format())).is_err());
+        // Without the new key, someone can't claim an old key rotated to it
+        let forged = Rotation {
+            new_signature: rotation.old_signature,
+            ..Rotation::create(&old, &new, 1000)
+        };
+        assert!(Rotation::decode(&forged.format()).is_err());
+    }
+
+    #[test]
+    fn test_delegation_round_trip() {
+        let (from, to) = (key(1), key(2).public_key());
+        let delegation = Delegation::create(&from, to, "docs/* and more".into(), 1000).unwrap();
+        let decoded = Delegation::decode(&delegation.format()).unwrap();
+        assert_eq!(decoded.from, from.public_key());
+        assert_eq!(decoded.to, to);
+        assert_eq!(decoded.pattern, "docs/* and more");
+        assert!(decoded.covers("docs/a and more"));
+        assert!(!decoded.covers("src/a and more"));
+    }
+
+    #[test]
+    fn test_delegation_rejects_tampering() {
+        let delegation = Delegation::create(&key(1), key(2).public_key(), "docs/*".into(), 1000)
+            .unwrap()
+            .format();
+        assert!(Delegation::decode(&delegation.replace("docs/*", "*")).is_err());
+        let statement = delegation.trim_end_matches(" docs/*");
+        assert!(Delegation::decode(&format!("{} docs/*", tamper(statement))).is_err());
+        assert!(Delegation::decode(statement).is_err());
+        for pattern in ["", "a\nb", "a\r"] {
+            assert!(Delegation::create(&key(1), key(2).public_key(), pattern.into(), 0).is_err());
+        }
+    }
+
+    /// Build a response to a query, answering with some records, of some type
+    fn dns_response(query: &[u8], answers: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut response = query.to_vec();
+        response[2] |= 0x80;
+        response[7] = answers.len() as u8;
+        for (kind, data) in answers {
+            // A pointer back to the name in the question
+            response.extend_from_slice(&[0xc0, 12]);
+            response.extend_from_slice(&kind.to_be_bytes());
+            response.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+            response.extend_from_slice(&300u32.to_be_bytes());
+            response.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            response.extend_from_slice(data);
+        }
+        response
+    }
+
+    #[test]
+    fn test_dns_query() {
+        let query = dns_query([1, 2], "eddo.example.com").unwrap();
+        assert_eq!(&query[..2], &[1, 2]);
+        assert_eq!(
+            &query[12..],
+            b"\x04eddo\x07example\x03com\x00\x00\x10\x00\x01"
+        );
+        assert!(dns_query([1, 2], "eddo..com").is_err());
+        assert!(dns_query([1, 2], &"a".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_parse_txt_response() {
+        let query = dns_query([1, 2], "eddo.example.com").unwrap();
+        let response = dns_response(
+            &query,
+            &[
+                (5, b"\x03foo\x00"),
+                (DNS_TYPE_TXT, b"\x05hello\x06 world"),
+                (DNS_TYPE_TXT, b"\x00"),
+            ],
+        );
+        assert_eq!(
+            parse_txt_response(&query, &response).unwrap(),
+            vec!["hello world".to_string(), String::new()]
+        );
+        // The case of the name in the question doesn't matter
+        let mut upper = response.clone();
+        upper[13..17].copy_from_slice(b"EDDO");
+        assert!(parse_txt_response(&query, &upper).is_ok());
+
+        let mut nxdomain = dns_response(&query, &[]);
+        nxdomain[3] |= 3;
+        assert!(parse_txt_response(&query, &nxdomain).unwrap().is_empty());
+        let mut truncated = response.clone();
+        truncated[2] |= 0x02;
+        assert!(parse_txt_response(&query, &truncated).is_err());
+        assert!(parse_txt_response(&query, &response[..response.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_parse_txt_response_checks_query() {
+        let query = dns_query([1, 2], "eddo.example.com").unwrap();
+        let response = dns_response(&query, &[(DNS_TYPE_TXT, b"\x05hello")]);
+
+        let other_id = dns_query([1, 3], "eddo.example.com").unwrap();
+        assert!(parse_txt_response(&other_id, &response).is_err());
+        let other_name = dns_query([1, 2], "eddo.example.org").unwrap();
+        assert!(parse_txt_response(&other_name, &response).is_err());
+        // A query, rather than a response, doesn't count
+        assert!(parse_txt_response(&query, &query).is_err());
+        let mut no_question = response.clone();
+        no_question[5] = 0;
+        assert!(parse_txt_response(&query, &no_question).is_err());
+        assert!(parse_txt_response(&query, &response[..12]).is_err());
+    }
+
+    fn audit_line(public: &PublicKey, message: &[u8], signature: &Signature) -> String {
+        format!(
+            r#"{{"public":"{}","message":"{}","signature":"{}"}}"#,
+            hex::encode(public.bytes),
+            hex::encode(message),
+            hex::encode(signature.bytes)
+        )
+    }
+
+    fn audit_lines(lines: &[String]) -> Vec<(&'static str, [&'static str; 3])> {
+        let records: Vec<_> = lines.iter().map(|l| parse_audit_record(l).1).collect();
+        audit_records(&records)
+            .iter()
+            .map(|status| (status.summary(), status.modes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_audit_reports_each_mode() {
+        let private = key(1);
+        let public = private.public_key();
+        let valid = audit_line(&public, b"message", &private.sign(b"message"));
+        let invalid = audit_line(&public, b"other", &private.sign(b"message"));
+        // R has a component of order 8, which only the cofactored equation ignores
+        let mixed = format!(
+            r#"{{"public":"{}","message":"{}","signature":"{}"}}"#,
+            "a47d1c5386f1e0ad6d1f4e059a58dae483430be3eafce41e879a3b791cac2ea7",
+            hex::encode(b"mixed order R"),
+            "3272afcdf94de1f8643c9d234197395dc8f038806b5a305de5bc11dc84234012d6e23b8882903e3893c078ac27f61db0c4ca041d4b00b3dd7b463932aaf25103"
+        );
+        let all_valid = ("valid", ["valid"; 3]);
+        let mixed_status = ("mixed", ["invalid", "invalid", "valid"]);
+
+        // The batch passes, which covers the cofactored mode for every record
+        assert_eq!(
+            audit_lines(&[valid.clone(), mixed.clone()]),
+            [all_valid, mixed_status]
+        );
+        // The batch fails, so each record gets checked on its own
+        assert_eq!(
+            audit_lines(&[valid, invalid, mixed, "{}".into()]),
+            [
+                all_valid,
+                ("invalid", ["invalid"; 3]),
+                mixed_status,
+                ("malformed", [""; 3])
+            ]
+        );
     }
 }
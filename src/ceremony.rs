@@ -0,0 +1,193 @@
+//! This module defines transcripts for multi-party key ceremonies.
+//!
+//! In a ceremony, each participant first commits to some entropy, and later reveals
+//! it. The transcript records the participants, their commitments and openings, and
+//! the public key which resulted from the ceremony. Every participant then signs the
+//! transcript, attesting that it reflects what happened.
+//!
+//! How the resulting key is produced isn't constrained by this module; the transcript
+//! only records it, so that everyone signs off on the same result.
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{sha512, PrivateKey, PublicKey, Signature};
+
+/// This is prepended to the encoding of a transcript, before signing it.
+const TRANSCRIPT_DOMAIN: &[u8] = b"eddo key ceremony transcript v1";
+
+/// This is prepended to the entropy of a participant, before hashing it.
+const COMMITMENT_DOMAIN: &[u8] = b"eddo key ceremony commitment v1";
+
+/// The number of bytes of entropy each participant contributes.
+pub const ENTROPY_SIZE: usize = 32;
+
+/// Calculate the commitment to a piece of entropy.
+///
+/// Participants should publish this value before any entropy gets revealed.
+pub fn commit(entropy: &[u8; ENTROPY_SIZE]) -> [u8; sha512::HASH_SIZE] {
+    let mut hasher = sha512::Hasher::new();
+    hasher.update(COMMITMENT_DOMAIN);
+    hasher.update(entropy);
+    hasher.finalize()
+}
+
+/// Represents a single participant in a ceremony.
+#[derive(Debug, Clone)]
+pub struct Participant {
+    /// A human readable name for this participant.
+    pub name: String,
+    /// The key this participant uses to sign the transcript.
+    pub public: PublicKey,
+    /// The commitment to this participant's entropy, as produced by `commit`.
+    pub commitment: [u8; sha512::HASH_SIZE],
+    /// The entropy this participant revealed.
+    pub opening: [u8; ENTROPY_SIZE],
+}
+
+/// Represents the transcript of a ceremony, before any signatures are attached.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub participants: Vec<Participant>,
+    /// The public key produced by the ceremony.
+    pub result: PublicKey,
+}
+
+fn write_length(out: &mut Vec<u8>, length: usize) {
+    out.extend_from_slice(&(length as u32).to_be_bytes());
+}
+
+impl Transcript {
+    /// Encode this transcript canonically, producing the bytes each participant signs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(TRANSCRIPT_DOMAIN);
+        write_length(&mut out, self.participants.len());
+        for participant in &self.participants {
+            write_length(&mut out, participant.name.len());
+            out.extend_from_slice(participant.name.as_bytes());
+            out.extend_from_slice(&participant.public.bytes);
+            out.extend_from_slice(&participant.commitment);
+            out.extend_from_slice(&participant.opening);
+        }
+        out.extend_from_slice(&self.result.bytes);
+        out
+    }
+
+    /// Sign this transcript, as one of its participants.
+    pub fn sign(&self, private: &PrivateKey) -> Signature {
+        private.sign(&self.to_bytes())
+    }
+}
+
+/// Represents a transcript, along with the signature of each participant.
+///
+/// The signatures should be in the same order as the participants.
+#[derive(Debug, Clone)]
+pub struct SignedTranscript {
+    pub transcript: Transcript,
+    pub signatures: Vec<Signature>,
+}
+
+/// Represents the ways in which a ceremony can fail to verify.
+///
+/// Each variant contains the index of the participant responsible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CeremonyError {
+    /// This participant didn't provide a signature.
+    MissingSignature(usize),
+    /// This participant's entropy doesn't match their commitment.
+    BadOpening(usize),
+    /// This participant's signature on the transcript is invalid.
+    BadSignature(usize),
+}
+
+/// Verify a signed ceremony transcript.
+///
+/// This checks that every participant opened their commitment correctly, and that
+/// every participant signed the transcript.
+pub fn verify_ceremony(signed: &SignedTranscript) -> Result<(), CeremonyError> {
+    let participants = &signed.transcript.participants;
+    if signed.signatures.len() < participants.len() {
+        return Err(CeremonyError::MissingSignature(signed.signatures.len()));
+    }
+    for (i, participant) in participants.iter().enumerate() {
+        if commit(&participant.opening) != participant.commitment {
+            return Err(CeremonyError::BadOpening(i));
+        }
+    }
+    let encoded = signed.transcript.to_bytes();
+    for (i, (participant, &signature)) in participants.iter().zip(&signed.signatures).enumerate() {
+        if !participant.public.verify(&encoded, signature) {
+            return Err(CeremonyError::BadSignature(i));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gen_keypair;
+    use rand::rngs::OsRng;
+
+    fn example_ceremony() -> SignedTranscript {
+        let mut privates = Vec::new();
+        let mut participants = Vec::new();
+        for i in 0..3u8 {
+            let (public, private) = gen_keypair(&mut OsRng);
+            let opening = [i; ENTROPY_SIZE];
+            participants.push(Participant {
+                name: format!("participant {}", i),
+                public,
+                commitment: commit(&opening),
+                opening,
+            });
+            privates.push(private);
+        }
+        let (result, _) = gen_keypair(&mut OsRng);
+        let transcript = Transcript {
+            participants,
+            result,
+        };
+        let signatures = privates.iter().map(|p| transcript.sign(p)).collect();
+        SignedTranscript {
+            transcript,
+            signatures,
+        }
+    }
+
+    #[test]
+    fn test_valid_ceremony_verifies() {
+        assert_eq!(verify_ceremony(&example_ceremony()), Ok(()));
+    }
+
+    #[test]
+    fn test_missing_signature_fails() {
+        let mut ceremony = example_ceremony();
+        ceremony.signatures.pop();
+        assert_eq!(
+            verify_ceremony(&ceremony),
+            Err(CeremonyError::MissingSignature(2))
+        );
+    }
+
+    #[test]
+    fn test_bad_opening_fails() {
+        let mut ceremony = example_ceremony();
+        ceremony.transcript.participants[1].opening[0] ^= 1;
+        assert_eq!(
+            verify_ceremony(&ceremony),
+            Err(CeremonyError::BadOpening(1))
+        );
+    }
+
+    #[test]
+    fn test_changed_result_fails() {
+        let mut ceremony = example_ceremony();
+        ceremony.transcript.result.bytes[0] ^= 1;
+        assert_eq!(
+            verify_ceremony(&ceremony),
+            Err(CeremonyError::BadSignature(0))
+        );
+    }
+}
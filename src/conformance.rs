@@ -0,0 +1,202 @@
+use eddo::{gen_keypair, PrivateKey, PublicKey, Signature};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use structopt::StructOpt;
+
+extern crate hex;
+extern crate structopt;
+
+/// Check that eddo interoperates with other Ed25519 implementations.
+///
+/// This generates random keys and messages, and checks them in both directions:
+/// signatures made by eddo must verify elsewhere, and vice versa. External
+/// implementations are driven through their command line tools, and skipped
+/// when those tools aren't installed.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "eddo-conformance")]
+struct Args {
+    /// The number of random cases to check
+    #[structopt(short = "n", long = "cases", default_value = "32")]
+    cases: usize,
+    /// The openssl binary to test against
+    #[structopt(long = "openssl", default_value = "openssl")]
+    openssl: String,
+}
+
+/// The DER prefix of a PKCS #8 Ed25519 private key, from RFC 8410, before the 32 byte seed.
+const PRIVATE_KEY_DER_PREFIX: &str = "302e020100300506032b657004220420";
+
+/// The DER prefix of an Ed25519 SubjectPublicKeyInfo, from RFC 8410, before the 32 byte key.
+const PUBLIC_KEY_DER_PREFIX: &str = "302a300506032b6570032100";
+
+/// An external implementation, driven through the openssl command line tool.
+struct OpenSsl {
+    binary: String,
+    dir: PathBuf,
+}
+
+impl OpenSsl {
+    fn new(binary: String) -> io::Result<Self> {
+        let dir = std::env::temp_dir().join(format!("eddo-conformance-{}", process::id()));
+        fs::create_dir_all(&dir)?;
+        Ok(OpenSsl { binary, dir })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    fn run(&self, args: &[&str]) -> io::Result<bool> {
+        let output = Command::new(&self.binary).args(args).output()?;
+        Ok(output.status.success())
+    }
+
+    fn write_private_key(&self, private: &PrivateKey) -> io::Result<PathBuf> {
+        let path = self.path("private.der");
+        let der = hex::decode(PRIVATE_KEY_DER_PREFIX).unwrap();
+        fs::write(&path, [&der[..], &private.bytes].concat())?;
+        Ok(path)
+    }
+
+    fn public_key(&self, private: &PrivateKey) -> io::Result<Option<PublicKey>> {
+        let key = self.write_private_key(private)?;
+        let out = self.path("public.der");
+        #[rustfmt::skip]
+        let ok = self.run(&[
+            "pkey", "-inform", "DER", "-in", path_str(&key),
+            "-pubout", "-outform", "DER", "-out", path_str(&out),
+        ])?;
+        if !ok {
+            return Ok(None);
+        }
+        let der = fs::read(&out)?;
+        if der.len() < 32 {
+            return Ok(None);
+        }
+        let mut public = PublicKey { bytes: [0; 32] };
+        public.bytes.copy_from_slice(&der[der.len() - 32..]);
+        Ok(Some(public))
+    }
+
+    fn sign(&self, private: &PrivateKey, message: &[u8]) -> io::Result<Option<Signature>> {
+        let key = self.write_private_key(private)?;
+        let message_path = self.path("message");
+        let sig_path = self.path("signature");
+        fs::write(&message_path, message)?;
+        #[rustfmt::skip]
+        let ok = self.run(&[
+            "pkeyutl", "-sign", "-keyform", "DER", "-inkey", path_str(&key), "-rawin",
+            "-in", path_str(&message_path), "-out", path_str(&sig_path),
+        ])?;
+        if !ok {
+            return Ok(None);
+        }
+        let bytes = fs::read(&sig_path)?;
+        if bytes.len() != 64 {
+            return Ok(None);
+        }
+        let mut sig = Signature { bytes: [0; 64] };
+        sig.bytes.copy_from_slice(&bytes);
+        Ok(Some(sig))
+    }
+
+    fn verify(&self, public: &PublicKey, message: &[u8], sig: &Signature) -> io::Result<bool> {
+        let key = self.path("public.der");
+        let der = hex::decode(PUBLIC_KEY_DER_PREFIX).unwrap();
+        fs::write(&key, [&der[..], &public.bytes].concat())?;
+        let message_path = self.path("message");
+        let sig_path = self.path("signature");
+        fs::write(&message_path, message)?;
+        fs::write(&sig_path, sig.bytes)?;
+        #[rustfmt::skip]
+        let ok = self.run(&[
+            "pkeyutl", "-verify", "-pubin", "-keyform", "DER", "-inkey", path_str(&key),
+            "-rawin", "-in", path_str(&message_path), "-sigfile", path_str(&sig_path),
+        ])?;
+        Ok(ok)
+    }
+}
+
+impl Drop for OpenSsl {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str()
+        .expect("temporary paths should be valid UTF-8")
+}
+
+/// The number of passing cases for each check we run.
+#[derive(Default)]
+struct Tally {
+    public_keys: usize,
+    eddo_to_other: usize,
+    other_to_eddo: usize,
+    identical: usize,
+    rejects_forgery: usize,
+}
+
+fn check_case(other: &OpenSsl, tally: &mut Tally) -> io::Result<()> {
+    let (public, private) = gen_keypair(&mut OsRng);
+    let mut message = vec![0; (OsRng.next_u32() % 1024) as usize];
+    OsRng.fill_bytes(&mut message);
+
+    if other.public_key(&private)? == Some(public) {
+        tally.public_keys += 1;
+    }
+    let sig = private.sign(&message);
+    if other.verify(&public, &message, &sig)? {
+        tally.eddo_to_other += 1;
+    }
+    let mut forged = sig;
+    forged.bytes[0] ^= 1;
+    if !other.verify(&public, &message, &forged)? {
+        tally.rejects_forgery += 1;
+    }
+    if let Some(other_sig) = other.sign(&private, &message)? {
+        if public.verify(&message, other_sig) {
+            tally.other_to_eddo += 1;
+        }
+        // Ed25519 is deterministic, so both signatures should be the same bytes
+        if other_sig == sig {
+            tally.identical += 1;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::from_args();
+    let other = OpenSsl::new(args.openssl.clone())?;
+    if !other.run(&["version"]).unwrap_or(false) {
+        println!("{}: not available, skipping", args.openssl);
+        return Ok(());
+    }
+
+    let mut tally = Tally::default();
+    for _ in 0..args.cases {
+        check_case(&other, &mut tally)?;
+    }
+
+    let rows = [
+        ("public key derivation", tally.public_keys),
+        ("eddo signs, openssl verifies", tally.eddo_to_other),
+        ("openssl signs, eddo verifies", tally.other_to_eddo),
+        ("identical signatures", tally.identical),
+        ("openssl rejects forgeries", tally.rejects_forgery),
+    ];
+    println!("{:<32}openssl", "check");
+    for (name, passed) in &rows {
+        println!("{:<32}{}/{}", name, passed, args.cases);
+    }
+    if rows.iter().any(|(_, passed)| *passed != args.cases) {
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,248 @@
+//! This module implements adaptor signatures, compatible with Ed25519.
+//!
+//! An adaptor signature, or pre-signature, is made with respect to an adaptor point
+//! T = t⋅B. The pre-signature can be checked by anyone, but only turns into a valid
+//! signature once adapted with the secret t. Conversely, anyone holding both the
+//! pre-signature and the adapted signature can extract t.
+//!
+//! A pre-signature is a pair (R', s'), with s' = r + k⋅a, and k = H(R' + T || A || M).
+//! Adapting it gives the signature (R' + T, s' + t), which verifies normally.
+use core::{
+    convert::{TryFrom, TryInto},
+    fmt,
+};
+
+use rand::{CryptoRng, RngCore};
+
+use super::{
+    challenge, point,
+    point::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    PrivateKey, PublicKey, Signature,
+};
+use crate::sha512;
+
+/// The context used when deriving the nonce.
+///
+/// Without this, the nonce of a pre-signature of M under T would be the nonce of
+/// a signature of T || M, and the two together would reveal our private key.
+const NONCE_DOMAIN: &[u8] = b"eddo adaptor nonce v1";
+
+/// Represents the secret scalar t behind an adaptor point.
+#[derive(Clone)]
+pub struct AdaptorSecret {
+    pub bytes: [u8; 32],
+}
+
+impl fmt::Debug for AdaptorSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AdaptorSecret(<redacted>)")
+    }
+}
+
+impl AdaptorSecret {
+    /// Generate a new random adaptor secret.
+    pub fn random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut wide = [0u8; 64];
+        rng.fill_bytes(&mut wide);
+        AdaptorSecret {
+            bytes: Scalar::from(wide).into(),
+        }
+    }
+
+    fn scalar(&self) -> Option<Scalar> {
+        Scalar::try_from(&self.bytes[..]).ok()
+    }
+
+    /// Calculate the adaptor point T = t⋅B associated with this secret.
+    ///
+    /// This returns `None` if the secret isn't a canonical scalar.
+    pub fn point(&self) -> Option<AdaptorPoint> {
+        Some(AdaptorPoint {
+            bytes: (point::B * self.scalar()?).compress().bytes,
+        })
+    }
+}
+
+/// Represents the public adaptor point T = t⋅B.
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptorPoint {
+    pub bytes: [u8; 32],
+}
+
+/// Represents a pre-signature, which can be adapted into a full signature.
+#[derive(Debug, Clone, Copy)]
+pub struct PreSignature {
+    pub bytes: [u8; 64],
+}
+
+impl PreSignature {
+    fn parts(&self) -> Option<(EdwardsPoint, Scalar)> {
+        let r = CompressedEdwardsY {
+            bytes: self.bytes[..32].try_into().unwrap(),
+        }
+        .decompress()?;
+        let s = Scalar::try_from(&self.bytes[32..]).ok()?;
+        Some((r, s))
+    }
+
+    /// Adapt this pre-signature into a full signature, using the adaptor secret.
+    ///
+    /// This returns `None` if the pre-signature or secret are malformed. The result
+    /// is only valid if the pre-signature was made for the point of this secret.
+    pub fn adapt(&self, secret: &AdaptorSecret) -> Option<Signature> {
+        let (r, s) = self.parts()?;
+        let t = secret.scalar()?;
+        let big_r = (r + point::B * t).compress().bytes;
+        let big_s: [u8; 32] = (s + t).into();
+        let mut out = Signature { bytes: [0; 64] };
+        out.bytes[..32].copy_from_slice(&big_r);
+        out.bytes[32..].copy_from_slice(&big_s);
+        Some(out)
+    }
+
+    /// Extract the adaptor secret, given the signature this pre-signature was adapted into.
+    ///
+    /// This returns `None` if either signature is malformed. The result should be
+    /// checked against the adaptor point, since the signature may come from elsewhere.
+    pub fn extract(&self, signature: &Signature) -> Option<AdaptorSecret> {
+        let (_, s_pre) = self.parts()?;
+        let s = Scalar::try_from(&signature.bytes[32..]).ok()?;
+        Some(AdaptorSecret {
+            bytes: (s + -s_pre).into(),
+        })
+    }
+}
+
+impl PrivateKey {
+    /// Create a pre-signature for a message, with respect to an adaptor point.
+    ///
+    /// This returns `None` if the adaptor point is invalid.
+    pub fn pre_sign(&self, message: &[u8], adaptor: &AdaptorPoint) -> Option<PreSignature> {
+        let t = CompressedEdwardsY {
+            bytes: adaptor.bytes,
+        }
+        .decompress()?;
+        let hash = sha512::hash(&self.bytes);
+        let a_scalar = Scalar::clamped(hash[..32].try_into().unwrap());
+        let a = (point::B * a_scalar).compress().bytes;
+
+        // The nonce depends on the adaptor point, otherwise pre-signing the same message
+        // with two different adaptors would reveal our private key.
+        let mut hasher = sha512::Hasher::new();
+        hasher.update(NONCE_DOMAIN);
+        hasher.update(&hash[32..]);
+        hasher.update(&adaptor.bytes);
+        hasher.update(message);
+        let r = Scalar::from(hasher.finalize());
+
+        let big_r_pre = point::B * r;
+        let big_r = (big_r_pre + t).compress().bytes;
+        let k = challenge(&big_r, &a, message);
+
+        let big_r_pre = big_r_pre.compress().bytes;
+        let big_s: [u8; 32] = (r + k * a_scalar).into();
+        let mut out = PreSignature { bytes: [0; 64] };
+        out.bytes[..32].copy_from_slice(&big_r_pre);
+        out.bytes[32..].copy_from_slice(&big_s);
+        Some(out)
+    }
+}
+
+impl PublicKey {
+    /// Verify a pre-signature for a message, with respect to an adaptor point.
+    ///
+    /// If this succeeds, adapting the pre-signature with the secret behind the
+    /// adaptor point will produce a valid signature.
+    pub fn verify_pre_signature(
+        &self,
+        message: &[u8],
+        adaptor: &AdaptorPoint,
+        pre_signature: &PreSignature,
+    ) -> bool {
+        let check = || -> Option<bool> {
+            let (r, s) = pre_signature.parts()?;
+            let t = CompressedEdwardsY {
+                bytes: adaptor.bytes,
+            }
+            .decompress()?;
+            let a = CompressedEdwardsY { bytes: self.bytes }.decompress()?;
+            let big_r = (r + t).compress().bytes;
+            let k = challenge(&big_r, &self.bytes, message);
+            let actual = (point::B * s + (a * -k)).compress().bytes;
+            Some(pre_signature.bytes[..32] == actual)
+        };
+        check().unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn setup() -> (PublicKey, PrivateKey, AdaptorSecret, AdaptorPoint) {
+        let private = PrivateKey { bytes: [5; 32] };
+        let public = private.public_key();
+        let secret = AdaptorSecret::random(&mut OsRng);
+        let point = secret.point().unwrap();
+        (public, private, secret, point)
+    }
+
+    #[test]
+    fn test_pre_signature_verifies() {
+        let (public, private, _, point) = setup();
+        let message = b"swap";
+        let pre = private.pre_sign(message, &point).unwrap();
+        assert!(public.verify_pre_signature(message, &point, &pre));
+        assert!(!public.verify_pre_signature(b"other", &point, &pre));
+    }
+
+    #[test]
+    fn test_pre_signature_is_not_a_signature() {
+        let (public, private, _, point) = setup();
+        let message = b"swap";
+        let pre = private.pre_sign(message, &point).unwrap();
+        assert!(!public.verify(message, Signature { bytes: pre.bytes }));
+    }
+
+    #[test]
+    fn test_pre_signature_nonce_differs_from_signature() {
+        let (_, private, _, point) = setup();
+        let message = b"swap";
+        let pre = private.pre_sign(message, &point).unwrap();
+        let mut joined = point.bytes.to_vec();
+        joined.extend_from_slice(message);
+        let sig = private.sign(&joined);
+        assert_ne!(pre.bytes[..32], sig.bytes[..32]);
+    }
+
+    #[test]
+    fn test_adapted_signature_verifies() {
+        let (public, private, secret, point) = setup();
+        let message = b"swap";
+        let pre = private.pre_sign(message, &point).unwrap();
+        let sig = pre.adapt(&secret).unwrap();
+        assert!(public.verify(message, sig));
+    }
+
+    #[test]
+    fn test_wrong_secret_does_not_adapt() {
+        let (public, private, _, point) = setup();
+        let message = b"swap";
+        let pre = private.pre_sign(message, &point).unwrap();
+        let other = AdaptorSecret::random(&mut OsRng);
+        let sig = pre.adapt(&other).unwrap();
+        assert!(!public.verify(message, sig));
+    }
+
+    #[test]
+    fn test_extract_recovers_secret() {
+        let (_, private, secret, point) = setup();
+        let message = b"swap";
+        let pre = private.pre_sign(message, &point).unwrap();
+        let sig = pre.adapt(&secret).unwrap();
+        let extracted = pre.extract(&sig).unwrap();
+        assert_eq!(extracted.bytes, secret.bytes);
+    }
+}
@@ -1,10 +1,12 @@
-use std::{
+use core::{
     cell::Cell,
     convert::TryInto,
     ops::{Add, AddAssign, Mul, Sub, SubAssign},
 };
 
-use subtle::{Choice, ConditionallySelectable};
+use subtle::{
+    Choice, ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater, ConstantTimeLess,
+};
 
 use crate::arch::{adc, mulc, sbb};
 
@@ -59,41 +61,103 @@ impl<const N: usize> U<N> {
         }
     }
 
-    /// Check if self >= other.
+    /// mul_wide multiplies two numbers, using the schoolbook method.
     ///
-    /// This method is not constant-time.
-    pub fn geq(&self, other: Self) -> bool {
-        for i in (0..N).rev() {
-            if other.limbs[i] > self.limbs[i] {
-                return false;
+    /// The result has O limbs, and is the full product when O = N + M. With fewer
+    /// limbs, the product is truncated, i.e. calculated modulo 2^(64 * O).
+    pub fn mul_wide<const M: usize, const O: usize>(&self, other: &U<M>) -> U<O> {
+        let mut out = U { limbs: [0; O] };
+        for i in 0..N {
+            let mut carry = 0;
+            for j in 0..M.min(O.saturating_sub(i)) {
+                // The largest result is (2^64 - 1)^2 + 2 * (2^64 - 1) = 2^128 - 1, so no overflow
+                let full_res = u128::from(out.limbs[i + j])
+                    + u128::from(self.limbs[i]) * u128::from(other.limbs[j])
+                    + u128::from(carry);
+                out.limbs[i + j] = full_res as u64;
+                carry = (full_res >> 64) as u64;
+            }
+            // Previous rows haven't touched this limb yet, so we can just set it
+            if i + M < O {
+                out.limbs[i + M] = carry;
             }
         }
-        true
+        out
     }
 
-    /// Check if self == other.
+    /// shifted creates a number from a smaller one, shifted up by a number of limbs.
     ///
-    /// This method is not constant-time.
-    pub fn eq(&self, other: Self) -> bool {
-        for i in (0..N).rev() {
-            if other.limbs[i] != self.limbs[i] {
-                return false;
+    /// Any limbs which don't fit are discarded.
+    pub fn shifted<const M: usize>(small: &U<M>, limbs: usize) -> Self {
+        let mut out = U { limbs: [0; N] };
+        for (i, &limb) in small.limbs.iter().enumerate() {
+            if i + limbs < N {
+                out.limbs[i + limbs] = limb;
             }
         }
-        true
+        out
+    }
+
+    /// Check if self >= other.
+    ///
+    /// This method is constant-time, only the result leaks.
+    pub fn geq(&self, other: Self) -> bool {
+        !bool::from(self.ct_lt(&other))
+    }
+
+    /// Check if self < other.
+    ///
+    /// This method is constant-time, only the result leaks.
+    pub fn lt(&self, other: Self) -> bool {
+        self.ct_lt(&other).into()
+    }
+
+    /// Check if self == other.
+    ///
+    /// This method is constant-time, only the result leaks.
+    pub fn eq(&self, other: Self) -> bool {
+        self.ct_eq(&other).into()
     }
 }
 
 impl<const N: usize> ConditionallySelectable for U<N> {
     fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
         let mut limbs = [0; N];
-        for i in 0..N {
-            limbs[i] = u64::conditional_select(&a.limbs[i], &b.limbs[i], choice)
+        for (limb, (a, b)) in limbs.iter_mut().zip(a.limbs.iter().zip(b.limbs.iter())) {
+            *limb = u64::conditional_select(a, b, choice)
         }
         Self { limbs }
     }
+
+    fn conditional_swap(a: &mut Self, b: &mut Self, choice: Choice) {
+        for i in 0..N {
+            u64::conditional_swap(&mut a.limbs[i], &mut b.limbs[i], choice);
+        }
+    }
+}
+
+impl<const N: usize> ConstantTimeEq for U<N> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        let mut out = Choice::from(1);
+        for i in 0..N {
+            out &= self.limbs[i].ct_eq(&other.limbs[i]);
+        }
+        out
+    }
+}
+
+impl<const N: usize> ConstantTimeGreater for U<N> {
+    fn ct_gt(&self, other: &Self) -> Choice {
+        // other - self underflows exactly when self > other
+        let mut scratch = *other;
+        let borrow = scratch.sub_with_borrow(*self);
+        Choice::from(borrow)
+    }
 }
 
+// The default implementation uses ct_gt and ct_eq, which is constant-time.
+impl<const N: usize> ConstantTimeLess for U<N> {}
+
 impl<const N: usize> From<u64> for U<N> {
     fn from(x: u64) -> Self {
         let mut limbs = [0; N];
@@ -102,27 +166,65 @@ impl<const N: usize> From<u64> for U<N> {
     }
 }
 
-impl Into<[u8; 32]> for U256 {
-    fn into(self) -> [u8; 32] {
-        let mut out = [0; 32];
-        let mut i = 0;
-        for limb in &self.limbs {
-            for &b in &limb.to_le_bytes() {
-                out[i] = b;
-                i += 1;
-            }
+impl<const N: usize> U<N> {
+    /// Encode this number as little endian bytes.
+    ///
+    /// `M` must be exactly 8 * N, the number of bytes in this number.
+    pub fn to_le_bytes<const M: usize>(self) -> [u8; M] {
+        assert_eq!(M, 8 * N, "wrong number of bytes");
+        let mut out = [0; M];
+        for (chunk, limb) in out.chunks_exact_mut(8).zip(self.limbs.iter()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        out
+    }
+
+    /// Encode this number as big endian bytes.
+    ///
+    /// `M` must be exactly 8 * N, the number of bytes in this number.
+    pub fn to_be_bytes<const M: usize>(self) -> [u8; M] {
+        assert_eq!(M, 8 * N, "wrong number of bytes");
+        let mut out = [0; M];
+        for (chunk, limb) in out.chunks_exact_mut(8).zip(self.limbs.iter().rev()) {
+            chunk.copy_from_slice(&limb.to_be_bytes());
+        }
+        out
+    }
+
+    /// Decode a number from little endian bytes.
+    ///
+    /// `M` must be exactly 8 * N, the number of bytes in this number.
+    pub fn from_le_bytes<const M: usize>(bytes: &[u8; M]) -> Self {
+        assert_eq!(M, 8 * N, "wrong number of bytes");
+        let mut out = Self { limbs: [0; N] };
+        for (limb, chunk) in out.limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        out
+    }
+
+    /// Decode a number from big endian bytes.
+    ///
+    /// `M` must be exactly 8 * N, the number of bytes in this number.
+    pub fn from_be_bytes<const M: usize>(bytes: &[u8; M]) -> Self {
+        assert_eq!(M, 8 * N, "wrong number of bytes");
+        let mut out = Self { limbs: [0; N] };
+        for (limb, chunk) in out.limbs.iter_mut().rev().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_be_bytes(chunk.try_into().unwrap());
         }
         out
     }
 }
 
+impl From<U256> for [u8; 32] {
+    fn from(x: U256) -> Self {
+        x.to_le_bytes()
+    }
+}
+
 impl From<[u8; 32]> for U256 {
     fn from(x: [u8; 32]) -> Self {
-        let mut out = Self { limbs: [0; 4] };
-        for (i, chunk) in x.chunks_exact(8).enumerate() {
-            out.limbs[i] = u64::from_le_bytes(chunk.try_into().unwrap());
-        }
-        out
+        Self::from_le_bytes(&x)
     }
 }
 
@@ -181,6 +283,38 @@ pub type U256 = U<4>;
 /// after multiplication.
 pub type U512 = U<8>;
 
+/// Represents a 1024 bit unsigned integer.
+///
+/// This is the result of multiplying two 512 bit integers together.
+pub type U1024 = U<16>;
+
+impl U256 {
+    /// Decode a number from the hex encoding of its 32 little endian bytes.
+    ///
+    /// This matches how RFC 8032 writes out encodings, and lets constants be written
+    /// that way. Malformed strings make this panic, which is a compile error in a constant.
+    pub const fn from_le_hex(hex: &str) -> U256 {
+        const fn digit(c: u8) -> u64 {
+            match c {
+                b'0'..=b'9' => (c - b'0') as u64,
+                b'a'..=b'f' => (c - b'a' + 10) as u64,
+                _ => panic!("invalid hex digit"),
+            }
+        }
+
+        let hex = hex.as_bytes();
+        assert!(hex.len() == 64, "expected 32 bytes of hex");
+        let mut limbs = [0; 4];
+        let mut i = 0;
+        while i < 32 {
+            let byte = (digit(hex[2 * i]) << 4) | digit(hex[2 * i + 1]);
+            limbs[i / 8] |= byte << (8 * (i % 8));
+            i += 1;
+        }
+        U256 { limbs }
+    }
+}
+
 impl U512 {
     pub fn lo(&self) -> U256 {
         U256 {
@@ -188,7 +322,6 @@ impl U512 {
         }
     }
 
-    #[cfg(test)]
     pub fn hi(&self) -> U256 {
         U256 {
             limbs: [self.limbs[4], self.limbs[5], self.limbs[6], self.limbs[7]],
@@ -375,6 +508,39 @@ impl Mul<U256> for U512 {
     }
 }
 
+impl Mul for U512 {
+    type Output = U1024;
+
+    fn mul(self, other: U512) -> Self::Output {
+        // We use one level of Karatsuba multiplication, writing each number as
+        // a = a1⋅2²⁵⁶ + a0, and using our efficient 256 bit multiplication.
+        //
+        // The idea is that (a0 + a1)(b0 + b1) - a0⋅b0 - a1⋅b1 = a0⋅b1 + a1⋅b0,
+        // which lets us calculate the middle term with one multiplication, instead of two.
+        let (a0, a1) = (self.lo(), self.hi());
+        let (b0, b1) = (other.lo(), other.hi());
+        let z0 = a0 * b0;
+        let z2 = a1 * b1;
+
+        // These sums might overflow, which we need to account for with the carries:
+        //     (ca⋅2²⁵⁶ + sa)(cb⋅2²⁵⁶ + sb) =
+        //     sa⋅sb + (ca⋅sb + cb⋅sa)⋅2²⁵⁶ + ca⋅cb⋅2⁵¹²
+        let mut sa = a0;
+        let ca = sa.add_with_carry(a1);
+        let mut sb = b0;
+        let cb = sb.add_with_carry(b1);
+        let mut z1 = U1024::shifted(&(sa * sb), 0);
+        z1.cond_add(U1024::shifted(&sb, 4), ca.ct_eq(&1));
+        z1.cond_add(U1024::shifted(&sa, 4), cb.ct_eq(&1));
+        z1.cond_add(U1024::shifted(&U256::from(1), 8), (ca & cb).ct_eq(&1));
+        // The true result is positive, so wrapping subtraction is fine here
+        z1 -= U1024::shifted(&z0, 0);
+        z1 -= U1024::shifted(&z2, 0);
+
+        U1024::shifted(&z0, 0) + U1024::shifted(&z1, 4) + U1024::shifted(&z2, 8)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -400,6 +566,69 @@ mod test {
         }
     }
 
+    proptest! {
+        #[test]
+        fn test_conditional_swap(a in arb_u256(), b in arb_u256()) {
+            let (mut x, mut y) = (a, b);
+            U256::conditional_swap(&mut x, &mut y, 0.into());
+            assert_eq!((x, y), (a, b));
+            U256::conditional_swap(&mut x, &mut y, 1.into());
+            assert_eq!((x, y), (b, a));
+        }
+    }
+
+    fn reference_cmp(a: &U256, b: &U256) -> core::cmp::Ordering {
+        a.limbs.iter().rev().cmp(b.limbs.iter().rev())
+    }
+
+    proptest! {
+        #[test]
+        fn test_comparisons_match_reference(a in arb_u256(), b in arb_u256()) {
+            let ordering = reference_cmp(&a, &b);
+            assert_eq!(a.geq(b), ordering.is_ge());
+            assert_eq!(a.lt(b), ordering.is_lt());
+            assert_eq!(a.eq(b), ordering.is_eq());
+            assert_eq!(bool::from(a.ct_gt(&b)), ordering.is_gt());
+            assert_eq!(bool::from(a.ct_lt(&b)), ordering.is_lt());
+            assert_eq!(bool::from(a.ct_eq(&b)), ordering.is_eq());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_comparisons_with_self(a in arb_u256()) {
+            assert!(a.geq(a));
+            assert!(!a.lt(a));
+            assert!(a.eq(a));
+        }
+    }
+
+    proptest! {
+        // Random numbers almost never share high limbs, so we test that case explicitly
+        #[test]
+        fn test_comparisons_differing_in_low_limb(a in arb_u256(), x in any::<u64>()) {
+            let mut b = a;
+            b.limbs[0] = x;
+            assert_eq!(a.lt(b), a.limbs[0] < x);
+            assert_eq!(a.geq(b), a.limbs[0] >= x);
+        }
+    }
+
+    #[test]
+    fn test_comparison_examples() {
+        let zero = U256::from(0);
+        let max = U256 { limbs: [!0; 4] };
+        let high_bit = U256 {
+            limbs: [0, 0, 0, 1 << 63],
+        };
+        assert!(max.geq(zero));
+        assert!(zero.lt(max));
+        assert!(high_bit.geq(U256 {
+            limbs: [!0, !0, !0, (1 << 63) - 1]
+        }));
+        assert!(!zero.lt(zero));
+    }
+
     #[test]
     fn test_addition_examples() {
         let a = U256 {
@@ -432,6 +661,81 @@ mod test {
         }
     }
 
+    prop_compose! {
+        fn arb_u512()(lo in arb_u256(), hi in arb_u256()) -> U512 {
+            U512::from_hi_lo(hi, lo)
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_schoolbook_matches_256_multiplication(a in arb_u256(), b in arb_u256()) {
+            assert_eq!(a.mul_wide::<4, 8>(&b), a * b);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_truncated_schoolbook_is_low_half(a in arb_u256(), b in arb_u256()) {
+            assert_eq!(a.mul_wide::<4, 4>(&b), (a * b).lo());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_karatsuba_matches_schoolbook(a in arb_u512(), b in arb_u512()) {
+            assert_eq!(a * b, a.mul_wide::<8, 16>(&b));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_512_multiplication_commutative(a in arb_u512(), b in arb_u512()) {
+            assert_eq!(a * b, b * a);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_byte_roundtrips(a in arb_u512()) {
+            assert_eq!(U512::from_le_bytes(&a.to_le_bytes::<64>()), a);
+            assert_eq!(U512::from_be_bytes(&a.to_be_bytes::<64>()), a);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_be_bytes_are_reversed_le_bytes(a in arb_u256()) {
+            let mut le: [u8; 32] = a.to_le_bytes();
+            le.reverse();
+            assert_eq!(le, a.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_byte_examples() {
+        let x = U256::from(0x0102);
+        let le: [u8; 32] = x.to_le_bytes();
+        let be: [u8; 32] = x.to_be_bytes();
+        assert_eq!(&le[..2], &[2, 1]);
+        assert_eq!(&be[30..], &[1, 2]);
+    }
+
+    #[test]
+    fn test_karatsuba_carries() {
+        // This makes both of the sums in Karatsuba overflow
+        let max = U512 { limbs: [!0; 8] };
+        assert_eq!(max * max, max.mul_wide::<8, 16>(&max));
+        // (2^512 - 1)^2 = 2^1024 - 2^513 + 1
+        let mut expected = U1024 { limbs: [0; 16] };
+        expected.limbs[0] = 1;
+        expected.limbs[8] = !0 - 1;
+        for limb in &mut expected.limbs[9..] {
+            *limb = !0;
+        }
+        assert_eq!(max * max, expected);
+    }
+
     proptest! {
         #[test]
         fn test_high_512_256_multiplication(a in arb_u256(), b in arb_u256()) {
@@ -526,4 +830,11 @@ mod test {
         let c = U256 { limbs: [64; 4] };
         assert_eq!((a * 64).1, c);
     }
+
+    #[test]
+    fn test_from_le_hex() {
+        let hex = "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20";
+        let bytes: [u8; 32] = hex::decode(hex).unwrap().try_into().unwrap();
+        assert_eq!(U256::from_le_hex(hex), U256::from_le_bytes(&bytes));
+    }
 }
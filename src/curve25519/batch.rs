@@ -0,0 +1,272 @@
+//! This module implements batch verification of signatures.
+//!
+//! Instead of checking each equation s⋅B = R + k⋅A on its own, we pick random
+//! coefficients z, and check that the sum of z⋅(s⋅B - R - k⋅A) over the batch is
+//! zero. This can be done with a single multiscalar multiplication, which is much
+//! faster than verifying each signature separately. The random coefficients stop
+//! an invalid signature from being cancelled out by another one in the batch.
+//!
+//! The combined equation is multiplied by the cofactor, so a batch may accept a
+//! signature with a small order component which `PublicKey::verify` rejects. When
+//! a batch fails, it tells you nothing about which signature was invalid.
+use alloc::vec::Vec;
+use core::convert::TryInto;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use rand::{CryptoRng, RngCore};
+
+#[cfg(feature = "std")]
+use super::hash_reader;
+use super::{point, point::CompressedEdwardsY, scalar::Scalar, PublicKey, Signature};
+use crate::sha512;
+
+/// Generate a random 128 bit coefficient, which is enough to make forgeries unlikely.
+fn random_coefficient<R: RngCore + CryptoRng>(rng: &mut R) -> Scalar {
+    let mut bytes = [0u8; 32];
+    rng.fill_bytes(&mut bytes[..16]);
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+/// Verify a batch of signatures at once, returning true only if all of them are valid.
+///
+/// The signature at index i should be for the message and public key at that index.
+/// An empty batch is valid.
+///
+/// # Panics
+///
+/// This panics if there aren't as many messages, signatures, and public keys.
+pub fn verify_batch<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    messages: &[&[u8]],
+    signatures: &[Signature],
+    public_keys: &[PublicKey],
+) -> bool {
+    assert!(
+        messages.len() == signatures.len() && signatures.len() == public_keys.len(),
+        "mismatched batch lengths"
+    );
+    // The challenges are H(R || A || M), which we hash together, to make use of SIMD,
+    // reading each part where it is, rather than copying the messages
+    let parts: Vec<[&[u8]; 3]> = messages
+        .iter()
+        .zip(signatures)
+        .zip(public_keys)
+        .map(|((message, signature), public)| [&signature.bytes[..32], &public.bytes, message])
+        .collect();
+    let inputs: Vec<&[&[u8]]> = parts.iter().map(|parts| &parts[..]).collect();
+    verify_hashed(
+        rng,
+        &sha512::hash_many_parts(&inputs),
+        signatures,
+        public_keys,
+    )
+}
+
+/// Verify a batch of signatures over messages read in chunks, like `PublicKey::verify_chunked`.
+///
+/// Each reader is read to the end, one after the other, using a single buffer
+/// of `chunk_size` bytes, so the messages never need to be in memory at once.
+/// Errors from the readers are returned, while invalid signatures produce `Ok(false)`.
+///
+/// # Panics
+///
+/// This panics if there aren't as many readers, signatures, and public keys,
+/// or if `chunk_size` is 0.
+#[cfg(feature = "std")]
+pub fn verify_batch_chunked<G: RngCore + CryptoRng, R: Read>(
+    rng: &mut G,
+    readers: &mut [R],
+    signatures: &[Signature],
+    public_keys: &[PublicKey],
+    chunk_size: usize,
+) -> io::Result<bool> {
+    assert!(
+        readers.len() == signatures.len() && signatures.len() == public_keys.len(),
+        "mismatched batch lengths"
+    );
+    assert!(chunk_size > 0, "chunk size must not be 0");
+    let mut buf = vec![0; chunk_size];
+    let mut hashes = Vec::with_capacity(readers.len());
+    for ((reader, signature), public) in readers.iter_mut().zip(signatures).zip(public_keys) {
+        let mut hasher = sha512::Hasher::new();
+        hasher.update(&signature.bytes[..32]);
+        hasher.update(&public.bytes);
+        hash_reader(&mut hasher, reader, &mut buf)?;
+        hashes.push(hasher.finalize());
+    }
+    Ok(verify_hashed(rng, &hashes, signatures, public_keys))
+}
+
+/// Verify a batch, given the hash H(R || A || M) for each signature.
+fn verify_hashed<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    hashes: &[[u8; 64]],
+    signatures: &[Signature],
+    public_keys: &[PublicKey],
+) -> bool {
+    let n = signatures.len();
+    let mut scalars = Vec::with_capacity(2 * n + 1);
+    let mut points = Vec::with_capacity(2 * n + 1);
+    let mut b_scalar = Scalar::from(0);
+    for ((signature, public), hash) in signatures.iter().zip(public_keys).zip(hashes) {
+        let r_bytes: [u8; 32] = signature.bytes[..32].try_into().unwrap();
+        let (s, r, a) = match (
+            Scalar::from_canonical_bytes(signature.bytes[32..].try_into().unwrap()),
+            CompressedEdwardsY { bytes: r_bytes }.decompress(),
+            CompressedEdwardsY {
+                bytes: public.bytes,
+            }
+            .decompress(),
+        ) {
+            (Some(s), Some(r), Some(a)) => (s, r, a),
+            _ => return false,
+        };
+        // We hashed A as given, which is fine, since decompress rejects non-canonical encodings
+        let k = Scalar::from(*hash);
+        let z = random_coefficient(rng);
+        b_scalar += z * s;
+        scalars.push(-z);
+        points.push(r);
+        scalars.push(-(z * k));
+        points.push(a);
+    }
+    scalars.push(b_scalar);
+    points.push(point::B);
+    point::EdwardsPoint::vartime_multiscalar_mul(&scalars, &points)
+        .mul_by_cofactor()
+        .is_identity()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::curve25519::PrivateKey;
+    use rand::rngs::OsRng;
+
+    fn batch(n: u8) -> (Vec<Vec<u8>>, Vec<Signature>, Vec<PublicKey>) {
+        let mut messages = Vec::new();
+        let mut signatures = Vec::new();
+        let mut public_keys = Vec::new();
+        for i in 0..n {
+            let private = PrivateKey { bytes: [i; 32] };
+            let message = vec![i; i as usize];
+            signatures.push(private.sign(&message));
+            public_keys.push(private.public_key());
+            messages.push(message);
+        }
+        (messages, signatures, public_keys)
+    }
+
+    #[test]
+    fn test_valid_batch_verifies() {
+        let (messages, signatures, public_keys) = batch(8);
+        let messages: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        assert!(verify_batch(
+            &mut OsRng,
+            &messages,
+            &signatures,
+            &public_keys
+        ));
+        assert!(verify_batch(&mut OsRng, &[], &[], &[]));
+    }
+
+    #[test]
+    fn test_one_bad_signature_fails_batch() {
+        let (messages, mut signatures, public_keys) = batch(8);
+        let messages: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        signatures[5].bytes[40] ^= 1;
+        assert!(!verify_batch(
+            &mut OsRng,
+            &messages,
+            &signatures,
+            &public_keys
+        ));
+    }
+
+    #[test]
+    fn test_swapped_messages_fail_batch() {
+        let (mut messages, signatures, public_keys) = batch(4);
+        messages.swap(1, 2);
+        let messages: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        assert!(!verify_batch(
+            &mut OsRng,
+            &messages,
+            &signatures,
+            &public_keys
+        ));
+    }
+
+    #[test]
+    fn test_batch_accepts_torsion_component() {
+        // A point of order 8, added to a public key, gives it a small order component
+        let mut order_8 = [0u8; 32];
+        hex::decode_to_slice(
+            "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a",
+            &mut order_8,
+        )
+        .unwrap();
+        let t = CompressedEdwardsY { bytes: order_8 }.decompress().unwrap();
+        let a = Scalar::from(1234u64);
+        let r = Scalar::from(5678u64);
+        let public = PublicKey {
+            bytes: (point::B * a + t).compress().bytes,
+        };
+        let r_bytes = (point::B * r).compress().bytes;
+        // With k not a multiple of 8, k⋅T doesn't vanish, so s⋅B = R + k⋅A only holds
+        // once multiplied by the cofactor
+        let (message, k) = (0u8..)
+            .map(|i| {
+                (
+                    vec![i],
+                    super::super::challenge(&r_bytes, &public.bytes, &[i]),
+                )
+            })
+            .find(|(_, k)| k.to_bytes()[0] % 8 != 0)
+            .unwrap();
+        let mut torsion_signature = Signature { bytes: [0; 64] };
+        torsion_signature.bytes[..32].copy_from_slice(&r_bytes);
+        torsion_signature.bytes[32..].copy_from_slice(&(r + k * a).to_bytes());
+        assert!(!public.verify(&message, torsion_signature));
+        assert!(public.verify_zip215(&message, torsion_signature));
+
+        let (messages, mut signatures, mut public_keys) = batch(4);
+        let mut messages: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        messages.push(&message);
+        signatures.push(torsion_signature);
+        public_keys.push(public);
+        for _ in 0..16 {
+            assert!(verify_batch(
+                &mut OsRng,
+                &messages,
+                &signatures,
+                &public_keys
+            ));
+        }
+
+        // Changing the message breaks the signature, in both cases
+        let other = [message[0] ^ 1];
+        messages[4] = &other;
+        assert!(!public.verify_zip215(&other, torsion_signature));
+        assert!(!verify_batch(
+            &mut OsRng,
+            &messages,
+            &signatures,
+            &public_keys
+        ));
+    }
+
+    #[test]
+    fn test_chunked_batch_matches_batch() {
+        let (messages, mut signatures, public_keys) = batch(8);
+        let mut readers: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        assert!(
+            verify_batch_chunked(&mut OsRng, &mut readers, &signatures, &public_keys, 3).unwrap()
+        );
+        signatures[5].bytes[40] ^= 1;
+        let mut readers: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        assert!(
+            !verify_batch_chunked(&mut OsRng, &mut readers, &signatures, &public_keys, 3).unwrap()
+        );
+    }
+}
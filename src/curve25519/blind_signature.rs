@@ -0,0 +1,248 @@
+//! This module implements blind Schnorr signatures, compatible with Ed25519.
+//!
+//! The server signs a message without learning it, and without being able to link
+//! the resulting signature to the session which produced it. The signature verifies
+//! with the normal Ed25519 verification procedure.
+//!
+//! The protocol goes as follows, with the server holding the secret a, and A = a⋅B:
+//!
+//! 1. The server picks a random k, and sends R = k⋅B.
+//! 2. The client picks random α and β, calculates R' = R + α⋅B + β⋅A, and
+//!    c' = H(R' || A || M), and then sends c = c' + β.
+//! 3. The server sends back s = k + c⋅a.
+//! 4. The client calculates s' = s + α, and obtains the signature (R', s').
+//!
+//! The server must never run multiple sessions concurrently with the same key,
+//! since interleaving many sessions allows clients to forge extra signatures, see
+//! `BlindSigner`, which enforces this.
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+
+use rand::{CryptoRng, RngCore};
+
+use super::{
+    challenge, point, point::CompressedEdwardsY, scalar::Scalar, PrivateKey, PublicKey, Signature,
+};
+use crate::sha512;
+
+fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Scalar {
+    let mut wide = [0u8; 64];
+    rng.fill_bytes(&mut wide);
+    Scalar::from(wide)
+}
+
+/// The server's first message, committing to its nonce.
+#[derive(Debug, Clone, Copy)]
+pub struct BlindCommitment {
+    pub bytes: [u8; 32],
+}
+
+/// The client's message, containing the blinded challenge.
+#[derive(Debug, Clone, Copy)]
+pub struct BlindChallenge {
+    pub bytes: [u8; 32],
+}
+
+/// The server's final message, which the client unblinds into a signature.
+#[derive(Debug, Clone, Copy)]
+pub struct BlindResponse {
+    pub bytes: [u8; 32],
+}
+
+/// The server's side of the protocol, running one session at a time.
+///
+/// # Concurrent Sessions
+///
+/// Blind Schnorr signatures are only secure if sessions run one after the other.
+/// A client which opens about 256 sessions at once, before sending any challenge,
+/// can combine the commitments into challenges yielding an extra signature, in
+/// polynomial time. This is the ROS attack, from "On the (in)security of ROS", by
+/// Benhamouda, Lepoint, Loss, Orrù, and Raykova.
+///
+/// To prevent this, a signer refuses to start a session while another is outstanding.
+/// This only holds per signer: the same key must never be used by several signers,
+/// or processes, at once.
+pub struct BlindSigner {
+    private: PrivateKey,
+    /// The nonce of the outstanding session, if any.
+    ///
+    /// This is used at most once, since using a nonce twice reveals the private key.
+    nonce: Option<Scalar>,
+}
+
+impl BlindSigner {
+    /// Create a signer for a given key, with no session outstanding.
+    pub fn new(private: PrivateKey) -> Self {
+        BlindSigner {
+            private,
+            nonce: None,
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.private.public_key()
+    }
+
+    /// Check whether or not a session is outstanding, waiting for a challenge.
+    pub fn in_session(&self) -> bool {
+        self.nonce.is_some()
+    }
+
+    /// Start a session, producing a commitment to send to the client.
+    ///
+    /// This returns `None` if another session is still outstanding.
+    pub fn commit<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> Option<BlindCommitment> {
+        if self.nonce.is_some() {
+            return None;
+        }
+        let k = random_scalar(rng);
+        self.nonce = Some(k);
+        Some(BlindCommitment {
+            bytes: (point::B * k).compress().bytes,
+        })
+    }
+
+    /// Respond to a blinded challenge, finishing the outstanding session.
+    ///
+    /// This returns `None` if there's no outstanding session, or if the challenge
+    /// isn't a canonical scalar. Either way, the session is over afterwards.
+    pub fn respond(&mut self, challenge: &BlindChallenge) -> Option<BlindResponse> {
+        let k = self.nonce.take()?;
+        let c = Scalar::try_from(&challenge.bytes[..]).ok()?;
+        let hash = sha512::hash(&self.private.bytes);
+        let a = Scalar::clamped(hash[..32].try_into().unwrap()).reduce();
+        Some(BlindResponse {
+            bytes: (k + c * a).into(),
+        })
+    }
+
+    /// Abandon the outstanding session, e.g. after its client timed out.
+    ///
+    /// The nonce is forgotten, so the session can't be finished afterwards.
+    pub fn abort(&mut self) {
+        self.nonce = None;
+    }
+}
+
+/// The state a client holds while requesting a blind signature.
+pub struct BlindSignatureRequest {
+    public: PublicKey,
+    message: Vec<u8>,
+    big_r: [u8; 32],
+    alpha: Scalar,
+}
+
+impl BlindSignatureRequest {
+    /// Blind a message, given the server's public key and commitment.
+    ///
+    /// This returns the state to keep, along with the challenge to send to the server.
+    /// This returns `None` if the public key or commitment aren't valid points.
+    pub fn new<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        public: &PublicKey,
+        commitment: &BlindCommitment,
+        message: &[u8],
+    ) -> Option<(Self, BlindChallenge)> {
+        let a = CompressedEdwardsY {
+            bytes: public.bytes,
+        }
+        .decompress()?;
+        let r = CompressedEdwardsY {
+            bytes: commitment.bytes,
+        }
+        .decompress()?;
+        let alpha = random_scalar(rng);
+        let beta = random_scalar(rng);
+        let big_r = (r + point::B * alpha + a * beta).compress().bytes;
+        let c = challenge(&big_r, &public.bytes, message) + beta;
+        let request = BlindSignatureRequest {
+            public: *public,
+            message: message.to_vec(),
+            big_r,
+            alpha,
+        };
+        Some((request, BlindChallenge { bytes: c.into() }))
+    }
+
+    /// Unblind the server's response, producing a signature.
+    ///
+    /// This returns `None` if the server's response doesn't lead to a valid signature.
+    pub fn finish(self, response: &BlindResponse) -> Option<Signature> {
+        let s = Scalar::try_from(&response.bytes[..]).ok()?;
+        let big_s: [u8; 32] = (s + self.alpha).into();
+        let mut out = Signature { bytes: [0; 64] };
+        out.bytes[..32].copy_from_slice(&self.big_r);
+        out.bytes[32..].copy_from_slice(&big_s);
+        if !self.public.verify(&self.message, out) {
+            return None;
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_blind_signature_verifies() {
+        let (public, private) = crate::gen_keypair(&mut OsRng);
+        let mut signer = BlindSigner::new(private);
+        let message = b"anonymous token";
+        let commitment = signer.commit(&mut OsRng).unwrap();
+        let (request, c) =
+            BlindSignatureRequest::new(&mut OsRng, &public, &commitment, message).unwrap();
+        let response = signer.respond(&c).unwrap();
+        let sig = request.finish(&response).unwrap();
+        assert!(public.verify(message, sig));
+    }
+
+    #[test]
+    fn test_blind_signature_hides_commitment() {
+        let (public, private) = crate::gen_keypair(&mut OsRng);
+        let mut signer = BlindSigner::new(private);
+        let message = b"anonymous token";
+        let commitment = signer.commit(&mut OsRng).unwrap();
+        let (request, c) =
+            BlindSignatureRequest::new(&mut OsRng, &public, &commitment, message).unwrap();
+        let response = signer.respond(&c).unwrap();
+        let sig = request.finish(&response).unwrap();
+        assert_ne!(sig.bytes[..32], commitment.bytes);
+        assert_ne!(sig.bytes[32..], response.bytes);
+    }
+
+    #[test]
+    fn test_bad_response_is_rejected() {
+        let (public, private) = crate::gen_keypair(&mut OsRng);
+        let mut signer = BlindSigner::new(private);
+        let commitment = signer.commit(&mut OsRng).unwrap();
+        let (request, c) =
+            BlindSignatureRequest::new(&mut OsRng, &public, &commitment, b"token").unwrap();
+        let mut response = signer.respond(&c).unwrap();
+        response.bytes[0] ^= 1;
+        assert!(request.finish(&response).is_none());
+    }
+
+    #[test]
+    fn test_one_session_at_a_time() {
+        let (public, private) = crate::gen_keypair(&mut OsRng);
+        let mut signer = BlindSigner::new(private);
+        let commitment = signer.commit(&mut OsRng).unwrap();
+        assert!(signer.in_session());
+        assert!(signer.commit(&mut OsRng).is_none());
+
+        let (request, c) =
+            BlindSignatureRequest::new(&mut OsRng, &public, &commitment, b"token").unwrap();
+        let response = signer.respond(&c).unwrap();
+        assert!(request.finish(&response).is_some());
+        // The nonce is gone, so responding again would need a new session
+        assert!(!signer.in_session());
+        assert!(signer.respond(&c).is_none());
+
+        signer.commit(&mut OsRng).unwrap();
+        signer.abort();
+        assert!(signer.respond(&c).is_none());
+        assert!(signer.commit(&mut OsRng).is_some());
+    }
+}
@@ -0,0 +1,132 @@
+//! This module implements public key blinding, in the style of Tor onion services.
+//!
+//! The idea is that given a blinding factor h, a keypair (a, A) can be transformed
+//! into (h⋅a, h⋅A). Signatures made with the blinded private key verify under
+//! the blinded public key, but without knowledge of h, the blinded key can't be
+//! linked back to the original one.
+//!
+//! This follows Appendix A.2 of Tor's rend-spec-v3:
+//! https://gitweb.torproject.org/torspec.git/tree/rend-spec-v3.txt
+
+use core::{convert::TryInto, fmt};
+
+use super::{
+    point, point::CompressedEdwardsY, scalar::Scalar, sign_with_scalar, PrivateKey, PublicKey,
+    Signature,
+};
+use crate::sha512;
+
+/// This string is used to derive the nonce prefix of a blinded private key.
+const PREFIX_DERIVATION_STRING: &[u8] = b"Derive temporary signing key hash input";
+
+/// Clamp a blinding factor, and then reduce it modulo L.
+///
+/// The clamping is the same as that used in Tor, which clears one more bit at
+/// the top than the usual Ed25519 clamping.
+fn blinding_scalar(factor: &[u8; 32]) -> Scalar {
+    let mut bytes = *factor;
+    bytes[31] &= 63;
+    Scalar::clamped(bytes).reduce()
+}
+
+impl PublicKey {
+    /// Blind this public key, using a 32 byte blinding factor.
+    ///
+    /// This returns `None` if this public key isn't a valid point.
+    ///
+    /// The resulting key will be the public key of the private key produced
+    /// by `PrivateKey::blind`, using the same factor.
+    pub fn blind(&self, factor: &[u8; 32]) -> Option<PublicKey> {
+        let a = CompressedEdwardsY { bytes: self.bytes }.decompress()?;
+        Some(PublicKey {
+            bytes: (a * blinding_scalar(factor)).compress().bytes,
+        })
+    }
+}
+
+/// Represents a private key which has been blinded.
+///
+/// Unlike a normal private key, this isn't derived from a seed, so we need to store
+/// the secret scalar, and the prefix used to derive nonces, separately.
+#[derive(Clone)]
+pub struct BlindedPrivateKey {
+    scalar: Scalar,
+    prefix: [u8; 32],
+}
+
+impl fmt::Debug for BlindedPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BlindedPrivateKey(<redacted>)")
+    }
+}
+
+impl BlindedPrivateKey {
+    /// Calculate the public key associated with this blinded private key.
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            bytes: (point::B * self.scalar).compress().bytes,
+        }
+    }
+
+    /// Sign a message using this blinded key.
+    ///
+    /// The resulting signature can be verified with the blinded public key.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        sign_with_scalar(self.scalar, &self.prefix, message)
+    }
+}
+
+impl PrivateKey {
+    /// Blind this private key, using a 32 byte blinding factor.
+    pub fn blind(&self, factor: &[u8; 32]) -> BlindedPrivateKey {
+        let hash = sha512::hash(&self.bytes);
+        let a = Scalar::clamped(hash[..32].try_into().unwrap()).reduce();
+        let scalar = blinding_scalar(factor) * a;
+
+        let mut hasher = sha512::Hasher::new();
+        hasher.update(PREFIX_DERIVATION_STRING);
+        hasher.update(&hash[32..]);
+        let prefix = hasher.finalize()[..32].try_into().unwrap();
+
+        BlindedPrivateKey { scalar, prefix }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blinded_keys_match() {
+        for b in 0..4u8 {
+            let private = PrivateKey { bytes: [b; 32] };
+            let public = private.derive_public_key();
+            let factor = [b + 1; 32];
+            let blinded_private = private.blind(&factor);
+            let blinded_public = public.blind(&factor).unwrap();
+            assert_eq!(blinded_private.public_key().bytes, blinded_public.bytes);
+            assert_ne!(blinded_public.bytes, public.bytes);
+        }
+    }
+
+    #[test]
+    fn test_blinded_signatures_verify() {
+        let private = PrivateKey { bytes: [7; 32] };
+        let public = private.derive_public_key();
+        let factor = [42; 32];
+        let blinded_public = public.blind(&factor).unwrap();
+        let message = b"onion";
+        let sig = private.blind(&factor).sign(message);
+        assert!(blinded_public.verify(message, sig));
+        assert!(!public.verify(message, sig));
+    }
+
+    #[test]
+    fn test_different_factors_give_different_keys() {
+        let private = PrivateKey { bytes: [3; 32] };
+        let public = private.derive_public_key();
+        let a = public.blind(&[1; 32]).unwrap();
+        let b = public.blind(&[2; 32]).unwrap();
+        assert_ne!(a.bytes, b.bytes);
+    }
+}
@@ -0,0 +1,65 @@
+//! This module implements Pedersen commitments over our curve.
+//!
+//! A commitment to a value v, with blinding factor r, is the point v⋅B + r⋅H.
+//! This hides v as long as r is random, and binds the committer to v as long as
+//! nobody knows the discrete logarithm of H with respect to B.
+//!
+//! Commitments are additively homomorphic: adding the commitments to (v0, r0) and
+//! (v1, r1) produces the commitment to (v0 + v1, r0 + r1).
+use super::{point, point::EdwardsPoint, scalar::Scalar};
+
+/// The message we hash to obtain the second generator H.
+const H_MESSAGE: &[u8] = b"Pedersen commitment generator H";
+
+/// The domain separation tag used when hashing to obtain H.
+const H_DST: &[u8] = b"eddo-v1-with-edwards25519_XMD:SHA-512_ELL2_RO_";
+
+/// The second generator H used in commitments.
+///
+/// This is obtained by hashing a fixed string to the curve, so nobody knows
+/// its discrete logarithm with respect to the standard basepoint.
+pub fn generator_h() -> EdwardsPoint {
+    EdwardsPoint::hash_to_curve(H_MESSAGE, H_DST)
+}
+
+/// Commit to a value, using a blinding factor.
+///
+/// The blinding factor should be chosen uniformly at random, and kept secret
+/// until the commitment is opened.
+pub fn commit(value: Scalar, blinding: Scalar) -> EdwardsPoint {
+    point::B * value + generator_h() * blinding
+}
+
+/// Check that a commitment opens to a given value and blinding factor.
+pub fn verify(commitment: EdwardsPoint, value: Scalar, blinding: Scalar) -> bool {
+    let expected = commit(value, blinding).compress().bytes;
+    let actual = commitment.compress().bytes;
+    expected == actual
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_commitment_opens() {
+        let c = commit(Scalar::from(5), Scalar::from(7));
+        assert!(verify(c, Scalar::from(5), Scalar::from(7)));
+        assert!(!verify(c, Scalar::from(6), Scalar::from(7)));
+        assert!(!verify(c, Scalar::from(5), Scalar::from(8)));
+    }
+
+    #[test]
+    fn test_commitment_is_homomorphic() {
+        let c0 = commit(Scalar::from(5), Scalar::from(7));
+        let c1 = commit(Scalar::from(11), Scalar::from(13));
+        assert!(verify(c0 + c1, Scalar::from(16), Scalar::from(20)));
+    }
+
+    #[test]
+    fn test_generator_h_is_not_b() {
+        let h = generator_h().compress().bytes;
+        let b = point::B.compress().bytes;
+        assert_ne!(h, b);
+    }
+}
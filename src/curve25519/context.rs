@@ -0,0 +1,100 @@
+//! This module implements Ed25519ctx, the variant of Ed25519 with a context.
+//!
+//! The context is bound into the signature, so that a signature made for one
+//! application can't be passed off as a signature for another, even over the
+//! same message, as long as they use different contexts.
+//!
+//! This follows Section 5.1 of RFC 8032:
+//! https://datatracker.ietf.org/doc/html/rfc8032#section-5.1
+use core::convert::TryInto;
+
+use super::{prehash::dom2, scalar::Scalar, sign_with_domain, PrivateKey, PublicKey, Signature};
+use crate::sha512;
+
+impl PrivateKey {
+    /// Sign a message, using Ed25519ctx, with a given context.
+    ///
+    /// The context must be between 1 and 255 bytes long, and this returns `None`
+    /// otherwise. An empty context should use plain Ed25519 instead.
+    pub fn sign_with_context(&self, message: &[u8], context: &[u8]) -> Option<Signature> {
+        if context.is_empty() {
+            return None;
+        }
+        let (domain, len) = dom2(0, context)?;
+        let hash = sha512::hash(&self.bytes);
+        let s = Scalar::clamped(hash[..32].try_into().unwrap());
+        Some(sign_with_domain(s, &domain[..len], &hash[32..], message))
+    }
+}
+
+impl PublicKey {
+    /// Verify an Ed25519ctx signature over a message, made with a given context.
+    pub fn verify_with_context(
+        &self,
+        message: &[u8],
+        context: &[u8],
+        signature: Signature,
+    ) -> bool {
+        if context.is_empty() {
+            return false;
+        }
+        match dom2(0, context) {
+            Some((domain, len)) => self
+                .verify_with_domain(&domain[..len], message, signature)
+                .is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::curve25519::ExpandedPrivateKey;
+
+    #[test]
+    fn test_rfc_8032_ed25519ctx_vector() {
+        // This is the first test vector from Section 7.2
+        let mut private = PrivateKey { bytes: [0; 32] };
+        hex::decode_to_slice(
+            "0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6",
+            &mut private.bytes,
+        )
+        .unwrap();
+        let public = private.public_key();
+        assert_eq!(
+            hex::encode(public.bytes),
+            "dfc9425e4f968f7f0c29f0259cf5f9aed6851c2bb4ad8bfb860cfee0ab248292"
+        );
+        let message = hex::decode("f726936d19c800494e3fdaff20b276a8").unwrap();
+        let sig = private.sign_with_context(&message, b"foo").unwrap();
+        assert_eq!(
+            hex::encode(sig.bytes),
+            "55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d"
+        );
+        assert!(public.verify_with_context(&message, b"foo", sig));
+    }
+
+    #[test]
+    fn test_contexts_are_separated() {
+        let private = PrivateKey { bytes: [4; 32] };
+        let public = private.public_key();
+        let sig = private.sign_with_context(b"message", b"app-v1").unwrap();
+        assert!(public.verify_with_context(b"message", b"app-v1", sig));
+        assert!(!public.verify_with_context(b"message", b"app-v2", sig));
+        assert!(!public.verify_with_context(b"message", b"", sig));
+        assert!(!public.verify(b"message", sig));
+        assert!(private.sign_with_context(b"message", b"").is_none());
+        assert!(private.sign_with_context(b"message", &[0; 256]).is_none());
+    }
+
+    #[test]
+    fn test_expanded_key_matches_private_key() {
+        let private = PrivateKey { bytes: [4; 32] };
+        let expanded = ExpandedPrivateKey::<sha512::Hasher>::from_private_key(&private);
+        assert_eq!(
+            expanded.sign_with_context(b"message", b"ctx").unwrap(),
+            private.sign_with_context(b"message", b"ctx").unwrap()
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! This module implements designated verifier signatures.
+//!
+//! A designated verifier signature convinces one chosen verifier that a message
+//! was signed, but nobody else. It's a Schnorr proof that the prover knows either
+//! the signer's secret a, or the verifier's secret v, made non-interactive with
+//! Fiat-Shamir, just like Ed25519 itself.
+//!
+//! Since the verifier knows v, they can produce such proofs for any message by
+//! themselves. So a proof convinces them that the signer was involved, but it
+//! can't convince anybody else, making these signatures non-transferable.
+//!
+//! A signature is c_a || c_v || z_a || z_v, and is valid when, with
+//! R_a = z_a⋅B - c_a⋅A, and R_v = z_v⋅B - c_v⋅V, we have:
+//!
+//! c_a + c_v = H(A || V || R_a || R_v || M)
+use core::convert::{TryFrom, TryInto};
+
+use super::{
+    point,
+    point::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    PrivateKey, PublicKey,
+};
+use crate::sha512;
+
+/// The number of bytes in a designated verifier signature.
+pub const DESIGNATED_SIGNATURE_SIZE: usize = 128;
+
+/// The context used when hashing, separating these proofs from other uses of SHA-512.
+const DOMAIN: &[u8] = b"eddo designated verifier signature v1";
+
+/// Represents a signature which only convinces one designated verifier.
+#[derive(Debug, Clone, Copy)]
+pub struct DesignatedSignature {
+    pub bytes: [u8; DESIGNATED_SIGNATURE_SIZE],
+}
+
+impl DesignatedSignature {
+    fn parts(&self) -> Option<[Scalar; 4]> {
+        let mut out = [Scalar::from(0); 4];
+        for (x, chunk) in out.iter_mut().zip(self.bytes.chunks_exact(32)) {
+            *x = Scalar::try_from(chunk).ok()?;
+        }
+        Some(out)
+    }
+}
+
+/// Calculate the combined challenge c = H(A || V || R_a || R_v || M).
+fn challenge(
+    a: &[u8; 32],
+    v: &[u8; 32],
+    r_a: EdwardsPoint,
+    r_v: EdwardsPoint,
+    message: &[u8],
+) -> Scalar {
+    let r_a = r_a.compress().bytes;
+    let r_v = r_v.compress().bytes;
+    let mut hasher = sha512::Hasher::new();
+    hasher.update(DOMAIN);
+    hasher.update(a);
+    hasher.update(v);
+    hasher.update(&r_a);
+    hasher.update(&r_v);
+    hasher.update(message);
+    Scalar::from(hasher.finalize())
+}
+
+/// Derive a scalar deterministically from a seed, and an index.
+fn derive_scalar(seed: &[u8; 64], index: u8) -> Scalar {
+    let mut to_hash = [0u8; 65];
+    to_hash[..64].copy_from_slice(seed);
+    to_hash[64] = index;
+    Scalar::from(sha512::hash(&to_hash))
+}
+
+/// Prove knowledge of the secret for one of the two keys.
+///
+/// `known` is the secret scalar for `known_key`, and `other_key` is the key
+/// whose branch of the proof gets simulated. The nonces are derived from the secret
+/// prefix of the known key, and the message. This returns the challenge and response
+/// for the known branch, followed by those for the other branch.
+fn prove(
+    known: Scalar,
+    known_key: &[u8; 32],
+    other_key: &[u8; 32],
+    prefix: &[u8],
+    message: &[u8],
+    challenge_for: impl Fn(EdwardsPoint, EdwardsPoint) -> Scalar,
+) -> Option<[Scalar; 4]> {
+    let other = CompressedEdwardsY { bytes: *other_key }.decompress()?;
+
+    let mut hasher = sha512::Hasher::new();
+    hasher.update(prefix);
+    hasher.update(message);
+    hasher.update(known_key);
+    hasher.update(other_key);
+    let seed = hasher.finalize();
+    let r = derive_scalar(&seed, 0);
+    let c_other = derive_scalar(&seed, 1);
+    let z_other = derive_scalar(&seed, 2);
+
+    let r_known = point::B * r;
+    let r_other = point::B * z_other + other * -c_other;
+    let c = challenge_for(r_known, r_other);
+    let c_known = c + -c_other;
+    let z_known = r + c_known * known;
+    Some([c_known, z_known, c_other, z_other])
+}
+
+fn encode(c_a: Scalar, c_v: Scalar, z_a: Scalar, z_v: Scalar) -> DesignatedSignature {
+    let mut out = DesignatedSignature {
+        bytes: [0; DESIGNATED_SIGNATURE_SIZE],
+    };
+    for (chunk, x) in out.bytes.chunks_exact_mut(32).zip([c_a, c_v, z_a, z_v]) {
+        let bytes: [u8; 32] = x.into();
+        chunk.copy_from_slice(&bytes);
+    }
+    out
+}
+
+impl PrivateKey {
+    fn secret_and_prefix(&self) -> (Scalar, [u8; 64]) {
+        let hash = sha512::hash(&self.bytes);
+        let s = Scalar::clamped(hash[..32].try_into().unwrap()).reduce();
+        (s, hash)
+    }
+
+    /// Sign a message, in a way that only convinces a designated verifier.
+    ///
+    /// This returns `None` if the verifier's public key isn't a valid point.
+    pub fn sign_designated(
+        &self,
+        verifier: &PublicKey,
+        message: &[u8],
+    ) -> Option<DesignatedSignature> {
+        let (a, hash) = self.secret_and_prefix();
+        let a_bytes = self.public_key().bytes;
+        let v_bytes = &verifier.bytes;
+        let [c_a, z_a, c_v, z_v] =
+            prove(a, &a_bytes, v_bytes, &hash[32..], message, |r_a, r_v| {
+                challenge(&a_bytes, v_bytes, r_a, r_v, message)
+            })?;
+        Some(encode(c_a, c_v, z_a, z_v))
+    }
+
+    /// Produce a designated signature by `signer`, addressed to ourselves.
+    ///
+    /// This requires no involvement from the signer, and is what makes these signatures
+    /// non-transferable: the designated verifier could have made any of them.
+    pub fn simulate_designated(
+        &self,
+        signer: &PublicKey,
+        message: &[u8],
+    ) -> Option<DesignatedSignature> {
+        let (v, hash) = self.secret_and_prefix();
+        let v_bytes = self.public_key().bytes;
+        let a_bytes = &signer.bytes;
+        let [c_v, z_v, c_a, z_a] =
+            prove(v, &v_bytes, a_bytes, &hash[32..], message, |r_v, r_a| {
+                challenge(a_bytes, &v_bytes, r_a, r_v, message)
+            })?;
+        Some(encode(c_a, c_v, z_a, z_v))
+    }
+}
+
+impl PublicKey {
+    /// Verify a designated signature made by this key, for a given verifier.
+    pub fn verify_designated(
+        &self,
+        verifier: &PublicKey,
+        message: &[u8],
+        signature: &DesignatedSignature,
+    ) -> bool {
+        let check = || -> Option<bool> {
+            let [c_a, c_v, z_a, z_v] = signature.parts()?;
+            let a = CompressedEdwardsY { bytes: self.bytes }.decompress()?;
+            let v = CompressedEdwardsY {
+                bytes: verifier.bytes,
+            }
+            .decompress()?;
+            let r_a = point::B * z_a + a * -c_a;
+            let r_v = point::B * z_v + v * -c_v;
+            let expected: [u8; 32] =
+                challenge(&self.bytes, &verifier.bytes, r_a, r_v, message).into();
+            let actual: [u8; 32] = (c_a + c_v).into();
+            Some(expected == actual)
+        };
+        check().unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn setup() -> (PublicKey, PrivateKey, PublicKey, PrivateKey) {
+        let signer = PrivateKey { bytes: [1; 32] };
+        let verifier = PrivateKey { bytes: [2; 32] };
+        (signer.public_key(), signer, verifier.public_key(), verifier)
+    }
+
+    #[test]
+    fn test_designated_signature_verifies() {
+        let (a, signer, v, _) = setup();
+        let sig = signer.sign_designated(&v, b"receipt").unwrap();
+        assert!(a.verify_designated(&v, b"receipt", &sig));
+        assert!(!a.verify_designated(&v, b"other", &sig));
+    }
+
+    #[test]
+    fn test_designated_signature_is_bound_to_verifier() {
+        let (a, signer, v, _) = setup();
+        let other = PrivateKey { bytes: [3; 32] }.public_key();
+        let sig = signer.sign_designated(&v, b"receipt").unwrap();
+        assert!(!a.verify_designated(&other, b"receipt", &sig));
+        assert!(!v.verify_designated(&a, b"receipt", &sig));
+    }
+
+    #[test]
+    fn test_verifier_can_simulate() {
+        let (a, _, v, verifier) = setup();
+        let sig = verifier.simulate_designated(&a, b"forged receipt").unwrap();
+        assert!(a.verify_designated(&v, b"forged receipt", &sig));
+    }
+
+    #[test]
+    fn test_tampered_signature_fails() {
+        let (a, signer, v, _) = setup();
+        let sig = signer.sign_designated(&v, b"receipt").unwrap();
+        for i in [0, 32, 64, 96] {
+            let mut tampered = sig;
+            tampered.bytes[i] ^= 1;
+            assert!(!a.verify_designated(&v, b"receipt", &tampered));
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! This module implements proofs that two discrete logarithms are equal.
+//!
+//! Given two bases G and H, and two points P = x⋅G and Q = x⋅H, a proof convinces
+//! a verifier that the same secret x was used for both points, without revealing x.
+//! This lets us show that two public keys share the same secret scalar, e.g. a key
+//! A = a⋅B, and the same secret used with another base.
+//!
+//! This is a Schnorr proof, made non-interactive with Fiat-Shamir. A proof is c || z,
+//! and is valid when, with R_G = z⋅G - c⋅P, and R_H = z⋅H - c⋅Q, we have:
+//!
+//! c = H(G || H || P || Q || R_G || R_H || context)
+//!
+//! Every point has to be torsion free. Otherwise, a small order component lets
+//! P and Q have discrete logarithms which only agree modulo the order of that component.
+use core::convert::TryInto;
+
+use subtle::ConstantTimeEq;
+
+use super::{
+    point,
+    point::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    PrivateKey, PublicKey,
+};
+use crate::sha512;
+
+/// The number of bytes in a proof.
+pub const DLEQ_PROOF_SIZE: usize = 64;
+
+/// The context used when hashing the challenge.
+const CHALLENGE_DOMAIN: &[u8] = b"eddo dleq challenge v1";
+
+/// The context used when deriving the nonce.
+const NONCE_DOMAIN: &[u8] = b"eddo dleq nonce v1";
+
+/// Represents a proof that two points share the same discrete logarithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DleqProof {
+    pub bytes: [u8; DLEQ_PROOF_SIZE],
+}
+
+/// Calculate the challenge c = H(G || H || P || Q || R_G || R_H || context).
+///
+/// Every point takes up 32 bytes, so the context, coming last, is the only input
+/// without a fixed length, and the encoding of the transcript can't be ambiguous.
+fn challenge(points: [EdwardsPoint; 6], context: &[u8]) -> Scalar {
+    let mut hasher = sha512::Hasher::new();
+    hasher.update(CHALLENGE_DOMAIN);
+    for p in points {
+        hasher.update(&p.compress().bytes);
+    }
+    hasher.update(context);
+    Scalar::from(hasher.finalize())
+}
+
+impl DleqProof {
+    /// Prove that x⋅G and x⋅H share the same secret x.
+    ///
+    /// The nonce is derived deterministically from the secret and the statement, so
+    /// no randomness is needed. The context is bound into the proof, and must match
+    /// when verifying.
+    pub fn new(x: Scalar, g: EdwardsPoint, h: EdwardsPoint, context: &[u8]) -> DleqProof {
+        let p = g * x;
+        let q = h * x;
+
+        let mut hasher = sha512::Hasher::new();
+        hasher.update(NONCE_DOMAIN);
+        hasher.update(&x.to_bytes());
+        hasher.update(&g.compress().bytes);
+        hasher.update(&h.compress().bytes);
+        hasher.update(context);
+        let r = Scalar::from(hasher.finalize());
+
+        let c = challenge([g, h, p, q, g * r, h * r], context);
+        let z = r + c * x;
+        let mut out = DleqProof {
+            bytes: [0; DLEQ_PROOF_SIZE],
+        };
+        out.bytes[..32].copy_from_slice(&c.to_bytes());
+        out.bytes[32..].copy_from_slice(&z.to_bytes());
+        out
+    }
+
+    /// Verify that P = x⋅G and Q = x⋅H, for some secret x.
+    ///
+    /// This returns `false` if any of the points has a small order component.
+    pub fn verify(
+        &self,
+        g: EdwardsPoint,
+        p: EdwardsPoint,
+        h: EdwardsPoint,
+        q: EdwardsPoint,
+        context: &[u8],
+    ) -> bool {
+        if ![g, p, h, q].iter().all(EdwardsPoint::is_torsion_free) {
+            return false;
+        }
+        let check = || -> Option<bool> {
+            let c = Scalar::from_canonical_bytes(self.bytes[..32].try_into().unwrap())?;
+            let z = Scalar::from_canonical_bytes(self.bytes[32..].try_into().unwrap())?;
+            let r_g = g * z - p * c;
+            let r_h = h * z - q * c;
+            let expected = challenge([g, h, p, q, r_g, r_h], context);
+            Some(expected.ct_eq(&c).into())
+        };
+        check().unwrap_or(false)
+    }
+}
+
+impl PrivateKey {
+    /// Use the secret scalar of this key with another base, proving that we did so.
+    ///
+    /// This returns the point a⋅H, along with a proof that it shares its secret
+    /// with our public key, A = a⋅B.
+    pub fn prove_key_equivalence(
+        &self,
+        h: EdwardsPoint,
+        context: &[u8],
+    ) -> (EdwardsPoint, DleqProof) {
+        let hash = sha512::hash(&self.bytes);
+        let a = Scalar::clamped(hash[..32].try_into().unwrap()).reduce();
+        (h * a, DleqProof::new(a, point::B, h, context))
+    }
+}
+
+impl PublicKey {
+    /// Verify that a point, with respect to the base H, shares its secret with this key.
+    ///
+    /// This returns `false` if this public key isn't a valid point.
+    pub fn verify_key_equivalence(
+        &self,
+        h: EdwardsPoint,
+        q: EdwardsPoint,
+        context: &[u8],
+        proof: &DleqProof,
+    ) -> bool {
+        match (CompressedEdwardsY { bytes: self.bytes }).decompress() {
+            Some(a) => proof.verify(point::B, a, h, q, context),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn other_base() -> EdwardsPoint {
+        EdwardsPoint::hash_to_curve(b"other base", b"eddo-v1-test")
+    }
+
+    #[test]
+    fn test_proof_verifies() {
+        let x = Scalar::from(1234);
+        let h = other_base();
+        let proof = DleqProof::new(x, point::B, h, b"ctx");
+        assert!(proof.verify(point::B, point::B * x, h, h * x, b"ctx"));
+        assert!(!proof.verify(point::B, point::B * x, h, h * x, b"other"));
+        let y = Scalar::from(1235);
+        assert!(!proof.verify(point::B, point::B * x, h, h * y, b"ctx"));
+    }
+
+    #[test]
+    fn test_tampered_proof_fails() {
+        let x = Scalar::from(99);
+        let h = other_base();
+        let proof = DleqProof::new(x, point::B, h, b"");
+        for i in [0, 32] {
+            let mut tampered = proof;
+            tampered.bytes[i] ^= 1;
+            assert!(!tampered.verify(point::B, point::B * x, h, h * x, b""));
+        }
+    }
+
+    #[test]
+    fn test_torsion_is_rejected() {
+        let mut order_8 = CompressedEdwardsY { bytes: [0; 32] };
+        hex::decode_to_slice(
+            "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a",
+            &mut order_8.bytes,
+        )
+        .unwrap();
+        let t = order_8.decompress_zip215().unwrap();
+        let x = Scalar::from(1234);
+        let h = other_base() + t;
+        let proof = DleqProof::new(x, point::B, h, b"ctx");
+        assert!(!proof.verify(point::B, point::B * x, h, h * x, b"ctx"));
+        // Q only has the same logarithm as P modulo 8
+        let h = other_base();
+        let proof = DleqProof::new(x, point::B, h, b"ctx");
+        assert!(!proof.verify(point::B, point::B * x, h, h * x + t, b"ctx"));
+    }
+
+    #[test]
+    fn test_key_equivalence() {
+        let private = PrivateKey { bytes: [7; 32] };
+        let public = private.public_key();
+        let h = other_base();
+        let (q, proof) = private.prove_key_equivalence(h, b"ctx");
+        assert!(public.verify_key_equivalence(h, q, b"ctx", &proof));
+        let stranger = PrivateKey { bytes: [8; 32] }.public_key();
+        assert!(!stranger.verify_key_equivalence(h, q, b"ctx", &proof));
+    }
+}
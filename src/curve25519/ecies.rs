@@ -0,0 +1,136 @@
+//! This module implements sealed box encryption to an Ed25519 public key.
+//!
+//! The sender generates an ephemeral key e, and does Diffie-Hellman with the
+//! recipient's public key A, giving a shared point e⋅A. The recipient, with secret
+//! scalar a, recovers the same point as a⋅E, using the ephemeral public key E = e⋅B.
+//!
+//! A key is derived from the shared point with HKDF-SHA512, and then used to
+//! encrypt the message with ChaCha20-Poly1305, authenticating E as associated data.
+//! Each key is only ever used once, so we can use a fixed nonce.
+//!
+//! A sealed message is laid out as E || ciphertext || tag.
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use rand::{CryptoRng, RngCore};
+
+use super::{
+    point,
+    point::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    PrivateKey, PublicKey,
+};
+use crate::{
+    aead::{self, TAG_SIZE},
+    kdf::hkdf,
+    sha512,
+};
+
+/// The number of bytes added to a message when sealing it.
+pub const OVERHEAD: usize = 32 + TAG_SIZE;
+
+/// The info string used when deriving keys, separating them from other protocols.
+const KDF_INFO: &[u8] = b"eddo ecies v2";
+
+/// The nonce we use, which is fine, since every key is fresh.
+const NONCE: [u8; aead::NONCE_SIZE] = [0; aead::NONCE_SIZE];
+
+/// Derive the encryption key, from the shared point.
+fn derive_key(
+    shared: EdwardsPoint,
+    ephemeral: &[u8; 32],
+    recipient: &[u8; 32],
+) -> [u8; aead::KEY_SIZE] {
+    let shared = shared.compress().bytes;
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral);
+    salt[32..].copy_from_slice(recipient);
+    let okm = hkdf(&salt, &shared, KDF_INFO);
+    okm[..aead::KEY_SIZE].try_into().unwrap()
+}
+
+/// Encrypt a message so that only the owner of a public key can read it.
+///
+/// This returns `None` if the public key isn't a valid point.
+pub fn seal<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    recipient: &PublicKey,
+    message: &[u8],
+) -> Option<Vec<u8>> {
+    let a = CompressedEdwardsY {
+        bytes: recipient.bytes,
+    }
+    .decompress()?;
+    let mut seed = [0u8; 32];
+    rng.fill_bytes(&mut seed);
+    // Clamping clears the cofactor, so small order components of A don't leak.
+    let e = Scalar::clamped(seed);
+    let ephemeral = (point::B * e).compress().bytes;
+    let key = derive_key(a * e, &ephemeral, &recipient.bytes);
+
+    let mut out = Vec::with_capacity(OVERHEAD + message.len());
+    out.extend_from_slice(&ephemeral);
+    out.extend_from_slice(message);
+    let t = aead::encrypt(&key, &NONCE, &ephemeral, &mut out[32..]);
+    out.extend_from_slice(&t);
+    Some(out)
+}
+
+/// Decrypt a message sealed to the public key associated with a private key.
+///
+/// This returns `None` if the sealed message is malformed, or has been tampered with.
+pub fn open(recipient: &PrivateKey, sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < OVERHEAD {
+        return None;
+    }
+    let (body, t) = sealed.split_at(sealed.len() - TAG_SIZE);
+    let ephemeral: [u8; 32] = body[..32].try_into().unwrap();
+    let t: &[u8; TAG_SIZE] = t.try_into().unwrap();
+    let big_e = CompressedEdwardsY { bytes: ephemeral }.decompress()?;
+
+    let hash = sha512::hash(&recipient.bytes);
+    let a = Scalar::clamped(hash[..32].try_into().unwrap());
+    let public = (point::B * a).compress().bytes;
+    let key = derive_key(big_e * a, &ephemeral, &public);
+
+    let mut out = body[32..].to_vec();
+    if !aead::decrypt(&key, &NONCE, &ephemeral, &mut out, t) {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_seal_then_open() {
+        let (public, private) = crate::gen_keypair(&mut OsRng);
+        let message = b"attack at dawn, but make it a long enough message to span blocks";
+        let sealed = seal(&mut OsRng, &public, message).unwrap();
+        assert_eq!(sealed.len(), message.len() + OVERHEAD);
+        assert_eq!(open(&private, &sealed).unwrap(), message);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let (public, private) = crate::gen_keypair(&mut OsRng);
+        let sealed = seal(&mut OsRng, &public, b"hello").unwrap();
+        for i in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 1;
+            assert!(open(&private, &tampered).is_none());
+        }
+        assert!(open(&private, &sealed[..OVERHEAD - 1]).is_none());
+    }
+
+    #[test]
+    fn test_wrong_key_cannot_open() {
+        let (public, _) = crate::gen_keypair(&mut OsRng);
+        let (_, other) = crate::gen_keypair(&mut OsRng);
+        let sealed = seal(&mut OsRng, &public, b"hello").unwrap();
+        assert!(open(&other, &sealed).is_none());
+    }
+}
@@ -1,6 +1,20 @@
+/// Represents the reason some bytes got rejected, when decoding or verifying.
+///
+/// Verification checks the encoding of s, then of A, and R, where they get decoded,
+/// and only then the equation, so the first problem found is the one reported.
+/// `verify_strict` also checks the orders of A and R, just before the equation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SignatureError {
+    /// The bytes aren't the encoding of any point on the curve.
     InvalidPoint,
+    /// A field element was too short, or at least P.
     InvalidFieldElement,
+    /// A scalar, like the s half of a signature, was too short, or at least L.
     InvalidScalar,
+    /// Everything decoded correctly, but the signature isn't valid for this key and message.
     InvalidEquation,
+    /// The bytes encode a point, but not canonically, with y at least P, or a sign bit for x = 0.
+    NonCanonicalPoint,
+    /// A point has small order, which strict verification doesn't allow for A or R.
+    SmallOrderPoint,
 }
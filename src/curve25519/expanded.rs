@@ -0,0 +1,287 @@
+//! This module implements expanded private keys, generic over the hash function.
+//!
+//! Ed25519 hashes the private key to get a secret scalar, and a nonce prefix,
+//! every time it signs. An expanded key does this once, which saves some work
+//! when signing many messages, and also lets us swap out SHA-512 for another
+//! hash function producing 64 bytes, like SHA3-512, giving Ed25519-SHA3.
+//!
+//! Signatures made with a different hash function are incompatible with Ed25519,
+//! and need to be checked with `PublicKey::verify_with_hash_function`.
+use core::{convert::TryInto, marker::PhantomData};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use super::{
+    point, prehash, scalar::Scalar, sign_with_domain, sign_with_hash, PrivateKey, PublicKey,
+    Signature,
+};
+use crate::sha512;
+
+/// A hash function producing 64 bytes, which signatures can be built with.
+pub trait SignatureHash: Default {
+    /// Feed more of the message into this hasher.
+    fn update(&mut self, data: &[u8]);
+
+    /// Finish hashing, producing the hash of all the input so far.
+    fn finalize(self) -> [u8; 64];
+}
+
+impl SignatureHash for sha512::Hasher {
+    fn update(&mut self, data: &[u8]) {
+        sha512::Hasher::update(self, data)
+    }
+
+    fn finalize(self) -> [u8; 64] {
+        sha512::Hasher::finalize(self)
+    }
+}
+
+#[cfg(feature = "sha3")]
+impl SignatureHash for crate::sha3::Sha3_512 {
+    fn update(&mut self, data: &[u8]) {
+        crate::sha3::Sha3_512::update(self, data)
+    }
+
+    fn finalize(self) -> [u8; 64] {
+        crate::sha3::Sha3_512::finalize(self)
+    }
+}
+
+/// A private key, which has already been hashed into a scalar, and a nonce prefix.
+///
+/// By default, this uses SHA-512, producing standard Ed25519 signatures.
+pub struct ExpandedPrivateKey<H = sha512::Hasher> {
+    scalar: Scalar,
+    prefix: [u8; 32],
+    public: PublicKey,
+    _hash: PhantomData<H>,
+}
+
+impl<H: SignatureHash> ExpandedPrivateKey<H> {
+    /// Expand a private key, using the hash function of this key type.
+    pub fn from_private_key(private: &PrivateKey) -> Self {
+        let mut hasher = H::default();
+        hasher.update(&private.bytes);
+        let hash = hasher.finalize();
+        let scalar = Scalar::clamped(hash[..32].try_into().unwrap());
+        ExpandedPrivateKey {
+            scalar,
+            prefix: hash[32..].try_into().unwrap(),
+            public: PublicKey {
+                bytes: (point::B * scalar).compress().bytes,
+            },
+            _hash: PhantomData,
+        }
+    }
+
+    /// The public key associated with this key.
+    pub fn public_key(&self) -> PublicKey {
+        self.public
+    }
+
+    /// Sign a message, using the hash function of this key type.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        sign_with_hash::<H>(self.scalar, &[], &self.prefix, message)
+    }
+
+    /// Sign a message read in chunks, using a bounded amount of memory.
+    ///
+    /// Signing needs two passes over the message, so `open` is called twice, and
+    /// should produce the same message each time. If the two readings differ,
+    /// this returns an error instead of a signature, since signing different
+    /// messages with the same nonce would reveal the private key.
+    ///
+    /// `chunk_size` must not be 0.
+    #[cfg(feature = "std")]
+    pub fn sign_chunked<R: Read>(
+        &self,
+        mut open: impl FnMut() -> io::Result<R>,
+        chunk_size: usize,
+    ) -> io::Result<Signature> {
+        if chunk_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "chunk size must not be 0",
+            ));
+        }
+        let mut buf = vec![0; chunk_size];
+
+        // The nonce is H(prefix || M)
+        let mut nonce_hasher = H::default();
+        nonce_hasher.update(&self.prefix);
+        let mut first_digest = sha512::Hasher::new();
+        read_chunks(open()?, &mut buf, |chunk| {
+            nonce_hasher.update(chunk);
+            first_digest.update(chunk);
+        })?;
+        let r = Scalar::from(nonce_hasher.finalize());
+        let big_r = (point::B * r).compress().bytes;
+
+        // The challenge is H(R || A || M), needing the message again
+        let mut challenge_hasher = H::default();
+        challenge_hasher.update(&big_r);
+        challenge_hasher.update(&self.public.bytes);
+        let mut second_digest = sha512::Hasher::new();
+        read_chunks(open()?, &mut buf, |chunk| {
+            challenge_hasher.update(chunk);
+            second_digest.update(chunk);
+        })?;
+        if first_digest.finalize() != second_digest.finalize() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message changed while it was being signed",
+            ));
+        }
+        let k = Scalar::from(challenge_hasher.finalize());
+
+        let big_s: [u8; 32] = (r + k * self.scalar).into();
+        let mut out = Signature { bytes: [0; 64] };
+        out.bytes[..32].copy_from_slice(&big_r);
+        out.bytes[32..].copy_from_slice(&big_s);
+        Ok(out)
+    }
+}
+
+/// Feed everything from a reader into a function, a chunk at a time.
+#[cfg(feature = "std")]
+fn read_chunks<R: Read>(mut reader: R, buf: &mut [u8], mut f: impl FnMut(&[u8])) -> io::Result<()> {
+    loop {
+        match reader.read(buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => f(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl ExpandedPrivateKey<sha512::Hasher> {
+    /// Sign the SHA-512 digest of a message, using Ed25519ph.
+    ///
+    /// This is the same as `PrivateKey::sign_prehashed`, returning `None` if the
+    /// context is longer than 255 bytes.
+    pub fn sign_prehashed(
+        &self,
+        digest: &[u8; prehash::DIGEST_SIZE],
+        context: &[u8],
+    ) -> Option<Signature> {
+        let (domain, len) = prehash::dom2(1, context)?;
+        Some(sign_with_domain(
+            self.scalar,
+            &domain[..len],
+            &self.prefix,
+            digest,
+        ))
+    }
+}
+
+impl ExpandedPrivateKey<sha512::Hasher> {
+    /// Sign a message, using Ed25519ctx, with a given context.
+    ///
+    /// This is the same as `PrivateKey::sign_with_context`, returning `None` if the
+    /// context is empty, or longer than 255 bytes.
+    pub fn sign_with_context(&self, message: &[u8], context: &[u8]) -> Option<Signature> {
+        if context.is_empty() {
+            return None;
+        }
+        let (domain, len) = prehash::dom2(0, context)?;
+        Some(sign_with_domain(
+            self.scalar,
+            &domain[..len],
+            &self.prefix,
+            message,
+        ))
+    }
+}
+
+impl PublicKey {
+    /// Verify a signature made with a given hash function, in place of SHA-512.
+    ///
+    /// With `sha512::Hasher`, this is the same as `verify`.
+    pub fn verify_with_hash_function<H: SignatureHash>(
+        &self,
+        message: &[u8],
+        signature: Signature,
+    ) -> bool {
+        self.verify_with_hash::<H>(&[], message, signature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sha512_expanded_key_matches_private_key() {
+        let private = PrivateKey { bytes: [1; 32] };
+        let expanded = ExpandedPrivateKey::<sha512::Hasher>::from_private_key(&private);
+        assert_eq!(expanded.public_key(), private.public_key());
+        assert_eq!(expanded.sign(b"message"), private.sign(b"message"));
+        let public = private.public_key();
+        assert!(public
+            .verify_with_hash_function::<sha512::Hasher>(b"message", expanded.sign(b"message")));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_sign_chunked_matches_sign() {
+        let private = PrivateKey { bytes: [5; 32] };
+        let expanded = ExpandedPrivateKey::<sha512::Hasher>::from_private_key(&private);
+        let message: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        for &chunk_size in &[1, 64, 100, 4096] {
+            let sig = expanded
+                .sign_chunked(|| Ok(&message[..]), chunk_size)
+                .unwrap();
+            assert_eq!(sig, private.sign(&message));
+        }
+        assert!(expanded.sign_chunked(|| Ok(&message[..]), 0).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_sign_chunked_rejects_changing_messages() {
+        let expanded =
+            ExpandedPrivateKey::<sha512::Hasher>::from_private_key(&PrivateKey { bytes: [6; 32] });
+        let mut calls = 0;
+        let result = expanded.sign_chunked(
+            || {
+                calls += 1;
+                Ok(if calls == 1 {
+                    &b"first"[..]
+                } else {
+                    &b"second"[..]
+                })
+            },
+            16,
+        );
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_expanded_prehashed_signatures_match_private_key() {
+        let private = PrivateKey { bytes: [3; 32] };
+        let expanded = ExpandedPrivateKey::<sha512::Hasher>::from_private_key(&private);
+        let digest = prehash::digest(b"artifact");
+        assert_eq!(
+            expanded.sign_prehashed(&digest, b"ctx"),
+            private.sign_prehashed(&digest, b"ctx")
+        );
+        assert_eq!(expanded.sign_prehashed(&digest, &[0; 256]), None);
+    }
+
+    #[cfg(feature = "sha3")]
+    #[test]
+    fn test_sha3_signatures_are_separate() {
+        use crate::sha3::Sha3_512;
+
+        let private = PrivateKey { bytes: [2; 32] };
+        let expanded = ExpandedPrivateKey::<Sha3_512>::from_private_key(&private);
+        let public = expanded.public_key();
+        // Even the public key differs, since it comes from hashing the private key.
+        assert_ne!(public, private.public_key());
+        let sig = expanded.sign(b"message");
+        assert!(public.verify_with_hash_function::<Sha3_512>(b"message", sig));
+        assert!(!public.verify_with_hash_function::<Sha3_512>(b"other", sig));
+        assert!(!public.verify(b"message", sig));
+    }
+}
@@ -1,119 +1,56 @@
-use std::{
-    convert::{TryFrom, TryInto},
-    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
-};
-
-use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
-
-use crate::arch::adc;
-
-use super::{arithmetic::U256, error::SignatureError};
-
-const P: U256 = U256 {
-    limbs: [
-        0xFFFF_FFFF_FFFF_FFED,
-        0xFFFF_FFFF_FFFF_FFFF,
-        0xFFFF_FFFF_FFFF_FFFF,
-        0x7FFF_FFFF_FFFF_FFFF,
-    ],
-};
-
-const TWO_P_MINUS_1_OVER_4: Z25519 = Z25519 {
-    value: U256 {
-        limbs: [
-            0xc4ee1b274a0ea0b0,
-            0x2f431806ad2fe478,
-            0x2b4d00993dfbd7a7,
-            0x2b8324804fc1df0b,
-        ],
-    },
-};
-
-/// Represents an element in the field Z/(2^255 - 19).
-///
-/// The operations in this field are defined through arithmetic modulo
-/// P := 2^255 - 19
-///
-/// # Creation
-///
-/// Elements in the field can be created from `u64`.
-#[derive(Clone, Copy, Debug)]
-// Only implement equality for tests. This is to avoid the temptation to introduce
-// a timing leak through equality comparison in other situations.
-#[cfg_attr(test, derive(PartialEq))]
-pub struct Z25519 {
-    pub value: U256,
-}
+//! This module implements arithmetic in the field Z/(2^255 - 19).
+//!
+//! The representation of elements is provided by a backend: by default, four
+//! 64 bit limbs, always fully reduced, and, with the `radix51` feature, five 51 bit
+//! limbs with lazy carries, like curve25519-dalek's serial backend. With the `fiat`
+//! feature, which takes precedence, the arithmetic comes from fiat-crypto's formally
+//! verified implementation instead. Everything built on top of the basic operations
+//! lives here, and is shared by all of them.
+//!
+//! A backend provides `Z25519`, which must be `Copy` and `Debug`, along with:
+//!
+//! - `const fn from_limbs([u64; 4])`, used by `from_le_hex` for constants, `From<u64>`, `From<[u64; 4]>`, and `TryFrom<&[u8]>`,
+//!   rejecting encodings ≥ P,
+//! - `Into<[u8; 32]>`, producing the canonical little endian encoding,
+//! - `reduce_wide(U512)`, `square`, and `is_negative`,
+//! - `Add`, `Sub`, `Neg`, `Mul`, `Mul<u64>`, and their assigning variants,
+//! - `ConditionallySelectable` and `ConstantTimeEq`, comparing elements, not limbs,
+//! - `PartialEq`, but only under `cfg(test)`.
+//!
+//! The tests in this module run against whichever backend is selected.
+use core::ops::Neg;
+
+use subtle::{Choice, ConditionallyNegatable, ConstantTimeEq};
+
+use super::arithmetic::U256;
+
+#[cfg(not(any(feature = "radix51", feature = "fiat")))]
+mod radix64;
+#[cfg(not(any(feature = "radix51", feature = "fiat")))]
+pub use self::radix64::Z25519;
+
+#[cfg(all(feature = "radix51", not(feature = "fiat")))]
+mod radix51;
+#[cfg(all(feature = "radix51", not(feature = "fiat")))]
+pub use self::radix51::Z25519;
+
+#[cfg(feature = "fiat")]
+mod fiat;
+#[cfg(feature = "fiat")]
+pub use self::fiat::Z25519;
+
+pub(crate) const P_MINUS_1_OVER_2: U256 =
+    U256::from_le_hex("f6ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff3f");
+
+const TWO_P_MINUS_1_OVER_4: Z25519 =
+    Z25519::from_le_hex("b0a00e4a271beec478e42fad0618432fa7d7fb3d99004d2b0bdfc14f8024832b");
 
 impl Z25519 {
-    /// reduce_after_addition reduces this element modulo P, after an addition.
-    ///
-    /// After an addition, we have at most 2P - 2, so at most one subtraction of P suffices.
-    fn reduce_after_addition(&mut self, carry: u8) {
-        let mut m_removed = *self;
-        // The largest result we've just calculated is 2P - 2. Therefore, we might
-        // need to subtract P once, if we have a result >= P.
-        let borrow = m_removed.value.sub_with_borrow(P);
-        // A few cases here:
-        //
-        // carry = 1, borrow = 0:
-        //    Impossible: we would need a result ≥ 2²⁵⁶ + P
-        // carry = 1, borrow = 1:
-        //     We produced a result larger than 2^256, with an extra bit, so certainly
-        //     we should subtract P. This will always produce a borrow, given our input ranges.
-        // carry = 0, borrow = 1:
-        //     Our result fits over 4 limbs, but is < P.
-        //     We don't want to choose the subtraction
-        // carry = 0, borrow = 0:
-        //     Our result fits over 4 limbs, but is ≥ P.
-        //     We want to choose the subtraction.
-        self.conditional_assign(&m_removed, borrow.ct_eq(&carry))
-    }
-
-    /// reduce_after_scaling reduces this element modulo P, after a scaling.
+    /// Create an element from the hex encoding of its 32 little endian bytes, which must be < P.
     ///
-    /// After a scaling, this number fits over 5 limbs, and there's an efficient way
-    /// to reduce it modulo P.
-    fn reduce_after_scaling(&mut self, carry: u64) {
-        // Let's say that:
-        //     A = q⋅2²⁵⁵ + R
-        // This means that:
-        //     A = q⋅P + R + 19q
-        // Modulo P, this entails:
-        //     A ≡ R + 19q mod P
-        // We can efficiently calculate q and R using shifting and masking.
-
-        // We pull in one bit from the top limb, in order to calculate the quotient
-        let q = (carry << 1) | (self.value.limbs[3] >> 63);
-        // Clear the top bit, thus calculating R
-        self.value.limbs[3] &= 0x7FFF_FFFF_FFFF_FFFF;
-        // Now we add in 19q
-        let full_res = 19 * u128::from(q);
-        let mut carry = 0;
-        carry = adc(
-            carry,
-            full_res as u64,
-            self.value.limbs[0],
-            &mut self.value.limbs[0],
-        );
-        carry = adc(
-            carry,
-            (full_res >> 64) as u64,
-            self.value.limbs[1],
-            &mut self.value.limbs[1],
-        );
-        carry = adc(carry, 0, self.value.limbs[2], &mut self.value.limbs[2]);
-        carry = adc(carry, 0, self.value.limbs[3], &mut self.value.limbs[3]);
-        // Now remove P if necessary
-        self.reduce_after_addition(carry);
-    }
-
-    /// calculate z <- z * z mod P.
-    ///
-    /// This is equivalent to z *= z, but is a bit more efficient, because it takes
-    /// advantage of the extra symmetry of this operation compared to the general case.
-    pub fn square(&mut self) {
-        *self *= *self;
+    /// This is meant for constants, see `U256::from_le_hex`.
+    pub(crate) const fn from_le_hex(hex: &str) -> Z25519 {
+        Z25519::from_limbs(U256::from_le_hex(hex).limbs)
     }
 
     /// calculates z * z mod P
@@ -151,6 +88,54 @@ impl Z25519 {
         out
     }
 
+    /// batch_invert replaces each element with its inverse, using a single inversion.
+    ///
+    /// This uses Montgomery's trick, costing 3 multiplications per element instead
+    /// of an inversion each. Like with `inverse`, elements equal to 0 are left as 0.
+    #[cfg(feature = "alloc")]
+    pub fn batch_invert(elements: &mut [Z25519]) {
+        use subtle::ConditionallySelectable;
+
+        let zero = Z25519::from(0);
+        let one = Z25519::from(1);
+        // products[i] holds the product of every non-zero element before i
+        let mut products = alloc::vec::Vec::with_capacity(elements.len());
+        let mut acc = one;
+        for x in elements.iter() {
+            products.push(acc);
+            acc *= Z25519::conditional_select(x, &one, x.ct_eq(&zero));
+        }
+        // Now we peel off one element at a time, starting from the end
+        let mut acc_inv = acc.inverse();
+        for (x, before) in elements.iter_mut().zip(products).rev() {
+            let is_zero = x.ct_eq(&zero);
+            let x_inv = acc_inv * before;
+            acc_inv *= Z25519::conditional_select(x, &one, is_zero);
+            *x = Z25519::conditional_select(&x_inv, &zero, is_zero);
+        }
+    }
+
+    /// pow calculates self^exponent mod P.
+    ///
+    /// This is constant-time with respect to self, but not the exponent, which
+    /// is assumed to be public.
+    pub fn pow(self, exponent: &U256) -> Z25519 {
+        let mut out = Z25519::from(1);
+        for limb in exponent.limbs.iter().rev() {
+            for i in (0..64).rev() {
+                out.square();
+                if (limb >> i) & 1 == 1 {
+                    out *= self;
+                }
+            }
+        }
+        out
+    }
+
+    /// fraction_root calculates a square root of u / v, if one exists.
+    ///
+    /// This avoids an inversion, and is the method used to decompress points, as per
+    /// section 5.1.3 of RFC 8032.
     pub fn fraction_root(u: Self, v: Self) -> Option<Self> {
         let v_2 = v.squared();
         let v_3 = v * v_2;
@@ -170,146 +155,63 @@ impl Z25519 {
         }
         let x = u * v_3 * powered;
         let v_x_2 = v * x.squared();
-        if v_x_2.value.eq(u.value) {
+        if bool::from(v_x_2.ct_eq(&u)) {
             return Some(x);
         }
-        if v_x_2.value.eq((-u).value) {
+        if bool::from(v_x_2.ct_eq(&-u)) {
             return Some(x * TWO_P_MINUS_1_OVER_4);
         }
         None
     }
 }
 
-impl Into<[u8; 32]> for Z25519 {
-    fn into(self) -> [u8; 32] {
-        self.value.into()
-    }
-}
-
-impl<'a> TryFrom<&'a [u8]> for Z25519 {
-    type Error = SignatureError;
-
-    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
-        if value.len() < 32 {
-            return Err(SignatureError::InvalidFieldElement);
-        }
-        let value_bytes: [u8; 32] = value[..32].try_into().unwrap();
-        let value = U256::from(value_bytes);
-        if value.geq(P) {
-            return Err(SignatureError::InvalidScalar);
-        }
-        Ok(Z25519 { value })
+impl Z25519 {
+    /// sqrt calculates a square root of this element, if one exists.
+    ///
+    /// Of the two square roots, this returns the one which isn't negative,
+    /// as defined by `is_negative`.
+    pub fn sqrt(self) -> Option<Self> {
+        let mut x = Self::fraction_root(self, Self::from(1))?;
+        x.conditional_negate(x.is_negative());
+        Some(x)
     }
-}
 
-impl From<u64> for Z25519 {
-    fn from(x: u64) -> Self {
-        Z25519 {
-            value: U256::from(x),
-        }
+    /// is_square checks whether or not this element has a square root.
+    ///
+    /// This calculates the Legendre symbol self^((P - 1) / 2), which is 1 for non-zero
+    /// squares, -1 for non-squares, and 0 for 0, which also counts as a square.
+    pub fn is_square(&self) -> Choice {
+        let legendre = self.pow(&P_MINUS_1_OVER_2);
+        legendre.ct_eq(&Self::from(1)) | legendre.ct_eq(&Self::from(0))
     }
 }
 
-impl From<[u64; 4]> for Z25519 {
-    fn from(limbs: [u64; 4]) -> Self {
-        Z25519 {
-            value: U256 { limbs },
-        }
+#[cfg(all(feature = "hazmat", feature = "alloc"))]
+impl Z25519 {
+    /// Encode this element as hex, using the same little endian bytes as its encoding.
+    pub fn to_hex(&self) -> alloc::string::String {
+        let bytes: [u8; 32] = (*self).into();
+        hex::encode(bytes)
     }
 }
 
-impl ConditionallySelectable for Z25519 {
-    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
-        Z25519 {
-            value: U256::conditional_select(&a.value, &b.value, choice),
+#[cfg(feature = "hazmat")]
+impl core::fmt::LowerHex for Z25519 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
         }
+        let bytes: [u8; 32] = (*self).into();
+        super::write_hex(f, &bytes)
     }
 }
 
-impl AddAssign for Z25519 {
-    fn add_assign(&mut self, other: Self) {
-        let carry = self.value.add_with_carry(other.value);
-        self.reduce_after_addition(carry);
-    }
-}
-
-impl Add for Z25519 {
-    type Output = Self;
-
-    fn add(mut self, other: Self) -> Self::Output {
-        self += other;
-        self
-    }
-}
-
-impl SubAssign for Z25519 {
-    fn sub_assign(&mut self, other: Z25519) {
-        // We perform the subtraction, and then add back P if we underflowed.
-        let borrow = self.value.sub_with_borrow(other.value);
-        self.value.cond_add(P, borrow.ct_eq(&1));
-    }
-}
-
-impl Sub for Z25519 {
-    type Output = Self;
-
-    fn sub(mut self, other: Z25519) -> Self::Output {
-        self -= other;
-        self
-    }
-}
-
-impl Neg for Z25519 {
-    type Output = Self;
-
-    fn neg(self) -> Self::Output {
-        // NOTE: Hopefully Rust inlines things, to avoid materializing 4 zeros in memory
-        Self::from(0) - self
-    }
-}
-
-impl MulAssign<u64> for Z25519 {
-    fn mul_assign(&mut self, small: u64) {
-        let (carry, lo) = self.value * small;
-        self.value = lo;
-        self.reduce_after_scaling(carry);
-    }
-}
-
-impl Mul<u64> for Z25519 {
+// Implementing negation for references gives us `ConditionallyNegatable` for free.
+impl Neg for &Z25519 {
     type Output = Z25519;
 
-    fn mul(mut self, small: u64) -> Self::Output {
-        self *= small;
-        self
-    }
-}
-
-impl MulAssign for Z25519 {
-    fn mul_assign(&mut self, other: Self) {
-        let res = self.value * other.value;
-        // At this point, we've multiplied things out, and have:
-        //     hi⋅2²⁵⁶ + lo
-        // Observe that 2²⁵⁶ = 2⋅(2²⁵⁵ - 19) + 38, so mod P, we have:
-        //     hi + 38⋅lo
-        // All that's left is to multiply hi by 38, and then add in lo
-        let mut carry = 0u64;
-        for i in 0..4 {
-            let full_res =
-                u128::from(carry) + u128::from(res.limbs[i]) + 38 * u128::from(res.limbs[i + 4]);
-            self.value.limbs[i] = full_res as u64;
-            carry = (full_res >> 64) as u64;
-        }
-        self.reduce_after_scaling(carry);
-    }
-}
-
-impl Mul for Z25519 {
-    type Output = Self;
-
-    fn mul(mut self, other: Self) -> Self::Output {
-        self *= other;
-        self
+    fn neg(self) -> Self::Output {
+        -*self
     }
 }
 
@@ -319,6 +221,7 @@ mod test {
 
     use super::Z25519;
     use proptest::prelude::*;
+    use subtle::{ConditionallyNegatable, ConditionallySelectable};
 
     prop_compose! {
         fn arb_z25519()(
@@ -326,9 +229,7 @@ mod test {
             z1 in any::<u64>(),
             z2 in any::<u64>(),
             z3 in 0..((1u64 << 63) - 19)) -> Z25519 {
-            Z25519 {
-                value: U256 { limbs: [z0, z1, z2, z3] }
-            }
+            Z25519::from_limbs([z0, z1, z2, z3])
         }
     }
 
@@ -453,87 +354,143 @@ mod test {
         }
     }
 
+    proptest! {
+        #[test]
+        fn test_conditional_negate(a in arb_z25519()) {
+            let mut x = a;
+            x.conditional_negate(0.into());
+            assert_eq!(x, a);
+            x.conditional_negate(1.into());
+            assert_eq!(x, -a);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_conditional_swap(a in arb_z25519(), b in arb_z25519()) {
+            let (mut x, mut y) = (a, b);
+            Z25519::conditional_swap(&mut x, &mut y, 0.into());
+            assert_eq!((x, y), (a, b));
+            Z25519::conditional_swap(&mut x, &mut y, 1.into());
+            assert_eq!((x, y), (b, a));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_pow_small_exponents(a in arb_z25519()) {
+            assert_eq!(a.pow(&U256::from(0)), 1.into());
+            assert_eq!(a.pow(&U256::from(1)), a);
+            assert_eq!(a.pow(&U256::from(5)), a * a * a * a * a);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_pow_p_minus_2_is_inverse(a in arb_z25519()) {
+            let p_minus_2 = U256 {
+                limbs: [
+                    0xFFFF_FFFF_FFFF_FFEB,
+                    0xFFFF_FFFF_FFFF_FFFF,
+                    0xFFFF_FFFF_FFFF_FFFF,
+                    0x7FFF_FFFF_FFFF_FFFF,
+                ],
+            };
+            assert_eq!(a.pow(&p_minus_2), a.inverse());
+        }
+    }
+
     #[test]
-    fn test_addition_examples() {
-        let z1 = Z25519 {
-            value: U256 {
-                limbs: [1, 1, 1, 1],
-            },
-        };
-        let z2 = Z25519 {
-            value: U256 {
-                limbs: [2, 2, 2, 2],
-            },
-        };
-        let z3 = Z25519 {
-            value: U256 {
-                limbs: [3, 3, 3, 3],
-            },
+    fn test_reduce_wide_examples() {
+        use super::super::arithmetic::U512;
+
+        let two_256 = U512 {
+            limbs: [0, 0, 0, 0, 1, 0, 0, 0],
         };
+        assert_eq!(Z25519::reduce_wide(two_256), 38.into());
+        let max = U512 { limbs: [!0; 8] };
+        // 2^512 - 1 = 38^2 - 1 mod P
+        assert_eq!(Z25519::reduce_wide(max), (38 * 38 - 1).into());
+    }
+
+    #[test]
+    fn test_addition_examples() {
+        let z1 = Z25519::from_limbs([1, 1, 1, 1]);
+        let z2 = Z25519::from_limbs([2, 2, 2, 2]);
+        let z3 = Z25519::from_limbs([3, 3, 3, 3]);
         assert_eq!(z3, z1 + z2);
 
-        let two_254 = Z25519 {
-            value: U256 {
-                limbs: [0, 0, 0, 1 << 62],
-            },
-        };
+        let two_254 = Z25519::from_limbs([0, 0, 0, 1 << 62]);
         assert_eq!(two_254 + two_254, Z25519::from(19));
     }
 
     #[test]
     fn test_subtraction_examples() {
-        let mut z1 = Z25519 {
-            value: U256 {
-                limbs: [1, 1, 1, 1],
-            },
-        };
+        let mut z1 = Z25519::from_limbs([1, 1, 1, 1]);
         z1 -= z1;
         assert_eq!(z1, 0.into());
         z1 -= 1.into();
-        let p_minus_one = Z25519 {
-            value: U256 {
-                limbs: [
-                    0xFFFF_FFFF_FFFF_FFEC,
-                    0xFFFF_FFFF_FFFF_FFFF,
-                    0xFFFF_FFFF_FFFF_FFFF,
-                    0x7FFF_FFFF_FFFF_FFFF,
-                ],
-            },
-        };
+        let p_minus_one = Z25519::from_limbs([
+            0xFFFF_FFFF_FFFF_FFEC,
+            0xFFFF_FFFF_FFFF_FFFF,
+            0xFFFF_FFFF_FFFF_FFFF,
+            0x7FFF_FFFF_FFFF_FFFF,
+        ]);
         assert_eq!(z1, p_minus_one);
     }
 
     #[test]
     fn test_small_multiplication_examples() {
-        let z1 = Z25519 {
-            value: U256 { limbs: [1; 4] },
-        };
+        let z1 = Z25519::from_limbs([1; 4]);
         assert_eq!(z1 + z1, z1 * 2);
         assert_eq!(z1 + z1 + z1, z1 * 3);
-        let p_minus_one = Z25519 {
-            value: U256 {
-                limbs: [
-                    0xFFFF_FFFF_FFFF_FFEC,
-                    0xFFFF_FFFF_FFFF_FFFF,
-                    0xFFFF_FFFF_FFFF_FFFF,
-                    0x7FFF_FFFF_FFFF_FFFF,
-                ],
-            },
-        };
+        let p_minus_one = Z25519::from_limbs([
+            0xFFFF_FFFF_FFFF_FFEC,
+            0xFFFF_FFFF_FFFF_FFFF,
+            0xFFFF_FFFF_FFFF_FFFF,
+            0x7FFF_FFFF_FFFF_FFFF,
+        ]);
         assert_eq!(p_minus_one * 2, p_minus_one - 1.into());
         assert_eq!(p_minus_one * 3, p_minus_one - 2.into());
     }
 
     #[test]
     fn test_2192_times_zero() {
-        let two192 = Z25519 {
-            value: U256 {
-                limbs: [0, 0, 0, 1],
-            },
-        };
+        let two192 = Z25519::from_limbs([0, 0, 0, 1]);
         assert_eq!(two192 * Z25519::from(0), 0.into());
     }
 
+    proptest! {
+        #[test]
+        fn test_sqrt_of_square(a in arb_z25519()) {
+            let a_2 = a.squared();
+            assert!(bool::from(a_2.is_square()));
+            let root = a_2.sqrt().unwrap();
+            assert_eq!(root.squared(), a_2);
+            assert!(!bool::from(root.is_negative()));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_batch_invert(a in arb_z25519(), b in arb_z25519(), c in arb_z25519()) {
+            let mut batch = [a, Z25519::from(0), b, c];
+            Z25519::batch_invert(&mut batch);
+            assert_eq!(batch, [a.inverse(), Z25519::from(0), b.inverse(), c.inverse()]);
+        }
+    }
+
+    #[test]
+    fn test_sqrt_examples() {
+        assert_eq!(Z25519::from(4).sqrt(), Some(Z25519::from(2)));
+        assert_eq!(Z25519::from(0).sqrt(), Some(Z25519::from(0)));
+        // 2 isn't a square, since P = 5 mod 8
+        assert_eq!(Z25519::from(2).sqrt(), None);
+        assert!(!bool::from(Z25519::from(2).is_square()));
+        assert!(bool::from(Z25519::from(0).is_square()));
+        assert!(bool::from((-Z25519::from(1)).is_square()));
+    }
+
     #[test]
     fn test_minus_one_squared() {
         let mut minus_one = Z25519::from(0) - Z25519::from(1);
@@ -543,11 +500,31 @@ mod test {
 
     #[test]
     fn test_two_255() {
-        let two_254 = Z25519 {
-            value: U256 {
-                limbs: [0, 0, 0, 0x4000000000000000],
-            },
-        };
+        let two_254 = Z25519::from_limbs([0, 0, 0, 0x4000000000000000]);
         assert_eq!(two_254 * Z25519::from(2), 19.into());
     }
+
+    #[test]
+    #[cfg(feature = "hazmat")]
+    fn test_hex_formatting() {
+        let x = Z25519::from(0xAB);
+        let expected = format!("ab{}", "00".repeat(31));
+        assert_eq!(x.to_hex(), expected);
+        assert_eq!(format!("{:x}", x), expected);
+        assert_eq!(format!("{:#x}", x), format!("0x{}", expected));
+    }
+
+    #[test]
+    fn test_constants() {
+        let two_255 = U256 {
+            limbs: [0, 0, 0, 1 << 63],
+        };
+        // 2⋅(P - 1)/2 + 1 + 19 = 2^255
+        assert_eq!(
+            super::P_MINUS_1_OVER_2 + super::P_MINUS_1_OVER_2 + U256::from(20),
+            two_255
+        );
+        let sqrt_m1 = super::TWO_P_MINUS_1_OVER_4;
+        assert_eq!(sqrt_m1.squared(), -Z25519::from(1));
+    }
 }
@@ -0,0 +1,247 @@
+use core::{
+    convert::{TryFrom, TryInto},
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use fiat_crypto::curve25519_64::{
+    fiat_25519_add, fiat_25519_carry, fiat_25519_carry_mul, fiat_25519_carry_square,
+    fiat_25519_from_bytes, fiat_25519_loose_field_element, fiat_25519_opp, fiat_25519_relax,
+    fiat_25519_selectznz, fiat_25519_sub, fiat_25519_tight_field_element, fiat_25519_to_bytes,
+};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use super::super::{
+    arithmetic::{U256, U512},
+    error::SignatureError,
+};
+
+/// The field modulus, 2^255 - 19.
+const P: U256 =
+    U256::from_le_hex("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+
+const LOW_51_BITS: u64 = (1 << 51) - 1;
+
+/// Represents an element in the field Z/(2^255 - 19).
+///
+/// The operations in this field are defined through arithmetic modulo
+/// P := 2^255 - 19
+///
+/// # Representation
+///
+/// An element is stored as 5 limbs of 51 bits, like the `radix51` backend, but
+/// the arithmetic is done by fiat-crypto, whose code is generated, along with
+/// a proof of its correctness, from a specification of the field.
+///
+/// The limbs always satisfy fiat-crypto's "tight" bounds, each fitting over 51 bits,
+/// with one extra value. Operations producing "loose" limbs are carried right away.
+///
+/// # Creation
+///
+/// Elements in the field can be created from `u64`.
+#[derive(Clone, Copy, Debug)]
+pub struct Z25519 {
+    limbs: [u64; 5],
+}
+
+// Only implement equality for tests. This is to avoid the temptation to introduce
+// a timing leak through equality comparison in other situations.
+#[cfg(test)]
+impl PartialEq for Z25519 {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Z25519 {
+    /// Create an element from 4 little endian 64 bit limbs, which must represent a number < P.
+    pub const fn from_limbs(limbs: [u64; 4]) -> Self {
+        // The top bit is worth 2^255 ≡ 19, so we can fold it in, even if the number isn't < P
+        let l0 = (limbs[0] & LOW_51_BITS) + 19 * (limbs[3] >> 63);
+        // Carrying the bottom limb once keeps every limb within the tight bounds
+        Z25519 {
+            limbs: [
+                l0 & LOW_51_BITS,
+                (((limbs[0] >> 51) | (limbs[1] << 13)) & LOW_51_BITS) + (l0 >> 51),
+                ((limbs[1] >> 38) | (limbs[2] << 26)) & LOW_51_BITS,
+                ((limbs[2] >> 25) | (limbs[3] << 39)) & LOW_51_BITS,
+                (limbs[3] >> 12) & LOW_51_BITS,
+            ],
+        }
+    }
+
+    fn tight(self) -> fiat_25519_tight_field_element {
+        fiat_25519_tight_field_element(self.limbs)
+    }
+
+    fn loose(self) -> fiat_25519_loose_field_element {
+        let mut out = fiat_25519_loose_field_element([0; 5]);
+        fiat_25519_relax(&mut out, &self.tight());
+        out
+    }
+
+    fn carry(loose: fiat_25519_loose_field_element) -> Z25519 {
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_carry(&mut out, &loose);
+        Z25519 { limbs: out.0 }
+    }
+
+    /// reduce_wide calculates a 512 bit number modulo P.
+    ///
+    /// This works for any 512 bit number.
+    pub fn reduce_wide(wide: U512) -> Z25519 {
+        // We have hi⋅2²⁵⁶ + lo, and 2²⁵⁶ ≡ 38 mod P.
+        let lo = Z25519::from_limbs(wide.lo().limbs);
+        let hi = Z25519::from_limbs(wide.hi().limbs);
+        lo + hi * 38
+    }
+
+    /// calculate z <- z * z mod P.
+    ///
+    /// This is equivalent to z *= z.
+    pub fn square(&mut self) {
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_carry_square(&mut out, &self.loose());
+        self.limbs = out.0;
+    }
+
+    /// is_negative checks whether or not this element is odd.
+    ///
+    /// This is the `sgn0` function from RFC 9380, and is used to pick between the two
+    /// square roots of a number, as well as for the sign bit of encoded points.
+    pub fn is_negative(&self) -> Choice {
+        let bytes: [u8; 32] = (*self).into();
+        Choice::from(bytes[0] & 1)
+    }
+}
+
+impl From<Z25519> for [u8; 32] {
+    fn from(z: Z25519) -> Self {
+        let mut out = [0; 32];
+        fiat_25519_to_bytes(&mut out, &z.tight());
+        out
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Z25519 {
+    type Error = SignatureError;
+
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        if value.len() < 32 {
+            return Err(SignatureError::InvalidFieldElement);
+        }
+        let value_bytes: [u8; 32] = value[..32].try_into().unwrap();
+        // fiat-crypto ignores the top bit, and doesn't reject numbers ≥ P, so we do
+        if U256::from(value_bytes).geq(P) {
+            return Err(SignatureError::InvalidFieldElement);
+        }
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_from_bytes(&mut out, &value_bytes);
+        Ok(Z25519 { limbs: out.0 })
+    }
+}
+
+impl From<u64> for Z25519 {
+    fn from(x: u64) -> Self {
+        Z25519::from_limbs([x, 0, 0, 0])
+    }
+}
+
+impl From<[u64; 4]> for Z25519 {
+    fn from(limbs: [u64; 4]) -> Self {
+        Z25519::from_limbs(limbs)
+    }
+}
+
+impl ConditionallySelectable for Z25519 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut limbs = [0; 5];
+        fiat_25519_selectznz(&mut limbs, choice.unwrap_u8(), &a.limbs, &b.limbs);
+        Z25519 { limbs }
+    }
+}
+
+impl ConstantTimeEq for Z25519 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        // Limbs don't uniquely represent elements, so we compare encodings instead
+        let a: [u8; 32] = (*self).into();
+        let b: [u8; 32] = (*other).into();
+        a.ct_eq(&b)
+    }
+}
+
+impl AddAssign for Z25519 {
+    fn add_assign(&mut self, other: Self) {
+        let mut out = fiat_25519_loose_field_element([0; 5]);
+        fiat_25519_add(&mut out, &self.tight(), &other.tight());
+        *self = Z25519::carry(out);
+    }
+}
+
+impl Add for Z25519 {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self::Output {
+        self += other;
+        self
+    }
+}
+
+impl SubAssign for Z25519 {
+    fn sub_assign(&mut self, other: Z25519) {
+        let mut out = fiat_25519_loose_field_element([0; 5]);
+        fiat_25519_sub(&mut out, &self.tight(), &other.tight());
+        *self = Z25519::carry(out);
+    }
+}
+
+impl Sub for Z25519 {
+    type Output = Self;
+
+    fn sub(mut self, other: Z25519) -> Self::Output {
+        self -= other;
+        self
+    }
+}
+
+impl Neg for Z25519 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        let mut out = fiat_25519_loose_field_element([0; 5]);
+        fiat_25519_opp(&mut out, &self.tight());
+        Z25519::carry(out)
+    }
+}
+
+impl MulAssign<u64> for Z25519 {
+    fn mul_assign(&mut self, small: u64) {
+        // fiat-crypto only multiplies by 121666, so we use a full multiplication
+        *self *= Z25519::from(small);
+    }
+}
+
+impl Mul<u64> for Z25519 {
+    type Output = Z25519;
+
+    fn mul(mut self, small: u64) -> Self::Output {
+        self *= small;
+        self
+    }
+}
+
+impl MulAssign for Z25519 {
+    fn mul_assign(&mut self, other: Self) {
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_carry_mul(&mut out, &self.loose(), &other.loose());
+        self.limbs = out.0;
+    }
+}
+
+impl Mul for Z25519 {
+    type Output = Self;
+
+    fn mul(mut self, other: Self) -> Self::Output {
+        self *= other;
+        self
+    }
+}
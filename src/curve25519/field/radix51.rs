@@ -0,0 +1,336 @@
+use core::{
+    convert::{TryFrom, TryInto},
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use super::super::{
+    arithmetic::{U256, U512},
+    error::SignatureError,
+};
+
+/// The field modulus, 2^255 - 19.
+const P: U256 =
+    U256::from_le_hex("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+
+const LOW_51_BITS: u64 = (1 << 51) - 1;
+
+/// 16⋅P, in 51 bit limbs, added before subtracting, to avoid underflow.
+const SIXTEEN_P: [u64; 5] = [
+    16 * (LOW_51_BITS - 18),
+    16 * LOW_51_BITS,
+    16 * LOW_51_BITS,
+    16 * LOW_51_BITS,
+    16 * LOW_51_BITS,
+];
+
+/// Represents an element in the field Z/(2^255 - 19).
+///
+/// The operations in this field are defined through arithmetic modulo
+/// P := 2^255 - 19
+///
+/// # Representation
+///
+/// An element is stored as 5 limbs of 51 bits, with
+///     z = l₀ + l₁⋅2⁵¹ + l₂⋅2¹⁰² + l₃⋅2¹⁵³ + l₄⋅2²⁰⁴
+/// The limbs may hold a few extra bits, and the number might not be fully reduced.
+/// We only carry as much as needed to keep the limbs from overflowing, and only
+/// reduce completely when encoding, or comparing, elements.
+///
+/// # Creation
+///
+/// Elements in the field can be created from `u64`.
+#[derive(Clone, Copy, Debug)]
+pub struct Z25519 {
+    limbs: [u64; 5],
+}
+
+// Only implement equality for tests. This is to avoid the temptation to introduce
+// a timing leak through equality comparison in other situations.
+#[cfg(test)]
+impl PartialEq for Z25519 {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Z25519 {
+    /// Create an element from 4 little endian 64 bit limbs, which must represent a number < P.
+    pub const fn from_limbs(limbs: [u64; 4]) -> Self {
+        // The top bit is worth 2^255 ≡ 19, so we can fold it in, even if the number isn't < P
+        Z25519 {
+            limbs: [
+                (limbs[0] & LOW_51_BITS) + 19 * (limbs[3] >> 63),
+                ((limbs[0] >> 51) | (limbs[1] << 13)) & LOW_51_BITS,
+                ((limbs[1] >> 38) | (limbs[2] << 26)) & LOW_51_BITS,
+                ((limbs[2] >> 25) | (limbs[3] << 39)) & LOW_51_BITS,
+                (limbs[3] >> 12) & LOW_51_BITS,
+            ],
+        }
+    }
+
+    /// carry propagates the excess bits of each limb into the next one.
+    ///
+    /// The carry out of the top limb is worth 2^255 ≡ 19. Afterwards, each limb
+    /// fits over 52 bits, with the top 4 only slightly over 51 bits.
+    fn carry(mut limbs: [u64; 5]) -> Z25519 {
+        // Calculating all the carries first lets them be computed in parallel
+        let c0 = limbs[0] >> 51;
+        let c1 = limbs[1] >> 51;
+        let c2 = limbs[2] >> 51;
+        let c3 = limbs[3] >> 51;
+        let c4 = limbs[4] >> 51;
+        for limb in limbs.iter_mut() {
+            *limb &= LOW_51_BITS;
+        }
+        limbs[0] += c4 * 19;
+        limbs[1] += c0;
+        limbs[2] += c1;
+        limbs[3] += c2;
+        limbs[4] += c3;
+        Z25519 { limbs }
+    }
+
+    /// Fully reduce this element modulo P, returning 4 little endian 64 bit limbs.
+    fn to_canonical_limbs(self) -> [u64; 4] {
+        let mut l = Self::carry(self.limbs).limbs;
+        // We now have a number < 2P, so we either subtract P once, or not at all.
+        // This happens exactly when z + 19 ≥ 2^255, which gives us the quotient.
+        let mut q = (l[0] + 19) >> 51;
+        q = (l[1] + q) >> 51;
+        q = (l[2] + q) >> 51;
+        q = (l[3] + q) >> 51;
+        q = (l[4] + q) >> 51;
+        // Subtracting P is adding 19, and then dropping 2^255.
+        l[0] += 19 * q;
+        l[1] += l[0] >> 51;
+        l[0] &= LOW_51_BITS;
+        l[2] += l[1] >> 51;
+        l[1] &= LOW_51_BITS;
+        l[3] += l[2] >> 51;
+        l[2] &= LOW_51_BITS;
+        l[4] += l[3] >> 51;
+        l[3] &= LOW_51_BITS;
+        l[4] &= LOW_51_BITS;
+        [
+            l[0] | (l[1] << 51),
+            (l[1] >> 13) | (l[2] << 38),
+            (l[2] >> 26) | (l[3] << 25),
+            (l[3] >> 39) | (l[4] << 12),
+        ]
+    }
+
+    /// reduce_wide calculates a 512 bit number modulo P.
+    ///
+    /// This works for any 512 bit number.
+    pub fn reduce_wide(wide: U512) -> Z25519 {
+        // We have hi⋅2²⁵⁶ + lo, and 2²⁵⁶ ≡ 38 mod P.
+        let lo = Z25519::from_limbs(wide.lo().limbs);
+        let hi = Z25519::from_limbs(wide.hi().limbs);
+        lo + hi * 38
+    }
+
+    /// calculate z <- z * z mod P.
+    ///
+    /// This is equivalent to z *= z.
+    pub fn square(&mut self) {
+        *self *= *self;
+    }
+
+    /// is_negative checks whether or not this element is odd.
+    ///
+    /// This is the `sgn0` function from RFC 9380, and is used to pick between the two
+    /// square roots of a number, as well as for the sign bit of encoded points.
+    pub fn is_negative(&self) -> Choice {
+        Choice::from((self.to_canonical_limbs()[0] & 1) as u8)
+    }
+}
+
+impl From<Z25519> for [u8; 32] {
+    fn from(z: Z25519) -> Self {
+        U256 {
+            limbs: z.to_canonical_limbs(),
+        }
+        .into()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Z25519 {
+    type Error = SignatureError;
+
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        if value.len() < 32 {
+            return Err(SignatureError::InvalidFieldElement);
+        }
+        let value_bytes: [u8; 32] = value[..32].try_into().unwrap();
+        let value = U256::from(value_bytes);
+        if value.geq(P) {
+            return Err(SignatureError::InvalidFieldElement);
+        }
+        Ok(Z25519::from_limbs(value.limbs))
+    }
+}
+
+impl From<u64> for Z25519 {
+    fn from(x: u64) -> Self {
+        Z25519::from_limbs([x, 0, 0, 0])
+    }
+}
+
+impl From<[u64; 4]> for Z25519 {
+    fn from(limbs: [u64; 4]) -> Self {
+        Z25519::from_limbs(limbs)
+    }
+}
+
+impl ConditionallySelectable for Z25519 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut limbs = [0; 5];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = u64::conditional_select(&a.limbs[i], &b.limbs[i], choice);
+        }
+        Z25519 { limbs }
+    }
+
+    fn conditional_swap(a: &mut Self, b: &mut Self, choice: Choice) {
+        for (x, y) in a.limbs.iter_mut().zip(b.limbs.iter_mut()) {
+            u64::conditional_swap(x, y, choice);
+        }
+    }
+}
+
+impl ConstantTimeEq for Z25519 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        // Limbs don't uniquely represent elements, so we need to reduce them first
+        self.to_canonical_limbs()[..].ct_eq(&other.to_canonical_limbs()[..])
+    }
+}
+
+impl AddAssign for Z25519 {
+    fn add_assign(&mut self, other: Self) {
+        let mut limbs = self.limbs;
+        for (l, o) in limbs.iter_mut().zip(other.limbs.iter()) {
+            *l += o;
+        }
+        *self = Z25519::carry(limbs);
+    }
+}
+
+impl Add for Z25519 {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self::Output {
+        self += other;
+        self
+    }
+}
+
+impl SubAssign for Z25519 {
+    fn sub_assign(&mut self, other: Z25519) {
+        // Adding 16P first makes every limb large enough to not underflow.
+        let mut limbs = self.limbs;
+        for ((l, o), p) in limbs
+            .iter_mut()
+            .zip(other.limbs.iter())
+            .zip(SIXTEEN_P.iter())
+        {
+            *l = (*l + p) - o;
+        }
+        *self = Z25519::carry(limbs);
+    }
+}
+
+impl Sub for Z25519 {
+    type Output = Self;
+
+    fn sub(mut self, other: Z25519) -> Self::Output {
+        self -= other;
+        self
+    }
+}
+
+impl Neg for Z25519 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::from(0) - self
+    }
+}
+
+impl MulAssign<u64> for Z25519 {
+    fn mul_assign(&mut self, small: u64) {
+        let mut carry = 0u128;
+        for limb in self.limbs.iter_mut() {
+            let full_res = u128::from(*limb) * u128::from(small) + carry;
+            *limb = (full_res as u64) & LOW_51_BITS;
+            carry = full_res >> 51;
+        }
+        // The final carry is worth 2^255 ≡ 19, and fits over 66 bits
+        let full_res = u128::from(self.limbs[0]) + 19 * carry;
+        self.limbs[0] = (full_res as u64) & LOW_51_BITS;
+        self.limbs[1] += (full_res >> 51) as u64;
+    }
+}
+
+impl Mul<u64> for Z25519 {
+    type Output = Z25519;
+
+    fn mul(mut self, small: u64) -> Self::Output {
+        self *= small;
+        self
+    }
+}
+
+impl MulAssign for Z25519 {
+    fn mul_assign(&mut self, other: Self) {
+        #[inline(always)]
+        fn m(x: u64, y: u64) -> u128 {
+            u128::from(x) * u128::from(y)
+        }
+
+        let a = &self.limbs;
+        let b = &other.limbs;
+        // Limbs past the top wrap around, multiplied by 2^255 ≡ 19. Our limbs
+        // never exceed 52 bits, so multiplying by 19 still fits over 64 bits.
+        let b1_19 = b[1] * 19;
+        let b2_19 = b[2] * 19;
+        let b3_19 = b[3] * 19;
+        let b4_19 = b[4] * 19;
+
+        let c0 = m(a[0], b[0]) + m(a[4], b1_19) + m(a[3], b2_19) + m(a[2], b3_19) + m(a[1], b4_19);
+        let mut c1 =
+            m(a[1], b[0]) + m(a[0], b[1]) + m(a[4], b2_19) + m(a[3], b3_19) + m(a[2], b4_19);
+        let mut c2 =
+            m(a[2], b[0]) + m(a[1], b[1]) + m(a[0], b[2]) + m(a[4], b3_19) + m(a[3], b4_19);
+        let mut c3 = m(a[3], b[0]) + m(a[2], b[1]) + m(a[1], b[2]) + m(a[0], b[3]) + m(a[4], b4_19);
+        let mut c4 = m(a[4], b[0]) + m(a[3], b[1]) + m(a[2], b[2]) + m(a[1], b[3]) + m(a[0], b[4]);
+
+        // Each coefficient fits over 108 bits, so we carry them down to 51 bits.
+        let mut out = [0u64; 5];
+        c1 += c0 >> 51;
+        out[0] = (c0 as u64) & LOW_51_BITS;
+        c2 += c1 >> 51;
+        out[1] = (c1 as u64) & LOW_51_BITS;
+        c3 += c2 >> 51;
+        out[2] = (c2 as u64) & LOW_51_BITS;
+        c4 += c3 >> 51;
+        out[3] = (c3 as u64) & LOW_51_BITS;
+        let carry = (c4 >> 51) as u64;
+        out[4] = (c4 as u64) & LOW_51_BITS;
+        // The carry fits over 56 bits, so this doesn't overflow
+        out[0] += carry * 19;
+        out[1] += out[0] >> 51;
+        out[0] &= LOW_51_BITS;
+        self.limbs = out;
+    }
+}
+
+impl Mul for Z25519 {
+    type Output = Self;
+
+    fn mul(mut self, other: Self) -> Self::Output {
+        self *= other;
+        self
+    }
+}
@@ -0,0 +1,272 @@
+use core::{
+    convert::{TryFrom, TryInto},
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use crate::arch::adc;
+
+use super::super::{
+    arithmetic::{U256, U512},
+    error::SignatureError,
+};
+
+/// The field modulus, 2^255 - 19.
+const P: U256 =
+    U256::from_le_hex("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+
+/// Represents an element in the field Z/(2^255 - 19).
+///
+/// The operations in this field are defined through arithmetic modulo
+/// P := 2^255 - 19
+///
+/// # Creation
+///
+/// Elements in the field can be created from `u64`.
+#[derive(Clone, Copy, Debug)]
+// Only implement equality for tests. This is to avoid the temptation to introduce
+// a timing leak through equality comparison in other situations.
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Z25519 {
+    pub value: U256,
+}
+
+impl Z25519 {
+    /// Create an element from 4 little endian 64 bit limbs, which must represent a number < P.
+    pub const fn from_limbs(limbs: [u64; 4]) -> Self {
+        Z25519 {
+            value: U256 { limbs },
+        }
+    }
+
+    /// reduce_after_addition reduces this element modulo P, after an addition.
+    ///
+    /// After an addition, we have at most 2P - 2, so at most one subtraction of P suffices.
+    fn reduce_after_addition(&mut self, carry: u8) {
+        let mut m_removed = *self;
+        // The largest result we've just calculated is 2P - 2. Therefore, we might
+        // need to subtract P once, if we have a result >= P.
+        let borrow = m_removed.value.sub_with_borrow(P);
+        // A few cases here:
+        //
+        // carry = 1, borrow = 0:
+        //    Impossible: we would need a result ≥ 2²⁵⁶ + P
+        // carry = 1, borrow = 1:
+        //     We produced a result larger than 2^256, with an extra bit, so certainly
+        //     we should subtract P. This will always produce a borrow, given our input ranges.
+        // carry = 0, borrow = 1:
+        //     Our result fits over 4 limbs, but is < P.
+        //     We don't want to choose the subtraction
+        // carry = 0, borrow = 0:
+        //     Our result fits over 4 limbs, but is ≥ P.
+        //     We want to choose the subtraction.
+        self.conditional_assign(&m_removed, borrow.ct_eq(&carry))
+    }
+
+    /// reduce_after_scaling reduces this element modulo P, after a scaling.
+    ///
+    /// After a scaling, this number fits over 5 limbs, and there's an efficient way
+    /// to reduce it modulo P.
+    fn reduce_after_scaling(&mut self, carry: u64) {
+        // Let's say that:
+        //     A = q⋅2²⁵⁵ + R
+        // This means that:
+        //     A = q⋅P + R + 19q
+        // Modulo P, this entails:
+        //     A ≡ R + 19q mod P
+        // We can efficiently calculate q and R using shifting and masking.
+
+        // We pull in one bit from the top limb, in order to calculate the quotient
+        let q = (carry << 1) | (self.value.limbs[3] >> 63);
+        // Clear the top bit, thus calculating R
+        self.value.limbs[3] &= 0x7FFF_FFFF_FFFF_FFFF;
+        // Now we add in 19q
+        let full_res = 19 * u128::from(q);
+        let mut carry = 0;
+        carry = adc(
+            carry,
+            full_res as u64,
+            self.value.limbs[0],
+            &mut self.value.limbs[0],
+        );
+        carry = adc(
+            carry,
+            (full_res >> 64) as u64,
+            self.value.limbs[1],
+            &mut self.value.limbs[1],
+        );
+        carry = adc(carry, 0, self.value.limbs[2], &mut self.value.limbs[2]);
+        carry = adc(carry, 0, self.value.limbs[3], &mut self.value.limbs[3]);
+        // Now remove P if necessary
+        self.reduce_after_addition(carry);
+    }
+
+    /// reduce_wide calculates a 512 bit number modulo P.
+    ///
+    /// This is used after multiplication, but works for any 512 bit number.
+    pub fn reduce_wide(wide: U512) -> Z25519 {
+        // At this point, we've multiplied things out, and have:
+        //     hi⋅2²⁵⁶ + lo
+        // Observe that 2²⁵⁶ = 2⋅(2²⁵⁵ - 19) + 38, so mod P, we have:
+        //     hi + 38⋅lo
+        // All that's left is to multiply hi by 38, and then add in lo
+        let mut out = Z25519::from(0);
+        let mut carry = 0u64;
+        for i in 0..4 {
+            let full_res =
+                u128::from(carry) + u128::from(wide.limbs[i]) + 38 * u128::from(wide.limbs[i + 4]);
+            out.value.limbs[i] = full_res as u64;
+            carry = (full_res >> 64) as u64;
+        }
+        out.reduce_after_scaling(carry);
+        out
+    }
+
+    /// calculate z <- z * z mod P.
+    ///
+    /// This is equivalent to z *= z, but is a bit more efficient, because it takes
+    /// advantage of the extra symmetry of this operation compared to the general case.
+    pub fn square(&mut self) {
+        *self *= *self;
+    }
+
+    /// is_negative checks whether or not this element is odd.
+    ///
+    /// This is the `sgn0` function from RFC 9380, and is used to pick between the two
+    /// square roots of a number, as well as for the sign bit of encoded points.
+    pub fn is_negative(&self) -> Choice {
+        Choice::from((self.value.limbs[0] & 1) as u8)
+    }
+}
+
+impl From<Z25519> for [u8; 32] {
+    fn from(z: Z25519) -> Self {
+        z.value.into()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Z25519 {
+    type Error = SignatureError;
+
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        if value.len() < 32 {
+            return Err(SignatureError::InvalidFieldElement);
+        }
+        let value_bytes: [u8; 32] = value[..32].try_into().unwrap();
+        let value = U256::from(value_bytes);
+        if value.geq(P) {
+            return Err(SignatureError::InvalidFieldElement);
+        }
+        Ok(Z25519 { value })
+    }
+}
+
+impl From<u64> for Z25519 {
+    fn from(x: u64) -> Self {
+        Z25519 {
+            value: U256::from(x),
+        }
+    }
+}
+
+impl From<[u64; 4]> for Z25519 {
+    fn from(limbs: [u64; 4]) -> Self {
+        Z25519 {
+            value: U256 { limbs },
+        }
+    }
+}
+
+impl ConditionallySelectable for Z25519 {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Z25519 {
+            value: U256::conditional_select(&a.value, &b.value, choice),
+        }
+    }
+
+    fn conditional_swap(a: &mut Self, b: &mut Self, choice: Choice) {
+        U256::conditional_swap(&mut a.value, &mut b.value, choice);
+    }
+}
+
+impl ConstantTimeEq for Z25519 {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        // Our operations always produce elements < P, so limbs uniquely represent elements
+        self.value.ct_eq(&other.value)
+    }
+}
+
+impl AddAssign for Z25519 {
+    fn add_assign(&mut self, other: Self) {
+        let carry = self.value.add_with_carry(other.value);
+        self.reduce_after_addition(carry);
+    }
+}
+
+impl Add for Z25519 {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self::Output {
+        self += other;
+        self
+    }
+}
+
+impl SubAssign for Z25519 {
+    fn sub_assign(&mut self, other: Z25519) {
+        // We perform the subtraction, and then add back P if we underflowed.
+        let borrow = self.value.sub_with_borrow(other.value);
+        self.value.cond_add(P, borrow.ct_eq(&1));
+    }
+}
+
+impl Sub for Z25519 {
+    type Output = Self;
+
+    fn sub(mut self, other: Z25519) -> Self::Output {
+        self -= other;
+        self
+    }
+}
+
+impl Neg for Z25519 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        // NOTE: Hopefully Rust inlines things, to avoid materializing 4 zeros in memory
+        Self::from(0) - self
+    }
+}
+
+impl MulAssign<u64> for Z25519 {
+    fn mul_assign(&mut self, small: u64) {
+        let (carry, lo) = self.value * small;
+        self.value = lo;
+        self.reduce_after_scaling(carry);
+    }
+}
+
+impl Mul<u64> for Z25519 {
+    type Output = Z25519;
+
+    fn mul(mut self, small: u64) -> Self::Output {
+        self *= small;
+        self
+    }
+}
+
+impl MulAssign for Z25519 {
+    fn mul_assign(&mut self, other: Self) {
+        *self = Z25519::reduce_wide(self.value * other.value);
+    }
+}
+
+impl Mul for Z25519 {
+    type Output = Self;
+
+    fn mul(mut self, other: Self) -> Self::Output {
+        self *= other;
+        self
+    }
+}
@@ -0,0 +1,367 @@
+//! This module implements hashing arbitrary messages to points on our curve.
+//!
+//! This follows RFC 9380, using the edwards25519_XMD:SHA-512_ELL2_RO_ suite:
+//! https://datatracker.ietf.org/doc/html/rfc9380#section-6.8.2
+use rand::{CryptoRng, RngCore};
+use subtle::{ConditionallySelectable, ConstantTimeEq, ConstantTimeGreater};
+
+use super::{
+    arithmetic::{U256, U512},
+    field::{P_MINUS_1_OVER_2, Z25519},
+    point::{AffinePoint, EdwardsPoint},
+};
+use crate::sha512;
+
+/// The number of bytes in a block of SHA-512, called `s_in_bytes` in the RFC.
+const BLOCK_SIZE: usize = 128;
+
+/// The number of bytes we use to produce each field element, called `L` in the RFC.
+const FIELD_BYTES: usize = 48;
+
+/// Domain separation tags longer than this need to be hashed first.
+const MAX_DST_SIZE: usize = 255;
+
+/// The prefix used when hashing oversized domain separation tags.
+const OVERSIZE_DST_PREFIX: &[u8] = b"H2C-OVERSIZE-DST-";
+
+/// The A coefficient of Curve25519, called J in the RFC.
+const J: u64 = 486662;
+
+/// 2^((P + 3) / 8), called c2 in the RFC.
+const C2: Z25519 =
+    Z25519::from_le_hex("b1a00e4a271beec478e42fad0618432fa7d7fb3d99004d2b0bdfc14f8024832b");
+
+/// sqrt(-1), called c3 in the RFC.
+const SQRT_M1: Z25519 =
+    Z25519::from_le_hex("b0a00e4a271beec478e42fad0618432fa7d7fb3d99004d2b0bdfc14f8024832b");
+
+/// (P - 5) / 8, called c4 in the RFC.
+const P_MINUS_5_OVER_8: U256 =
+    U256::from_le_hex("fdffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0f");
+
+/// The even square root of -486664, used to map from Curve25519 to our curve.
+const SQRT_M486664: Z25519 =
+    Z25519::from_le_hex("067e45ffaa046ecc821a7d4bd1d3a1c57e4ffc03dc087bd2bb06a060f4ed260f");
+
+/// Expand a message into a number of uniformly random bytes, using SHA-512.
+///
+/// This follows Section 5.3.1:
+/// https://datatracker.ietf.org/doc/html/rfc9380#section-5.3.1
+///
+/// `out` must contain at most 255 * 64 bytes.
+fn expand_message_xmd(msg: &[u8], dst: &[u8], out: &mut [u8]) {
+    let hashed_dst;
+    let dst = if dst.len() > MAX_DST_SIZE {
+        let mut hasher = sha512::Hasher::new();
+        hasher.update(OVERSIZE_DST_PREFIX);
+        hasher.update(dst);
+        hashed_dst = hasher.finalize();
+        &hashed_dst[..]
+    } else {
+        dst
+    };
+    let ell = out.len().div_ceil(sha512::HASH_SIZE);
+    assert!(ell <= 255, "too many bytes requested");
+
+    let mut hasher = sha512::Hasher::new();
+    hasher.update(&[0; BLOCK_SIZE]);
+    hasher.update(msg);
+    hasher.update(&(out.len() as u16).to_be_bytes());
+    hasher.update(&[0]);
+    hasher.update(dst);
+    hasher.update(&[dst.len() as u8]);
+    let b_0 = hasher.finalize();
+
+    let mut b_i = [0; sha512::HASH_SIZE];
+    for (i, chunk) in out.chunks_mut(sha512::HASH_SIZE).enumerate() {
+        // b_1 = H(b_0 || 1 || DST'), and then b_i = H((b_0 ^ b_(i - 1)) || i || DST')
+        for (x, &y) in b_i.iter_mut().zip(b_0.iter()) {
+            *x ^= y;
+        }
+        let mut hasher = sha512::Hasher::new();
+        hasher.update(&b_i);
+        hasher.update(&[(i + 1) as u8]);
+        hasher.update(dst);
+        hasher.update(&[dst.len() as u8]);
+        b_i = hasher.finalize();
+        chunk.copy_from_slice(&b_i[..chunk.len()]);
+    }
+}
+
+/// Hash a message into two field elements, following Section 5.2:
+/// https://datatracker.ietf.org/doc/html/rfc9380#section-5.2
+fn hash_to_field(msg: &[u8], dst: &[u8]) -> [Z25519; 2] {
+    let mut uniform_bytes = [0; 2 * FIELD_BYTES];
+    expand_message_xmd(msg, dst, &mut uniform_bytes);
+    let mut out = [Z25519::from(0); 2];
+    for (u, chunk) in out.iter_mut().zip(uniform_bytes.chunks_exact(FIELD_BYTES)) {
+        // The bytes are big endian, so we pad them on the left
+        let mut padded = [0; 64];
+        padded[64 - FIELD_BYTES..].copy_from_slice(chunk);
+        *u = Z25519::reduce_wide(U512::from_be_bytes(&padded));
+    }
+    out
+}
+
+/// Map a field element to a point on Curve25519, as fractions (xn, xd, yn, yd).
+///
+/// This is the straight-line procedure from Appendix G.2.1:
+/// https://datatracker.ietf.org/doc/html/rfc9380#appendix-G.2.1
+fn map_to_curve_elligator2_curve25519(u: Z25519) -> (Z25519, Z25519, Z25519, Z25519) {
+    let mut tv1 = u.squared();
+    tv1 *= 2;
+    let xd = tv1 + Z25519::from(1);
+    let x1n = -Z25519::from(J);
+    let mut tv2 = xd.squared();
+    let gxd = tv2 * xd;
+    let mut gx1 = tv1 * J;
+    gx1 *= x1n;
+    gx1 += tv2;
+    gx1 *= x1n;
+    let mut tv3 = gxd.squared();
+    tv2 = tv3.squared();
+    tv3 *= gxd;
+    tv3 *= gx1;
+    tv2 *= tv3;
+    let mut y11 = tv2.pow(&P_MINUS_5_OVER_8);
+    y11 *= tv3;
+    let y12 = y11 * SQRT_M1;
+    tv2 = y11.squared();
+    tv2 *= gxd;
+    let e1 = tv2.ct_eq(&gx1);
+    let y1 = Z25519::conditional_select(&y12, &y11, e1);
+    let x2n = x1n * tv1;
+    let mut y21 = y11 * u;
+    y21 *= C2;
+    let y22 = y21 * SQRT_M1;
+    let gx2 = gx1 * tv1;
+    tv2 = y21.squared();
+    tv2 *= gxd;
+    let e2 = tv2.ct_eq(&gx2);
+    let y2 = Z25519::conditional_select(&y22, &y21, e2);
+    tv2 = y1.squared();
+    tv2 *= gxd;
+    let e3 = tv2.ct_eq(&gx1);
+    let xn = Z25519::conditional_select(&x2n, &x1n, e3);
+    let mut y = Z25519::conditional_select(&y2, &y1, e3);
+    let e4 = y.is_negative();
+    y = Z25519::conditional_select(&y, &-y, e3 ^ e4);
+    (xn, xd, y, Z25519::from(1))
+}
+
+/// Map a field element to a point on our curve.
+///
+/// This follows Appendix G.2.2:
+/// https://datatracker.ietf.org/doc/html/rfc9380#appendix-G.2.2
+fn map_to_curve_elligator2_edwards25519(u: Z25519) -> EdwardsPoint {
+    let (xmn, xmd, ymn, ymd) = map_to_curve_elligator2_curve25519(u);
+    let mut xn = xmn * ymd;
+    xn *= SQRT_M486664;
+    let mut xd = xmd * ymn;
+    let mut yn = xmn - xmd;
+    let mut yd = xmn + xmd;
+    let e = (xd * yd).ct_eq(&Z25519::from(0));
+    xn.conditional_assign(&Z25519::from(0), e);
+    xd.conditional_assign(&Z25519::from(1), e);
+    yn.conditional_assign(&Z25519::from(1), e);
+    yd.conditional_assign(&Z25519::from(1), e);
+    EdwardsPoint::from_fractions(xn, xd, yn, yd)
+}
+
+impl EdwardsPoint {
+    /// Hash an arbitrary message to a point on our curve.
+    ///
+    /// The resulting point will be in the prime order subgroup. The domain separation
+    /// tag `dst` should be unique to the protocol using this function.
+    ///
+    /// This implements the edwards25519_XMD:SHA-512_ELL2_RO_ suite from RFC 9380:
+    /// https://datatracker.ietf.org/doc/html/rfc9380#section-6.8.2
+    pub fn hash_to_curve(msg: &[u8], dst: &[u8]) -> EdwardsPoint {
+        let [u0, u1] = hash_to_field(msg, dst);
+        let q0 = map_to_curve_elligator2_edwards25519(u0);
+        let q1 = map_to_curve_elligator2_edwards25519(u1);
+        (q0 + q1).mul_by_cofactor()
+    }
+
+    /// Decode a point from 32 bytes produced by `to_uniform_bytes`.
+    ///
+    /// Any 32 bytes decode to some point, through the Elligator2 map. The top two bits
+    /// are ignored, since they only hold random padding.
+    pub fn from_uniform_bytes(bytes: &[u8; 32]) -> EdwardsPoint {
+        let mut bytes = *bytes;
+        bytes[31] &= 0x3F;
+        // This is less than 2^254, and so is already reduced.
+        let r = Z25519::from_limbs(U256::from_le_bytes(&bytes).limbs);
+        map_to_curve_elligator2_edwards25519(r)
+    }
+
+    /// Encode this point as 32 bytes indistinguishable from random, if possible.
+    ///
+    /// Only about half of all points can be encoded, with `None` returned for the rest.
+    /// The usual approach is to generate ephemeral keys until one can be encoded.
+    /// The top two bits of the encoding are filled with randomness.
+    ///
+    /// The encoding only looks random if the points themselves are uniform over the
+    /// whole curve. Points in the prime order subgroup, like public keys, should have
+    /// a random low order component added first.
+    pub fn to_uniform_bytes<R: RngCore + CryptoRng>(self, rng: &mut R) -> Option<[u8; 32]> {
+        let r = self.elligator2_representative()?;
+        let mut bytes: [u8; 32] = r.into();
+        let mut padding = [0u8; 1];
+        rng.fill_bytes(&mut padding);
+        bytes[31] |= padding[0] & 0xC0;
+        Some(bytes)
+    }
+
+    /// Find r ≤ (P - 1) / 2, with the Elligator2 map sending r to this point.
+    fn elligator2_representative(&self) -> Option<Z25519> {
+        let one = Z25519::from(1);
+        let AffinePoint { x, y } = AffinePoint::from(*self);
+        // The identity has r = 0 as a representative, but isn't handled below.
+        if self.is_identity() {
+            return Some(Z25519::from(0));
+        }
+        // Invert the rational map from Appendix D.1, to get back to Curve25519.
+        let xm = (one + y) * (one - y).inverse();
+        let ym = SQRT_M486664 * xm * x.inverse();
+        // If x came from the first candidate, -J / (1 + 2r²), then y is negative,
+        // and r² = -(x + J) / 2x. Otherwise, r² = -x / 2(x + J).
+        let xm_plus_j = xm + Z25519::from(J);
+        let negative = ym.is_negative();
+        let num = Z25519::conditional_select(&-xm, &-xm_plus_j, negative);
+        let den = Z25519::conditional_select(&xm_plus_j, &xm, negative) * 2;
+        let mut r = Z25519::fraction_root(num, den)?;
+        let r_bytes: [u8; 32] = r.into();
+        let too_large = U256::from(r_bytes).ct_gt(&P_MINUS_1_OVER_2);
+        r.conditional_assign(&-r, too_large);
+        // This also rules out the points the map never reaches, like those of order 2.
+        if !bool::from(map_to_curve_elligator2_edwards25519(r).ct_eq(self)) {
+            return None;
+        }
+        Some(r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::convert::TryInto;
+
+    use rand::rngs::OsRng;
+
+    use super::super::{
+        point::{self, CompressedEdwardsY},
+        scalar::Scalar,
+    };
+    use super::*;
+
+    #[test]
+    fn test_expand_message_xmd_vectors() {
+        // These come from Appendix K.3
+        let dst = b"QUUX-V01-CS02-with-expander-SHA512-256";
+        let mut out = [0; 32];
+        expand_message_xmd(b"", dst, &mut out);
+        assert_eq!(
+            hex::encode(out),
+            "6b9a7312411d92f921c6f68ca0b6380730a1a4d982c507211a90964c394179ba"
+        );
+        expand_message_xmd(b"abc", dst, &mut out);
+        assert_eq!(
+            hex::encode(out),
+            "0da749f12fbe5483eb066a5f595055679b976e93abe9be6f0f6318bce7aca8dc"
+        );
+    }
+
+    #[test]
+    fn test_hash_to_curve_vectors() {
+        // These come from Appendix J.5.1, with the points in their compressed form
+        let dst = b"QUUX-V01-CS02-with-edwards25519_XMD:SHA-512_ELL2_RO_";
+        let q128 = [b"q128_".as_ref(), &[b'q'; 128]].concat();
+        let vectors: [(&[u8], &str); 4] = [
+            (
+                b"",
+                "21dc15e10253796df23a7699c8a383ea624cce88c52431f6be220b1a56c8a609",
+            ),
+            (
+                b"abc",
+                "31558a26887f23fb8218f143e69d5f0af2e7831130bd5b432ef23883b895839a",
+            ),
+            (
+                b"abcdef0123456789",
+                "a661c58eea707f2171dd1a8a641e41758ac842cfd31e64dabc7f0e143d0a0653",
+            ),
+            (
+                &q128,
+                "f7d2895eea2ef7b737ed56594f99e238a1eeb0dd672f98d239fafc55e315ca2e",
+            ),
+        ];
+        for (msg, expected) in vectors.iter() {
+            let encoded = EdwardsPoint::hash_to_curve(msg, dst).compress().bytes;
+            assert_eq!(hex::encode(encoded), *expected);
+        }
+    }
+
+    #[test]
+    fn test_constants() {
+        let two_255 = U256 {
+            limbs: [0, 0, 0, 1 << 63],
+        };
+        // 8⋅(P - 5)/8 + 5 + 19 = 2^255
+        let (carry, eight_c4) = P_MINUS_5_OVER_8 * 8;
+        assert_eq!(carry, 0);
+        assert_eq!(eight_c4 + U256::from(24), two_255);
+        assert_eq!(C2, Z25519::from(2).pow(&(P_MINUS_5_OVER_8 + U256::from(1))));
+        assert_eq!(SQRT_M1.squared(), -Z25519::from(1));
+        assert_eq!(SQRT_M486664.squared(), -Z25519::from(486664));
+        assert!(!bool::from(SQRT_M486664.is_negative()));
+    }
+
+    #[test]
+    fn test_uniform_bytes_roundtrip() {
+        let mut encodable = 0;
+        for i in 1..64u64 {
+            let point = point::B * Scalar::from(i);
+            if let Some(bytes) = point.to_uniform_bytes(&mut OsRng) {
+                encodable += 1;
+                assert_eq!(EdwardsPoint::from_uniform_bytes(&bytes), point);
+            }
+        }
+        // About half of all points should be encodable.
+        assert!((16..48).contains(&encodable));
+        let identity = EdwardsPoint::identity();
+        let bytes = identity.to_uniform_bytes(&mut OsRng).unwrap();
+        assert_eq!(bytes[..31], [0; 31]);
+        assert_eq!(bytes[31] & 0x3F, 0);
+        assert_eq!(EdwardsPoint::from_uniform_bytes(&bytes), identity);
+    }
+
+    #[test]
+    fn test_uniform_bytes_representatives() {
+        // Decoding and encoding again should give back the same bytes, up to padding.
+        for i in 0..32u8 {
+            let mut bytes: [u8; 32] = sha512::hash(&[i])[..32].try_into().unwrap();
+            bytes[31] &= 0x3F;
+            let point = EdwardsPoint::from_uniform_bytes(&bytes);
+            let mut encoded = point.to_uniform_bytes(&mut OsRng).unwrap();
+            encoded[31] &= 0x3F;
+            assert_eq!(encoded, bytes);
+            bytes[31] |= 0xC0;
+            assert_eq!(EdwardsPoint::from_uniform_bytes(&bytes), point);
+        }
+    }
+
+    #[test]
+    fn test_order_two_point_is_not_encodable() {
+        let order_two = CompressedEdwardsY {
+            bytes: {
+                let mut bytes = [0; 32];
+                bytes[0] = 0xEC;
+                bytes[1..31].copy_from_slice(&[0xFF; 30]);
+                bytes[31] = 0x7F;
+                bytes
+            },
+        }
+        .decompress()
+        .unwrap();
+        assert!(order_two.to_uniform_bytes(&mut OsRng).is_none());
+    }
+}
@@ -0,0 +1,117 @@
+//! This module separates keys by how they're meant to be used.
+//!
+//! An identity key is long lived: it signs on our behalf, and gets saved to disk.
+//! An ephemeral key is used for a single exchange, and then thrown away. Mixing the
+//! two up is a common mistake, e.g. persisting a key meant to provide forward secrecy,
+//! or decrypting with a key whose compromise would let others impersonate us.
+//!
+//! Both types wrap a `PrivateKey`, but only expose the operations fitting their use.
+//! Ephemeral keys can't be serialized, or even cloned, and identity keys can't be used
+//! to open sealed boxes, without explicitly converting them back into a `PrivateKey`.
+use alloc::vec::Vec;
+
+use rand::{CryptoRng, RngCore};
+
+use super::{ecies, gen_keypair, PrivateKey, PublicKey, Signature, PRIVATE_KEY_SIZE};
+
+/// A long lived key, used to sign as a stable identity.
+#[derive(Debug, Clone)]
+pub struct IdentityKey {
+    key: PrivateKey,
+}
+
+impl IdentityKey {
+    /// Generate a new identity key, using a secure source of randomness.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        IdentityKey {
+            key: gen_keypair(rng).1,
+        }
+    }
+
+    /// Restore an identity key from its bytes, as produced by `to_bytes`.
+    pub fn from_bytes(bytes: [u8; PRIVATE_KEY_SIZE]) -> Self {
+        IdentityKey {
+            key: PrivateKey { bytes },
+        }
+    }
+
+    /// Encode this key as bytes, in order to store it.
+    pub fn to_bytes(&self) -> [u8; PRIVATE_KEY_SIZE] {
+        self.key.bytes
+    }
+
+    /// The public key, identifying us to others.
+    pub fn public_key(&self) -> PublicKey {
+        self.key.public_key()
+    }
+
+    /// Sign a message with this identity.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        self.key.sign(message)
+    }
+
+    /// Convert this identity back into an unrestricted private key.
+    ///
+    /// This is the explicit escape hatch for using an identity in an exchange.
+    pub fn into_private_key(self) -> PrivateKey {
+        self.key
+    }
+}
+
+/// A short lived key, used for a single exchange, and never stored.
+#[derive(Debug)]
+pub struct EphemeralKey {
+    key: PrivateKey,
+}
+
+impl EphemeralKey {
+    /// Generate a new ephemeral key, using a secure source of randomness.
+    ///
+    /// This is the only way to create such a key, since they're never restored from storage.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        EphemeralKey {
+            key: gen_keypair(rng).1,
+        }
+    }
+
+    /// The public key, to send to the other party of the exchange.
+    pub fn public_key(&self) -> PublicKey {
+        self.key.public_key()
+    }
+
+    /// Open a box sealed to this key, with `ecies::seal`.
+    ///
+    /// This consumes the key, since it should only ever be used for one exchange.
+    pub fn open(self, sealed: &[u8]) -> Option<Vec<u8>> {
+        ecies::open(&self.key, sealed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_identity_roundtrip() {
+        let identity = IdentityKey::generate(&mut OsRng);
+        let restored = IdentityKey::from_bytes(identity.to_bytes());
+        let sig = restored.sign(b"hello");
+        assert!(identity.public_key().verify(b"hello", sig));
+    }
+
+    #[test]
+    fn test_ephemeral_exchange() {
+        let ephemeral = EphemeralKey::generate(&mut OsRng);
+        let sealed = ecies::seal(&mut OsRng, &ephemeral.public_key(), b"secret").unwrap();
+        assert_eq!(ephemeral.open(&sealed).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_identity_needs_conversion_to_open() {
+        let identity = IdentityKey::generate(&mut OsRng);
+        let sealed = ecies::seal(&mut OsRng, &identity.public_key(), b"secret").unwrap();
+        let private = identity.into_private_key();
+        assert_eq!(ecies::open(&private, &sealed).unwrap(), b"secret");
+    }
+}
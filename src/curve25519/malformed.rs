@@ -0,0 +1,237 @@
+//! Tests that verification rejects malformed signatures and keys, with the right error.
+//!
+//! Starting from valid signatures, we corrupt each byte of the signature, and of the
+//! public key, in a few ways, and check what every verification method reports. The
+//! encodings get checked in order: s first, then A, then R, for the methods which decode
+//! it, and only then the equation, so the first problem is the one which gets reported.
+use core::convert::{TryFrom, TryInto};
+
+use super::{
+    error::SignatureError::{self, *},
+    field::Z25519,
+    point::CompressedEdwardsY,
+    scalar::Scalar,
+    PrivateKey, PublicKey, Signature,
+};
+
+/// The ways each byte gets corrupted: flipping the lowest bit, the highest bit, or every bit.
+const MASKS: [u8; 3] = [0x01, 0x80, 0xFF];
+
+struct Valid {
+    public: PublicKey,
+    message: Vec<u8>,
+    signature: Signature,
+}
+
+fn valid() -> Vec<Valid> {
+    (1..3u8)
+        .map(|i| {
+            let private = PrivateKey { bytes: [i; 32] };
+            let message = vec![i; 17 * i as usize];
+            let signature = private.sign(&message);
+            Valid {
+                public: private.public_key(),
+                message,
+                signature,
+            }
+        })
+        .collect()
+}
+
+fn decode(hex: &str) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    hex::decode_to_slice(hex, &mut bytes).unwrap();
+    bytes
+}
+
+/// The error from decoding a point, rejecting non-canonical encodings, if it fails.
+fn point_error(bytes: &[u8]) -> Option<SignatureError> {
+    let compressed = CompressedEdwardsY {
+        bytes: bytes.try_into().unwrap(),
+    };
+    match (compressed.decompress(), compressed.decompress_zip215()) {
+        (Some(_), _) => None,
+        (None, Some(_)) => Some(NonCanonicalPoint),
+        (None, None) => Some(InvalidPoint),
+    }
+}
+
+/// The error from decoding a point, accepting non-canonical encodings, if it fails.
+fn zip215_point_error(bytes: &[u8]) -> Option<SignatureError> {
+    let compressed = CompressedEdwardsY {
+        bytes: bytes.try_into().unwrap(),
+    };
+    match compressed.decompress_zip215() {
+        Some(_) => None,
+        None => Some(InvalidPoint),
+    }
+}
+
+/// The errors from verify, verify_strict, and verify_zip215, in that order.
+fn errors(
+    public: &PublicKey,
+    message: &[u8],
+    signature: Signature,
+) -> [Result<(), SignatureError>; 3] {
+    [
+        public.verify_result(message, signature),
+        public.verify_strict_result(message, signature),
+        public.verify_zip215_result(message, signature),
+    ]
+}
+
+#[test]
+fn test_corrupted_signatures() {
+    for valid in valid() {
+        assert_eq!(
+            errors(&valid.public, &valid.message, valid.signature),
+            [Ok(()), Ok(()), Ok(())]
+        );
+        for i in 0..64 {
+            for &mask in &MASKS {
+                let mut signature = valid.signature;
+                signature.bytes[i] ^= mask;
+                let expected = if i < 32 {
+                    // R only gets compared to what we recompute, unless it's checked
+                    let r = &signature.bytes[..32];
+                    [
+                        InvalidEquation,
+                        point_error(r).unwrap_or(InvalidEquation),
+                        zip215_point_error(r).unwrap_or(InvalidEquation),
+                    ]
+                } else {
+                    let s = signature.bytes[32..].try_into().unwrap();
+                    let error = match Scalar::from_canonical_bytes(s) {
+                        Some(_) => InvalidEquation,
+                        None => InvalidScalar,
+                    };
+                    [error; 3]
+                };
+                assert_eq!(
+                    errors(&valid.public, &valid.message, signature),
+                    [Err(expected[0]), Err(expected[1]), Err(expected[2])],
+                    "byte {} ^ {:#04x}",
+                    i,
+                    mask
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_corrupted_public_keys() {
+    for valid in valid() {
+        for i in 0..32 {
+            for &mask in &MASKS {
+                let mut public = valid.public;
+                public.bytes[i] ^= mask;
+                let error = point_error(&public.bytes).unwrap_or(InvalidEquation);
+                let zip215_error = zip215_point_error(&public.bytes).unwrap_or(InvalidEquation);
+                assert_eq!(
+                    errors(&public, &valid.message, valid.signature),
+                    [Err(error), Err(error), Err(zip215_error)],
+                    "byte {} ^ {:#04x}",
+                    i,
+                    mask
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_corrupted_messages() {
+    for valid in valid() {
+        let mut message = valid.message.clone();
+        message.push(0);
+        assert_eq!(
+            errors(&valid.public, &message, valid.signature),
+            [Err(InvalidEquation); 3]
+        );
+        let other = PrivateKey { bytes: [0xAA; 32] }.public_key();
+        assert_eq!(
+            errors(&other, &valid.message, valid.signature),
+            [Err(InvalidEquation); 3]
+        );
+    }
+}
+
+#[test]
+fn test_error_taxonomy() {
+    let valid = &valid()[0];
+    let with_s = |s: [u8; 32]| {
+        let mut signature = valid.signature;
+        signature.bytes[32..].copy_from_slice(&s);
+        signature
+    };
+    let with_r = |r: [u8; 32]| {
+        let mut signature = valid.signature;
+        signature.bytes[..32].copy_from_slice(&r);
+        signature
+    };
+    let l = decode("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+    let mut l_minus_1 = l;
+    l_minus_1[0] -= 1;
+    // The identity, encoded as y = P + 1, and as -0, with the sign bit set
+    let non_canonical = [
+        decode("eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f"),
+        decode("0100000000000000000000000000000000000000000000000000000000000080"),
+    ];
+    // y = 2, which has no matching x
+    let invalid = decode("0200000000000000000000000000000000000000000000000000000000000000");
+
+    let check = |public: &PublicKey, signature, expected: [SignatureError; 3]| {
+        assert_eq!(
+            errors(public, &valid.message, signature),
+            [Err(expected[0]), Err(expected[1]), Err(expected[2])]
+        );
+    };
+    for s in &[l, [0xFF; 32]] {
+        check(&valid.public, with_s(*s), [InvalidScalar; 3]);
+        // s gets checked before anything else
+        check(
+            &PublicKey { bytes: invalid },
+            with_s(*s),
+            [InvalidScalar; 3],
+        );
+    }
+    check(&valid.public, with_s(l_minus_1), [InvalidEquation; 3]);
+    for bytes in &non_canonical {
+        let public = PublicKey { bytes: *bytes };
+        check(
+            &public,
+            valid.signature,
+            [NonCanonicalPoint, NonCanonicalPoint, InvalidEquation],
+        );
+        check(
+            &valid.public,
+            with_r(*bytes),
+            [InvalidEquation, NonCanonicalPoint, InvalidEquation],
+        );
+    }
+    check(
+        &PublicKey { bytes: invalid },
+        valid.signature,
+        [InvalidPoint; 3],
+    );
+    check(
+        &valid.public,
+        with_r(invalid),
+        [InvalidEquation, InvalidPoint, InvalidPoint],
+    );
+}
+
+#[test]
+fn test_decoding_errors() {
+    let p = decode("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+    assert_eq!(Z25519::try_from(&p[..]).err(), Some(InvalidFieldElement));
+    assert_eq!(
+        Z25519::try_from(&[0; 31][..]).err(),
+        Some(InvalidFieldElement)
+    );
+    assert!(Z25519::try_from(&[0; 32][..]).is_ok());
+    assert_eq!(Scalar::try_from(&[0; 31][..]).err(), Some(InvalidScalar));
+    assert_eq!(Scalar::try_from(&[0xFF; 32][..]).err(), Some(InvalidScalar));
+    assert!(Scalar::try_from(&[0; 32][..]).is_ok());
+}
@@ -1,54 +1,184 @@
-use std::convert::{TryFrom, TryInto};
+use core::{
+    convert::{TryFrom, TryInto},
+    fmt,
+};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
 
 use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConstantTimeEq};
 
-use crate::{
-    curve25519::{point::Point, scalar::Scalar},
-    sha512,
-};
+use crate::sha512;
 
+pub use self::adaptor::{AdaptorPoint, AdaptorSecret, PreSignature};
+#[cfg(feature = "alloc")]
+pub use self::batch::verify_batch;
+#[cfg(feature = "std")]
+pub use self::batch::verify_batch_chunked;
+#[cfg(feature = "alloc")]
+pub use self::blind_signature::{
+    BlindChallenge, BlindCommitment, BlindResponse, BlindSignatureRequest, BlindSigner,
+};
+pub use self::blinding::BlindedPrivateKey;
+pub use self::designated::{DesignatedSignature, DESIGNATED_SIGNATURE_SIZE};
 use self::error::SignatureError;
+pub use self::expanded::{ExpandedPrivateKey, SignatureHash};
+#[cfg(feature = "alloc")]
+pub use self::key_usage::{EphemeralKey, IdentityKey};
+#[cfg(feature = "alloc")]
+pub use self::multiscalar::PippengerParams;
+pub use self::passphrase::{PassphraseError, PassphraseParams};
+pub use self::point::{CompressedEdwardsY, EdwardsPoint};
+pub use self::prehash::DIGEST_SIZE;
+#[cfg(feature = "alloc")]
+pub use self::salted::{SaltedSignature, SALTED_SIGNATURE_SIZE, SALT_SIZE};
+pub use self::scalar::Scalar;
+#[cfg(feature = "secure-memory")]
+pub use self::secure_memory::LockedPrivateKey;
 
+mod adaptor;
 mod arithmetic;
+#[cfg(feature = "alloc")]
+mod batch;
+#[cfg(feature = "alloc")]
+mod blind_signature;
+mod blinding;
+#[cfg(feature = "hazmat")]
+pub mod commitment;
+mod context;
+mod designated;
+#[cfg(feature = "hazmat")]
+pub mod dleq;
+#[cfg(feature = "alloc")]
+pub mod ecies;
 mod error;
-mod field;
-mod point;
-mod scalar;
+mod expanded;
+pub(crate) mod field;
+mod hash_to_curve;
+#[cfg(feature = "alloc")]
+mod key_usage;
+#[cfg(test)]
+mod malformed;
+#[cfg(feature = "alloc")]
+mod multiscalar;
+mod passphrase;
+pub(crate) mod point;
+pub mod prehash;
+#[cfg(feature = "dangerous-key-recovery")]
+pub mod recovery;
+#[cfg(feature = "alloc")]
+mod salted;
+pub(crate) mod scalar;
+#[cfg(feature = "secure-memory")]
+mod secure_memory;
+#[cfg(test)]
+mod snapshot;
+#[cfg(test)]
+mod taming;
+
+/// Write some bytes as lowercase hex, without allocating.
+pub(crate) fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for b in bytes {
+        write!(f, "{:02x}", b)?;
+    }
+    Ok(())
+}
 
 pub const SIGNATURE_SIZE: usize = 64;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct Signature {
     pub bytes: [u8; SIGNATURE_SIZE],
 }
 
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Signature(")?;
+        write_hex(f, &self.bytes)?;
+        f.write_str(")")
+    }
+}
+
+impl ConstantTimeEq for Signature {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.bytes.ct_eq(&other.bytes)
+    }
+}
+
+impl PartialEq for Signature {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for Signature {}
+
 pub const PUBLIC_KEY_SIZE: usize = 32;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct PublicKey {
     pub bytes: [u8; PUBLIC_KEY_SIZE],
 }
 
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PublicKey(")?;
+        write_hex(f, &self.bytes)?;
+        f.write_str(")")
+    }
+}
+
+impl ConstantTimeEq for PublicKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.bytes.ct_eq(&other.bytes)
+    }
+}
+
+impl PartialEq for PublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for PublicKey {}
+
 impl PublicKey {
     fn from_hash(hash: &[u8; 64]) -> Self {
         let scalar = Scalar::clamped(hash[..32].try_into().unwrap());
         PublicKey {
-            bytes: (point::B * scalar).into(),
+            bytes: (point::B * scalar).compress().bytes,
         }
     }
 
     fn verify_result(&self, message: &[u8], signature: Signature) -> Result<(), SignatureError> {
+        self.verify_with_domain(&[], message, signature)
+    }
+
+    /// Verify a signature, with a domain separation prefix included in the challenge.
+    fn verify_with_domain(
+        &self,
+        domain: &[u8],
+        message: &[u8],
+        signature: Signature,
+    ) -> Result<(), SignatureError> {
+        self.verify_with_hash::<sha512::Hasher>(domain, message, signature)
+    }
+
+    /// Verify a signature, using a given hash function for the challenge.
+    fn verify_with_hash<H: SignatureHash>(
+        &self,
+        domain: &[u8],
+        message: &[u8],
+        signature: Signature,
+    ) -> Result<(), SignatureError> {
         let s = Scalar::try_from(&signature.bytes[32..])?;
-        let a = Point::try_from(&self.bytes[..])?;
-        let mut to_hash = Vec::with_capacity(64 + message.len());
+        let a = decode_canonical(&self.bytes)?;
         let r_bytes = &signature.bytes[..32];
-        to_hash.extend_from_slice(&r_bytes);
-        let a_bytes: [u8; 32] = a.into();
-        to_hash.extend_from_slice(&a_bytes);
-        to_hash.extend_from_slice(message);
-        let k = Scalar::from(sha512::hash(&to_hash));
-        let check_encoded: [u8; 32] = (point::B * s + (a * -k)).into();
-        if r_bytes != &check_encoded {
+        let k = challenge_with_hash::<H>(domain, r_bytes, &self.bytes, message);
+        // Negating k⋅A, rather than k, matters when A has a small order component,
+        // since -k is reduced modulo L, which isn't a multiple of 8.
+        let check_encoded = (point::B * s - a * k).compress().bytes;
+        if *r_bytes != check_encoded {
             return Err(SignatureError::InvalidEquation);
         }
         Ok(())
@@ -57,48 +187,259 @@ impl PublicKey {
     pub fn verify(&self, message: &[u8], signature: Signature) -> bool {
         self.verify_result(message, signature).is_ok()
     }
+
+    /// Verify a signature, following the strictest reading of RFC 8032.
+    ///
+    /// Beyond what `verify` checks, this rejects A and R when they have small order,
+    /// so that a key like the identity, which every signature with R of small order
+    /// is valid for, gets rejected. Like `verify`, this checks the equation
+    /// s⋅B = R + k⋅A without the cofactor, so it rejects some signatures which
+    /// `verify_batch` and `verify_zip215` accept.
+    pub fn verify_strict(&self, message: &[u8], signature: Signature) -> bool {
+        self.verify_strict_result(message, signature).is_ok()
+    }
+
+    fn verify_strict_result(
+        &self,
+        message: &[u8],
+        signature: Signature,
+    ) -> Result<(), SignatureError> {
+        let s = Scalar::try_from(&signature.bytes[32..])?;
+        let a = decode_canonical(&self.bytes)?;
+        let r_bytes: [u8; 32] = signature.bytes[..32].try_into().unwrap();
+        let r = decode_canonical(&r_bytes)?;
+        if a.is_small_order() || r.is_small_order() {
+            return Err(SignatureError::SmallOrderPoint);
+        }
+        let k = challenge(&r_bytes, &self.bytes, message);
+        if !(point::B * s - a * k - r).is_identity() {
+            return Err(SignatureError::InvalidEquation);
+        }
+        Ok(())
+    }
+
+    /// Verify a signature following the rules of ZIP 215, as used by Zcash.
+    ///
+    /// This accepts non-canonical encodings of A and R, hashing them as given, and
+    /// checks the cofactored equation [8][s]B = [8]R + [8][k]A. s must still be below L.
+    /// These rules accept a superset of what `verify` does, and are meant for consensus,
+    /// where every implementation has to agree on exactly which signatures are valid:
+    /// https://zips.z.cash/zip-0215
+    pub fn verify_zip215(&self, message: &[u8], signature: Signature) -> bool {
+        self.verify_zip215_result(message, signature).is_ok()
+    }
+
+    fn verify_zip215_result(
+        &self,
+        message: &[u8],
+        signature: Signature,
+    ) -> Result<(), SignatureError> {
+        let s = Scalar::try_from(&signature.bytes[32..])?;
+        let a = CompressedEdwardsY { bytes: self.bytes }
+            .decompress_zip215()
+            .ok_or(SignatureError::InvalidPoint)?;
+        let r_bytes: [u8; 32] = signature.bytes[..32].try_into().unwrap();
+        let r = CompressedEdwardsY { bytes: r_bytes }
+            .decompress_zip215()
+            .ok_or(SignatureError::InvalidPoint)?;
+        let k = challenge(&r_bytes, &self.bytes, message);
+        if !(point::B * s - r - a * k).mul_by_cofactor().is_identity() {
+            return Err(SignatureError::InvalidEquation);
+        }
+        Ok(())
+    }
+
+    /// Verify a signature for a message read in chunks, using a bounded amount of memory.
+    ///
+    /// This allocates a single buffer of `chunk_size` bytes, and otherwise only needs
+    /// a few hundred bytes of state, no matter how long the message is. Errors from
+    /// the reader are returned, while an invalid signature produces `Ok(false)`.
+    ///
+    /// `chunk_size` must not be 0.
+    #[cfg(feature = "std")]
+    pub fn verify_chunked<R: Read>(
+        &self,
+        mut reader: R,
+        signature: Signature,
+        chunk_size: usize,
+    ) -> io::Result<bool> {
+        if chunk_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "chunk size must not be 0",
+            ));
+        }
+        let (s, a) = match (
+            Scalar::try_from(&signature.bytes[32..]),
+            CompressedEdwardsY { bytes: self.bytes }.decompress(),
+        ) {
+            (Ok(s), Some(a)) => (s, a),
+            _ => return Ok(false),
+        };
+        let r_bytes = &signature.bytes[..32];
+        let a_bytes = a.compress().bytes;
+
+        // The challenge is H(R || A || M), so we can hash the message as it arrives
+        let mut hasher = sha512::Hasher::new();
+        hasher.update(r_bytes);
+        hasher.update(&a_bytes);
+        hash_reader(&mut hasher, &mut reader, &mut vec![0; chunk_size])?;
+        let k = Scalar::from(hasher.finalize());
+
+        let check_encoded = (point::B * s - a * k).compress().bytes;
+        Ok(r_bytes == check_encoded)
+    }
+}
+
+/// Feed everything a reader produces into a hasher, reading into a given buffer.
+#[cfg(feature = "std")]
+fn hash_reader<R: Read>(
+    hasher: &mut sha512::Hasher,
+    reader: &mut R,
+    buf: &mut [u8],
+) -> io::Result<()> {
+    loop {
+        match reader.read(buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => hasher.update(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Decode a point, checking that encoding it again produces the same bytes.
+///
+/// This distinguishes bytes which don't encode a point at all from non-canonical
+/// encodings of a point, which `decompress` rejects alike.
+fn decode_canonical(bytes: &[u8; 32]) -> Result<EdwardsPoint, SignatureError> {
+    let point = CompressedEdwardsY { bytes: *bytes }
+        .decompress_zip215()
+        .ok_or(SignatureError::InvalidPoint)?;
+    if point.compress().bytes != *bytes {
+        return Err(SignatureError::NonCanonicalPoint);
+    }
+    Ok(point)
 }
 
 pub const PRIVATE_KEY_SIZE: usize = 32;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct PrivateKey {
     pub bytes: [u8; PRIVATE_KEY_SIZE],
 }
 
+// We never print the bytes of a private key, to avoid leaking them into logs.
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PrivateKey(<redacted>)")
+    }
+}
+
+impl ConstantTimeEq for PrivateKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.bytes.ct_eq(&other.bytes)
+    }
+}
+
+impl PartialEq for PrivateKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for PrivateKey {}
+
 impl PrivateKey {
     fn derive_public_key(&self) -> PublicKey {
         let hash = sha512::hash(&self.bytes);
         PublicKey::from_hash(&hash)
     }
 
+    /// Calculate the public key associated with this private key.
+    pub fn public_key(&self) -> PublicKey {
+        self.derive_public_key()
+    }
+
     pub fn sign(&self, message: &[u8]) -> Signature {
         let hash = sha512::hash(&self.bytes);
         let s = Scalar::clamped(hash[..32].try_into().unwrap());
-        let a: [u8; 32] = (point::B * s).into();
-        let prefix = &hash[32..];
+        sign_with_scalar(s, &hash[32..], message)
+    }
+}
 
-        let mut to_hash = Vec::with_capacity(64 + message.len());
-        to_hash.extend_from_slice(prefix);
-        to_hash.extend_from_slice(message);
-        let r = Scalar::from(sha512::hash(&to_hash));
+/// Calculate the challenge scalar k = H(R || A || M) used in signatures.
+///
+/// This is described in Section 5.1.6:
+/// https://datatracker.ietf.org/doc/html/rfc8032#section-5.1.6
+fn challenge(big_r: &[u8], a: &[u8], message: &[u8]) -> Scalar {
+    challenge_with_domain(&[], big_r, a, message)
+}
 
-        let big_r: [u8; 32] = (point::B * r).into();
+/// Calculate the challenge scalar k = H(dom || R || A || M), with a domain prefix.
+///
+/// The prefix is empty for plain Ed25519, but used by the variants in Section 5.1:
+/// https://datatracker.ietf.org/doc/html/rfc8032#section-5.1
+fn challenge_with_domain(domain: &[u8], big_r: &[u8], a: &[u8], message: &[u8]) -> Scalar {
+    challenge_with_hash::<sha512::Hasher>(domain, big_r, a, message)
+}
 
-        to_hash.clear();
-        to_hash.extend_from_slice(&big_r);
-        to_hash.extend_from_slice(&a);
-        to_hash.extend_from_slice(message);
-        let k = Scalar::from(sha512::hash(&to_hash));
+/// Calculate the challenge scalar, using a given hash function.
+fn challenge_with_hash<H: SignatureHash>(
+    domain: &[u8],
+    big_r: &[u8],
+    a: &[u8],
+    message: &[u8],
+) -> Scalar {
+    let mut hasher = H::default();
+    hasher.update(domain);
+    hasher.update(big_r);
+    hasher.update(a);
+    hasher.update(message);
+    Scalar::from(hasher.finalize())
+}
 
-        let big_s: [u8; 32] = (r + k * s).into();
+/// Sign a message, given the secret scalar and nonce prefix directly.
+///
+/// This is the second half of the signing procedure in Section 5.1.6:
+/// https://datatracker.ietf.org/doc/html/rfc8032#section-5.1.6
+///
+/// Keys which aren't derived from a seed, like blinded keys, only have access
+/// to this part of the procedure.
+fn sign_with_scalar(s: Scalar, prefix: &[u8], message: &[u8]) -> Signature {
+    sign_with_domain(s, &[], prefix, message)
+}
 
-        let mut out = Signature { bytes: [0; 64] };
-        out.bytes[..32].copy_from_slice(&big_r);
-        out.bytes[32..].copy_from_slice(&big_s);
+/// Sign a message, with a domain separation prefix included in every hash.
+fn sign_with_domain(s: Scalar, domain: &[u8], prefix: &[u8], message: &[u8]) -> Signature {
+    sign_with_hash::<sha512::Hasher>(s, domain, prefix, message)
+}
 
-        out
-    }
+/// Sign a message, using a given hash function for the nonce and challenge.
+fn sign_with_hash<H: SignatureHash>(
+    s: Scalar,
+    domain: &[u8],
+    prefix: &[u8],
+    message: &[u8],
+) -> Signature {
+    let a = (point::B * s).compress().bytes;
+
+    let mut hasher = H::default();
+    hasher.update(domain);
+    hasher.update(prefix);
+    hasher.update(message);
+    let r = Scalar::from(hasher.finalize());
+
+    let big_r = (point::B * r).compress().bytes;
+    let k = challenge_with_hash::<H>(domain, &big_r, &a, message);
+
+    let big_s: [u8; 32] = (r + k * s).into();
+
+    let mut out = Signature { bytes: [0; 64] };
+    out.bytes[..32].copy_from_slice(&big_r);
+    out.bytes[32..].copy_from_slice(&big_s);
+
+    out
 }
 
 pub fn gen_keypair<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, PrivateKey) {
@@ -111,46 +452,170 @@ pub fn gen_keypair<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, PrivateKe
 mod test {
     use super::*;
 
-    #[test]
-    fn test_signature_example1() {
-        let mut private = PrivateKey { bytes: [0; 32] };
-        hex::decode_to_slice(
-            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
-            &mut private.bytes,
-        )
-        .unwrap();
-        let mut expected = [0; 64];
-        hex::decode_to_slice(
-            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
-            &mut expected,
-        )
-        .unwrap();
-        let message = &[];
-        let sig = private.sign(message);
-        assert_eq!(sig.bytes, expected);
-        let public = private.derive_public_key();
-        assert!(public.verify(message, sig));
+    /// Which procedure from RFC 8032 a test vector uses.
+    enum Variant {
+        Ed25519,
+        Ed25519ph,
     }
 
+    /// A test vector, with its secret key, public key, message, and signature in hex.
+    struct Vector {
+        name: &'static str,
+        variant: Variant,
+        secret: &'static str,
+        public: &'static str,
+        message: &'static str,
+        signature: &'static str,
+    }
+
+    /// The message of TEST 1024, from Section 7.1, which is 1023 bytes long.
+    const TEST_1024_MESSAGE: &str = concat!(
+        "08b8b2b733424243760fe426a4b54908632110a66c2f6591eabd3345e3e4eb98",
+        "fa6e264bf09efe12ee50f8f54e9f77b1e355f6c50544e23fb1433ddf73be84d8",
+        "79de7c0046dc4996d9e773f4bc9efe5738829adb26c81b37c93a1b270b20329d",
+        "658675fc6ea534e0810a4432826bf58c941efb65d57a338bbd2e26640f89ffbc",
+        "1a858efcb8550ee3a5e1998bd177e93a7363c344fe6b199ee5d02e82d522c4fe",
+        "ba15452f80288a821a579116ec6dad2b3b310da903401aa62100ab5d1a36553e",
+        "06203b33890cc9b832f79ef80560ccb9a39ce767967ed628c6ad573cb116dbef",
+        "efd75499da96bd68a8a97b928a8bbc103b6621fcde2beca1231d206be6cd9ec7",
+        "aff6f6c94fcd7204ed3455c68c83f4a41da4af2b74ef5c53f1d8ac70bdcb7ed1",
+        "85ce81bd84359d44254d95629e9855a94a7c1958d1f8ada5d0532ed8a5aa3fb2",
+        "d17ba70eb6248e594e1a2297acbbb39d502f1a8c6eb6f1ce22b3de1a1f40cc24",
+        "554119a831a9aad6079cad88425de6bde1a9187ebb6092cf67bf2b13fd65f270",
+        "88d78b7e883c8759d2c4f5c65adb7553878ad575f9fad878e80a0c9ba63bcbcc",
+        "2732e69485bbc9c90bfbd62481d9089beccf80cfe2df16a2cf65bd92dd597b07",
+        "07e0917af48bbb75fed413d238f5555a7a569d80c3414a8d0859dc65a46128ba",
+        "b27af87a71314f318c782b23ebfe808b82b0ce26401d2e22f04d83d1255dc51a",
+        "ddd3b75a2b1ae0784504df543af8969be3ea7082ff7fc9888c144da2af58429e",
+        "c96031dbcad3dad9af0dcbaaaf268cb8fcffead94f3c7ca495e056a9b47acdb7",
+        "51fb73e666c6c655ade8297297d07ad1ba5e43f1bca32301651339e22904cc8c",
+        "42f58c30c04aafdb038dda0847dd988dcda6f3bfd15c4b4c4525004aa06eeff8",
+        "ca61783aacec57fb3d1f92b0fe2fd1a85f6724517b65e614ad6808d6f6ee34df",
+        "f7310fdc82aebfd904b01e1dc54b2927094b2db68d6f903b68401adebf5a7e08",
+        "d78ff4ef5d63653a65040cf9bfd4aca7984a74d37145986780fc0b16ac451649",
+        "de6188a7dbdf191f64b5fc5e2ab47b57f7f7276cd419c17a3ca8e1b939ae49e4",
+        "88acba6b965610b5480109c8b17b80e1b7b750dfc7598d5d5011fd2dcc5600a3",
+        "2ef5b52a1ecc820e308aa342721aac0943bf6686b64b2579376504ccc493d97e",
+        "6aed3fb0f9cd71a43dd497f01f17c0e2cb3797aa2a2f256656168e6c496afc5f",
+        "b93246f6b1116398a346f1a641f3b041e989f7914f90cc2c7fff357876e506b5",
+        "0d334ba77c225bc307ba537152f3f1610e4eafe595f6d9d90d11faa933a15ef1",
+        "369546868a7f3a45a96768d40fd9d03412c091c6315cf4fde7cb68606937380d",
+        "b2eaaa707b4c4185c32eddcdd306705e4dc1ffc872eeee475a64dfac86aba41c",
+        "0618983f8741c5ef68d3a101e8a3b8cac60c905c15fc910840b94c00a0b9d0",
+    );
+
+    // The vectors from Sections 7.1 and 7.3 of RFC 8032, followed by vectors for keys
+    // at the edges of the seed space. The RFC has none of those, so they were generated
+    // with OpenSSL, which shares no code with us.
+    const VECTORS: [Vector; 10] = [
+        Vector {
+            name: "TEST 1",
+            variant: Variant::Ed25519,
+            secret: "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            public: "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            message: "",
+            signature: "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        },
+        Vector {
+            name: "TEST 2",
+            variant: Variant::Ed25519,
+            secret: "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            public: "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            message: "72",
+            signature: "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        },
+        Vector {
+            name: "TEST 3",
+            variant: Variant::Ed25519,
+            secret: "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            public: "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            message: "af82",
+            signature: "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+        },
+        Vector {
+            name: "TEST 1024",
+            variant: Variant::Ed25519,
+            secret: "f5e5767cf153319517630f226876b86c8160cc583bc013744c6bf255f5cc0ee5",
+            public: "278117fc144c72340f67d0f2316e8386ceffbf2b2428c9c51fef7c597f1d426e",
+            message: TEST_1024_MESSAGE,
+            signature: "0aab4c900501b3e24d7cdf4663326a3a87df5e4843b2cbdb67cbf6e460fec350aa5371b1508f9f4528ecea23c436d94b5e8fcd4f681e30a6ac00a9704a188a03",
+        },
+        Vector {
+            name: "TEST SHA(abc)",
+            variant: Variant::Ed25519,
+            secret: "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+            public: "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+            message: "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            signature: "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b58909351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704",
+        },
+        Vector {
+            name: "TEST abc, Ed25519ph",
+            variant: Variant::Ed25519ph,
+            secret: "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+            public: "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+            message: "616263",
+            signature: "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406",
+        },
+        Vector {
+            name: "all zero seed",
+            variant: Variant::Ed25519,
+            secret: "0000000000000000000000000000000000000000000000000000000000000000",
+            public: "3b6a27bcceb6a42d62a3a8d02a6f0d73653215771de243a63ac048a18b59da29",
+            message: "",
+            signature: "8f895b3cafe2c9506039d0e2a66382568004674fe8d237785092e40d6aaf483e4fc60168705f31f101596138ce21aa357c0d32a064f423dc3ee4aa3abf53f803",
+        },
+        Vector {
+            name: "all one seed",
+            variant: Variant::Ed25519,
+            secret: "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            public: "76a1592044a6e4f511265bca73a604d90b0529d1df602be30a19a9257660d1f5",
+            message: "00",
+            signature: "5c18fc95bdf24a65ac83d1ce350f58a99fa86edef34b698ab37c1d535b11968ea93267eb084dfa6b6abd4cd6fedd801537b86ac2fefb84ac3229d9b39c388408",
+        },
+        Vector {
+            name: "seed with only the lowest bit set",
+            variant: Variant::Ed25519,
+            secret: "0100000000000000000000000000000000000000000000000000000000000000",
+            public: "cecc1507dc1ddd7295951c290888f095adb9044d1b73d696e6df065d683bd4fc",
+            message: "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            signature: "a38af64f0c7cfb1ef17f9385831e91984a201d945930b1627088fd96aec79eb34e61fc6365f753c19d6185dee39ad03e19c2713b26c5e55f1e8839056a626d09",
+        },
+        Vector {
+            name: "seed with only the highest bit set",
+            variant: Variant::Ed25519,
+            secret: "0000000000000000000000000000000000000000000000000000000000000080",
+            public: "be1fb996be80e7bd2b78da157a224ab52320cca02ae5a02efbb419179be928f9",
+            message: "65646765",
+            signature: "cb9d99cbd6bfafc109fb6906a4560b1ec92ec64ccf1ece695e82ee55db5d1f2d80354b31434182ea8447a0fa9befe9dd30d9fc5b987fa49b09f1a77b445c5d07",
+        },
+    ];
+
     #[test]
-    fn test_signature_example2() {
-        let mut private = PrivateKey { bytes: [0; 32] };
-        hex::decode_to_slice(
-            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
-            &mut private.bytes,
-        )
-        .unwrap();
-        let mut expected = [0; 64];
-        hex::decode_to_slice(
-            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
-            &mut expected,
-        )
-        .unwrap();
-        let message = &[0x72];
-        let sig = private.sign(message);
-        assert_eq!(sig.bytes, expected);
-        let public = private.derive_public_key();
-        assert!(public.verify(message, sig));
+    fn test_rfc_8032_vectors() {
+        for vector in VECTORS.iter() {
+            let mut private = PrivateKey { bytes: [0; 32] };
+            hex::decode_to_slice(vector.secret, &mut private.bytes).unwrap();
+            let public = private.public_key();
+            assert_eq!(hex::encode(public.bytes), vector.public, "{}", vector.name);
+            let message = hex::decode(vector.message).unwrap();
+            let (sig, valid) = match vector.variant {
+                Variant::Ed25519 => {
+                    let sig = private.sign(&message);
+                    (sig, public.verify(&message, sig))
+                }
+                Variant::Ed25519ph => {
+                    let digest = prehash::digest(&message);
+                    let sig = private.sign_prehashed(&digest, &[]).unwrap();
+                    (sig, public.verify_prehashed(&digest, &[], sig))
+                }
+            };
+            assert_eq!(hex::encode(sig.bytes), vector.signature, "{}", vector.name);
+            assert!(valid, "{}", vector.name);
+            let mut flipped = sig;
+            flipped.bytes[0] ^= 1;
+            assert!(!public.verify(&message, flipped), "{}", vector.name);
+        }
+        assert_eq!(TEST_1024_MESSAGE.len(), 2 * 1023);
     }
 
     #[test]
@@ -165,4 +630,121 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_key_and_signature_equality() {
+        let private = PrivateKey { bytes: [1; 32] };
+        let other = PrivateKey { bytes: [2; 32] };
+        assert_eq!(private, private.clone());
+        assert_ne!(private, other);
+        assert_eq!(private.public_key(), private.public_key());
+        assert_ne!(private.public_key(), other.public_key());
+        assert_eq!(private.sign(b"a"), private.sign(b"a"));
+        assert_ne!(private.sign(b"a"), private.sign(b"b"));
+        assert!(bool::from(
+            private.public_key().ct_eq(&private.public_key())
+        ));
+    }
+
+    #[test]
+    fn test_debug_formatting() {
+        let private = PrivateKey { bytes: [0xAB; 32] };
+        assert_eq!(format!("{:?}", private), "PrivateKey(<redacted>)");
+        let public = PublicKey { bytes: [0xCD; 32] };
+        assert_eq!(
+            format!("{:?}", public),
+            format!("PublicKey({})", "cd".repeat(32))
+        );
+        let sig = Signature { bytes: [0xEF; 64] };
+        assert_eq!(
+            format!("{:?}", sig),
+            format!("Signature({})", "ef".repeat(64))
+        );
+    }
+
+    #[test]
+    fn test_verify_chunked_matches_verify() {
+        let private = PrivateKey { bytes: [4; 32] };
+        let public = private.public_key();
+        let message: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let sig = private.sign(&message);
+        for &chunk_size in &[1, 64, 100, 4096] {
+            assert!(public
+                .verify_chunked(&message[..], sig, chunk_size)
+                .unwrap());
+            assert!(!public
+                .verify_chunked(&message[1..], sig, chunk_size)
+                .unwrap());
+        }
+        assert!(public.verify_chunked(&message[..], sig, 0).is_err());
+    }
+
+    #[test]
+    fn test_verify_strict_accepts_valid_signatures() {
+        let private = PrivateKey { bytes: [6; 32] };
+        let public = private.public_key();
+        let sig = private.sign(b"strict");
+        assert!(public.verify_strict(b"strict", sig));
+        assert!(!public.verify_strict(b"lenient", sig));
+    }
+
+    // Each of these is a non-canonical encoding of a point with x = 0.
+    const NON_CANONICAL_POINTS: [&str; 3] = [
+        // y = 1, with the sign bit set, but x = 0 has no negative
+        "0100000000000000000000000000000000000000000000000000000000000080",
+        // y = P + 1
+        "eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        // y = P - 1 = -1, with the sign bit set
+        "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+    ];
+
+    #[test]
+    fn test_verify_strict_rejects_non_canonical_points() {
+        let private = PrivateKey { bytes: [6; 32] };
+        let public = private.public_key();
+        let sig = private.sign(b"strict");
+        for encoded in NON_CANONICAL_POINTS.iter() {
+            let mut bytes = [0; 32];
+            hex::decode_to_slice(encoded, &mut bytes).unwrap();
+            let mut bad_r = sig;
+            bad_r.bytes[..32].copy_from_slice(&bytes);
+            assert!(!public.verify_strict(b"strict", bad_r));
+            assert!(!public.verify(b"strict", bad_r));
+            let bad_a = PublicKey { bytes };
+            assert!(!bad_a.verify_strict(b"strict", sig));
+            assert!(!bad_a.verify(b"strict", sig));
+            assert!(decode_canonical(&bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn test_verify_strict_rejects_torsion() {
+        // A and R are the identity, which has small order, and s = 0
+        let mut small = Signature { bytes: [0; 64] };
+        small.bytes[0] = 1;
+        let identity = PublicKey {
+            bytes: small.bytes[..32].try_into().unwrap(),
+        };
+        // R has a component of order 8, which only the cofactored equation ignores
+        let mut mixed = Signature { bytes: [0; 64] };
+        hex::decode_to_slice("3272afcdf94de1f8643c9d234197395dc8f038806b5a305de5bc11dc84234012d6e23b8882903e3893c078ac27f61db0c4ca041d4b00b3dd7b463932aaf25103", &mut mixed.bytes).unwrap();
+        let mut public = PublicKey { bytes: [0; 32] };
+        hex::decode_to_slice(
+            "a47d1c5386f1e0ad6d1f4e059a58dae483430be3eafce41e879a3b791cac2ea7",
+            &mut public.bytes,
+        )
+        .unwrap();
+
+        assert!(identity.verify(b"any message", small));
+        assert_eq!(
+            identity.verify_strict_result(b"any message", small),
+            Err(SignatureError::SmallOrderPoint)
+        );
+        assert!(!public.verify(b"mixed order R", mixed));
+        assert!(public.verify_zip215(b"mixed order R", mixed));
+        assert_eq!(
+            public.verify_strict_result(b"mixed order R", mixed),
+            Err(SignatureError::InvalidEquation)
+        );
+    }
 }
@@ -0,0 +1,318 @@
+//! This module implements multiscalar multiplication.
+//!
+//! This calculates a sum s0⋅P0 + s1⋅P1 + ... + sn⋅Pn, much faster than doing each
+//! multiplication separately. We use two methods:
+//!
+//! - Straus' method, which shares the doublings between all of the points, and works
+//!   best for small batches.
+//! - Pippenger's method, which sorts points into buckets by their scalar digits,
+//!   and works best for large batches. With the `parallel` feature, the windows
+//!   of this method can be calculated on multiple threads.
+use alloc::{vec, vec::Vec};
+
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+use super::{point::EdwardsPoint, scalar::Scalar};
+
+/// The number of bits in each window of Straus' method.
+const STRAUS_WINDOW: usize = 4;
+
+/// Above this many points, Pippenger's method beats Straus' method.
+const PIPPENGER_THRESHOLD: usize = 190;
+
+/// Extract the `width` bits of a scalar starting at bit `start`.
+fn digit(scalar: &Scalar, start: usize, width: usize) -> usize {
+    let limbs = &scalar.value.limbs;
+    let (limb, shift) = (start / 64, start % 64);
+    let mut bits = limbs[limb] >> shift;
+    if shift + width > 64 && limb + 1 < limbs.len() {
+        bits |= limbs[limb + 1] << (64 - shift);
+    }
+    (bits & ((1 << width) - 1)) as usize
+}
+
+/// Calculate the multiples P, 2P, ..., 15P of a point.
+fn straus_table(point: EdwardsPoint) -> [EdwardsPoint; (1 << STRAUS_WINDOW) - 1] {
+    let mut table = [point; (1 << STRAUS_WINDOW) - 1];
+    for i in 1..table.len() {
+        table[i] = point + table[i - 1];
+    }
+    table
+}
+
+fn straus(scalars: &[Scalar], points: &[EdwardsPoint], constant_time: bool) -> EdwardsPoint {
+    let tables: Vec<_> = points.iter().map(|p| straus_table(*p)).collect();
+    let mut out = EdwardsPoint::identity();
+    for window in (0..256 / STRAUS_WINDOW).rev() {
+        for _ in 0..STRAUS_WINDOW {
+            out = out.doubled();
+        }
+        for (scalar, table) in scalars.iter().zip(&tables) {
+            let d = digit(scalar, window * STRAUS_WINDOW, STRAUS_WINDOW);
+            if constant_time {
+                let mut selected = EdwardsPoint::identity();
+                for (i, multiple) in table.iter().enumerate() {
+                    selected.conditional_assign(multiple, d.ct_eq(&(i + 1)));
+                }
+                out = out + selected;
+            } else if d != 0 {
+                out = out + table[d - 1];
+            }
+        }
+    }
+    out
+}
+
+/// The parameters used by Pippenger's method.
+///
+/// Points are sorted into 2^window - 1 buckets for each window of their scalars.
+/// Larger windows mean fewer windows, but more buckets to sum up in each of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PippengerParams {
+    /// The number of bits in each window, between 1 and 16.
+    pub window: usize,
+}
+
+impl PippengerParams {
+    /// Pick a window size for a batch, roughly log2 of the number of points.
+    pub fn for_size(n: usize) -> Self {
+        let window = match n {
+            0..=499 => 6,
+            500..=799 => 7,
+            800..=3999 => 8,
+            4000..=15999 => 10,
+            _ => 12,
+        };
+        PippengerParams { window }
+    }
+}
+
+/// Calculate the sum of d_i⋅P_i, where d_i is the digit of s_i in a given window.
+fn pippenger_window(
+    scalars: &[Scalar],
+    points: &[EdwardsPoint],
+    c: usize,
+    window: usize,
+) -> EdwardsPoint {
+    // buckets[i] holds the sum of the points whose digit is i + 1
+    let mut buckets = vec![EdwardsPoint::identity(); (1 << c) - 1];
+    for (scalar, point) in scalars.iter().zip(points) {
+        let d = digit(scalar, window * c, c.min(256 - window * c));
+        if d != 0 {
+            buckets[d - 1] = buckets[d - 1] + *point;
+        }
+    }
+    // Summing the running sums adds each bucket in as many times as its digit
+    let mut running = EdwardsPoint::identity();
+    let mut total = EdwardsPoint::identity();
+    for bucket in buckets.into_iter().rev() {
+        running = running + bucket;
+        total = total + running;
+    }
+    total
+}
+
+/// Combine the sums of each window, starting from the most significant.
+fn combine_windows(sums: impl DoubleEndedIterator<Item = EdwardsPoint>, c: usize) -> EdwardsPoint {
+    let mut out = EdwardsPoint::identity();
+    for sum in sums.rev() {
+        for _ in 0..c {
+            out = out.doubled();
+        }
+        out = out + sum;
+    }
+    out
+}
+
+fn check_params(params: PippengerParams) -> usize {
+    assert!(
+        (1..=16).contains(&params.window),
+        "Pippenger window must be between 1 and 16 bits"
+    );
+    params.window
+}
+
+fn pippenger(scalars: &[Scalar], points: &[EdwardsPoint], params: PippengerParams) -> EdwardsPoint {
+    let c = check_params(params);
+    let windows = 256usize.div_ceil(c);
+    combine_windows(
+        (0..windows).map(|w| pippenger_window(scalars, points, c, w)),
+        c,
+    )
+}
+
+#[cfg(feature = "parallel")]
+fn par_pippenger(
+    scalars: &[Scalar],
+    points: &[EdwardsPoint],
+    params: PippengerParams,
+) -> EdwardsPoint {
+    use rayon::prelude::*;
+
+    let c = check_params(params);
+    let windows = 256usize.div_ceil(c);
+    // Each window is independent, so we can calculate them all in parallel
+    let sums: Vec<_> = (0..windows)
+        .into_par_iter()
+        .map(|w| pippenger_window(scalars, points, c, w))
+        .collect();
+    combine_windows(sums.into_iter(), c)
+}
+
+impl EdwardsPoint {
+    /// Calculate s0⋅P0 + s1⋅P1 + ... + sn⋅Pn, in constant time.
+    ///
+    /// This uses Straus' method, and is safe to use with secret scalars.
+    ///
+    /// # Panics
+    ///
+    /// This panics if there aren't as many scalars as points.
+    pub fn multiscalar_mul(scalars: &[Scalar], points: &[EdwardsPoint]) -> EdwardsPoint {
+        assert_eq!(
+            scalars.len(),
+            points.len(),
+            "mismatched multiscalar lengths"
+        );
+        straus(scalars, points, true)
+    }
+
+    /// Calculate s0⋅P0 + s1⋅P1 + ... + sn⋅Pn, in variable time.
+    ///
+    /// This leaks information about the scalars through timing, and should only be
+    /// used with public scalars, e.g. when verifying signatures. This uses Straus' method
+    /// for small batches, and Pippenger's method for large ones.
+    ///
+    /// # Panics
+    ///
+    /// This panics if there aren't as many scalars as points.
+    pub fn vartime_multiscalar_mul(scalars: &[Scalar], points: &[EdwardsPoint]) -> EdwardsPoint {
+        assert_eq!(
+            scalars.len(),
+            points.len(),
+            "mismatched multiscalar lengths"
+        );
+        if points.len() < PIPPENGER_THRESHOLD {
+            straus(scalars, points, false)
+        } else {
+            pippenger(scalars, points, PippengerParams::for_size(points.len()))
+        }
+    }
+
+    /// Calculate s0⋅P0 + s1⋅P1 + ... + sn⋅Pn, in variable time, with Pippenger's method.
+    ///
+    /// This is like `vartime_multiscalar_mul`, but lets the window size be tuned.
+    ///
+    /// # Panics
+    ///
+    /// This panics if there aren't as many scalars as points, or if the window
+    /// isn't between 1 and 16 bits.
+    pub fn vartime_pippenger_mul(
+        scalars: &[Scalar],
+        points: &[EdwardsPoint],
+        params: PippengerParams,
+    ) -> EdwardsPoint {
+        assert_eq!(
+            scalars.len(),
+            points.len(),
+            "mismatched multiscalar lengths"
+        );
+        pippenger(scalars, points, params)
+    }
+
+    /// Like `vartime_pippenger_mul`, but spreading the work over multiple threads.
+    ///
+    /// This is only available with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn par_vartime_pippenger_mul(
+        scalars: &[Scalar],
+        points: &[EdwardsPoint],
+        params: PippengerParams,
+    ) -> EdwardsPoint {
+        assert_eq!(
+            scalars.len(),
+            points.len(),
+            "mismatched multiscalar lengths"
+        );
+        par_pippenger(scalars, points, params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::curve25519::point::B;
+
+    fn inputs(n: u64) -> (Vec<Scalar>, Vec<EdwardsPoint>) {
+        let scalars = (0..n)
+            .map(|i| Scalar::from([i as u8 ^ 0xA5; 64]) * Scalar::from(i + 1))
+            .collect();
+        let points = (0..n).map(|i| B * Scalar::from(3 * i + 7)).collect();
+        (scalars, points)
+    }
+
+    fn naive(scalars: &[Scalar], points: &[EdwardsPoint]) -> EdwardsPoint {
+        scalars
+            .iter()
+            .zip(points)
+            .fold(EdwardsPoint::identity(), |acc, (s, p)| acc + *p * *s)
+    }
+
+    #[test]
+    fn test_straus_matches_naive() {
+        for n in [0, 1, 2, 9] {
+            let (scalars, points) = inputs(n);
+            let expected = naive(&scalars, &points);
+            assert_eq!(EdwardsPoint::multiscalar_mul(&scalars, &points), expected);
+            assert_eq!(
+                EdwardsPoint::vartime_multiscalar_mul(&scalars, &points),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_pippenger_matches_naive() {
+        let (scalars, points) = inputs(PIPPENGER_THRESHOLD as u64 + 10);
+        let expected = naive(&scalars, &points);
+        assert_eq!(
+            EdwardsPoint::vartime_multiscalar_mul(&scalars, &points),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_pippenger_windows() {
+        let (scalars, points) = inputs(20);
+        let expected = naive(&scalars, &points);
+        for window in [1, 5, 8, 11] {
+            let params = PippengerParams { window };
+            assert_eq!(
+                EdwardsPoint::vartime_pippenger_mul(&scalars, &points, params),
+                expected
+            );
+            #[cfg(feature = "parallel")]
+            assert_eq!(
+                EdwardsPoint::par_vartime_pippenger_mul(&scalars, &points, params),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_pippenger_rejects_empty_window() {
+        let (scalars, points) = inputs(1);
+        EdwardsPoint::vartime_pippenger_mul(&scalars, &points, PippengerParams { window: 0 });
+    }
+
+    #[test]
+    fn test_digit_crosses_limbs() {
+        let s = Scalar {
+            value: crate::curve25519::arithmetic::U256 {
+                limbs: [0xF000_0000_0000_0000, 0x3, 0, 0],
+            },
+        };
+        assert_eq!(digit(&s, 60, 6), 0x3F);
+        assert_eq!(digit(&s, 62, 4), 0xF);
+    }
+}
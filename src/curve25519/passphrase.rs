@@ -0,0 +1,107 @@
+//! This module implements deriving private keys from passphrases.
+//!
+//! Passphrases chosen by humans are much weaker than random keys, so we use
+//! PBKDF2-HMAC-SHA512, and refuse to run with a cost or salt that's too small.
+//! This doesn't make a weak passphrase strong, but it does make guessing slower.
+use super::PrivateKey;
+use crate::kdf;
+
+/// The smallest number of PBKDF2 iterations we accept.
+pub const MIN_ITERATIONS: u32 = 100_000;
+
+/// The smallest salt, in bytes, that we accept.
+pub const MIN_SALT_SIZE: usize = 16;
+
+/// The parameters controlling how expensive deriving a key is.
+#[derive(Debug, Clone, Copy)]
+pub struct PassphraseParams {
+    /// The number of PBKDF2 iterations to perform.
+    pub iterations: u32,
+}
+
+impl Default for PassphraseParams {
+    fn default() -> Self {
+        PassphraseParams {
+            iterations: 210_000,
+        }
+    }
+}
+
+/// Represents the reasons we might refuse to derive a key from a passphrase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PassphraseError {
+    /// The number of iterations was below `MIN_ITERATIONS`.
+    WeakParams,
+    /// The salt was shorter than `MIN_SALT_SIZE`.
+    ShortSalt,
+    /// The passphrase was empty.
+    EmptyPassphrase,
+}
+
+impl PrivateKey {
+    /// Derive a private key from a passphrase, and a salt.
+    ///
+    /// The same passphrase, salt, and parameters will always produce the same key.
+    /// The salt doesn't need to be secret, but it should be unique to each user.
+    ///
+    /// Keys derived from passphrases are only as strong as the passphrase, so
+    /// prefer generating random keys whenever possible.
+    pub fn from_passphrase(
+        passphrase: &[u8],
+        salt: &[u8],
+        params: PassphraseParams,
+    ) -> Result<PrivateKey, PassphraseError> {
+        if params.iterations < MIN_ITERATIONS {
+            return Err(PassphraseError::WeakParams);
+        }
+        if salt.len() < MIN_SALT_SIZE {
+            return Err(PassphraseError::ShortSalt);
+        }
+        if passphrase.is_empty() {
+            return Err(PassphraseError::EmptyPassphrase);
+        }
+        let mut out = PrivateKey { bytes: [0; 32] };
+        kdf::pbkdf2_hmac_sha512(passphrase, salt, params.iterations, &mut out.bytes);
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SALT: &[u8] = b"eddo test salt!!";
+
+    #[test]
+    fn test_weak_inputs_are_rejected() {
+        let weak = PassphraseParams {
+            iterations: MIN_ITERATIONS - 1,
+        };
+        assert_eq!(
+            PrivateKey::from_passphrase(b"hunter2", SALT, weak).unwrap_err(),
+            PassphraseError::WeakParams
+        );
+        assert_eq!(
+            PrivateKey::from_passphrase(b"hunter2", b"short", PassphraseParams::default())
+                .unwrap_err(),
+            PassphraseError::ShortSalt
+        );
+        assert_eq!(
+            PrivateKey::from_passphrase(b"", SALT, PassphraseParams::default()).unwrap_err(),
+            PassphraseError::EmptyPassphrase
+        );
+    }
+
+    #[test]
+    fn test_derivation_matches_pbkdf2() {
+        let params = PassphraseParams {
+            iterations: MIN_ITERATIONS,
+        };
+        let private =
+            PrivateKey::from_passphrase(b"correct horse battery staple", SALT, params).unwrap();
+        assert_eq!(
+            hex::encode(private.bytes),
+            "a44a36ae2f8715e273f9dd1e51355394c2285f0541e6981de28000aac6ff943f"
+        );
+    }
+}
@@ -2,69 +2,34 @@
 //! This follows sections of RFC 8032:
 //! https://datatracker.ietf.org/doc/html/rfc8032
 
-use std::{
-    convert::{TryFrom, TryInto},
-    ops::{Add, Mul},
+use core::{
+    convert::TryFrom,
+    ops::{Add, Mul, Neg, Sub},
 };
 
-use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use subtle::{Choice, ConditionallyNegatable, ConditionallySelectable, ConstantTimeEq};
 
-use super::{arithmetic::U256, error::SignatureError, field::Z25519, scalar::Scalar};
+use super::{error::SignatureError, field::Z25519, scalar::Scalar};
 
-const D: Z25519 = Z25519 {
-    value: U256 {
-        limbs: [
-            0x75eb4dca135978a3,
-            0x00700a4d4141d8ab,
-            0x8cc740797779e898,
-            0x52036cee2b6ffe73,
-        ],
-    },
-};
+/// The d coefficient of our curve, -121665/121666.
+const D: Z25519 =
+    Z25519::from_le_hex("a3785913ca4deb75abd841414d0a700098e879777940c78c73fe6f2bee6c0352");
 
-pub const B: Point = Point {
-    x: Z25519 {
-        value: U256 {
-            limbs: [
-                0xc9562d608f25d51a,
-                0x692cc7609525a7b2,
-                0xc0a4e231fdd6dc5c,
-                0x216936d3cd6e53fe,
-            ],
-        },
-    },
-    y: Z25519 {
-        value: U256 {
-            limbs: [
-                0x6666666666666658,
-                0x6666666666666666,
-                0x6666666666666666,
-                0x6666666666666666,
-            ],
-        },
-    },
-    z: Z25519 {
-        value: U256 {
-            limbs: [1, 0, 0, 0],
-        },
-    },
-    t: Z25519 {
-        value: U256 {
-            limbs: [
-                0x6dde8ab3a5b7dda3,
-                0x20f09f80775152f5,
-                0x66ea4e8e64abe37d,
-                0x67875f0fd78b7665,
-            ],
-        },
-    },
+/// The standard basepoint, with y = 4/5, and x even, in extended coordinates.
+pub const B: EdwardsPoint = EdwardsPoint {
+    x: Z25519::from_le_hex("1ad5258f602d56c9b2a7259560c72c695cdcd6fd31e2a4c0fe536ecdd3366921"),
+    y: Z25519::from_le_hex("5866666666666666666666666666666666666666666666666666666666666666"),
+    z: Z25519::from_limbs([1, 0, 0, 0]),
+    t: Z25519::from_le_hex("a3ddb7a5b38ade6df5525177809ff0207de3ab648e4eea6665768bd70f5f8767"),
 };
 
 /// Represents a point on our Edward's Curve.
 ///
 /// This is used to implement the finite group we use for our cryptographic operations.
+/// The group has order 8⋅L, with the points of order L forming the prime order subgroup
+/// generated by the standard basepoint.
 #[derive(Clone, Copy, Debug)]
-pub struct Point {
+pub struct EdwardsPoint {
     // We use extended homogenous coordinate, as per section 5.1.4:
     // https://datatracker.ietf.org/doc/html/rfc8032#section-5.1.4
     x: Z25519,
@@ -73,10 +38,13 @@ pub struct Point {
     t: Z25519,
 }
 
-impl Point {
-    // Return the identity element of this group.
-    fn identity() -> Point {
-        Point {
+impl EdwardsPoint {
+    /// The standard basepoint of Ed25519, generating the prime order subgroup.
+    pub const BASEPOINT: EdwardsPoint = B;
+
+    /// Return the identity element of this group.
+    pub fn identity() -> EdwardsPoint {
+        EdwardsPoint {
             x: Z25519::from(0),
             y: Z25519::from(1),
             z: Z25519::from(1),
@@ -85,8 +53,8 @@ impl Point {
     }
 
     // Creates a point from two affine coordinates, assumed to be on the curve.
-    fn from_affine_unchecked(x: Z25519, y: Z25519) -> Point {
-        Point {
+    fn from_affine_unchecked(x: Z25519, y: Z25519) -> EdwardsPoint {
+        EdwardsPoint {
             x,
             y,
             z: Z25519::from(1),
@@ -95,9 +63,86 @@ impl Point {
         }
     }
 
+    // Creates a point from affine coordinates given as fractions, x = xn / xd, and y = yn / yd.
+    //
+    // The point is assumed to be on the curve, and the denominators non-zero.
+    pub(crate) fn from_fractions(xn: Z25519, xd: Z25519, yn: Z25519, yd: Z25519) -> EdwardsPoint {
+        EdwardsPoint {
+            x: xn * yd,
+            y: yn * xd,
+            z: xd * yd,
+            t: xn * yn,
+        }
+    }
+
+    /// Multiply this point by the cofactor of our curve, 8.
+    #[must_use]
+    pub fn mul_by_cofactor(&self) -> EdwardsPoint {
+        self.doubled().doubled().doubled()
+    }
+
+    /// Compress this point into its 32 byte encoding.
+    pub fn compress(&self) -> CompressedEdwardsY {
+        AffinePoint::from(*self).compress()
+    }
+
+    /// Convert many points to affine coordinates at once.
+    ///
+    /// Like `compress_batch`, this needs only a single field inversion for the whole batch.
+    #[cfg(feature = "alloc")]
+    pub fn to_affine(points: &[EdwardsPoint]) -> alloc::vec::Vec<AffinePoint> {
+        let mut zinvs: alloc::vec::Vec<Z25519> = points.iter().map(|p| p.z).collect();
+        Z25519::batch_invert(&mut zinvs);
+        points
+            .iter()
+            .zip(zinvs)
+            .map(|(p, zinv)| p.to_affine_with_zinv(zinv))
+            .collect()
+    }
+
+    /// Compress many points at once.
+    ///
+    /// This is faster than compressing each point separately, since it needs only
+    /// a single field inversion for the whole batch.
+    #[cfg(feature = "alloc")]
+    pub fn compress_batch(points: &[EdwardsPoint]) -> alloc::vec::Vec<CompressedEdwardsY> {
+        EdwardsPoint::to_affine(points)
+            .iter()
+            .map(AffinePoint::compress)
+            .collect()
+    }
+
+    // Convert this point to affine coordinates, given the inverse of its z coordinate.
+    fn to_affine_with_zinv(self, zinv: Z25519) -> AffinePoint {
+        AffinePoint {
+            x: self.x * zinv,
+            y: self.y * zinv,
+        }
+    }
+
+    /// Check whether or not this point is the identity.
+    pub fn is_identity(&self) -> bool {
+        self.ct_eq(&EdwardsPoint::identity()).into()
+    }
+
+    /// Check whether or not this point has an order dividing the cofactor.
+    ///
+    /// Such points are useless as public keys, since they only generate a handful of points.
+    pub fn is_small_order(&self) -> bool {
+        self.mul_by_cofactor().is_identity()
+    }
+
+    /// Check whether or not this point lies in the prime order subgroup.
+    ///
+    /// This means that the point has no component of small order, and L⋅P is the identity.
+    pub fn is_torsion_free(&self) -> bool {
+        // Scalars are reduced mod L, so we compute (L - 1)⋅P + P instead of L⋅P.
+        (*self * -Scalar::from(1) + *self).is_identity()
+    }
+
     // this calculates self + self, but in a more efficient way, exploiting symmetry.
     #[must_use]
-    fn doubled(&self) -> Point {
+    pub(crate) fn doubled(&self) -> EdwardsPoint {
         // This is taken from the second routine in section 5.1.4:
         // https://datatracker.ietf.org/doc/html/rfc8032#section-5.1.4
         let a = self.x.squared();
@@ -107,7 +152,7 @@ impl Point {
         let e = h - (self.x + self.y).squared();
         let g = a - b;
         let f = c + g;
-        Point {
+        EdwardsPoint {
             x: e * f,
             y: g * h,
             t: e * h,
@@ -116,57 +161,197 @@ impl Point {
     }
 }
 
-impl ConditionallySelectable for Point {
+impl ConditionallySelectable for EdwardsPoint {
     fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
-        Point {
+        EdwardsPoint {
             x: Z25519::conditional_select(&a.x, &b.x, choice),
             y: Z25519::conditional_select(&a.y, &b.y, choice),
             z: Z25519::conditional_select(&a.z, &b.z, choice),
             t: Z25519::conditional_select(&a.t, &b.t, choice),
         }
     }
+
+    fn conditional_swap(a: &mut Self, b: &mut Self, choice: Choice) {
+        Z25519::conditional_swap(&mut a.x, &mut b.x, choice);
+        Z25519::conditional_swap(&mut a.y, &mut b.y, choice);
+        Z25519::conditional_swap(&mut a.z, &mut b.z, choice);
+        Z25519::conditional_swap(&mut a.t, &mut b.t, choice);
+    }
 }
 
-impl Into<[u8; 32]> for Point {
-    fn into(self) -> [u8; 32] {
-        let zinv = self.z.inverse();
-        let x = self.x * zinv;
-        let y = self.y * zinv;
-        let mut out: [u8; 32] = y.into();
-        out[31] |= ((x.value.limbs[0] & 1) as u8) << 7;
-        out
+impl ConstantTimeEq for EdwardsPoint {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        // With x = X / Z, checking x0 = x1 amounts to checking X0 * Z1 = X1 * Z0.
+        let x_eq = (self.x * other.z).ct_eq(&(other.x * self.z));
+        let y_eq = (self.y * other.z).ct_eq(&(other.y * self.z));
+        x_eq & y_eq
+    }
+}
+
+impl PartialEq for EdwardsPoint {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for EdwardsPoint {}
+
+impl Neg for EdwardsPoint {
+    type Output = EdwardsPoint;
+
+    fn neg(self) -> Self::Output {
+        // Negating a point on our curve amounts to negating its x coordinate.
+        // Since t / z = x * y, we negate t as well.
+        EdwardsPoint {
+            x: -self.x,
+            y: self.y,
+            z: self.z,
+            t: -self.t,
+        }
     }
 }
 
-impl<'a> TryFrom<&'a [u8]> for Point {
+// Implementing negation for references gives us `ConditionallyNegatable` for free.
+impl Neg for &EdwardsPoint {
+    type Output = EdwardsPoint;
+
+    fn neg(self) -> Self::Output {
+        -*self
+    }
+}
+
+#[cfg(all(feature = "hazmat", feature = "alloc"))]
+impl EdwardsPoint {
+    /// Encode the compressed form of this point as hex.
+    pub fn to_hex(&self) -> alloc::string::String {
+        let bytes = self.compress().bytes;
+        hex::encode(bytes)
+    }
+}
+
+#[cfg(feature = "hazmat")]
+impl core::fmt::LowerHex for EdwardsPoint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        let bytes = self.compress().bytes;
+        super::write_hex(f, &bytes)
+    }
+}
+
+/// Represents a point on our curve, in affine coordinates.
+///
+/// This is the point (x, y), with -x² + y² = 1 + d⋅x²⋅y². Unlike `EdwardsPoint`,
+/// each point has a single representation, which is convenient for serializing
+/// precomputed tables, or for writing down test vectors.
+#[derive(Clone, Copy, Debug)]
+pub struct AffinePoint {
+    pub x: Z25519,
+    pub y: Z25519,
+}
+
+// Only implement equality for tests. This is to avoid the temptation to introduce
+// a timing leak through equality comparison in other situations.
+#[cfg(test)]
+impl PartialEq for AffinePoint {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl AffinePoint {
+    /// Check whether or not these coordinates satisfy the curve equation.
+    pub fn is_on_curve(&self) -> bool {
+        let x2 = self.x.squared();
+        let y2 = self.y.squared();
+        (y2 - x2).ct_eq(&(Z25519::from(1) + D * x2 * y2)).into()
+    }
+
+    /// Compress this point into its 32 byte encoding.
+    pub fn compress(&self) -> CompressedEdwardsY {
+        let mut bytes: [u8; 32] = self.y.into();
+        bytes[31] |= self.x.is_negative().unwrap_u8() << 7;
+        CompressedEdwardsY { bytes }
+    }
+}
+
+impl From<EdwardsPoint> for AffinePoint {
+    fn from(point: EdwardsPoint) -> Self {
+        point.to_affine_with_zinv(point.z.inverse())
+    }
+}
+
+impl TryFrom<AffinePoint> for EdwardsPoint {
     type Error = SignatureError;
 
-    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
-        if value.len() < 32 {
+    /// Convert affine coordinates back to a point, checking that they lie on the curve.
+    fn try_from(point: AffinePoint) -> Result<Self, Self::Error> {
+        if !point.is_on_curve() {
             return Err(SignatureError::InvalidPoint);
         }
-        let mut value_bytes: [u8; 32] = value[..32].try_into().unwrap();
-        let x_0 = u64::from(value_bytes[31] >> 7);
-        value_bytes[31] &= 0x7F;
-        let y = Z25519::try_from(&value_bytes[..])?;
+        Ok(EdwardsPoint::from_affine_unchecked(point.x, point.y))
+    }
+}
+
+impl ConstantTimeEq for AffinePoint {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.x.ct_eq(&other.x) & self.y.ct_eq(&other.y)
+    }
+}
+
+/// Represents the compressed encoding of a point.
+///
+/// This is the y coordinate, with the sign of the x coordinate stored in the top bit,
+/// as per section 5.1.2:
+/// https://datatracker.ietf.org/doc/html/rfc8032#section-5.1.2
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompressedEdwardsY {
+    pub bytes: [u8; 32],
+}
+
+impl CompressedEdwardsY {
+    /// Decompress this encoding into a point.
+    ///
+    /// This returns `None` if the bytes don't encode a point on the curve.
+    pub fn decompress(&self) -> Option<EdwardsPoint> {
+        self.decompress_with(false)
+    }
+
+    /// Decompress this encoding, also accepting the non-canonical encodings ZIP 215 allows.
+    ///
+    /// These are y coordinates of P or more, which get reduced, and the sign bit being
+    /// set when x is 0.
+    pub(crate) fn decompress_zip215(&self) -> Option<EdwardsPoint> {
+        self.decompress_with(true)
+    }
+
+    fn decompress_with(&self, allow_non_canonical: bool) -> Option<EdwardsPoint> {
+        let mut bytes = self.bytes;
+        let x_0 = Choice::from(bytes[31] >> 7);
+        bytes[31] &= 0x7F;
+        let y = match Z25519::try_from(&bytes[..]) {
+            Ok(y) => y,
+            // The only values this rejects are P + t, for t < 19, whose low byte is 0xED + t
+            Err(_) if allow_non_canonical => Z25519::from(u64::from(bytes[0] - 0xED)),
+            Err(_) => return None,
+        };
         let y_2 = y.squared();
         let u = y_2 - Z25519::from(1);
         let v = D * y_2 + Z25519::from(1);
-        let mut x = Z25519::fraction_root(u, v).ok_or(SignatureError::InvalidPoint)?;
-        if x_0 == 1 && x.value.eq(U256::from(0)) {
-            return Err(SignatureError::InvalidPoint);
+        let mut x = Z25519::fraction_root(u, v)?;
+        if !allow_non_canonical && bool::from(x_0 & x.ct_eq(&Z25519::from(0))) {
+            return None;
         }
-        if x_0 != x.value.limbs[0] % 2 {
-            x = -x;
-        }
-        Ok(Point::from_affine_unchecked(x, y))
+        x.conditional_negate(x_0 ^ x.is_negative());
+        Some(EdwardsPoint::from_affine_unchecked(x, y))
     }
 }
 
-impl Add for Point {
-    type Output = Point;
+impl Add for EdwardsPoint {
+    type Output = EdwardsPoint;
 
-    fn add(self, other: Point) -> Self::Output {
+    fn add(self, other: EdwardsPoint) -> Self::Output {
         let a = (self.y - self.x) * (other.y - other.x);
         let b = (self.y + self.x) * (other.y + other.x);
         let c = self.t * D * other.t * 2;
@@ -175,7 +360,7 @@ impl Add for Point {
         let f = d - c;
         let g = d + c;
         let h = b + a;
-        Point {
+        EdwardsPoint {
             x: e * f,
             y: g * h,
             t: e * h,
@@ -184,13 +369,21 @@ impl Add for Point {
     }
 }
 
-impl Mul<Scalar> for Point {
-    type Output = Point;
+impl Sub for EdwardsPoint {
+    type Output = EdwardsPoint;
+
+    fn sub(self, other: EdwardsPoint) -> Self::Output {
+        self + -other
+    }
+}
+
+impl Mul<Scalar> for EdwardsPoint {
+    type Output = EdwardsPoint;
 
     fn mul(self, other: Scalar) -> Self::Output {
-        let mut out = Point::identity();
+        let mut out = EdwardsPoint::identity();
         const WINDOW_SIZE: usize = 4;
-        let mut window = [Point::identity(); (1 << WINDOW_SIZE) - 1];
+        let mut window = [EdwardsPoint::identity(); (1 << WINDOW_SIZE) - 1];
         window[0] = self;
         for i in 1..window.len() {
             window[i] = self + window[i - 1];
@@ -203,9 +396,9 @@ impl Mul<Scalar> for Point {
                 out = out.doubled();
 
                 let w = ((x >> i) & ((1 << WINDOW_SIZE) - 1)) as usize;
-                let mut selected = Point::identity();
-                for i in 0..window.len() {
-                    selected.conditional_assign(&window[i], w.ct_eq(&(i + 1)));
+                let mut selected = EdwardsPoint::identity();
+                for (i, point) in window.iter().enumerate() {
+                    selected.conditional_assign(point, w.ct_eq(&(i + 1)));
                 }
                 out = out + selected;
             }
@@ -213,3 +406,306 @@ impl Mul<Scalar> for Point {
         out
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+    use subtle::ConditionallyNegatable;
+
+    fn encode(point: EdwardsPoint) -> [u8; 32] {
+        point.compress().bytes
+    }
+
+    #[test]
+    fn test_negation_cancels() {
+        let p = B * Scalar::from(7);
+        assert_eq!(encode(p + -p), encode(EdwardsPoint::identity()));
+    }
+
+    #[test]
+    fn test_conditional_negate() {
+        let p = B * Scalar::from(5);
+        let mut q = p;
+        q.conditional_negate(0.into());
+        assert_eq!(encode(q), encode(p));
+        q.conditional_negate(1.into());
+        assert_eq!(encode(q), encode(-p));
+    }
+
+    #[test]
+    fn test_conditional_swap() {
+        let p = B * Scalar::from(2);
+        let q = B * Scalar::from(3);
+        let (mut a, mut b) = (p, q);
+        EdwardsPoint::conditional_swap(&mut a, &mut b, 0.into());
+        assert_eq!((encode(a), encode(b)), (encode(p), encode(q)));
+        EdwardsPoint::conditional_swap(&mut a, &mut b, 1.into());
+        assert_eq!((encode(a), encode(b)), (encode(q), encode(p)));
+    }
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let p = B * Scalar::from(11);
+        assert_eq!(p.compress().decompress(), Some(p));
+        let mut bad = CompressedEdwardsY { bytes: [0xFF; 32] };
+        bad.bytes[31] = 0x7F;
+        assert_eq!(bad.decompress(), None);
+    }
+
+    #[test]
+    fn test_decompress_zip215_reduces_y() {
+        let decode = |hex: &str| {
+            let mut bytes = [0; 32];
+            hex::decode_to_slice(hex, &mut bytes).unwrap();
+            CompressedEdwardsY { bytes }
+        };
+        // y = P + 1, which is the identity, as is -0, the identity with the sign bit set
+        for encoded in &[
+            "eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            "0100000000000000000000000000000000000000000000000000000000000080",
+        ] {
+            assert_eq!(decode(encoded).decompress(), None);
+            assert_eq!(
+                decode(encoded).decompress_zip215(),
+                Some(EdwardsPoint::identity())
+            );
+        }
+        // y = P, which is a point of order 4, with y = 0
+        let order_4 = decode("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+        let canonical = decode("0000000000000000000000000000000000000000000000000000000000000000");
+        assert_eq!(order_4.decompress(), None);
+        assert_eq!(order_4.decompress_zip215(), canonical.decompress());
+        // y = P + 2, which isn't on the curve, since y = 2 isn't
+        assert_eq!(
+            decode("efffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f")
+                .decompress_zip215(),
+            None
+        );
+        let p = B * Scalar::from(11);
+        assert_eq!(p.compress().decompress_zip215(), Some(p));
+    }
+
+    #[test]
+    fn test_affine_roundtrip() {
+        let points: Vec<_> = (1..6).map(|i| B * Scalar::from(i)).collect();
+        let affine = EdwardsPoint::to_affine(&points);
+        for (p, a) in points.iter().zip(affine.iter()) {
+            assert_eq!(*a, AffinePoint::from(*p));
+            assert!(a.is_on_curve());
+            assert_eq!(a.compress(), p.compress());
+            assert_eq!(EdwardsPoint::try_from(*a).ok(), Some(*p));
+        }
+        assert_eq!(
+            AffinePoint::from(B),
+            AffinePoint {
+                x: Z25519::from_le_hex(
+                    "1ad5258f602d56c9b2a7259560c72c695cdcd6fd31e2a4c0fe536ecdd3366921"
+                ),
+                y: Z25519::from(4) * Z25519::from(5).inverse(),
+            }
+        );
+        let off_curve = AffinePoint {
+            x: Z25519::from(1),
+            y: Z25519::from(1),
+        };
+        assert!(!off_curve.is_on_curve());
+        assert!(EdwardsPoint::try_from(off_curve).is_err());
+        assert!(EdwardsPoint::to_affine(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_compress_batch() {
+        let points: Vec<_> = (1..6).map(|i| B * Scalar::from(i)).collect();
+        let expected: Vec<_> = points.iter().map(|p| p.compress()).collect();
+        assert_eq!(EdwardsPoint::compress_batch(&points), expected);
+        assert!(EdwardsPoint::compress_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_equality_ignores_representation() {
+        let p = B * Scalar::from(3);
+        assert_eq!(p.doubled(), p + p);
+        assert_eq!(p - p, EdwardsPoint::identity());
+        assert_ne!(p, B);
+    }
+
+    /// A point of order 8, from the list of small order encodings libsodium rejects.
+    fn order_8() -> EdwardsPoint {
+        CompressedEdwardsY {
+            bytes: [
+                0xc7, 0x17, 0x6a, 0x70, 0x3d, 0x4d, 0xd8, 0x4f, 0xba, 0x3c, 0x0b, 0x76, 0x0d, 0x10,
+                0x67, 0x0f, 0x2a, 0x20, 0x53, 0xfa, 0x2c, 0x39, 0xcc, 0xc6, 0x4e, 0xc7, 0xfd, 0x77,
+                0x92, 0xac, 0x03, 0x7a,
+            ],
+        }
+        .decompress()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_torsion_checks() {
+        assert!(B.is_torsion_free());
+        assert!(!B.is_small_order());
+        assert!(EdwardsPoint::identity().is_small_order());
+        let small = order_8();
+        assert!(small.is_small_order());
+        assert!(!small.is_torsion_free());
+        assert!(!(B + small).is_torsion_free());
+        assert!(!(B + small).is_small_order());
+    }
+
+    #[test]
+    #[cfg(feature = "hazmat")]
+    fn test_hex_formatting() {
+        let expected = "5866666666666666666666666666666666666666666666666666666666666666";
+        assert_eq!(B.to_hex(), expected);
+        assert_eq!(format!("{:x}", B), expected);
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(D * 121666, -Z25519::from(121665));
+        assert_eq!(B.y * 5, Z25519::from(4));
+        assert_eq!(B.z, Z25519::from(1));
+        assert_eq!(B.t, B.x * B.y);
+        assert!(!bool::from(B.x.is_negative()));
+        // -x² + y² = 1 + d⋅x²⋅y²
+        let (x2, y2) = (B.x.squared(), B.y.squared());
+        assert_eq!(y2 - x2, Z25519::from(1) + D * x2 * y2);
+    }
+
+    #[test]
+    fn test_known_multiples() {
+        let mut l_minus_1 = [
+            0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9,
+            0xde, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x10,
+        ];
+        l_minus_1[0] -= 1;
+        let mut two_64 = [0; 32];
+        two_64[8] = 1;
+        let multiples = [
+            (
+                Scalar::from(2),
+                "c9a3f86aae465f0e56513864510f3997561fa2c9e85ea21dc2292309f3cd6022",
+            ),
+            (
+                Scalar::from(3),
+                "d4b4f5784868c3020403246717ec169ff79e26608ea126a1ab69ee77d1b16712",
+            ),
+            (
+                Scalar::from(4),
+                "2f1132ca61ab38dff00f2fea3228f24c6c71d58085b80e47e19515cb27e8d047",
+            ),
+            (
+                Scalar::from(8),
+                "b4b937fca95b2f1e93e41e62fc3c78818ff38a66096fad6e7973e5c90006d321",
+            ),
+            (
+                Scalar::from(0xDEADBEEF),
+                "aa8a6aca3fad09b66800f719b672863ecc5c477a6fe745b3978a783081f5b080",
+            ),
+            (
+                Scalar::from_bytes_mod_order(two_64),
+                "1353e48257fa1e8f062b90ba08b610544f7c1b26edda6bdd25d04eea42bb2503",
+            ),
+            // -B, which only differs from B in the sign bit
+            (
+                Scalar::from_bytes_mod_order(l_minus_1),
+                "58666666666666666666666666666666666666666666666666666666666666e6",
+            ),
+        ];
+        for (n, expected) in &multiples {
+            assert_eq!(hex::encode(encode(B * *n)), *expected);
+        }
+        let mut sum = EdwardsPoint::identity();
+        for n in 0..32 {
+            assert_eq!(B * Scalar::from(n), sum);
+            sum = sum + B;
+        }
+    }
+
+    prop_compose! {
+        fn arb_scalar()(bytes in any::<[u8; 32]>()) -> Scalar {
+            Scalar::from_bytes_mod_order(bytes)
+        }
+    }
+
+    prop_compose! {
+        // Points with a small order component too, so that the laws get checked on the whole group
+        fn arb_point()(s in arb_scalar(), j in 0..8u64) -> EdwardsPoint {
+            B * s + order_8() * Scalar::from(j)
+        }
+    }
+
+    // Each case needs a few scalar multiplications, which are slow without optimizations
+    fn config() -> ProptestConfig {
+        ProptestConfig::with_cases(64)
+    }
+
+    proptest! {
+        #![proptest_config(config())]
+        #[test]
+        fn test_addition_associative(p in arb_point(), q in arb_point(), r in arb_point()) {
+            assert_eq!((p + q) + r, p + (q + r));
+        }
+    }
+
+    proptest! {
+        #![proptest_config(config())]
+        #[test]
+        fn test_addition_commutative(p in arb_point(), q in arb_point()) {
+            assert_eq!(p + q, q + p);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(config())]
+        #[test]
+        fn test_add_identity(p in arb_point()) {
+            assert_eq!(p + EdwardsPoint::identity(), p);
+            assert_eq!(EdwardsPoint::identity() + p, p);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(config())]
+        #[test]
+        fn test_add_negation(p in arb_point(), q in arb_point()) {
+            assert!((p + -p).is_identity());
+            assert_eq!(-(-p), p);
+            assert_eq!(p - q, p + -q);
+            assert_eq!((p - q) + q, p);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(config())]
+        #[test]
+        fn test_doubling_is_addition(p in arb_point()) {
+            assert_eq!(p.doubled(), p + p);
+            assert_eq!(p * Scalar::from(2), p + p);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(config())]
+        #[test]
+        fn test_scalar_multiplication_distributive(p in arb_point(), q in arb_point(), a in arb_scalar(), b in arb_scalar()) {
+            assert_eq!((p + q) * a, p * a + q * a);
+            // Since a + b and a⋅b get reduced modulo L, these only hold without torsion
+            assert_eq!(B * (a + b), B * a + B * b);
+            assert_eq!((B * a) * b, B * (a * b));
+        }
+    }
+
+    proptest! {
+        #![proptest_config(config())]
+        #[test]
+        fn test_compress_roundtrip_arbitrary(p in arb_point()) {
+            assert_eq!(p.compress().decompress(), Some(p));
+            assert_eq!((-p).compress().decompress(), Some(-p));
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! This module implements Ed25519ph, the pre-hashed variant of Ed25519.
+//!
+//! Instead of signing a message directly, we sign its SHA-512 digest, letting
+//! large messages be hashed once, possibly elsewhere, and then signed or verified.
+//!
+//! This follows Section 5.1 of RFC 8032:
+//! https://datatracker.ietf.org/doc/html/rfc8032#section-5.1
+use core::convert::TryInto;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+#[cfg(feature = "digest")]
+use ::digest::{consts::U64, Digest};
+
+use super::{scalar::Scalar, sign_with_domain, PrivateKey, PublicKey, Signature};
+use crate::sha512;
+#[cfg(feature = "std")]
+use crate::sha512::Sha512Writer;
+
+/// The number of bytes in a digest we can sign.
+pub const DIGEST_SIZE: usize = sha512::HASH_SIZE;
+
+/// The prefix of dom2, as defined in Section 2.
+const DOM2_PREFIX: &[u8] = b"SigEd25519 no Ed25519 collisions";
+
+/// The largest number of bytes dom2 can take, with a context of 255 bytes.
+const DOM2_MAX_SIZE: usize = DOM2_PREFIX.len() + 2 + 255;
+
+/// Calculate dom2(phflag, context), the domain separator for Ed25519ph and Ed25519ctx.
+///
+/// The flag is 1 for Ed25519ph, and 0 for Ed25519ctx. This returns a buffer, along
+/// with how many of its bytes are used. Contexts are at most 255 bytes long, and
+/// this returns `None` otherwise.
+pub(super) fn dom2(phflag: u8, context: &[u8]) -> Option<([u8; DOM2_MAX_SIZE], usize)> {
+    if context.len() > 255 {
+        return None;
+    }
+    let mut out = [0; DOM2_MAX_SIZE];
+    let prefix_len = DOM2_PREFIX.len();
+    out[..prefix_len].copy_from_slice(DOM2_PREFIX);
+    out[prefix_len] = phflag;
+    out[prefix_len + 1] = context.len() as u8;
+    let len = prefix_len + 2 + context.len();
+    out[prefix_len + 2..len].copy_from_slice(context);
+    Some((out, len))
+}
+
+/// Calculate the SHA-512 digest of a message, for use with Ed25519ph.
+pub fn digest(message: &[u8]) -> [u8; DIGEST_SIZE] {
+    sha512::hash(message)
+}
+
+impl PrivateKey {
+    /// Sign the SHA-512 digest of a message, using Ed25519ph.
+    ///
+    /// The context, of at most 255 bytes, is also bound into the signature, and
+    /// this returns `None` if it's longer.
+    pub fn sign_prehashed(&self, digest: &[u8; DIGEST_SIZE], context: &[u8]) -> Option<Signature> {
+        let (domain, len) = dom2(1, context)?;
+        let hash = sha512::hash(&self.bytes);
+        let s = Scalar::clamped(hash[..32].try_into().unwrap());
+        Some(sign_with_domain(s, &domain[..len], &hash[32..], digest))
+    }
+}
+
+impl PublicKey {
+    /// Verify an Ed25519ph signature over the SHA-512 digest of a message.
+    pub fn verify_prehashed(
+        &self,
+        digest: &[u8; DIGEST_SIZE],
+        context: &[u8],
+        signature: Signature,
+    ) -> bool {
+        match dom2(1, context) {
+            Some((domain, len)) => self
+                .verify_with_domain(&domain[..len], digest, signature)
+                .is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(feature = "digest")]
+impl PrivateKey {
+    /// Sign the digest computed by a hash function, using Ed25519ph.
+    ///
+    /// RFC 8032 specifies SHA-512, e.g. `sha512::Hasher`, but any hash function
+    /// with 64 bytes of output can be used, as long as verifiers use the same one.
+    /// Like `sign_prehashed`, this returns `None` if the context is too long.
+    pub fn sign_digest<D>(&self, digest: D, context: &[u8]) -> Option<Signature>
+    where
+        D: Digest<OutputSize = U64>,
+    {
+        self.sign_prehashed(&digest.finalize().into(), context)
+    }
+}
+
+#[cfg(feature = "digest")]
+impl PublicKey {
+    /// Verify an Ed25519ph signature over the digest computed by a hash function.
+    pub fn verify_digest<D>(&self, digest: D, context: &[u8], signature: Signature) -> bool
+    where
+        D: Digest<OutputSize = U64>,
+    {
+        self.verify_prehashed(&digest.finalize().into(), context, signature)
+    }
+}
+
+/// An adapter producing an Ed25519ph signature over everything written through it.
+///
+/// Plain Ed25519 needs to read the message twice, so streams are signed with
+/// Ed25519ph instead, and can be checked with `PublicKey::verify_prehashed`,
+/// using the digest of the same data. Data is passed along to an inner writer.
+#[cfg(feature = "std")]
+pub struct SigningWriter<'a, W> {
+    key: &'a PrivateKey,
+    context: &'a [u8],
+    writer: Sha512Writer<W>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, W: Write> SigningWriter<'a, W> {
+    /// Create a new adapter, signing with a given key and context.
+    ///
+    /// Like `PrivateKey::sign_prehashed`, this returns `None` if the context
+    /// is longer than 255 bytes.
+    pub fn new(key: &'a PrivateKey, context: &'a [u8], inner: W) -> Option<Self> {
+        dom2(1, context)?;
+        Some(SigningWriter {
+            key,
+            context,
+            writer: Sha512Writer::new(inner),
+        })
+    }
+
+    /// Finish signing, returning the inner writer, and the signature of everything written.
+    pub fn finalize(self) -> (W, Signature) {
+        let (inner, digest) = self.writer.finalize();
+        // We've already checked the length of the context.
+        let signature = self.key.sign_prehashed(&digest, self.context).unwrap();
+        (inner, signature)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, W: Write> Write for SigningWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rfc_8032_ed25519ph_vector() {
+        // This is the test vector from Section 7.3
+        let mut private = PrivateKey { bytes: [0; 32] };
+        hex::decode_to_slice(
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+            &mut private.bytes,
+        )
+        .unwrap();
+        let public = private.public_key();
+        assert_eq!(
+            hex::encode(public.bytes),
+            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf"
+        );
+        let sig = private.sign_prehashed(&digest(b"abc"), &[]).unwrap();
+        assert_eq!(
+            hex::encode(sig.bytes),
+            "98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406"
+        );
+        assert!(public.verify_prehashed(&digest(b"abc"), &[], sig));
+    }
+
+    #[test]
+    fn test_prehashed_signatures_are_separated() {
+        let private = PrivateKey { bytes: [3; 32] };
+        let public = private.public_key();
+        let d = digest(b"artifact");
+        let sig = private.sign_prehashed(&d, b"ctx").unwrap();
+        assert!(public.verify_prehashed(&d, b"ctx", sig));
+        assert!(!public.verify_prehashed(&d, b"other", sig));
+        assert!(!public.verify(&d, sig));
+        assert!(private.sign_prehashed(&d, &[0; 256]).is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_signing_writer_matches_sign_prehashed() {
+        let private = PrivateKey { bytes: [5; 32] };
+        let message = [9u8; 1000];
+        let mut writer = SigningWriter::new(&private, b"ctx", Vec::new()).unwrap();
+        io::copy(&mut &message[..], &mut writer).unwrap();
+        let (written, sig) = writer.finalize();
+        assert_eq!(written, message);
+        let public = private.public_key();
+        assert!(public.verify_prehashed(&digest(&message), b"ctx", sig));
+        assert!(SigningWriter::new(&private, &[0; 256], io::sink()).is_none());
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn test_sign_digest_matches_sign_prehashed() {
+        let private = PrivateKey { bytes: [5; 32] };
+        let public = private.public_key();
+        let message = [9u8; 1000];
+        let hasher = sha512::Hasher::new().chain_update(&message[..500]);
+        let hasher = hasher.chain_update(&message[500..]);
+        let sig = private.sign_digest(hasher.clone(), b"ctx").unwrap();
+        assert_eq!(
+            sig,
+            private
+                .sign_prehashed(&sha512::hash(&message), b"ctx")
+                .unwrap()
+        );
+        assert!(public.verify_digest(hasher, b"ctx", sig));
+        assert!(public.verify_prehashed(&digest(&message), b"ctx", sig));
+        let other = sha512::Hasher::new().chain_update(b"other");
+        assert!(!public.verify_digest(other, b"ctx", sig));
+    }
+}
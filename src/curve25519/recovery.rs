@@ -0,0 +1,91 @@
+//! This module recovers private keys from signatures which reused a nonce.
+//!
+//! An Ed25519 signature is (R, s), with s = r + c⋅a, and c = H(R || A || M).
+//! Deterministic signing never reuses r across different messages, but a broken
+//! implementation, or a failed RNG in a randomized variant, might. Given two
+//! signatures (R, s1) and (R, s2) over different messages, we can solve for the
+//! secret scalar:
+//!
+//! a = (s1 - s2) / (c1 - c2)
+//!
+//! This exists so that security teams can demonstrate the impact of such a failure,
+//! and is only available with the `dangerous-key-recovery` feature.
+use core::convert::TryFrom;
+
+use super::{challenge, point, scalar::Scalar, PublicKey, Signature};
+
+/// Recover the secret scalar of a key, from two signatures sharing the same nonce.
+///
+/// Both signatures must be valid, over different messages, and share the same R.
+/// This returns `None` otherwise, or if the recovered scalar doesn't match the public key.
+///
+/// The scalar is enough to produce signatures, but isn't the private key itself,
+/// since that is a seed hashed to produce the scalar.
+pub fn recover_private_scalar(
+    sig1: &Signature,
+    msg1: &[u8],
+    sig2: &Signature,
+    msg2: &[u8],
+    public: &PublicKey,
+) -> Option<Scalar> {
+    let big_r = &sig1.bytes[..32];
+    if big_r != &sig2.bytes[..32] || msg1 == msg2 {
+        return None;
+    }
+    if !public.verify(msg1, *sig1) || !public.verify(msg2, *sig2) {
+        return None;
+    }
+    let s1 = Scalar::try_from(&sig1.bytes[32..]).ok()?;
+    let s2 = Scalar::try_from(&sig2.bytes[32..]).ok()?;
+    let c1 = challenge(big_r, &public.bytes, msg1);
+    let c2 = challenge(big_r, &public.bytes, msg2);
+    let a = (s1 - s2) * (c1 - c2).invert();
+    if (point::B * a).compress().bytes != public.bytes {
+        return None;
+    }
+    Some(a)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::curve25519::sign_with_scalar;
+
+    #[test]
+    fn test_recovers_scalar_after_reuse() {
+        let a = Scalar::from(0xdead_beef);
+        let public = PublicKey {
+            bytes: (point::B * a).compress().bytes,
+        };
+        // Normal signing derives the nonce from the message, so we reuse one by hand.
+        let r = Scalar::from(42);
+        let big_r = (point::B * r).compress().bytes;
+        let sign = |msg: &[u8]| {
+            let s = r + challenge(&big_r, &public.bytes, msg) * a;
+            let mut sig = Signature { bytes: [0; 64] };
+            sig.bytes[..32].copy_from_slice(&big_r);
+            sig.bytes[32..].copy_from_slice(&s.to_bytes());
+            sig
+        };
+        let (sig1, sig2) = (sign(b"first"), sign(b"second"));
+        let recovered = recover_private_scalar(&sig1, b"first", &sig2, b"second", &public);
+        assert_eq!(recovered, Some(a));
+        let forged = sign_with_scalar(recovered.unwrap(), &[0; 32], b"third");
+        assert!(public.verify(b"third", forged));
+        assert_eq!(
+            recover_private_scalar(&sig1, b"first", &sig1, b"first", &public),
+            None
+        );
+    }
+
+    #[test]
+    fn test_distinct_nonces_recover_nothing() {
+        let private = crate::PrivateKey { bytes: [5; 32] };
+        let public = private.public_key();
+        let (sig1, sig2) = (private.sign(b"first"), private.sign(b"second"));
+        assert_eq!(
+            recover_private_scalar(&sig1, b"first", &sig2, b"second", &public),
+            None
+        );
+    }
+}
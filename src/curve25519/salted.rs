@@ -0,0 +1,135 @@
+//! This module implements randomized signatures, using a fresh salt per message.
+//!
+//! Ed25519 signatures are deterministic, so signing the same message twice produces
+//! the same bytes, letting observers link the two. A salted signature is an Ed25519
+//! signature over tag || salt || M, with a fresh random salt. The salt feeds into the
+//! nonce, making each signature unique, and is carried alongside the signature for
+//! verification. The fixed tag keeps salted signatures apart from plain ones: otherwise,
+//! a plain signature over a message starting with 32 bytes would also be a salted
+//! signature over the rest of it.
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use rand::{CryptoRng, RngCore};
+
+use super::{PrivateKey, PublicKey, Signature, SIGNATURE_SIZE};
+
+/// This is prepended to the salt and message, before signing them.
+const SALTED_DOMAIN: &[u8] = b"eddo salted v1";
+
+/// The number of bytes in the salt of a salted signature.
+pub const SALT_SIZE: usize = 32;
+
+/// The number of bytes in a salted signature, including the salt.
+pub const SALTED_SIGNATURE_SIZE: usize = SALT_SIZE + SIGNATURE_SIZE;
+
+/// Represents a signature along with the salt used to make it.
+///
+/// The bytes are laid out as salt || R || S.
+#[derive(Debug, Clone, Copy)]
+pub struct SaltedSignature {
+    pub bytes: [u8; SALTED_SIGNATURE_SIZE],
+}
+
+impl SaltedSignature {
+    /// The salt used to randomize this signature.
+    pub fn salt(&self) -> [u8; SALT_SIZE] {
+        self.bytes[..SALT_SIZE].try_into().unwrap()
+    }
+
+    /// The inner signature, over tag || salt || M.
+    pub fn signature(&self) -> Signature {
+        Signature {
+            bytes: self.bytes[SALT_SIZE..].try_into().unwrap(),
+        }
+    }
+}
+
+fn salted_message(salt: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(SALTED_DOMAIN.len() + salt.len() + message.len());
+    out.extend_from_slice(SALTED_DOMAIN);
+    out.extend_from_slice(salt);
+    out.extend_from_slice(message);
+    out
+}
+
+impl PrivateKey {
+    /// Sign a message, using a fresh salt, so that the signature is unique.
+    pub fn sign_salted<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        message: &[u8],
+    ) -> SaltedSignature {
+        let mut out = SaltedSignature {
+            bytes: [0; SALTED_SIGNATURE_SIZE],
+        };
+        rng.fill_bytes(&mut out.bytes[..SALT_SIZE]);
+        let sig = self.sign(&salted_message(&out.bytes[..SALT_SIZE], message));
+        out.bytes[SALT_SIZE..].copy_from_slice(&sig.bytes);
+        out
+    }
+}
+
+impl PublicKey {
+    /// Verify a salted signature for a message.
+    pub fn verify_salted(&self, message: &[u8], signature: &SaltedSignature) -> bool {
+        self.verify(
+            &salted_message(&signature.bytes[..SALT_SIZE], message),
+            signature.signature(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_salted_signature_verifies() {
+        let private = PrivateKey { bytes: [7; 32] };
+        let public = private.public_key();
+        let sig = private.sign_salted(&mut OsRng, b"hello");
+        assert!(public.verify_salted(b"hello", &sig));
+        assert!(!public.verify_salted(b"goodbye", &sig));
+    }
+
+    #[test]
+    fn test_salted_signatures_are_unlinkable() {
+        let private = PrivateKey { bytes: [7; 32] };
+        let sig0 = private.sign_salted(&mut OsRng, b"hello");
+        let sig1 = private.sign_salted(&mut OsRng, b"hello");
+        assert_ne!(sig0.bytes[..], sig1.bytes[..]);
+        assert_ne!(sig0.bytes[SALT_SIZE..], sig1.bytes[SALT_SIZE..]);
+    }
+
+    #[test]
+    fn test_changing_salt_fails() {
+        let private = PrivateKey { bytes: [7; 32] };
+        let public = private.public_key();
+        let mut sig = private.sign_salted(&mut OsRng, b"hello");
+        sig.bytes[0] ^= 1;
+        assert!(!public.verify_salted(b"hello", &sig));
+        assert!(!public.verify(b"hello", sig.signature()));
+    }
+
+    #[test]
+    fn test_plain_signature_is_not_salted() {
+        let private = PrivateKey { bytes: [7; 32] };
+        let public = private.public_key();
+        let mut message = [1u8; SALT_SIZE + 5];
+        message[SALT_SIZE..].copy_from_slice(b"hello");
+        let plain = private.sign(&message);
+        let mut forged = SaltedSignature {
+            bytes: [0; SALTED_SIGNATURE_SIZE],
+        };
+        forged.bytes[..SALT_SIZE].copy_from_slice(&message[..SALT_SIZE]);
+        forged.bytes[SALT_SIZE..].copy_from_slice(&plain.bytes);
+        assert!(!public.verify_salted(b"hello", &forged));
+        // And salted signatures aren't plain signatures over salt || M either
+        let sig = private.sign_salted(&mut OsRng, b"hello");
+        let mut salted_message = sig.salt().to_vec();
+        salted_message.extend_from_slice(b"hello");
+        assert!(!public.verify(&salted_message, sig.signature()));
+    }
+}
@@ -1,52 +1,47 @@
-use std::{
+use core::{
     convert::{TryFrom, TryInto},
-    ops::{Add, AddAssign, Mul, MulAssign, Neg},
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
-use subtle::{ConditionallySelectable, ConstantTimeEq};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
 
 use super::{
     arithmetic::{U256, U512},
     error::SignatureError,
 };
 
-const L: U256 = U256 {
-    limbs: [
-        0x5812631a5cf5d3ed,
-        0x14def9dea2f79cd6,
-        0x0000000000000000,
-        0x1000000000000000,
-    ],
-};
+/// The order of the prime order subgroup, 2^252 + 27742317777372353535851937790883648493.
+const L: U256 =
+    U256::from_le_hex("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
 
-const N_SQUARED: U256 = U256 {
-    limbs: [
-        0xe2edf685ab128969,
-        0x680392762298a31d,
-        0x3dceec73d217f5be,
-        0x01b399411b7c309a,
-    ],
-};
+/// 2^504 mod L, used to fold the top byte of wide inputs.
+const N_SQUARED: U256 =
+    U256::from_le_hex("698912ab85f6ede21da3982276920368bef517d273ecce3d9a307c1b4199b301");
 
-const R: U256 = U256 {
-    limbs: [
-        0x9fb673968c28b04c,
-        0xac84188574218ca6,
-        0xffffffffffffffff,
-        0x3fffffffffffffff,
-    ],
-};
+/// ⌊2^506 / L⌋, used to estimate quotients in Barrett reduction.
+const R: U256 =
+    U256::from_le_hex("4cb0288c9673b69fa68c2174851884acffffffffffffffffffffffffffffff3f");
+
+/// R² mod L, with R := 2^256, used to move scalars into Montgomery form.
+const R_SQUARED: U256 =
+    U256::from_le_hex("010f9c44e31106a447938568a71b0ed065bef517d273ecce3d9a307c1b419903");
+
+/// -L⁻¹ mod 2^64, used in Montgomery reduction.
+const L_NEG_INV: u64 = 0xd2b51da312547e1b;
 
 /// Represents a scalar in Z/(L) the order of our curve group.
 ///
 /// The operations in this ring are defined through arithmetic modulo
 /// L := 2^252 + 27742317777372353535851937790883648493
+///
+/// Scalars are always kept reduced modulo L, and can be compared in constant time
+/// through `ConstantTimeEq`.
 #[derive(Clone, Copy, Debug)]
 // Only implement equality for tests. This is to avoid the temptation to introduce
 // a timing leak through equality comparison in other situations.
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Scalar {
-    pub value: U256,
+    pub(crate) value: U256,
 }
 
 impl Scalar {
@@ -55,15 +50,72 @@ impl Scalar {
     /// This will apply a standard clamping procedure to the bytes, as described
     /// in Section 5.1.5:
     /// https://datatracker.ietf.org/doc/html/rfc8032#section-5.1.5
-    pub fn clamped(mut bytes: [u8; 32]) -> Scalar {
+    pub(crate) fn clamped(mut bytes: [u8; 32]) -> Scalar {
         bytes[0] &= 248;
         bytes[31] &= 127;
         bytes[31] |= 64;
-        let mut value = U256::from(0);
-        for (i, chunk) in bytes.chunks_exact(8).enumerate() {
-            value.limbs[i] = u64::from_le_bytes(chunk.try_into().unwrap());
+        Scalar {
+            value: U256::from_le_bytes(&bytes),
+        }
+    }
+
+    /// Reduce a scalar which might be larger than L.
+    ///
+    /// Clamped scalars aren't necessarily reduced, which is fine for multiplying
+    /// points, but not for multiplying other scalars.
+    pub(crate) fn reduce(self) -> Scalar {
+        Scalar::from_bytes_mod_order(self.to_bytes())
+    }
+
+    /// Create a scalar from 32 little endian bytes, reducing them modulo L.
+    pub fn from_bytes_mod_order(bytes: [u8; 32]) -> Scalar {
+        let mut wide = [0u8; 64];
+        wide[..32].copy_from_slice(&bytes);
+        Scalar::from(wide)
+    }
+
+    /// Create a scalar from 64 little endian bytes, reducing them modulo L.
+    ///
+    /// This is the right way to turn the output of a hash into a scalar, since
+    /// the result is close to uniform.
+    pub fn from_bytes_mod_order_wide(bytes: [u8; 64]) -> Scalar {
+        Scalar::from(bytes)
+    }
+
+    /// Create a scalar from 32 little endian bytes, if they're already reduced modulo L.
+    ///
+    /// This is the check Ed25519 verification makes on the s half of a signature.
+    pub fn from_canonical_bytes(bytes: [u8; 32]) -> Option<Scalar> {
+        Scalar::try_from(&bytes[..]).ok()
+    }
+
+    /// Encode this scalar as 32 little endian bytes.
+    pub fn to_bytes(self) -> [u8; 32] {
+        self.into()
+    }
+
+    /// Calculate the multiplicative inverse of this scalar.
+    ///
+    /// The inverse of 0 is taken to be 0.
+    pub fn invert(self) -> Scalar {
+        // Since L is prime, a^(L - 2) is the inverse of a, by Fermat's little theorem.
+        // We stay in Montgomery form throughout, to avoid converting after each step.
+        let exponent = L - U256::from(2);
+        let one = MontgomeryScalar::from(Scalar::from(1));
+        let this = MontgomeryScalar::from(self);
+        let mut out = one;
+        for limb in exponent.limbs.iter().rev() {
+            for i in (0..64).rev() {
+                out *= out;
+                let selected = MontgomeryScalar::conditional_select(
+                    &one,
+                    &this,
+                    Choice::from(((limb >> i) & 1) as u8),
+                );
+                out *= selected;
+            }
         }
-        Scalar { value }
+        out.into()
     }
 
     fn reduce_after_addition(&mut self) {
@@ -91,6 +143,99 @@ impl Scalar {
     }
 }
 
+/// Represents a scalar a in Montgomery form, i.e. as a⋅R mod L, with R := 2^256.
+///
+/// Multiplying two scalars in this form only needs a single Montgomery reduction,
+/// which is cheaper than the Barrett reduction used for regular scalars. This makes
+/// it worth converting when doing many multiplications in a row, as when inverting.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MontgomeryScalar {
+    value: U256,
+}
+
+impl MontgomeryScalar {
+    /// Calculate a⋅b⋅R⁻¹ mod L, for any a and b with a⋅b < R⋅L.
+    ///
+    /// This holds whenever one of a and b is below L, so the other one doesn't need
+    /// to be reduced, and can be a clamped scalar, for example.
+    ///
+    /// This interleaves multiplication and reduction, one limb of b at a time.
+    fn montgomery_mul(a: &U256, b: &U256) -> U256 {
+        let mut t = [0u64; 6];
+        for &b_i in b.limbs.iter() {
+            // t += a * b_i
+            let mut carry = 0u128;
+            for (t_j, &a_j) in t.iter_mut().zip(a.limbs.iter()) {
+                let full_res = u128::from(*t_j) + u128::from(a_j) * u128::from(b_i) + carry;
+                *t_j = full_res as u64;
+                carry = full_res >> 64;
+            }
+            let full_res = u128::from(t[4]) + carry;
+            t[4] = full_res as u64;
+            t[5] = (full_res >> 64) as u64;
+            // Adding m * L makes t divisible by 2^64, so we can shift it down a limb.
+            let m = t[0].wrapping_mul(L_NEG_INV);
+            let mut carry = (u128::from(t[0]) + u128::from(m) * u128::from(L.limbs[0])) >> 64;
+            for j in 1..4 {
+                let full_res = u128::from(t[j]) + u128::from(m) * u128::from(L.limbs[j]) + carry;
+                t[j - 1] = full_res as u64;
+                carry = full_res >> 64;
+            }
+            let full_res = u128::from(t[4]) + carry;
+            t[3] = full_res as u64;
+            t[4] = t[5] + (full_res >> 64) as u64;
+        }
+        // t = (a⋅b + m⋅L) / R, with m < R, so a⋅b < R⋅L means that t < 2L, which fits
+        // over 4 limbs, and needs at most one subtraction.
+        let mut out = Scalar {
+            value: U256 {
+                limbs: [t[0], t[1], t[2], t[3]],
+            },
+        };
+        out.reduce_after_addition();
+        out.value
+    }
+}
+
+impl From<Scalar> for MontgomeryScalar {
+    fn from(scalar: Scalar) -> Self {
+        MontgomeryScalar {
+            value: MontgomeryScalar::montgomery_mul(&scalar.value, &R_SQUARED),
+        }
+    }
+}
+
+impl From<MontgomeryScalar> for Scalar {
+    fn from(scalar: MontgomeryScalar) -> Self {
+        Scalar {
+            value: MontgomeryScalar::montgomery_mul(&scalar.value, &U256::from(1)),
+        }
+    }
+}
+
+impl ConditionallySelectable for MontgomeryScalar {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        MontgomeryScalar {
+            value: U256::conditional_select(&a.value, &b.value, choice),
+        }
+    }
+}
+
+impl MulAssign for MontgomeryScalar {
+    fn mul_assign(&mut self, other: Self) {
+        self.value = MontgomeryScalar::montgomery_mul(&self.value, &other.value);
+    }
+}
+
+impl Mul for MontgomeryScalar {
+    type Output = Self;
+
+    fn mul(mut self, other: Self) -> Self::Output {
+        self *= other;
+        self
+    }
+}
+
 impl From<u64> for Scalar {
     fn from(x: u64) -> Self {
         Scalar {
@@ -103,10 +248,7 @@ impl From<[u8; 64]> for Scalar {
     fn from(mut bytes: [u8; 64]) -> Self {
         let hi = u64::from(bytes[63]);
         bytes[63] = 0;
-        let mut lo = U512 { limbs: [0; 8] };
-        for (i, chunk) in bytes.chunks_exact(8).enumerate() {
-            lo.limbs[i] = u64::from_le_bytes(chunk.try_into().unwrap());
-        }
+        let lo = U512::from_le_bytes(&bytes);
         let (hi_reduced_hi, hi_reduced_lo) = N_SQUARED * hi;
         let hi_reduced = U512 {
             limbs: [
@@ -124,9 +266,29 @@ impl From<[u8; 64]> for Scalar {
     }
 }
 
-impl Into<[u8; 32]> for Scalar {
-    fn into(self) -> [u8; 32] {
-        self.value.into()
+impl From<Scalar> for [u8; 32] {
+    fn from(scalar: Scalar) -> Self {
+        scalar.value.into()
+    }
+}
+
+#[cfg(all(feature = "hazmat", feature = "alloc"))]
+impl Scalar {
+    /// Encode this scalar as hex, using the same little endian bytes as its encoding.
+    pub fn to_hex(&self) -> alloc::string::String {
+        let bytes: [u8; 32] = (*self).into();
+        hex::encode(bytes)
+    }
+}
+
+#[cfg(feature = "hazmat")]
+impl core::fmt::LowerHex for Scalar {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        let bytes: [u8; 32] = (*self).into();
+        super::write_hex(f, &bytes)
     }
 }
 
@@ -154,6 +316,12 @@ impl ConditionallySelectable for Scalar {
     }
 }
 
+impl ConstantTimeEq for Scalar {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.value.limbs.ct_eq(&other.value.limbs)
+    }
+}
+
 impl Neg for Scalar {
     type Output = Scalar;
 
@@ -183,10 +351,27 @@ impl Add for Scalar {
     }
 }
 
+impl SubAssign for Scalar {
+    fn sub_assign(&mut self, other: Self) {
+        *self += -other;
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Self;
+
+    fn sub(mut self, other: Self) -> Self::Output {
+        self -= other;
+        self
+    }
+}
+
 impl MulAssign for Scalar {
     fn mul_assign(&mut self, other: Self) {
-        let large = self.value * other.value;
-        *self = Scalar::reduce_barret(large);
+        // (a⋅b⋅R⁻¹)⋅R²⋅R⁻¹ = a⋅b, and two Montgomery multiplications beat a Barrett reduction.
+        // This needs one of the scalars to be reduced, which rules out two clamped ones.
+        let ab = MontgomeryScalar::montgomery_mul(&self.value, &other.value);
+        self.value = MontgomeryScalar::montgomery_mul(&ab, &R_SQUARED);
     }
 }
 
@@ -201,12 +386,13 @@ impl Mul for Scalar {
 
 #[cfg(test)]
 mod test {
-    use crate::curve25519::scalar::L;
+    use crate::curve25519::scalar::{L, L_NEG_INV, N_SQUARED, R, R_SQUARED};
 
-    use super::super::arithmetic::U256;
+    use super::super::arithmetic::{U256, U512};
 
-    use super::Scalar;
+    use super::{MontgomeryScalar, Scalar};
     use proptest::prelude::*;
+    use subtle::ConstantTimeEq;
 
     prop_compose! {
         fn arb_scalar()(
@@ -335,4 +521,121 @@ mod test {
         };
         assert_eq!(Scalar::from(bytes), expected);
     }
+
+    proptest! {
+        #[test]
+        fn test_subtraction_inverts_addition(a in arb_scalar(), b in arb_scalar()) {
+            assert_eq!((a + b) - b, a);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_invert(a in arb_scalar()) {
+            prop_assume!(a != Scalar::from(0));
+            assert_eq!(a * a.invert(), Scalar::from(1));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_montgomery_roundtrip(a in arb_scalar()) {
+            assert_eq!(Scalar::from(MontgomeryScalar::from(a)), a);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_montgomery_multiplication_matches_barrett(a in arb_scalar(), b in arb_scalar()) {
+            let barrett = Scalar::reduce_barret(a.value * b.value);
+            let montgomery = MontgomeryScalar::from(a) * MontgomeryScalar::from(b);
+            assert_eq!(Scalar::from(montgomery), barrett);
+            assert_eq!(a * b, barrett);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_multiplication_with_unreduced_scalar(limbs in any::<[u64; 4]>(), b in arb_scalar()) {
+            let a = Scalar { value: U256 { limbs } };
+            let b = b.reduce();
+            let expected = Scalar::from((a.value * b.value).to_le_bytes::<64>());
+            assert_eq!(a * b, expected);
+            assert_eq!(b * a, expected);
+        }
+    }
+
+    #[test]
+    fn test_montgomery_mul_bound() {
+        // The largest inputs with a⋅b < R⋅L
+        let a = U256 {
+            limbs: [u64::MAX; 4],
+        };
+        let b = L - U256::from(1);
+        let ab = Scalar {
+            value: MontgomeryScalar::montgomery_mul(&a, &b),
+        };
+        let expected = Scalar::from((a * b).to_le_bytes::<64>());
+        let r_inverse = Scalar {
+            value: MontgomeryScalar::montgomery_mul(&U256::from(1), &U256::from(1)),
+        };
+        assert_eq!(ab, expected * r_inverse);
+        let clamped = Scalar::clamped([0xFF; 32]);
+        assert_eq!(
+            clamped * Scalar { value: b },
+            clamped.reduce() * Scalar { value: b }
+        );
+    }
+
+    #[test]
+    fn test_byte_constructors() {
+        let l_bytes: [u8; 32] = L.into();
+        assert_eq!(Scalar::from_canonical_bytes(l_bytes), None);
+        assert_eq!(Scalar::from_bytes_mod_order(l_bytes), Scalar::from(0));
+        let mut wide = [0u8; 64];
+        wide[..32].copy_from_slice(&l_bytes);
+        wide[0] += 5;
+        assert_eq!(Scalar::from_bytes_mod_order_wide(wide), Scalar::from(5));
+        let x = Scalar::from(0x0102);
+        assert_eq!(Scalar::from_canonical_bytes(x.to_bytes()), Some(x));
+        assert!(bool::from(x.ct_eq(&Scalar::from(0x0102))));
+        assert!(!bool::from(x.ct_eq(&Scalar::from(0x0103))));
+    }
+
+    #[test]
+    #[cfg(feature = "hazmat")]
+    fn test_hex_formatting() {
+        let x = Scalar::from(0x0102);
+        let expected = format!("0201{}", "00".repeat(30));
+        assert_eq!(x.to_hex(), expected);
+        assert_eq!(format!("{:x}", x), expected);
+    }
+
+    #[test]
+    fn test_constants() {
+        let two_252 = U256 {
+            limbs: [0, 0, 0, 1 << 60],
+        };
+        let delta: u128 = 27742317777372353535851937790883648493;
+        let delta = U256 {
+            limbs: [delta as u64, (delta >> 64) as u64, 0, 0],
+        };
+        assert_eq!(two_252 + delta, L);
+        // R⋅L ≤ 2^506 < (R + 1)⋅L
+        let two_506 = U512 {
+            limbs: [0, 0, 0, 0, 0, 0, 0, 1 << 58],
+        };
+        assert!(two_506.geq(R * L));
+        assert!(!two_506.geq((R + U256::from(1)) * L));
+        let mut power = Scalar::from(1);
+        for _ in 0..504 {
+            power = power + power;
+        }
+        assert_eq!(power.value, N_SQUARED);
+        for _ in 0..8 {
+            power = power + power;
+        }
+        assert_eq!(power.value, R_SQUARED);
+        assert_eq!(L.limbs[0].wrapping_mul(L_NEG_INV), u64::MAX);
+    }
 }
@@ -0,0 +1,228 @@
+//! This module provides a way to hold a private key in memory for a long time.
+//!
+//! Long running daemons keep their signing keys in memory, where they might end up
+//! in swap, or in a core dump. A `LockedPrivateKey` keeps the key encrypted, under a
+//! key encryption key (KEK) generated when it's created, and only decrypts it while
+//! it's being used. The KEK and the encrypted key live in separate pages, which we
+//! ask the OS to keep out of swap, and, on Linux, out of core dumps.
+//!
+//! This is defense in depth, and best effort. The KEK and the encrypted key are both
+//! in the memory of the process, so anything able to read all of that memory, like a
+//! debugger, or a full core dump, gets the key anyway. Keeping them in separate pages
+//! only helps against leaks of part of that memory. The key also briefly exists in
+//! the clear, and locking can fail, e.g. because of `RLIMIT_MEMLOCK`. Locking, and
+//! volatile writes, need unsafe code, so they're skipped entirely with the `pure` feature.
+use std::{
+    fmt,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+use rand::{CryptoRng, RngCore};
+
+use super::{PrivateKey, PublicKey, Signature, PRIVATE_KEY_SIZE};
+use crate::kdf::hmac_sha512;
+
+/// This is used to derive the pad encrypting the key, from the KEK.
+const PAD_DERIVATION_STRING: &[u8] = b"eddo locked private key pad v1";
+
+const PAGE_SIZE: usize = 4096;
+
+/// A page holding the encrypted key, and the decrypted copy, while it's in use.
+///
+/// The alignment lets us lock, and advise the OS about, exactly this page.
+#[repr(C, align(4096))]
+struct Page {
+    sealed: [u8; PRIVATE_KEY_SIZE],
+    scratch: PrivateKey,
+    _padding: [u8; PAGE_SIZE - 2 * PRIVATE_KEY_SIZE],
+}
+
+/// A page holding only the KEK, so that it doesn't sit next to the key it encrypts.
+#[repr(C, align(4096))]
+struct KekPage {
+    kek: [u8; 32],
+    _padding: [u8; PAGE_SIZE - 32],
+}
+
+/// Overwrite some secret bytes with zeros, in a way the compiler can't skip.
+#[cfg(not(feature = "pure"))]
+fn wipe(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        // The pointer comes from a mutable reference, so it's valid, and aligned
+        unsafe { std::ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrite some secret bytes with zeros.
+///
+/// Without volatile writes, nothing stops the compiler from removing this if the
+/// bytes are never read again, so this is only a best effort.
+#[cfg(feature = "pure")]
+fn wipe(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        *b = 0;
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(all(unix, not(feature = "pure")))]
+fn lock<T>(page: &T) -> bool {
+    let ptr = page as *const T as *mut libc::c_void;
+    // Locking and advising on memory we own doesn't affect its validity
+    let locked = unsafe { libc::mlock(ptr, std::mem::size_of::<T>()) } == 0;
+    #[cfg(target_os = "linux")]
+    unsafe {
+        libc::madvise(ptr, std::mem::size_of::<T>(), libc::MADV_DONTDUMP);
+    }
+    locked
+}
+
+#[cfg(all(unix, not(feature = "pure")))]
+fn unlock<T>(page: &T) {
+    let ptr = page as *const T as *mut libc::c_void;
+    unsafe {
+        libc::munlock(ptr, std::mem::size_of::<T>());
+    }
+}
+
+#[cfg(all(windows, not(feature = "pure")))]
+fn lock<T>(page: &T) -> bool {
+    let ptr = page as *const T as *mut winapi::ctypes::c_void;
+    // Locking memory we own doesn't affect its validity
+    unsafe { winapi::um::memoryapi::VirtualLock(ptr, std::mem::size_of::<T>()) != 0 }
+}
+
+#[cfg(all(windows, not(feature = "pure")))]
+fn unlock<T>(page: &T) {
+    let ptr = page as *const T as *mut winapi::ctypes::c_void;
+    unsafe {
+        winapi::um::memoryapi::VirtualUnlock(ptr, std::mem::size_of::<T>());
+    }
+}
+
+#[cfg(any(feature = "pure", not(any(unix, windows))))]
+fn lock<T>(_page: &T) -> bool {
+    false
+}
+
+#[cfg(any(feature = "pure", not(any(unix, windows))))]
+fn unlock<T>(_page: &T) {}
+
+/// A private key, kept encrypted in locked memory while not in use.
+pub struct LockedPrivateKey {
+    page: Box<Page>,
+    kek: Box<KekPage>,
+    public: PublicKey,
+    locked: bool,
+}
+
+// We never print any of the secret material.
+impl fmt::Debug for LockedPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LockedPrivateKey(<redacted>)")
+    }
+}
+
+impl LockedPrivateKey {
+    /// Move a private key into locked memory, encrypting it under a fresh KEK.
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R, mut key: PrivateKey) -> Self {
+        let mut page = Box::new(Page {
+            sealed: [0; PRIVATE_KEY_SIZE],
+            scratch: PrivateKey {
+                bytes: [0; PRIVATE_KEY_SIZE],
+            },
+            _padding: [0; PAGE_SIZE - 2 * PRIVATE_KEY_SIZE],
+        });
+        let mut kek = Box::new(KekPage {
+            kek: [0; 32],
+            _padding: [0; PAGE_SIZE - 32],
+        });
+        // Both pages need to be locked for the key to count as locked
+        let locked = lock(&*page) & lock(&*kek);
+        rng.fill_bytes(&mut kek.kek);
+        page.sealed = key.bytes;
+        Self::apply_pad(&mut page.sealed, &kek.kek);
+        let public = key.public_key();
+        wipe(&mut key.bytes);
+        LockedPrivateKey {
+            page,
+            kek,
+            public,
+            locked,
+        }
+    }
+
+    /// XOR some bytes with the pad derived from the KEK, encrypting or decrypting them.
+    fn apply_pad(bytes: &mut [u8; PRIVATE_KEY_SIZE], kek: &[u8; 32]) {
+        let mut pad = hmac_sha512(kek, PAD_DERIVATION_STRING);
+        for (b, p) in bytes.iter_mut().zip(pad.iter()) {
+            *b ^= p;
+        }
+        wipe(&mut pad);
+    }
+
+    /// Whether or not the OS agreed to keep this key's memory out of swap.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// The public key associated with this private key.
+    pub fn public_key(&self) -> PublicKey {
+        self.public
+    }
+
+    /// Decrypt the key, and use it, wiping the decrypted copy afterwards.
+    pub fn with_key<T>(&mut self, f: impl FnOnce(&PrivateKey) -> T) -> T {
+        let page = &mut *self.page;
+        page.scratch.bytes = page.sealed;
+        Self::apply_pad(&mut page.scratch.bytes, &self.kek.kek);
+        let out = f(&page.scratch);
+        wipe(&mut page.scratch.bytes);
+        out
+    }
+
+    /// Sign a message, decrypting the key only for the duration of the signature.
+    pub fn sign(&mut self, message: &[u8]) -> Signature {
+        self.with_key(|key| key.sign(message))
+    }
+}
+
+impl Drop for LockedPrivateKey {
+    fn drop(&mut self) {
+        wipe(&mut self.kek.kek);
+        wipe(&mut self.page.sealed);
+        wipe(&mut self.page.scratch.bytes);
+        // Unlocking a page which isn't locked is harmless
+        unlock(&*self.page);
+        unlock(&*self.kek);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_locked_key_signs() {
+        let private = PrivateKey { bytes: [3; 32] };
+        let expected = private.sign(b"hello");
+        let mut locked = LockedPrivateKey::new(&mut OsRng, private.clone());
+        assert_eq!(locked.public_key(), private.public_key());
+        assert_eq!(locked.sign(b"hello"), expected);
+    }
+
+    #[test]
+    fn test_key_is_encrypted_at_rest() {
+        let private = PrivateKey { bytes: [3; 32] };
+        let mut locked = LockedPrivateKey::new(&mut OsRng, private);
+        assert_ne!(locked.page.sealed, [3; 32]);
+        locked.with_key(|key| assert_eq!(key.bytes, [3; 32]));
+        assert_eq!(locked.page.scratch.bytes, [0; 32]);
+        // The KEK lives in a different page from the key
+        let kek = locked.kek.kek.as_ptr() as usize;
+        let sealed = locked.page.sealed.as_ptr() as usize;
+        assert_ne!(kek / PAGE_SIZE, sealed / PAGE_SIZE);
+    }
+}
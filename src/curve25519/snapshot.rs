@@ -0,0 +1,173 @@
+//! Snapshot tests pinning down the exact bytes of every encoding we produce.
+//!
+//! Any change to these values changes what we put on the wire, so they should only
+//! ever be updated deliberately, never to make a refactor pass.
+use rand::{CryptoRng, RngCore};
+
+#[cfg(feature = "hazmat")]
+use super::commitment;
+use super::{ecies, point, scalar::Scalar, AdaptorSecret, PassphraseParams, PrivateKey, PublicKey};
+
+/// An RNG producing a fixed stream of bytes, making randomized encodings reproducible.
+struct FixedRng(u8);
+
+impl RngCore for FixedRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for x in dest {
+            *x = self.0;
+            self.0 = self.0.wrapping_add(1);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for FixedRng {}
+
+fn private() -> PrivateKey {
+    PrivateKey { bytes: [0x42; 32] }
+}
+
+fn other_public() -> PublicKey {
+    PrivateKey { bytes: [0x24; 32] }.public_key()
+}
+
+#[test]
+fn snapshot_public_key() {
+    assert_eq!(
+        hex::encode(private().public_key().bytes),
+        "2152f8d19b791d24453242e15f2eab6cb7cffa7b6a5ed30097960e069881db12"
+    );
+}
+
+#[test]
+fn snapshot_signature() {
+    assert_eq!(
+        hex::encode(private().sign(b"snapshot").bytes),
+        "f58e0e4e710cd81875fb872ab899795557cf7b4ea48fc8e35370a8e4db0131255c11237e5d33cfcbb79bc516c0d7c45a69b898d70b2b0ca422fe796d4924d202"
+    );
+}
+
+#[test]
+fn snapshot_salted_signature() {
+    let sig = private().sign_salted(&mut FixedRng(0), b"snapshot");
+    assert_eq!(
+        hex::encode(sig.bytes),
+        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1ff987877080d1f41d43799564d897e62b93a02772ebe33c7f186be1412ae2f37f228a9aa3422e5bdca9bca4c60d2ed44e6dc3084a2d4b88c7afc0ef1f2567570e"
+    );
+}
+
+#[test]
+fn snapshot_designated_signature() {
+    let sig = private()
+        .sign_designated(&other_public(), b"snapshot")
+        .unwrap();
+    assert_eq!(
+        hex::encode(sig.bytes),
+        "82c600207ca99f46dc8fed37d0c1018f59349cf00ca96c3e33714c063354dd03e46538d232fe1fc3cbbc004a1e81c824115af22505dfe2a69977117163355e013648fad88e02736b35c109a172d1693fbb30e1fef906b8a2ec1c12b2082615040ec2fde3fc9905749f689fe5f22097c0fc08ad91181b8096496458e2740bd908"
+    );
+}
+
+#[test]
+fn snapshot_adaptor() {
+    let secret = AdaptorSecret::random(&mut FixedRng(0));
+    assert_eq!(
+        hex::encode(secret.bytes),
+        "7a3c6282f02d37a05023b60d5428e6cc5961d4c31221937adae0b574e4d07205"
+    );
+    let point = secret.point().unwrap();
+    assert_eq!(
+        hex::encode(point.bytes),
+        "f9302fcb3a2937cff4950e4c6272340e171b0a65ed680d8fca72087ab4da078d"
+    );
+    let pre = private().pre_sign(b"snapshot", &point).unwrap();
+    assert_eq!(
+        hex::encode(pre.bytes),
+        "fa3137ffd1f3f987edf5e9d6c30f00075ce5dce96423736ddfd6d66161a54a00a8df4ec2b83de977e247d9f0b27ef1b21e44908a8e9a197371f3854e2aca5d01"
+    );
+}
+
+#[test]
+fn snapshot_blinded_key() {
+    let blinded = private().blind(&[7; 32]);
+    assert_eq!(
+        hex::encode(blinded.public_key().bytes),
+        "f876227d72b4c349238b46b5a546cd970dc2016061febad2f003ed911390c14c"
+    );
+    assert_eq!(
+        hex::encode(blinded.sign(b"snapshot").bytes),
+        "cce4972a5e29897163773e5e15415640533ee5f61aa758e0dd8723c4b52e322cc70e864aa816b03a44e36fdd58b07f08c824ae8a132a6e248c075ef634d0a500"
+    );
+}
+
+#[test]
+fn snapshot_sealed_box() {
+    let public = private().public_key();
+    let sealed = ecies::seal(&mut FixedRng(0), &public, b"snapshot").unwrap();
+    assert_eq!(
+        hex::encode(sealed),
+        "1ac105ea144728da5ebea01e5ee75d70584f1f3cd448b1ec7c2bddda3fbd1f0e13b8c4e15449b28a1d90605e7d93d1ca7c653c3b95315fea"
+    );
+}
+
+#[test]
+fn snapshot_passphrase_key() {
+    let params = PassphraseParams {
+        iterations: 100_000,
+    };
+    let private = PrivateKey::from_passphrase(b"snapshot", b"snapshot salt 16", params).unwrap();
+    assert_eq!(
+        hex::encode(private.bytes),
+        "02dbc770699791581d93a25bea0fa428759f908e0f39a5f399a155e689c2bc88"
+    );
+}
+
+#[test]
+fn snapshot_compressed_point() {
+    let p = (point::B * Scalar::from(1234)).compress().bytes;
+    assert_eq!(
+        hex::encode(p),
+        "b35b48dceeb8675cd12ade9d75839dfaac4c779d7c073855e65d0aed00637bc8"
+    );
+}
+
+#[test]
+fn snapshot_wide_scalar() {
+    let s: [u8; 32] = Scalar::from([0xFF; 64]).into();
+    assert_eq!(
+        hex::encode(s),
+        "000f9c44e31106a447938568a71b0ed065bef517d273ecce3d9a307c1b419903"
+    );
+}
+
+#[test]
+#[cfg(feature = "hazmat")]
+fn snapshot_commitment() {
+    let h = commitment::generator_h().compress().bytes;
+    assert_eq!(
+        hex::encode(h),
+        "a1f6d34635782c72dee086ed27a5dc87afa69d7484b3296dc25c3e9a8e1ed622"
+    );
+    let c = commitment::commit(Scalar::from(1), Scalar::from(2))
+        .compress()
+        .bytes;
+    assert_eq!(
+        hex::encode(c),
+        "dd4b07fa75703b32d6402920ca88556776d7b99406d7f3826a19a4f577a5263e"
+    );
+}
@@ -0,0 +1,253 @@
+//! The test matrix from "Taming the many EdDSAs", by Chalkias, Garillot, and Nikolaenko:
+//! https://eprint.iacr.org/2020/1244
+//!
+//! The paper has 12 signatures, probing where Ed25519 implementations disagree: small
+//! and mixed order points, values of s at least L, and non-canonical encodings of R and A.
+//! We build each case from its description, then check which verification methods accept it:
+//!
+//! | case | s          | A                  | R                  | verify | strict | zip215 |
+//! |------|------------|--------------------|--------------------|--------|--------|--------|
+//! | 0    | 0          | small order        | small order        | yes    | no     | yes    |
+//! | 1    | below L    | small order        | mixed order        | yes    | no     | yes    |
+//! | 2    | below L    | mixed order        | small order        | yes    | no     | yes    |
+//! | 3    | below L    | mixed order        | mixed order        | yes    | yes    | yes    |
+//! | 4    | below L    | mixed order        | mixed order        | no     | no     | yes    |
+//! | 5    | below L    | mixed order        | order L            | no     | no     | yes    |
+//! | 6    | s + L      | order L            | order L            | no     | no     | no     |
+//! | 7    | s + 8L     | order L            | order L            | no     | no     | no     |
+//! | 8    | below L    | mixed order        | small, y + P       | no     | no     | yes    |
+//! | 9    | below L    | mixed order        | small, y + P       | no     | no     | no     |
+//! | 10   | below L    | small, y + P       | mixed order        | no     | no     | yes    |
+//! | 11   | below L    | small, y + P       | mixed order        | no     | no     | yes    |
+//!
+//! Cases 0 to 3 satisfy s⋅B = R + k⋅A, while 4 and 5 only satisfy the equation multiplied
+//! by the cofactor, 8. Case 6 only passes a check that s < 2^253, and case 7 fails even that.
+//! Cases 8 and 10 hash R and A as given, while 9 and 11 hash their canonical encodings.
+//!
+//! Neither `verify` nor `verify_strict` use the cofactor, and both reject non-canonical
+//! encodings. `verify` accepts small order points, while `verify_strict` rejects them,
+//! in cases 0 to 2, so that only case 3 passes it.
+//! Cases 0 to 3 also rely on negating k⋅A, and not k, since -k is reduced modulo L.
+//! `verify_zip215` uses the cofactor, and hashes R and A as given, so only case 9
+//! fails its equation. Case 11 still passes, since A has small order, and so k⋅A vanishes
+//! once multiplied by 8, no matter what k is.
+use core::convert::TryInto;
+
+use super::{
+    challenge,
+    point::{CompressedEdwardsY, EdwardsPoint, B},
+    scalar::Scalar,
+    sha512, PublicKey, Signature,
+};
+
+/// A point of order 8, from which we make every small order component.
+const ORDER_8: &str = "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a";
+
+/// L, the order of the base point, as little endian hex.
+const L: &str = "edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Order {
+    Small,
+    Mixed,
+    Prime,
+}
+
+fn order(point: EdwardsPoint) -> Order {
+    if point.is_small_order() {
+        Order::Small
+    } else if point.is_torsion_free() {
+        Order::Prime
+    } else {
+        Order::Mixed
+    }
+}
+
+struct Case {
+    public: [u8; 32],
+    message: Vec<u8>,
+    signature: Signature,
+}
+
+fn decode(hex: &str) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    hex::decode_to_slice(hex, &mut bytes).unwrap();
+    bytes
+}
+
+/// A multiple of our point of order 8.
+fn torsion(j: u64) -> EdwardsPoint {
+    let t = CompressedEdwardsY {
+        bytes: decode(ORDER_8),
+    }
+    .decompress()
+    .unwrap();
+    t * Scalar::from(j)
+}
+
+fn scalar(label: &[u8]) -> Scalar {
+    Scalar::from_bytes_mod_order(sha512::hash(label)[..32].try_into().unwrap())
+}
+
+/// Encode a point with y = 0 as y = P instead, keeping its sign bit.
+fn non_canonical(point: EdwardsPoint) -> [u8; 32] {
+    let canonical = point.compress().bytes;
+    assert_eq!(canonical[..31], [0; 31]);
+    assert_eq!(canonical[31] & 0x7F, 0);
+    let mut bytes = [0xFF; 32];
+    bytes[0] = 0xED;
+    bytes[31] = 0x7F | canonical[31];
+    bytes
+}
+
+/// Add two numbers, as little endian bytes.
+fn add(x: [u8; 32], y: [u8; 32]) -> [u8; 32] {
+    let mut out = [0; 32];
+    let mut carry = 0u16;
+    for i in 0..32 {
+        let sum = u16::from(x[i]) + u16::from(y[i]) + carry;
+        out[i] = sum as u8;
+        carry = sum >> 8;
+    }
+    assert_eq!(carry, 0);
+    out
+}
+
+/// Find a message whose challenge, hashing these encodings of R and A, satisfies a condition
+/// on its value modulo 8.
+fn find_message(r: &[u8; 32], a: &[u8; 32], accept: impl Fn(u8) -> bool) -> (Vec<u8>, Scalar) {
+    (0u32..)
+        .map(|i| i.to_le_bytes().to_vec())
+        .map(|message| {
+            let k = challenge(r, a, &message);
+            (message, k)
+        })
+        .find(|(_, k)| accept(k.to_bytes()[0] & 7))
+        .unwrap()
+}
+
+fn case(r: [u8; 32], s: [u8; 32], a: [u8; 32], message: Vec<u8>) -> Case {
+    let mut signature = Signature { bytes: [0; 64] };
+    signature.bytes[..32].copy_from_slice(&r);
+    signature.bytes[32..].copy_from_slice(&s);
+    Case {
+        public: a,
+        message,
+        signature,
+    }
+}
+
+/// Build a case with s below L, and k⋅A needing to cancel the torsion in R.
+///
+/// R is r⋅B - j⋅T, and A is a⋅B + T, where T has order 8. With k ≡ j mod 8, the
+/// torsion cancels, and s⋅B = R + k⋅A holds exactly, otherwise only with the cofactor.
+fn mixed_case(r: Scalar, a: Scalar, j: u64, cancels: bool) -> Case {
+    let big_a = (B * a + torsion(1)).compress().bytes;
+    let big_r = (B * r - torsion(j)).compress().bytes;
+    let (message, k) = find_message(&big_r, &big_a, |k| (u64::from(k) == j % 8) == cancels);
+    case(big_r, (r + k * a).to_bytes(), big_a, message)
+}
+
+fn cases() -> Vec<Case> {
+    let a = scalar(b"a");
+    let r = scalar(b"r");
+    let mut out = Vec::new();
+
+    // 0: s = 0, with A = T, and R = -3⋅T, so R + k⋅A = 0 when k ≡ 3 mod 8
+    let big_a = torsion(1).compress().bytes;
+    let big_r = (-torsion(3)).compress().bytes;
+    let (message, _) = find_message(&big_r, &big_a, |k| k == 3);
+    out.push(case(big_r, [0; 32], big_a, message));
+
+    // 1: A = T, and R = r⋅B - 3⋅T, with s = r
+    let big_r = (B * r - torsion(3)).compress().bytes;
+    let (message, _) = find_message(&big_r, &big_a, |k| k == 3);
+    out.push(case(big_r, r.to_bytes(), big_a, message));
+
+    // 2: A = a⋅B + T, and R = -3⋅T, with s = k⋅a
+    out.push(mixed_case(Scalar::from(0), a, 3, true));
+
+    // 3 and 4: A = a⋅B + T, and R = r⋅B - 3⋅T, with the torsion cancelling, or not
+    out.push(mixed_case(r, a, 3, true));
+    out.push(mixed_case(r, a, 3, false));
+
+    // 5: A = a⋅B + T, and R = r⋅B, with k not a multiple of 8
+    out.push(mixed_case(r, a, 0, false));
+
+    // 6 and 7: a valid signature, with L or 8L added to s
+    let big_a = (B * a).compress().bytes;
+    let big_r = (B * r).compress().bytes;
+    let (message, k) = find_message(&big_r, &big_a, |_| true);
+    let s = (r + k * a).to_bytes();
+    let s_plus_l = add(s, decode(L));
+    assert_eq!(s_plus_l[31] >> 5, 0);
+    out.push(case(big_r, s_plus_l, big_a, message.clone()));
+    let s_plus_8l = (0..8).fold(s, |s, _| add(s, decode(L)));
+    assert_ne!(s_plus_8l[31] >> 5, 0);
+    out.push(case(big_r, s_plus_8l, big_a, message));
+
+    // 8 and 9: A = a⋅B + T, and R = -2⋅T, which has y = 0, encoded as y = P
+    let big_a = (B * a + torsion(1)).compress().bytes;
+    let big_r = non_canonical(-torsion(2));
+    let (message, k) = find_message(&big_r, &big_a, |k| k == 2);
+    out.push(case(big_r, (k * a).to_bytes(), big_a, message));
+    let canonical_r = (-torsion(2)).compress().bytes;
+    let (message, k) = find_message(&canonical_r, &big_a, |k| k == 2);
+    out.push(case(big_r, (k * a).to_bytes(), big_a, message));
+
+    // 10 and 11: A = 2⋅T, which has y = 0, encoded as y = P, and R = r⋅B - 2⋅T
+    let big_a = non_canonical(torsion(2));
+    let canonical_a = torsion(2).compress().bytes;
+    let big_r = (B * r - torsion(2)).compress().bytes;
+    let (message, _) = find_message(&big_r, &big_a, |k| k % 4 == 1);
+    out.push(case(big_r, r.to_bytes(), big_a, message));
+    let (message, _) = find_message(&big_r, &canonical_a, |k| k % 4 == 1);
+    out.push(case(big_r, r.to_bytes(), big_a, message));
+
+    out
+}
+
+#[test]
+fn test_taming_the_many_eddsas() {
+    use Order::*;
+    // The orders of A and R, and whether verify, verify_strict, and verify_zip215 accept
+    let expected = [
+        (Small, Small, true, false, true),
+        (Small, Mixed, true, false, true),
+        (Mixed, Small, true, false, true),
+        (Mixed, Mixed, true, true, true),
+        (Mixed, Mixed, false, false, true),
+        (Mixed, Prime, false, false, true),
+        (Prime, Prime, false, false, false),
+        (Prime, Prime, false, false, false),
+        (Mixed, Small, false, false, true),
+        (Mixed, Small, false, false, false),
+        (Small, Mixed, false, false, true),
+        (Small, Mixed, false, false, true),
+    ];
+    let cases = cases();
+    assert_eq!(cases.len(), expected.len());
+    for (i, (case, &(a_order, r_order, default, strict, zip215))) in
+        cases.iter().zip(expected.iter()).enumerate()
+    {
+        let decompress = |bytes: &[u8]| {
+            CompressedEdwardsY {
+                bytes: bytes.try_into().unwrap(),
+            }
+            .decompress_zip215()
+            .unwrap()
+        };
+        assert_eq!(order(decompress(&case.public)), a_order, "case {}", i);
+        assert_eq!(
+            order(decompress(&case.signature.bytes[..32])),
+            r_order,
+            "case {}",
+            i
+        );
+        let public = PublicKey { bytes: case.public };
+        let (message, sig) = (&case.message[..], case.signature);
+        assert_eq!(public.verify(message, sig), default, "case {}", i);
+        assert_eq!(public.verify_strict(message, sig), strict, "case {}", i);
+        assert_eq!(public.verify_zip215(message, sig), zip215, "case {}", i);
+    }
+}
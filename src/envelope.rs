@@ -0,0 +1,364 @@
+//! This module defines a compact CBOR envelope, for carrying signatures on constrained links.
+//!
+//! An envelope identifies the key and the algorithm used for a signature, and
+//! can carry a counter, e.g. to detect replays. It's encoded as a CBOR array:
+//!
+//! [key id: bytes(8), algorithm: int, signature: bytes(64), counter?: uint]
+//!
+//! The algorithm uses the COSE identifiers, with EdDSA being -8. We only produce, and
+//! accept, the deterministic encoding from section 4.2 of RFC 8949, so integers use
+//! their shortest form, and lengths are never indefinite. An envelope has at most
+//! 22 bytes of overhead on top of the signature.
+//!
+//! Encoding and decoding work over slices, without allocating.
+use core::convert::{TryFrom, TryInto};
+
+use crate::{PublicKey, Signature, SIGNATURE_SIZE};
+
+/// The number of bytes in a key id.
+pub const KEY_ID_SIZE: usize = 8;
+
+/// The largest number of bytes an encoded envelope can take.
+pub const MAX_ENVELOPE_SIZE: usize = 1 + (1 + KEY_ID_SIZE) + 1 + (2 + SIGNATURE_SIZE) + 9;
+
+// The CBOR major types we use.
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_ARRAY: u8 = 4;
+
+/// Represents the reasons an envelope might fail to encode, or decode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnvelopeError {
+    /// The output buffer was too small to hold the envelope.
+    BufferTooSmall,
+    /// The input ended in the middle of the envelope.
+    Truncated,
+    /// The input wasn't an envelope, or wasn't deterministically encoded.
+    Malformed,
+    /// The envelope used an algorithm we don't support, given by its COSE identifier.
+    UnknownAlgorithm(i64),
+    /// The input had extra bytes after the envelope.
+    TrailingBytes,
+}
+
+/// Represents a short identifier for a public key.
+///
+/// This is the first 8 bytes of the key, and isn't guaranteed to be unique: a key id
+/// is only used to select a key, and the signature still needs to be verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyId {
+    pub bytes: [u8; KEY_ID_SIZE],
+}
+
+impl KeyId {
+    /// Calculate the id of a public key.
+    pub fn of(public: &PublicKey) -> Self {
+        KeyId {
+            bytes: public.bytes[..KEY_ID_SIZE].try_into().unwrap(),
+        }
+    }
+}
+
+/// Represents the signature algorithms an envelope can indicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Pure Ed25519 signatures, as produced by `PrivateKey::sign`.
+    EdDsa,
+}
+
+impl Algorithm {
+    /// The COSE identifier of this algorithm.
+    pub fn cose_id(self) -> i64 {
+        match self {
+            Algorithm::EdDsa => -8,
+        }
+    }
+
+    /// Find the algorithm with a given COSE identifier.
+    pub fn from_cose_id(id: i64) -> Option<Self> {
+        match id {
+            -8 => Some(Algorithm::EdDsa),
+            _ => None,
+        }
+    }
+}
+
+/// Represents a signature, along with the information needed to check it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub key_id: KeyId,
+    pub algorithm: Algorithm,
+    pub signature: Signature,
+    pub counter: Option<u64>,
+}
+
+/// Write the head of a CBOR item, using the shortest form for its argument.
+fn write_head(
+    out: &mut [u8],
+    at: &mut usize,
+    major: u8,
+    argument: u64,
+) -> Result<(), EnvelopeError> {
+    let (info, size) = match argument {
+        0..=23 => (argument as u8, 0),
+        24..=0xFF => (24, 1),
+        0x100..=0xFFFF => (25, 2),
+        0x1_0000..=0xFFFF_FFFF => (26, 4),
+        _ => (27, 8),
+    };
+    write_bytes(out, at, &[(major << 5) | info])?;
+    write_bytes(out, at, &argument.to_be_bytes()[8 - size..])
+}
+
+fn write_bytes(out: &mut [u8], at: &mut usize, bytes: &[u8]) -> Result<(), EnvelopeError> {
+    let end = *at + bytes.len();
+    out.get_mut(*at..end)
+        .ok_or(EnvelopeError::BufferTooSmall)?
+        .copy_from_slice(bytes);
+    *at = end;
+    Ok(())
+}
+
+/// Reads CBOR items from the start of a slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], EnvelopeError> {
+        if self.bytes.len() < count {
+            return Err(EnvelopeError::Truncated);
+        }
+        let (out, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(out)
+    }
+
+    /// Read the head of an item, rejecting arguments not in their shortest form.
+    fn head(&mut self) -> Result<(u8, u64), EnvelopeError> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1F);
+        let (size, min) = match info {
+            0..=23 => return Ok((major, u64::from(info))),
+            24 => (1, 24),
+            25 => (2, 0x100),
+            26 => (4, 0x1_0000),
+            27 => (8, 0x1_0000_0000),
+            _ => return Err(EnvelopeError::Malformed),
+        };
+        let mut be = [0; 8];
+        be[8 - size..].copy_from_slice(self.take(size)?);
+        let argument = u64::from_be_bytes(be);
+        if argument < min {
+            return Err(EnvelopeError::Malformed);
+        }
+        Ok((major, argument))
+    }
+
+    fn byte_string(&mut self, len: usize) -> Result<&'a [u8], EnvelopeError> {
+        match self.head()? {
+            (MAJOR_BYTES, l) if l == len as u64 => self.take(len),
+            _ => Err(EnvelopeError::Malformed),
+        }
+    }
+
+    fn int(&mut self) -> Result<i64, EnvelopeError> {
+        match self.head()? {
+            (MAJOR_UNSIGNED, x) => x.try_into().map_err(|_| EnvelopeError::Malformed),
+            (MAJOR_NEGATIVE, x) => i64::try_from(x)
+                .map(|x| -1 - x)
+                .map_err(|_| EnvelopeError::Malformed),
+            _ => Err(EnvelopeError::Malformed),
+        }
+    }
+}
+
+impl Envelope {
+    /// Wrap a pure Ed25519 signature, made with a given key.
+    pub fn new(public: &PublicKey, signature: Signature, counter: Option<u64>) -> Self {
+        Envelope {
+            key_id: KeyId::of(public),
+            algorithm: Algorithm::EdDsa,
+            signature,
+            counter,
+        }
+    }
+
+    /// Encode this envelope into a buffer, returning the number of bytes written.
+    ///
+    /// A buffer of `MAX_ENVELOPE_SIZE` bytes is always large enough.
+    pub fn encode(&self, out: &mut [u8]) -> Result<usize, EnvelopeError> {
+        let mut at = 0;
+        let items = if self.counter.is_some() { 4 } else { 3 };
+        write_head(out, &mut at, MAJOR_ARRAY, items)?;
+        write_head(out, &mut at, MAJOR_BYTES, KEY_ID_SIZE as u64)?;
+        write_bytes(out, &mut at, &self.key_id.bytes)?;
+        let id = self.algorithm.cose_id();
+        if id < 0 {
+            write_head(out, &mut at, MAJOR_NEGATIVE, (-1 - id) as u64)?;
+        } else {
+            write_head(out, &mut at, MAJOR_UNSIGNED, id as u64)?;
+        }
+        write_head(out, &mut at, MAJOR_BYTES, SIGNATURE_SIZE as u64)?;
+        write_bytes(out, &mut at, &self.signature.bytes)?;
+        if let Some(counter) = self.counter {
+            write_head(out, &mut at, MAJOR_UNSIGNED, counter)?;
+        }
+        Ok(at)
+    }
+
+    /// Decode an envelope, which must take up the entire input.
+    pub fn decode(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        let mut reader = Reader { bytes };
+        let items = match reader.head()? {
+            (MAJOR_ARRAY, items @ 3..=4) => items,
+            _ => return Err(EnvelopeError::Malformed),
+        };
+        let key_id = KeyId {
+            bytes: reader.byte_string(KEY_ID_SIZE)?.try_into().unwrap(),
+        };
+        let id = reader.int()?;
+        let algorithm = Algorithm::from_cose_id(id).ok_or(EnvelopeError::UnknownAlgorithm(id))?;
+        let signature = Signature {
+            bytes: reader.byte_string(SIGNATURE_SIZE)?.try_into().unwrap(),
+        };
+        let counter = if items == 4 {
+            match reader.head()? {
+                (MAJOR_UNSIGNED, counter) => Some(counter),
+                _ => return Err(EnvelopeError::Malformed),
+            }
+        } else {
+            None
+        };
+        if !reader.bytes.is_empty() {
+            return Err(EnvelopeError::TrailingBytes);
+        }
+        Ok(Envelope {
+            key_id,
+            algorithm,
+            signature,
+            counter,
+        })
+    }
+
+    /// Verify the signature in this envelope, checking that it was made by a given key.
+    pub fn verify(&self, public: &PublicKey, message: &[u8]) -> bool {
+        self.key_id == KeyId::of(public) && public.verify(message, self.signature)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PrivateKey;
+
+    fn envelope(counter: Option<u64>) -> Envelope {
+        Envelope {
+            key_id: KeyId {
+                bytes: [1, 2, 3, 4, 5, 6, 7, 8],
+            },
+            algorithm: Algorithm::EdDsa,
+            signature: Signature { bytes: [0x11; 64] },
+            counter,
+        }
+    }
+
+    fn encode_hex(envelope: &Envelope) -> String {
+        let mut out = [0; MAX_ENVELOPE_SIZE];
+        let len = envelope.encode(&mut out).unwrap();
+        hex::encode(&out[..len])
+    }
+
+    #[test]
+    fn test_golden_encodings() {
+        let sig = format!("5840{}", "11".repeat(64));
+        let cases = [
+            (None, format!("83480102030405060708{}{}", "27", sig)),
+            (Some(0), format!("84480102030405060708{}{}00", "27", sig)),
+            (Some(23), format!("84480102030405060708{}{}17", "27", sig)),
+            (Some(24), format!("84480102030405060708{}{}1818", "27", sig)),
+            (
+                Some(1000),
+                format!("84480102030405060708{}{}1903e8", "27", sig),
+            ),
+            (
+                Some(1 << 32),
+                format!("84480102030405060708{}{}1b0000000100000000", "27", sig),
+            ),
+        ];
+        for (counter, expected) in cases.iter() {
+            let envelope = envelope(*counter);
+            assert_eq!(&encode_hex(&envelope), expected);
+            let bytes = hex::decode(expected).unwrap();
+            assert_eq!(Envelope::decode(&bytes), Ok(envelope));
+        }
+    }
+
+    #[test]
+    fn test_maximum_size() {
+        let mut out = [0; MAX_ENVELOPE_SIZE];
+        let len = envelope(Some(u64::MAX)).encode(&mut out).unwrap();
+        assert_eq!(len, MAX_ENVELOPE_SIZE);
+        assert_eq!(MAX_ENVELOPE_SIZE - SIGNATURE_SIZE, 22);
+        assert_eq!(
+            envelope(None).encode(&mut out[..10]),
+            Err(EnvelopeError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_non_deterministic_encodings_are_rejected() {
+        let sig = format!("5840{}", "11".repeat(64));
+        let bad = [
+            // A counter of 23, not in its shortest form
+            (
+                format!("84480102030405060708{}{}1817", "27", sig),
+                EnvelopeError::Malformed,
+            ),
+            // An indefinite length array
+            (
+                format!("9f480102030405060708{}{}ff", "27", sig),
+                EnvelopeError::Malformed,
+            ),
+            // A key id of the wrong length
+            (
+                format!("834701020304050607{}{}", "27", sig),
+                EnvelopeError::Malformed,
+            ),
+            // ES256, instead of EdDSA
+            (
+                format!("83480102030405060708{}{}", "26", sig),
+                EnvelopeError::UnknownAlgorithm(-7),
+            ),
+            // Extra bytes after the envelope
+            (
+                format!("83480102030405060708{}{}00", "27", sig),
+                EnvelopeError::TrailingBytes,
+            ),
+            // A missing counter
+            (
+                format!("84480102030405060708{}{}", "27", sig),
+                EnvelopeError::Truncated,
+            ),
+        ];
+        for (encoded, error) in bad.iter() {
+            let bytes = hex::decode(encoded).unwrap();
+            assert_eq!(Envelope::decode(&bytes), Err(*error));
+        }
+    }
+
+    #[test]
+    fn test_envelope_verifies() {
+        let private = PrivateKey { bytes: [4; 32] };
+        let public = private.public_key();
+        let envelope = Envelope::new(&public, private.sign(b"reading=12"), Some(3));
+        let mut out = [0; MAX_ENVELOPE_SIZE];
+        let len = envelope.encode(&mut out).unwrap();
+        let decoded = Envelope::decode(&out[..len]).unwrap();
+        assert!(decoded.verify(&public, b"reading=12"));
+        assert!(!decoded.verify(&public, b"reading=13"));
+        let other = PrivateKey { bytes: [5; 32] }.public_key();
+        assert!(!decoded.verify(&other, b"reading=12"));
+    }
+}
@@ -0,0 +1,266 @@
+//! This module defines signed heartbeats, which devices emit to report their status.
+//!
+//! A heartbeat identifies a device, carries a counter and a timestamp, and commits to
+//! a payload, e.g. some status report sent alongside it, through a digest. The encoding
+//! is fixed size, with a single valid encoding for each heartbeat:
+//!
+//! version (1) || device id (16) || counter (8) || timestamp (8) || digest (32) || signature (64)
+//!
+//! with integers in big endian. The signature covers everything before it, prefixed
+//! with a domain separator, so that heartbeats can't be confused with other messages
+//! signed by the same key.
+use core::convert::TryInto;
+
+use crate::{sha512, PrivateKey, PublicKey, Signature, SIGNATURE_SIZE};
+
+/// This is prepended to the encoding of a heartbeat, before signing it.
+const HEARTBEAT_DOMAIN: &[u8] = b"eddo heartbeat v1";
+
+/// This is prepended to a payload, before hashing it.
+const PAYLOAD_DOMAIN: &[u8] = b"eddo heartbeat payload v1";
+
+/// The only version of the encoding we produce, or accept.
+const VERSION: u8 = 1;
+
+/// The number of bytes in a device id.
+pub const DEVICE_ID_SIZE: usize = 16;
+
+/// The number of bytes in the digest of a payload.
+pub const PAYLOAD_DIGEST_SIZE: usize = 32;
+
+/// The number of bytes in an encoded heartbeat, without its signature.
+const BODY_SIZE: usize = 1 + DEVICE_ID_SIZE + 8 + 8 + PAYLOAD_DIGEST_SIZE;
+
+/// The number of bytes in the message signed by a device.
+const SIGNED_SIZE: usize = HEARTBEAT_DOMAIN.len() + BODY_SIZE;
+
+/// The number of bytes in an encoded, signed, heartbeat.
+pub const HEARTBEAT_SIZE: usize = BODY_SIZE + SIGNATURE_SIZE;
+
+/// Represents the reasons a heartbeat might be rejected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeartbeatError {
+    /// The encoding didn't have exactly `HEARTBEAT_SIZE` bytes.
+    WrongLength,
+    /// The encoding used a version we don't understand.
+    UnknownVersion(u8),
+    /// The signature didn't verify under the device's key.
+    BadSignature,
+    /// The counter wasn't larger than the last one we accepted from this device.
+    Replayed,
+    /// The timestamp was too far away from the current time.
+    Stale,
+}
+
+/// Calculate the digest of a payload, as included in a heartbeat.
+pub fn payload_digest(payload: &[u8]) -> [u8; PAYLOAD_DIGEST_SIZE] {
+    let mut hasher = sha512::Hasher::new();
+    hasher.update(PAYLOAD_DOMAIN);
+    hasher.update(payload);
+    hasher.finalize()[..PAYLOAD_DIGEST_SIZE].try_into().unwrap()
+}
+
+/// Represents the status reported by a device, before signing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    pub device_id: [u8; DEVICE_ID_SIZE],
+    /// A counter, which the device increases with each heartbeat.
+    pub counter: u64,
+    /// The time this heartbeat was produced, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The digest of the payload accompanying this heartbeat, as produced by `payload_digest`.
+    pub payload_digest: [u8; PAYLOAD_DIGEST_SIZE],
+}
+
+impl Heartbeat {
+    /// Create a new heartbeat, committing to a given payload.
+    pub fn new(
+        device_id: [u8; DEVICE_ID_SIZE],
+        counter: u64,
+        timestamp: u64,
+        payload: &[u8],
+    ) -> Self {
+        Heartbeat {
+            device_id,
+            counter,
+            timestamp,
+            payload_digest: payload_digest(payload),
+        }
+    }
+
+    /// Check whether or not this heartbeat commits to a given payload.
+    pub fn matches_payload(&self, payload: &[u8]) -> bool {
+        payload_digest(payload) == self.payload_digest
+    }
+
+    fn to_body(self) -> [u8; BODY_SIZE] {
+        let mut out = [0; BODY_SIZE];
+        out[0] = VERSION;
+        out[1..17].copy_from_slice(&self.device_id);
+        out[17..25].copy_from_slice(&self.counter.to_be_bytes());
+        out[25..33].copy_from_slice(&self.timestamp.to_be_bytes());
+        out[33..].copy_from_slice(&self.payload_digest);
+        out
+    }
+
+    fn signed_message(self) -> [u8; SIGNED_SIZE] {
+        let mut out = [0; SIGNED_SIZE];
+        out[..HEARTBEAT_DOMAIN.len()].copy_from_slice(HEARTBEAT_DOMAIN);
+        out[HEARTBEAT_DOMAIN.len()..].copy_from_slice(&self.to_body());
+        out
+    }
+
+    /// Sign this heartbeat, with the key of the device.
+    pub fn sign(&self, private: &PrivateKey) -> SignedHeartbeat {
+        SignedHeartbeat {
+            heartbeat: *self,
+            signature: private.sign(&self.signed_message()),
+        }
+    }
+}
+
+/// Represents a heartbeat, along with the signature of the device.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignedHeartbeat {
+    pub heartbeat: Heartbeat,
+    pub signature: Signature,
+}
+
+impl SignedHeartbeat {
+    /// Encode this heartbeat, in order to send it.
+    pub fn to_bytes(&self) -> [u8; HEARTBEAT_SIZE] {
+        let mut out = [0; HEARTBEAT_SIZE];
+        out[..BODY_SIZE].copy_from_slice(&self.heartbeat.to_body());
+        out[BODY_SIZE..].copy_from_slice(&self.signature.bytes);
+        out
+    }
+
+    /// Decode a heartbeat, without verifying its signature.
+    ///
+    /// Only encodings produced by `to_bytes` are accepted, so each heartbeat has
+    /// exactly one encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HeartbeatError> {
+        if bytes.len() != HEARTBEAT_SIZE {
+            return Err(HeartbeatError::WrongLength);
+        }
+        if bytes[0] != VERSION {
+            return Err(HeartbeatError::UnknownVersion(bytes[0]));
+        }
+        Ok(SignedHeartbeat {
+            heartbeat: Heartbeat {
+                device_id: bytes[1..17].try_into().unwrap(),
+                counter: u64::from_be_bytes(bytes[17..25].try_into().unwrap()),
+                timestamp: u64::from_be_bytes(bytes[25..33].try_into().unwrap()),
+                payload_digest: bytes[33..BODY_SIZE].try_into().unwrap(),
+            },
+            signature: Signature {
+                bytes: bytes[BODY_SIZE..].try_into().unwrap(),
+            },
+        })
+    }
+
+    /// Verify that this heartbeat was signed by a given device key.
+    pub fn verify(&self, public: &PublicKey) -> Result<(), HeartbeatError> {
+        if !public.verify(&self.heartbeat.signed_message(), self.signature) {
+            return Err(HeartbeatError::BadSignature);
+        }
+        Ok(())
+    }
+
+    /// Verify this heartbeat, and check that it's fresh.
+    ///
+    /// The counter must be larger than the last counter accepted from this device,
+    /// if any, and the timestamp must be at most `max_skew` seconds away from `now`.
+    /// After accepting a heartbeat, its counter should be remembered for the next call.
+    pub fn verify_fresh(
+        &self,
+        public: &PublicKey,
+        last_counter: Option<u64>,
+        now: u64,
+        max_skew: u64,
+    ) -> Result<(), HeartbeatError> {
+        self.verify(public)?;
+        if last_counter.is_some_and(|last| self.heartbeat.counter <= last) {
+            return Err(HeartbeatError::Replayed);
+        }
+        if self.heartbeat.timestamp.abs_diff(now) > max_skew {
+            return Err(HeartbeatError::Stale);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEVICE: [u8; DEVICE_ID_SIZE] = [0xAA; DEVICE_ID_SIZE];
+
+    fn signed() -> (PublicKey, SignedHeartbeat) {
+        let private = PrivateKey { bytes: [9; 32] };
+        let heartbeat = Heartbeat::new(DEVICE, 7, 1_700_000_000, b"battery=93");
+        (private.public_key(), heartbeat.sign(&private))
+    }
+
+    #[test]
+    fn test_heartbeat_roundtrips() {
+        let (public, signed) = signed();
+        let bytes = signed.to_bytes();
+        assert_eq!(bytes.len(), HEARTBEAT_SIZE);
+        let decoded = SignedHeartbeat::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, signed);
+        assert_eq!(decoded.verify(&public), Ok(()));
+        assert!(decoded.heartbeat.matches_payload(b"battery=93"));
+        assert!(!decoded.heartbeat.matches_payload(b"battery=94"));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let (public, signed) = signed();
+        let bytes = signed.to_bytes();
+        for i in [1, 20, 30, 40, BODY_SIZE] {
+            let mut tampered = bytes;
+            tampered[i] ^= 1;
+            let decoded = SignedHeartbeat::from_bytes(&tampered).unwrap();
+            assert_eq!(decoded.verify(&public), Err(HeartbeatError::BadSignature));
+        }
+    }
+
+    #[test]
+    fn test_non_canonical_encodings_are_rejected() {
+        let (_, signed) = signed();
+        let bytes = signed.to_bytes();
+        let mut longer = bytes.to_vec();
+        longer.push(0);
+        assert_eq!(
+            SignedHeartbeat::from_bytes(&longer),
+            Err(HeartbeatError::WrongLength)
+        );
+        assert_eq!(
+            SignedHeartbeat::from_bytes(&bytes[..HEARTBEAT_SIZE - 1]),
+            Err(HeartbeatError::WrongLength)
+        );
+        let mut versioned = bytes;
+        versioned[0] = 2;
+        assert_eq!(
+            SignedHeartbeat::from_bytes(&versioned),
+            Err(HeartbeatError::UnknownVersion(2))
+        );
+    }
+
+    #[test]
+    fn test_freshness() {
+        let (public, signed) = signed();
+        let now = 1_700_000_030;
+        assert_eq!(signed.verify_fresh(&public, None, now, 60), Ok(()));
+        assert_eq!(signed.verify_fresh(&public, Some(6), now, 60), Ok(()));
+        assert_eq!(
+            signed.verify_fresh(&public, Some(7), now, 60),
+            Err(HeartbeatError::Replayed)
+        );
+        assert_eq!(
+            signed.verify_fresh(&public, None, now, 10),
+            Err(HeartbeatError::Stale)
+        );
+    }
+}
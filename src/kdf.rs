@@ -0,0 +1,139 @@
+//! This module implements the key derivation functions we build on SHA-512.
+//!
+//! This includes HMAC (RFC 2104), HKDF (RFC 5869), and PBKDF2 (RFC 8018).
+
+use crate::sha512;
+
+/// The number of bytes in a block of SHA-512.
+const BLOCK_SIZE: usize = 128;
+
+/// Calculate HMAC-SHA512, as described in RFC 2104:
+/// https://datatracker.ietf.org/doc/html/rfc2104
+///
+/// Keys longer than a SHA-512 block are hashed first, as the RFC prescribes.
+/// Tags should be compared in constant time, e.g. with `subtle::ConstantTimeEq`.
+pub fn hmac_sha512(key: &[u8], message: &[u8]) -> [u8; sha512::HASH_SIZE] {
+    hmac_parts(key, &[message])
+}
+
+/// Calculate HMAC-SHA512 over the concatenation of several parts, without copying them.
+fn hmac_parts(key: &[u8], parts: &[&[u8]]) -> [u8; sha512::HASH_SIZE] {
+    Hmac::new(key).mac(parts)
+}
+
+/// HMAC-SHA512, with the padded keys already absorbed.
+///
+/// Each padded key takes up a whole block, so this saves two compressions
+/// for every tag we calculate with the same key, which adds up in PBKDF2.
+struct Hmac {
+    inner: sha512::Hasher,
+    outer: sha512::Hasher,
+}
+
+impl Hmac {
+    fn new(key: &[u8]) -> Self {
+        let mut padded_key = [0u8; BLOCK_SIZE];
+        if key.len() > BLOCK_SIZE {
+            padded_key[..sha512::HASH_SIZE].copy_from_slice(&sha512::hash(key));
+        } else {
+            padded_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner = sha512::Hasher::new();
+        inner.update(&padded_key.map(|x| x ^ 0x36));
+        let mut outer = sha512::Hasher::new();
+        outer.update(&padded_key.map(|x| x ^ 0x5c));
+        Hmac { inner, outer }
+    }
+
+    fn mac(&self, parts: &[&[u8]]) -> [u8; sha512::HASH_SIZE] {
+        let mut inner = self.inner.clone();
+        for part in parts {
+            inner.update(part);
+        }
+        let mut outer = self.outer.clone();
+        outer.update(&inner.finalize());
+        outer.finalize()
+    }
+}
+
+/// Derive a single block of key material with HKDF-SHA512, as described in RFC 5869:
+/// https://datatracker.ietf.org/doc/html/rfc5869
+#[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+pub(crate) fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; sha512::HASH_SIZE] {
+    let prk = hmac_sha512(salt, ikm);
+    hmac_parts(&prk, &[info, &[1]])
+}
+
+/// Derive key material from a password with PBKDF2-HMAC-SHA512, filling `out`.
+///
+/// This follows Section 5.2 of RFC 8018:
+/// https://datatracker.ietf.org/doc/html/rfc8018#section-5.2
+///
+/// The cost grows linearly with `iterations`, which should be as large as you
+/// can tolerate; an iteration count of 0 is treated like 1. For deriving private
+/// keys, see `PrivateKey::from_passphrase`, which also enforces a minimum cost.
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+    let hmac = Hmac::new(password);
+    for (i, chunk) in out.chunks_mut(sha512::HASH_SIZE).enumerate() {
+        let mut u = hmac.mac(&[salt, &(i as u32 + 1).to_be_bytes()]);
+        let mut t = u;
+        for _ in 1..iterations {
+            u = hmac.mac(&[&u]);
+            for (t_i, u_i) in t.iter_mut().zip(u.iter()) {
+                *t_i ^= u_i;
+            }
+        }
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hmac_vectors() {
+        // Test cases 1, 2, and 6 from RFC 4231, the last using a key larger than a block
+        let vectors: [(&[u8], &[u8], &str); 3] = [
+            (
+                &[0x0b; 20],
+                b"Hi There",
+                "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+            ),
+            (
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+            ),
+        ];
+        for (key, message, expected) in vectors.iter() {
+            assert_eq!(hex::encode(hmac_sha512(key, message)), *expected);
+        }
+    }
+
+    #[test]
+    fn test_pbkdf2_vectors() {
+        let mut out = [0; 64];
+        pbkdf2_hmac_sha512(b"password", b"salt", 1, &mut out);
+        assert_eq!(
+            hex::encode(out),
+            "867f70cf1ade02cff3752599a3a53dc4af34c7a669815ae5d513554e1c8cf252c02d470a285a0501bad999bfe943c08f050235d7d68b1da55e63f73b60a57fce"
+        );
+        pbkdf2_hmac_sha512(b"password", b"salt", 2, &mut out);
+        assert_eq!(
+            hex::encode(out),
+            "e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53cf76cab2868a39b9f7840edce4fef5a82be67335c77a6068e04112754f27ccf4e"
+        );
+        pbkdf2_hmac_sha512(b"password", b"salt", 4096, &mut out);
+        assert_eq!(
+            hex::encode(out),
+            "d197b1b33db0143e018b12f3d1d1479e6cdebdcc97c5c0f87f6902e072f457b5143f30602641b3d55cd335988cb36b84376060ecd532e039b742a239434af2d5"
+        );
+    }
+}
@@ -0,0 +1,954 @@
+//! This module implements the key files written by `eddo generate`.
+//!
+//! There are two formats. The original one is a single line, holding the private key
+//! with a prefix, or in armor, usually after a `# Public Key: ` comment. The versioned
+//! format starts with `KEY_FILE_HEADER`, followed by a field on each line, an empty
+//! line, and then the private key:
+//!
+//! ```text
+//! eddo key file v2
+//! created: 1700000000
+//! key id: 0123456789abcdef
+//! public key: エッドの公開鍵...
+//! comment: release signing
+//!
+//! エッドの秘密鍵...
+//! ```
+//!
+//! Private keys can be encrypted under a passphrase, with scrypt and ChaCha20-Poly1305.
+//! In the versioned format, the cipher, scrypt parameters, salt, and nonce are fields,
+//! and the key itself is just the ciphertext and tag. The encryption authenticates the
+//! other fields too, so that the creation time or comment can't be swapped undetected.
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{convert::TryInto, fmt};
+
+use rand::{CryptoRng, RngCore};
+
+use crate::{
+    aead,
+    armor::{self, ArmorError},
+    scrypt, PrivateKey, PublicKey,
+};
+
+pub const PUBLIC_KEY_PREFIX: &str = "エッドの公開鍵";
+pub const PUBLIC_KEY_ARMOR: &str = "EDDO PUBLIC KEY";
+pub const PRIVATE_KEY_PREFIX: &str = "エッドの秘密鍵";
+pub const PRIVATE_KEY_ARMOR: &str = "EDDO PRIVATE KEY";
+pub const ENCRYPTED_PRIVATE_KEY_PREFIX: &str = "エッドの暗号化秘密鍵";
+pub const ENCRYPTED_PRIVATE_KEY_ARMOR: &str = "EDDO ENCRYPTED PRIVATE KEY";
+
+/// The first line of a key file in the versioned format.
+pub const KEY_FILE_HEADER: &str = "eddo key file v2";
+
+/// The comment preceding the private key in the original format.
+const LEGACY_PUBLIC_KEY_COMMENT: &str = "# Public Key: ";
+
+const CREATED_FIELD: &str = "created: ";
+const KEY_ID_FIELD: &str = "key id: ";
+const PUBLIC_KEY_FIELD: &str = "public key: ";
+const COMMENT_FIELD: &str = "comment: ";
+const CIPHER_FIELD: &str = "cipher: ";
+const KDF_FIELD: &str = "kdf: ";
+const SALT_FIELD: &str = "salt: ";
+const NONCE_FIELD: &str = "nonce: ";
+
+const FIELDS: [&str; 8] = [
+    CREATED_FIELD,
+    KEY_ID_FIELD,
+    PUBLIC_KEY_FIELD,
+    COMMENT_FIELD,
+    CIPHER_FIELD,
+    KDF_FIELD,
+    SALT_FIELD,
+    NONCE_FIELD,
+];
+
+/// The cipher encrypted key files use, which is the only one we support.
+const KEY_FILE_CIPHER: &str = "chacha20-poly1305";
+
+/// The scrypt parameters for new key files, using 128 MiB of memory.
+pub const DEFAULT_SCRYPT: scrypt::Params = scrypt::Params {
+    log_n: 17,
+    r: 8,
+    p: 1,
+};
+
+/// The most memory, in bytes, and work, that scrypt may use to decrypt a key file.
+///
+/// These are the limits minisign creates its keys with, see `scrypt::Params::from_limits`,
+/// and stop a corrupted or malicious key file from exhausting memory, or hanging.
+const MAX_MEMORY: u128 = 1 << 30;
+const MAX_OPS: u128 = 1 << 25;
+
+/// The version of a standalone encrypted key, in the original format.
+///
+/// Only the header of these keys is authenticated.
+const LEGACY_ENCRYPTED_VERSION: u8 = 1;
+
+/// The version of an encrypted key in the versioned format.
+///
+/// This never appears in the file, but marks that the other fields are authenticated.
+const ENCRYPTED_VERSION: u8 = 2;
+
+/// The size of the salt used to derive the key file encryption key.
+const SALT_SIZE: usize = 32;
+
+/// The size of the header, containing the version, scrypt parameters, salt, and nonce.
+const HEADER_SIZE: usize = 1 + 1 + 4 + 4 + SALT_SIZE + aead::NONCE_SIZE;
+
+/// The size of an encrypted private key, including its header.
+const ENCRYPTED_KEY_SIZE: usize = HEADER_SIZE + 32 + aead::TAG_SIZE;
+
+/// The size of an encrypted private key, without its header.
+const ENCRYPTED_BODY_SIZE: usize = ENCRYPTED_KEY_SIZE - HEADER_SIZE;
+
+/// Represents the reasons a key file might fail to parse, or decrypt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyFileError {
+    /// The file had no key in it.
+    NoKey,
+    /// The file started with an unknown header.
+    UnknownFormat,
+    /// A line of the header wasn't a field we know.
+    UnknownField(String),
+    /// A field we need was missing, given by its name.
+    MissingField(&'static str),
+    /// A key, or field, was malformed, with a description of what was wrong.
+    Malformed(&'static str),
+    /// The key id didn't match the public key.
+    KeyIdMismatch,
+    /// The key was encrypted with a cipher we don't support.
+    UnsupportedCipher(String),
+    /// The key was encrypted with a kdf we don't support.
+    UnsupportedKdf(String),
+    /// The scrypt parameters would use more memory or time than we allow.
+    KdfTooExpensive(scrypt::Params),
+    /// An encrypted key had an unknown version.
+    UnknownVersion(u8),
+    /// Some armor was malformed.
+    Armor(ArmorError),
+    /// The key is encrypted, but no passphrase was given.
+    PassphraseRequired,
+    /// The passphrase was wrong, or the file was modified.
+    WrongPassphrase,
+    /// The public key in the file doesn't belong to its private key.
+    PublicKeyMismatch,
+}
+
+impl From<ArmorError> for KeyFileError {
+    fn from(err: ArmorError) -> Self {
+        KeyFileError::Armor(err)
+    }
+}
+
+impl fmt::Display for KeyFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyFileError::NoKey => write!(f, "no private key found"),
+            KeyFileError::UnknownFormat => write!(f, "unknown key file format"),
+            KeyFileError::UnknownField(line) => write!(f, "unknown key file field: {}", line),
+            KeyFileError::MissingField(name) => write!(f, "missing key file field: {}", name),
+            KeyFileError::Malformed(what) => write!(f, "{}", what),
+            KeyFileError::KeyIdMismatch => write!(f, "key id doesn't match the public key"),
+            KeyFileError::UnsupportedCipher(cipher) => {
+                write!(f, "unsupported key file cipher: {}", cipher)
+            }
+            KeyFileError::UnsupportedKdf(kdf) => write!(f, "unsupported key file kdf: {}", kdf),
+            KeyFileError::KdfTooExpensive(params) => write!(
+                f,
+                "key file scrypt parameters log_n={} r={} p={} are too expensive",
+                params.log_n, params.r, params.p
+            ),
+            KeyFileError::UnknownVersion(version) => {
+                write!(f, "unknown key file version: {}", version)
+            }
+            KeyFileError::Armor(ArmorError::InvalidBase64) => write!(f, "invalid base64"),
+            KeyFileError::Armor(ArmorError::WrongLabel) => write!(f, "unexpected armor label"),
+            KeyFileError::Armor(ArmorError::Malformed) => write!(f, "malformed armor"),
+            KeyFileError::PassphraseRequired => {
+                write!(
+                    f,
+                    "the private key is encrypted, but no passphrase was given"
+                )
+            }
+            KeyFileError::WrongPassphrase => write!(f, "wrong passphrase, or corrupted key file"),
+            KeyFileError::PublicKeyMismatch => {
+                write!(f, "the key file's public key doesn't match its private key")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeyFileError {}
+
+/// The short id of a public key, as written in key files.
+pub fn key_id(public: &PublicKey) -> String {
+    hex::encode(&public.bytes[..8])
+}
+
+/// Decode a value written with a prefix, followed by hex, or in armor with a given label.
+pub fn decode_prefixed<const N: usize>(
+    prefix: &str,
+    label: Option<&str>,
+    input: &str,
+) -> Result<[u8; N], KeyFileError> {
+    if let (Some(label), Some(_)) = (label, armor::armor_label(input)) {
+        return armor::dearmor(label, input)?
+            .try_into()
+            .map_err(|_| KeyFileError::Malformed("incorrect size"));
+    }
+    let just_hex = input
+        .strip_prefix(prefix)
+        .ok_or(KeyFileError::Malformed("incorrect prefix"))?;
+    if just_hex.len() != 2 * N {
+        return Err(KeyFileError::Malformed("incorrect size"));
+    }
+    let mut bytes = [0; N];
+    hex::decode_to_slice(just_hex, &mut bytes)
+        .map_err(|_| KeyFileError::Malformed("invalid hex"))?;
+    Ok(bytes)
+}
+
+pub fn format_public_key(public: PublicKey) -> String {
+    format!("{}{}", PUBLIC_KEY_PREFIX, hex::encode(public.bytes))
+}
+
+pub fn decode_public_key(input: &str) -> Result<PublicKey, KeyFileError> {
+    Ok(PublicKey {
+        bytes: decode_prefixed(PUBLIC_KEY_PREFIX, Some(PUBLIC_KEY_ARMOR), input)?,
+    })
+}
+
+pub fn format_private_key(private: &PrivateKey) -> String {
+    format!("{}{}", PRIVATE_KEY_PREFIX, hex::encode(private.bytes))
+}
+
+pub fn decode_private_key(input: &str) -> Result<PrivateKey, KeyFileError> {
+    Ok(PrivateKey {
+        bytes: decode_prefixed(PRIVATE_KEY_PREFIX, Some(PRIVATE_KEY_ARMOR), input)?,
+    })
+}
+
+/// Check that scrypt parameters from a key file stay within our limits.
+fn check_kdf_limits(params: scrypt::Params) -> Result<(), KeyFileError> {
+    // N * r * 128 bytes of memory, and 4 * N * r * p operations, counted like libsodium
+    let n = 1u128.checked_shl(params.log_n.into()).unwrap_or(u128::MAX);
+    let memory = n.saturating_mul(128 * u128::from(params.r));
+    let ops = n.saturating_mul(4 * u128::from(params.r) * u128::from(params.p));
+    if params.log_n >= 64 || memory > MAX_MEMORY || ops > MAX_OPS {
+        return Err(KeyFileError::KdfTooExpensive(params));
+    }
+    Ok(())
+}
+
+/// Parse scrypt parameters, written like scrypt log_n=17 r=8 p=1.
+fn parse_kdf(input: &str) -> Result<scrypt::Params, KeyFileError> {
+    let malformed = || KeyFileError::UnsupportedKdf(input.to_string());
+    let mut parts = input.split_whitespace();
+    if parts.next() != Some("scrypt") {
+        return Err(malformed());
+    }
+    let (mut log_n, mut r, mut p) = (None, None, None);
+    for part in parts {
+        let (name, value) = part.split_once('=').ok_or_else(malformed)?;
+        let value: u32 = value.parse().map_err(|_| malformed())?;
+        let slot = match name {
+            "log_n" => &mut log_n,
+            "r" => &mut r,
+            "p" => &mut p,
+            _ => return Err(malformed()),
+        };
+        if slot.replace(value).is_some() {
+            return Err(malformed());
+        }
+    }
+    match (log_n, r, p) {
+        (Some(log_n), Some(r), Some(p)) if log_n < 64 => Ok(scrypt::Params {
+            log_n: log_n as u8,
+            r,
+            p,
+        }),
+        _ => Err(malformed()),
+    }
+}
+
+/// Represents a private key encrypted under a passphrase.
+///
+/// This is laid out as version || log_n || r || p || salt || nonce || ciphertext || tag,
+/// with everything before the ciphertext authenticated as associated data.
+#[derive(Clone, PartialEq)]
+pub struct EncryptedKey {
+    bytes: [u8; ENCRYPTED_KEY_SIZE],
+}
+
+impl fmt::Debug for EncryptedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EncryptedKey({})", hex::encode(self.bytes))
+    }
+}
+
+impl EncryptedKey {
+    fn new(version: u8, params: scrypt::Params, salt: &[u8], nonce: &[u8], body: &[u8]) -> Self {
+        let mut bytes = [0; ENCRYPTED_KEY_SIZE];
+        bytes[0] = version;
+        bytes[1] = params.log_n;
+        bytes[2..6].copy_from_slice(&params.r.to_be_bytes());
+        bytes[6..10].copy_from_slice(&params.p.to_be_bytes());
+        bytes[10..10 + SALT_SIZE].copy_from_slice(salt);
+        bytes[10 + SALT_SIZE..HEADER_SIZE].copy_from_slice(nonce);
+        bytes[HEADER_SIZE..].copy_from_slice(body);
+        EncryptedKey { bytes }
+    }
+
+    fn params(&self) -> scrypt::Params {
+        scrypt::Params {
+            log_n: self.bytes[1],
+            r: u32::from_be_bytes(self.bytes[2..6].try_into().unwrap()),
+            p: u32::from_be_bytes(self.bytes[6..10].try_into().unwrap()),
+        }
+    }
+
+    fn salt(&self) -> &[u8] {
+        &self.bytes[10..10 + SALT_SIZE]
+    }
+
+    fn nonce(&self) -> [u8; aead::NONCE_SIZE] {
+        self.bytes[10 + SALT_SIZE..HEADER_SIZE].try_into().unwrap()
+    }
+
+    /// Check the version and parameters, before we spend time on a passphrase.
+    fn check(&self, version: u8) -> Result<(), KeyFileError> {
+        if self.bytes[0] != version {
+            return Err(KeyFileError::UnknownVersion(self.bytes[0]));
+        }
+        check_kdf_limits(self.params())
+    }
+
+    /// Derive the encryption key from a passphrase.
+    fn key(
+        passphrase: &str,
+        salt: &[u8],
+        params: scrypt::Params,
+    ) -> Result<[u8; aead::KEY_SIZE], KeyFileError> {
+        let mut key = [0; aead::KEY_SIZE];
+        scrypt::scrypt(passphrase.as_bytes(), salt, params, &mut key)
+            .map_err(|_| KeyFileError::UnsupportedKdf(format!("{:?}", params)))?;
+        Ok(key)
+    }
+
+    /// Encrypt a private key, authenticating some extra data along with the header.
+    fn seal<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        version: u8,
+        params: scrypt::Params,
+        private: &PrivateKey,
+        passphrase: &str,
+        extra: &[u8],
+    ) -> Result<Self, KeyFileError> {
+        check_kdf_limits(params)?;
+        let mut salt = [0; SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+        let mut nonce = [0; aead::NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+        let key = Self::key(passphrase, &salt, params)?;
+        let mut out = Self::new(version, params, &salt, &nonce, &[0; ENCRYPTED_BODY_SIZE]);
+
+        let (header, body) = out.bytes.split_at_mut(HEADER_SIZE);
+        body[..32].copy_from_slice(&private.bytes);
+        let aad = [&header[..], extra].concat();
+        let tag = aead::encrypt(&key, &nonce, &aad, &mut body[..32]);
+        body[32..].copy_from_slice(&tag);
+        Ok(out)
+    }
+
+    /// Decrypt the private key, checking the extra data it was sealed with.
+    fn open(&self, passphrase: &str, extra: &[u8]) -> Result<PrivateKey, KeyFileError> {
+        let key = Self::key(passphrase, self.salt(), self.params())?;
+        let (header, body) = self.bytes.split_at(HEADER_SIZE);
+        let mut private = PrivateKey { bytes: [0; 32] };
+        private.bytes.copy_from_slice(&body[..32]);
+        let tag: &[u8; aead::TAG_SIZE] = body[32..].try_into().unwrap();
+        let aad = [header, extra].concat();
+        if !aead::decrypt(&key, &self.nonce(), &aad, &mut private.bytes, tag) {
+            return Err(KeyFileError::WrongPassphrase);
+        }
+        Ok(private)
+    }
+}
+
+/// Represents the private key held by a key file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeySecret {
+    Plain(PrivateKey),
+    Encrypted(EncryptedKey),
+}
+
+/// Represents a key file in the versioned format.
+///
+/// The fields are only readable, since an encrypted key is bound to their values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyFile {
+    created: Option<u64>,
+    public: PublicKey,
+    comment: Option<String>,
+    secret: KeySecret,
+}
+
+/// Append an optional field to the associated data of an encrypted key.
+fn push_field(out: &mut Vec<u8>, field: Option<&[u8]>) {
+    match field {
+        None => out.push(0),
+        Some(field) => {
+            out.push(1);
+            out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            out.extend_from_slice(field);
+        }
+    }
+}
+
+impl KeyFile {
+    /// Create a key file holding an unencrypted private key.
+    ///
+    /// The comment can't contain line breaks, since it needs to fit in a field.
+    pub fn new(
+        private: PrivateKey,
+        created: Option<u64>,
+        comment: Option<String>,
+    ) -> Result<Self, KeyFileError> {
+        if comment
+            .as_deref()
+            .is_some_and(|c| c.contains(&['\n', '\r'][..]))
+        {
+            return Err(KeyFileError::Malformed(
+                "comments must fit on a single line",
+            ));
+        }
+        Ok(KeyFile {
+            created,
+            public: private.public_key(),
+            comment,
+            secret: KeySecret::Plain(private),
+        })
+    }
+
+    /// Create a key file holding a private key encrypted under a passphrase.
+    pub fn encrypted<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        private: PrivateKey,
+        created: Option<u64>,
+        comment: Option<String>,
+        passphrase: &str,
+        params: scrypt::Params,
+    ) -> Result<Self, KeyFileError> {
+        let mut out = Self::new(private.clone(), created, comment)?;
+        let extra = out.associated_data();
+        out.secret = KeySecret::Encrypted(EncryptedKey::seal(
+            rng,
+            ENCRYPTED_VERSION,
+            params,
+            &private,
+            passphrase,
+            &extra,
+        )?);
+        Ok(out)
+    }
+
+    /// When the key was created, in seconds since the Unix epoch.
+    pub fn created(&self) -> Option<u64> {
+        self.created
+    }
+
+    pub fn public(&self) -> PublicKey {
+        self.public
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self.secret, KeySecret::Encrypted(_))
+    }
+
+    /// The fields an encrypted key authenticates, on top of its own header.
+    fn associated_data(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(KEY_FILE_HEADER.as_bytes());
+        out.extend_from_slice(&self.public.bytes);
+        push_field(
+            &mut out,
+            self.created.map(u64::to_be_bytes).as_ref().map(|c| &c[..]),
+        );
+        push_field(&mut out, self.comment.as_ref().map(|c| c.as_bytes()));
+        out
+    }
+
+    /// Format this key file, with the private key as armor, or as hex.
+    pub fn format(&self, armored: bool) -> String {
+        let mut lines = vec![KEY_FILE_HEADER.to_string()];
+        if let Some(created) = self.created {
+            lines.push(format!("{}{}", CREATED_FIELD, created));
+        }
+        lines.push(format!("{}{}", KEY_ID_FIELD, key_id(&self.public)));
+        lines.push(format!(
+            "{}{}",
+            PUBLIC_KEY_FIELD,
+            format_public_key(self.public)
+        ));
+        if let Some(comment) = &self.comment {
+            lines.push(format!("{}{}", COMMENT_FIELD, comment));
+        }
+        let body = match &self.secret {
+            KeySecret::Plain(private) if armored => armor::armor(PRIVATE_KEY_ARMOR, &private.bytes),
+            KeySecret::Plain(private) => format_private_key(private),
+            KeySecret::Encrypted(encrypted) => {
+                let params = encrypted.params();
+                lines.push(format!("{}{}", CIPHER_FIELD, KEY_FILE_CIPHER));
+                lines.push(format!(
+                    "{}scrypt log_n={} r={} p={}",
+                    KDF_FIELD, params.log_n, params.r, params.p
+                ));
+                lines.push(format!("{}{}", SALT_FIELD, hex::encode(encrypted.salt())));
+                lines.push(format!("{}{}", NONCE_FIELD, hex::encode(encrypted.nonce())));
+                let body = &encrypted.bytes[HEADER_SIZE..];
+                if armored {
+                    armor::armor(ENCRYPTED_PRIVATE_KEY_ARMOR, body)
+                } else {
+                    format!("{}{}", ENCRYPTED_PRIVATE_KEY_PREFIX, hex::encode(body))
+                }
+            }
+        };
+        lines.push(String::new());
+        lines.push(body);
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Parse a key file in the versioned format, without decrypting its key.
+    pub fn parse(contents: &str) -> Result<Self, KeyFileError> {
+        let mut lines = contents
+            .trim_start()
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.starts_with('#'));
+        if lines.next() != Some(KEY_FILE_HEADER) {
+            return Err(KeyFileError::UnknownFormat);
+        }
+        let mut fields: Vec<(&str, &str)> = Vec::new();
+        for line in lines.by_ref().take_while(|line| !line.is_empty()) {
+            let field = FIELDS
+                .iter()
+                .find(|field| line.starts_with(*field))
+                .ok_or_else(|| KeyFileError::UnknownField(line.to_string()))?;
+            if fields.iter().any(|(name, _)| name == field) {
+                return Err(KeyFileError::Malformed("repeated key file field"));
+            }
+            fields.push((field, &line[field.len()..]));
+        }
+        let get = |name: &str| {
+            fields
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, value)| *value)
+        };
+        let field = |name: &'static str| {
+            get(name).ok_or(KeyFileError::MissingField(name.trim_end_matches(": ")))
+        };
+        // The key is either a single line, or armor spread over several
+        let body = lines.collect::<Vec<_>>().join("\n");
+        let body = body.trim();
+
+        let public = decode_public_key(field(PUBLIC_KEY_FIELD)?)?;
+        if get(KEY_ID_FIELD).is_some_and(|id| id != key_id(&public)) {
+            return Err(KeyFileError::KeyIdMismatch);
+        }
+        let created = match get(CREATED_FIELD) {
+            Some(created) => Some(
+                created
+                    .parse()
+                    .map_err(|_| KeyFileError::Malformed("malformed time"))?,
+            ),
+            None => None,
+        };
+        let secret = match get(CIPHER_FIELD) {
+            None => KeySecret::Plain(decode_private_key(body)?),
+            Some(KEY_FILE_CIPHER) => {
+                let params = parse_kdf(field(KDF_FIELD)?)?;
+                check_kdf_limits(params)?;
+                let salt: [u8; SALT_SIZE] = decode_prefixed("", None, field(SALT_FIELD)?)
+                    .map_err(|_| KeyFileError::Malformed("malformed salt"))?;
+                let nonce: [u8; aead::NONCE_SIZE] = decode_prefixed("", None, field(NONCE_FIELD)?)
+                    .map_err(|_| KeyFileError::Malformed("malformed nonce"))?;
+                let body: [u8; ENCRYPTED_BODY_SIZE] = decode_prefixed(
+                    ENCRYPTED_PRIVATE_KEY_PREFIX,
+                    Some(ENCRYPTED_PRIVATE_KEY_ARMOR),
+                    body,
+                )?;
+                KeySecret::Encrypted(EncryptedKey::new(
+                    ENCRYPTED_VERSION,
+                    params,
+                    &salt,
+                    &nonce,
+                    &body,
+                ))
+            }
+            Some(cipher) => return Err(KeyFileError::UnsupportedCipher(cipher.to_string())),
+        };
+        Ok(KeyFile {
+            created,
+            public,
+            comment: get(COMMENT_FIELD).map(|comment| comment.to_string()),
+            secret,
+        })
+    }
+
+    /// The private key in this file, decrypting it if necessary.
+    ///
+    /// This fails with `PassphraseRequired` if the key is encrypted, and no passphrase
+    /// is given, so that callers only need to ask for one when it's needed.
+    pub fn private_key(&self, passphrase: Option<&str>) -> Result<PrivateKey, KeyFileError> {
+        let private = match &self.secret {
+            KeySecret::Plain(private) => private.clone(),
+            KeySecret::Encrypted(encrypted) => {
+                let passphrase = passphrase.ok_or(KeyFileError::PassphraseRequired)?;
+                encrypted.open(passphrase, &self.associated_data())?
+            }
+        };
+        if private.public_key() != self.public {
+            return Err(KeyFileError::PublicKeyMismatch);
+        }
+        Ok(private)
+    }
+}
+
+/// The key in a file of the original format, after removing comments.
+fn legacy_key(contents: &str) -> Result<String, KeyFileError> {
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    // The key is either a single line, or armor spread over several
+    let key = lines.join("\n").trim().to_string();
+    if key.is_empty() {
+        return Err(KeyFileError::NoKey);
+    }
+    Ok(key)
+}
+
+/// Decode the key in a file of the original format, if it's encrypted.
+fn legacy_encrypted_key(key: &str) -> Result<Option<EncryptedKey>, KeyFileError> {
+    if !key.starts_with(ENCRYPTED_PRIVATE_KEY_PREFIX)
+        && armor::armor_label(key) != Some(ENCRYPTED_PRIVATE_KEY_ARMOR)
+    {
+        return Ok(None);
+    }
+    let bytes = decode_prefixed(
+        ENCRYPTED_PRIVATE_KEY_PREFIX,
+        Some(ENCRYPTED_PRIVATE_KEY_ARMOR),
+        key,
+    )?;
+    let encrypted = EncryptedKey { bytes };
+    // Check the header before a passphrase is asked for, which would be wasted
+    encrypted.check(LEGACY_ENCRYPTED_VERSION)?;
+    Ok(Some(encrypted))
+}
+
+/// Parse the contents of a key file, in either format, decrypting the key if necessary.
+///
+/// This fails with `PassphraseRequired` if the key is encrypted, and no passphrase
+/// is given, after checking everything that can be checked without it.
+pub fn parse_private_key(
+    contents: &str,
+    passphrase: Option<&str>,
+) -> Result<PrivateKey, KeyFileError> {
+    if contents.trim_start().starts_with(KEY_FILE_HEADER) {
+        return KeyFile::parse(contents)?.private_key(passphrase);
+    }
+    let key = legacy_key(contents)?;
+    match legacy_encrypted_key(&key)? {
+        Some(encrypted) => {
+            let passphrase = passphrase.ok_or(KeyFileError::PassphraseRequired)?;
+            encrypted.open(passphrase, &[])
+        }
+        None => decode_private_key(&key),
+    }
+}
+
+/// Find the public key in the contents of a key file, only decrypting it if we have to.
+pub fn parse_public_key(
+    contents: &str,
+    passphrase: Option<&str>,
+) -> Result<PublicKey, KeyFileError> {
+    if contents.trim_start().starts_with(KEY_FILE_HEADER) {
+        return Ok(KeyFile::parse(contents)?.public);
+    }
+    for line in contents.lines() {
+        if let Some(public) = line.strip_prefix(LEGACY_PUBLIC_KEY_COMMENT) {
+            return decode_public_key(public.trim());
+        }
+    }
+    Ok(parse_private_key(contents, passphrase)?.public_key())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    /// Cheap scrypt parameters, so the tests run quickly.
+    const TEST_SCRYPT: scrypt::Params = scrypt::Params {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    fn private() -> PrivateKey {
+        PrivateKey { bytes: [7; 32] }
+    }
+
+    fn encrypted(comment: Option<&str>) -> KeyFile {
+        KeyFile::encrypted(
+            &mut OsRng,
+            private(),
+            Some(1_700_000_000),
+            comment.map(String::from),
+            "hunter2",
+            TEST_SCRYPT,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plain_round_trip() {
+        for armored in [false, true] {
+            let file =
+                KeyFile::new(private(), Some(1_700_000_000), Some("a comment".into())).unwrap();
+            let parsed = KeyFile::parse(&file.format(armored)).unwrap();
+            assert_eq!(parsed, file);
+            assert_eq!(parsed.created(), Some(1_700_000_000));
+            assert_eq!(parsed.comment(), Some("a comment"));
+            assert_eq!(parsed.public(), private().public_key());
+            assert!(!parsed.is_encrypted());
+            assert_eq!(parsed.private_key(None).unwrap(), private());
+        }
+    }
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        for armored in [false, true] {
+            let file = encrypted(Some("a comment"));
+            let formatted = file.format(armored);
+            let parsed = KeyFile::parse(&formatted).unwrap();
+            assert_eq!(parsed, file);
+            assert!(parsed.is_encrypted());
+            assert_eq!(
+                parsed.private_key(None),
+                Err(KeyFileError::PassphraseRequired)
+            );
+            assert_eq!(
+                parsed.private_key(Some("hunter3")),
+                Err(KeyFileError::WrongPassphrase)
+            );
+            assert_eq!(parsed.private_key(Some("hunter2")).unwrap(), private());
+            assert_eq!(
+                parse_private_key(&formatted, Some("hunter2")).unwrap(),
+                private()
+            );
+            assert_eq!(
+                parse_public_key(&formatted, None).unwrap(),
+                private().public_key()
+            );
+        }
+    }
+
+    #[test]
+    fn test_encrypted_fields_are_authenticated() {
+        let formatted = encrypted(Some("release key")).format(false);
+        let swapped = [
+            formatted.replace("comment: release key", "comment: test key"),
+            formatted.replace("\ncomment: release key", ""),
+            formatted.replace("created: 1700000000", "created: 1600000000"),
+            formatted.replace("created: 1700000000\n", ""),
+        ];
+        for contents in &swapped {
+            assert_ne!(contents, &formatted);
+            let parsed = KeyFile::parse(contents).unwrap();
+            assert_eq!(
+                parsed.private_key(Some("hunter2")),
+                Err(KeyFileError::WrongPassphrase)
+            );
+        }
+    }
+
+    #[test]
+    fn test_legacy_format() {
+        let public = private().public_key();
+        let plain = format!(
+            "# Public Key: {}\n{}\n",
+            format_public_key(public),
+            format_private_key(&private())
+        );
+        assert_eq!(parse_private_key(&plain, None).unwrap(), private());
+        assert_eq!(parse_public_key(&plain, None).unwrap(), public);
+        let armored = armor::armor(PRIVATE_KEY_ARMOR, &private().bytes);
+        assert_eq!(parse_private_key(&armored, None).unwrap(), private());
+        assert_eq!(parse_public_key(&armored, None).unwrap(), public);
+
+        let encrypted =
+            EncryptedKey::seal(&mut OsRng, 1, TEST_SCRYPT, &private(), "hunter2", &[]).unwrap();
+        let contents = format!(
+            "{}{}",
+            ENCRYPTED_PRIVATE_KEY_PREFIX,
+            hex::encode(encrypted.bytes)
+        );
+        assert_eq!(
+            parse_private_key(&contents, None),
+            Err(KeyFileError::PassphraseRequired)
+        );
+        assert_eq!(
+            parse_private_key(&contents, Some("hunter2")).unwrap(),
+            private()
+        );
+        assert_eq!(
+            parse_public_key(&contents, Some("hunter2")).unwrap(),
+            public
+        );
+    }
+
+    #[test]
+    fn test_expensive_kdf_is_rejected_before_passphrase() {
+        let formatted = encrypted(None).format(false);
+        let expensive = formatted.replace("log_n=4", "log_n=40");
+        assert!(matches!(
+            KeyFile::parse(&expensive),
+            Err(KeyFileError::KdfTooExpensive(_))
+        ));
+
+        let mut legacy =
+            EncryptedKey::seal(&mut OsRng, 1, TEST_SCRYPT, &private(), "hunter2", &[]).unwrap();
+        legacy.bytes[1] = 40;
+        let contents = format!(
+            "{}{}",
+            ENCRYPTED_PRIVATE_KEY_PREFIX,
+            hex::encode(legacy.bytes)
+        );
+        assert!(matches!(
+            parse_private_key(&contents, None),
+            Err(KeyFileError::KdfTooExpensive(_))
+        ));
+        legacy.bytes[0] = 9;
+        let contents = format!(
+            "{}{}",
+            ENCRYPTED_PRIVATE_KEY_PREFIX,
+            hex::encode(legacy.bytes)
+        );
+        assert_eq!(
+            parse_private_key(&contents, None),
+            Err(KeyFileError::UnknownVersion(9))
+        );
+    }
+
+    #[test]
+    fn test_malformed_key_files() {
+        let formatted = KeyFile::new(private(), Some(1), None)
+            .unwrap()
+            .format(false);
+        let other = PrivateKey { bytes: [8; 32] };
+        let cases = [
+            ("", KeyFileError::NoKey),
+            (
+                &formatted.replace("created: ", "made: "),
+                KeyFileError::UnknownField("made: 1".into()),
+            ),
+            (
+                &formatted.replace("created: 1", "created: 1\ncreated: 2"),
+                KeyFileError::Malformed("repeated key file field"),
+            ),
+            (
+                &formatted.replace("created: 1", "created: yesterday"),
+                KeyFileError::Malformed("malformed time"),
+            ),
+            (
+                &formatted.replace(
+                    &format!("{}{}", KEY_ID_FIELD, key_id(&private().public_key())),
+                    "key id: 0000000000000000",
+                ),
+                KeyFileError::KeyIdMismatch,
+            ),
+            (
+                &formatted.replace(&hex::encode(private().bytes), &hex::encode(other.bytes)),
+                KeyFileError::PublicKeyMismatch,
+            ),
+            (
+                &formatted.replace(PRIVATE_KEY_PREFIX, "秘密鍵"),
+                KeyFileError::Malformed("incorrect prefix"),
+            ),
+            (
+                &formatted.replace(&hex::encode(private().bytes), "abcd"),
+                KeyFileError::Malformed("incorrect size"),
+            ),
+        ];
+        for (contents, expected) in cases.iter() {
+            assert_eq!(&parse_private_key(contents, None).unwrap_err(), expected);
+        }
+
+        assert_eq!(
+            KeyFile::parse("eddo key file v3\n"),
+            Err(KeyFileError::UnknownFormat)
+        );
+        let lines: Vec<&str> = formatted.lines().collect();
+        let without_public = lines
+            .iter()
+            .filter(|line| !line.starts_with(PUBLIC_KEY_FIELD))
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(
+            KeyFile::parse(&without_public),
+            Err(KeyFileError::MissingField("public key"))
+        );
+
+        let encrypted = encrypted(None).format(false);
+        let cases = [
+            (
+                encrypted.replace(KEY_FILE_CIPHER, "aes-256-gcm"),
+                KeyFileError::UnsupportedCipher("aes-256-gcm".into()),
+            ),
+            (
+                encrypted.replace("scrypt log_n=4 r=8 p=1", "argon2id"),
+                KeyFileError::UnsupportedKdf("argon2id".into()),
+            ),
+            (
+                encrypted.replace("r=8 p=1", "r=8 r=8 p=1"),
+                KeyFileError::UnsupportedKdf("scrypt log_n=4 r=8 r=8 p=1".into()),
+            ),
+            (
+                encrypted.replace(SALT_FIELD, "salt: 00"),
+                KeyFileError::Malformed("malformed salt"),
+            ),
+            (
+                encrypted.replace(NONCE_FIELD, "nonce: zz"),
+                KeyFileError::Malformed("malformed nonce"),
+            ),
+        ];
+        for (contents, expected) in cases.iter() {
+            assert_eq!(&KeyFile::parse(contents).unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn test_comments_must_be_single_line() {
+        assert_eq!(
+            KeyFile::new(private(), None, Some("two\nlines".into())),
+            Err(KeyFileError::Malformed(
+                "comments must fit on a single line"
+            ))
+        );
+    }
+}
@@ -1,11 +1,69 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "pure", forbid(unsafe_code))]
+#[cfg(feature = "alloc")]
+extern crate alloc;
 extern crate hex;
 extern crate subtle;
 
+pub mod aead;
 mod arch;
+#[cfg(feature = "alloc")]
+pub mod armor;
+#[cfg(feature = "alloc")]
+pub mod ceremony;
 mod curve25519;
-mod sha512;
+pub mod envelope;
+pub mod heartbeat;
+pub mod kdf;
+#[cfg(feature = "alloc")]
+pub mod keyfile;
+#[cfg(feature = "std")]
+pub mod metered;
+#[cfg(feature = "alloc")]
+pub mod policy;
+#[cfg(feature = "alloc")]
+pub mod scrypt;
+#[cfg(feature = "alloc")]
+pub mod selftest;
+pub mod sha256;
+#[cfg(feature = "sha3")]
+pub mod sha3;
+pub mod sha512;
+pub mod transparency;
+#[cfg(feature = "alloc")]
+pub mod vectors;
 
+/// Low level building blocks, for implementing other protocols over our curve.
+///
+/// These are easy to misuse, and only available with the `hazmat` feature.
+#[cfg(feature = "hazmat")]
+pub mod hazmat {
+    pub use crate::curve25519::{
+        commitment, dleq,
+        field::Z25519,
+        point::{AffinePoint, CompressedEdwardsY, EdwardsPoint},
+        scalar::Scalar,
+    };
+}
+
+#[cfg(feature = "alloc")]
+pub use curve25519::ecies;
+pub use curve25519::prehash;
+#[cfg(feature = "dangerous-key-recovery")]
+pub use curve25519::recovery;
+#[cfg(feature = "std")]
+pub use curve25519::verify_batch_chunked;
+#[cfg(feature = "secure-memory")]
+pub use curve25519::LockedPrivateKey;
+pub use curve25519::{
+    gen_keypair, AdaptorPoint, AdaptorSecret, BlindedPrivateKey, DesignatedSignature,
+    ExpandedPrivateKey, PassphraseError, PassphraseParams, PreSignature, PrivateKey, PublicKey,
+    Signature, SignatureHash, DESIGNATED_SIGNATURE_SIZE, DIGEST_SIZE, PRIVATE_KEY_SIZE,
+    PUBLIC_KEY_SIZE, SIGNATURE_SIZE,
+};
+#[cfg(feature = "alloc")]
 pub use curve25519::{
-    gen_keypair, PrivateKey, PublicKey, Signature, PRIVATE_KEY_SIZE, PUBLIC_KEY_SIZE,
-    SIGNATURE_SIZE,
+    verify_batch, BlindChallenge, BlindCommitment, BlindResponse, BlindSignatureRequest,
+    BlindSigner, EphemeralKey, IdentityKey, PippengerParams, SaltedSignature,
+    SALTED_SIGNATURE_SIZE, SALT_SIZE,
 };
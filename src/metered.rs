@@ -0,0 +1,128 @@
+//! This module defines a signer which limits how many signatures each client gets.
+//!
+//! A signer holding a key on behalf of several clients can use this to give each
+//! of them a quota of signatures per window of time, so that a single client can't
+//! use up the key, e.g. when its own credentials leak.
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{PrivateKey, PublicKey, Signature};
+
+/// Represents the reasons a metered signer might refuse to sign.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MeterError {
+    /// The client used up its quota, and can sign again after this long.
+    RateLimited(Duration),
+}
+
+/// Represents a limit of signatures per window of time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    pub limit: u32,
+    pub window: Duration,
+}
+
+/// Represents a private key, which signs for each client up to a quota.
+///
+/// Clients are identified by 32 bytes, e.g. their own public keys.
+#[derive(Debug)]
+pub struct MeteredSigner {
+    private: PrivateKey,
+    quota: Option<Quota>,
+    /// For each client, the start of their current window, and the signatures made in it
+    windows: Mutex<HashMap<[u8; 32], (Instant, u32)>>,
+}
+
+impl MeteredSigner {
+    /// Create a signer, giving each client a quota, or no limit at all.
+    pub fn new(private: PrivateKey, quota: Option<Quota>) -> Self {
+        MeteredSigner {
+            private,
+            quota,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.private.public_key()
+    }
+
+    /// The private key behind this signer.
+    pub fn private_key(&self) -> &PrivateKey {
+        &self.private
+    }
+
+    /// Check whether or not a client can make another signature, counting it if so.
+    fn charge(&self, client: &[u8; 32], now: Instant) -> Result<(), MeterError> {
+        let quota = match self.quota {
+            None => return Ok(()),
+            Some(quota) => quota,
+        };
+        let mut windows = self.windows.lock().unwrap();
+        let (start, count) = windows.entry(*client).or_insert((now, 0));
+        let elapsed = now.duration_since(*start);
+        if elapsed >= quota.window {
+            *start = now;
+            *count = 0;
+        } else if *count >= quota.limit {
+            return Err(MeterError::RateLimited(quota.window - elapsed));
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    /// Sign a message for a client, if it hasn't used up its quota.
+    pub fn sign(&self, client: &[u8; 32], message: &[u8]) -> Result<Signature, MeterError> {
+        self.charge(client, Instant::now())?;
+        Ok(self.private.sign(message))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signer(limit: u32, window: Duration) -> MeteredSigner {
+        MeteredSigner::new(PrivateKey { bytes: [1; 32] }, Some(Quota { limit, window }))
+    }
+
+    #[test]
+    fn test_quota_is_per_client() {
+        let signer = signer(2, Duration::from_secs(60));
+        let public = signer.public_key();
+        let sig = signer.sign(&[1; 32], b"a").unwrap();
+        assert!(public.verify(b"a", sig));
+        assert!(signer.sign(&[1; 32], b"b").is_ok());
+        assert!(matches!(
+            signer.sign(&[1; 32], b"c"),
+            Err(MeterError::RateLimited(_))
+        ));
+        assert!(signer.sign(&[2; 32], b"c").is_ok());
+    }
+
+    #[test]
+    fn test_quota_resets_after_window() {
+        let signer = signer(1, Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(signer.charge(&[1; 32], start), Ok(()));
+        assert_eq!(
+            signer.charge(&[1; 32], start + Duration::from_secs(20)),
+            Err(MeterError::RateLimited(Duration::from_secs(40)))
+        );
+        assert_eq!(
+            signer.charge(&[1; 32], start + Duration::from_secs(60)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let signer = MeteredSigner::new(PrivateKey { bytes: [1; 32] }, None);
+        for i in 0..100u8 {
+            assert!(signer.sign(&[0; 32], &[i]).is_ok());
+        }
+    }
+}
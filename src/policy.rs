@@ -0,0 +1,126 @@
+//! This module defines k-of-n signature policies.
+//!
+//! A policy lists n public keys, and accepts a message once at least k of those
+//! keys have signed it. Signatures can be given in any order, and extra signatures,
+//! from unknown keys or repeated keys, are simply ignored.
+
+use alloc::vec::Vec;
+
+use crate::{PublicKey, Signature};
+
+/// Represents the reasons a policy might be invalid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PolicyError {
+    /// The threshold was 0, which would accept any message.
+    ZeroThreshold,
+    /// The threshold was larger than the number of keys, and could never be met.
+    ThresholdTooLarge,
+    /// The key at this index appeared earlier in the list.
+    DuplicateKey(usize),
+}
+
+/// Represents a policy accepting messages signed by k out of n keys.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    keys: Vec<PublicKey>,
+    threshold: usize,
+}
+
+impl Policy {
+    /// Create a new policy, requiring `threshold` signatures from distinct keys.
+    pub fn new(keys: Vec<PublicKey>, threshold: usize) -> Result<Self, PolicyError> {
+        if threshold == 0 {
+            return Err(PolicyError::ZeroThreshold);
+        }
+        if threshold > keys.len() {
+            return Err(PolicyError::ThresholdTooLarge);
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].contains(key) {
+                return Err(PolicyError::DuplicateKey(i));
+            }
+        }
+        Ok(Policy { keys, threshold })
+    }
+
+    /// The keys which can sign for this policy.
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.keys
+    }
+
+    /// The number of distinct keys which need to sign.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Count how many distinct keys of this policy signed a message.
+    pub fn count_signers(&self, message: &[u8], signatures: &[Signature]) -> usize {
+        self.keys
+            .iter()
+            .filter(|key| signatures.iter().any(|sig| key.verify(message, *sig)))
+            .count()
+    }
+
+    /// Check that at least `threshold` distinct keys signed a message.
+    pub fn verify(&self, message: &[u8], signatures: &[Signature]) -> bool {
+        self.count_signers(message, signatures) >= self.threshold
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PrivateKey;
+
+    fn keys() -> Vec<PrivateKey> {
+        (1..=3).map(|i| PrivateKey { bytes: [i; 32] }).collect()
+    }
+
+    fn policy(threshold: usize) -> Policy {
+        Policy::new(keys().iter().map(|k| k.public_key()).collect(), threshold).unwrap()
+    }
+
+    #[test]
+    fn test_threshold_is_enforced() {
+        let privates = keys();
+        let sigs: Vec<_> = privates.iter().map(|k| k.sign(b"release")).collect();
+        let policy = policy(2);
+        assert!(!policy.verify(b"release", &sigs[..1]));
+        assert!(policy.verify(b"release", &sigs[..2]));
+        assert!(policy.verify(b"release", &sigs));
+        assert!(!policy.verify(b"other", &sigs));
+    }
+
+    #[test]
+    fn test_repeated_signatures_count_once() {
+        let sig = keys()[0].sign(b"release");
+        assert!(!policy(2).verify(b"release", &[sig, sig]));
+    }
+
+    #[test]
+    fn test_unknown_signers_are_ignored() {
+        let privates = keys();
+        let stranger = PrivateKey { bytes: [9; 32] };
+        let sigs = [stranger.sign(b"release"), privates[2].sign(b"release")];
+        assert_eq!(policy(2).count_signers(b"release", &sigs), 1);
+    }
+
+    #[test]
+    fn test_invalid_policies() {
+        let publics: Vec<_> = keys().iter().map(|k| k.public_key()).collect();
+        assert_eq!(
+            Policy::new(publics.clone(), 0).unwrap_err(),
+            PolicyError::ZeroThreshold
+        );
+        assert_eq!(
+            Policy::new(publics.clone(), 4).unwrap_err(),
+            PolicyError::ThresholdTooLarge
+        );
+        let mut duplicated = publics;
+        duplicated.push(duplicated[1]);
+        assert_eq!(
+            Policy::new(duplicated, 2).unwrap_err(),
+            PolicyError::DuplicateKey(3)
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! This module implements the scrypt key derivation function, from RFC 7914:
+//! https://datatracker.ietf.org/doc/html/rfc7914
+//!
+//! Unlike PBKDF2, scrypt needs a large amount of memory, which makes guessing
+//! passphrases on dedicated hardware much more expensive. This is the function
+//! minisign uses to encrypt its secret keys, and `Params::from_limits` picks
+//! parameters the same way it does, so that derived keys match byte for byte.
+use alloc::{vec, vec::Vec};
+use core::convert::TryInto;
+
+use crate::sha256;
+
+/// The number of bytes in a block of SHA-256.
+const SHA256_BLOCK_SIZE: usize = 64;
+
+/// The number of 32 bit words in a Salsa20 block.
+const SALSA_WORDS: usize = 16;
+
+/// The parameters controlling how expensive scrypt is.
+///
+/// This uses `N * r * 128` bytes of memory, and `p` times that much work.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Params {
+    /// The base 2 logarithm of the CPU/memory cost, N.
+    pub log_n: u8,
+    /// The block size, r.
+    pub r: u32,
+    /// The parallelization parameter, p.
+    pub p: u32,
+}
+
+impl Params {
+    /// Pick parameters from limits on operations and memory, in bytes.
+    ///
+    /// This follows `pickparams` in libsodium, used by minisign, whose defaults
+    /// are limits of 2^25 operations, and 2^30 bytes of memory.
+    pub fn from_limits(ops_limit: u64, mem_limit: u64) -> Params {
+        let ops_limit = ops_limit.max(32768);
+        let r = 8u32;
+        // The smallest log_n such that 2^log_n > max_n / 2, capped at 63.
+        let pick_log_n = |max_n: u64| (1..63).find(|&n| (1u64 << n) > max_n / 2).unwrap_or(63);
+        if ops_limit < mem_limit / 32 {
+            let log_n = pick_log_n(ops_limit / (u64::from(r) * 4));
+            Params { log_n, r, p: 1 }
+        } else {
+            let log_n = pick_log_n(mem_limit / (u64::from(r) * 128));
+            let max_rp = ((ops_limit / 4) >> log_n).min(0x3fff_ffff) as u32;
+            Params {
+                log_n,
+                r,
+                p: max_rp / r,
+            }
+        }
+    }
+}
+
+/// Represents the reasons scrypt might refuse to run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScryptError {
+    /// The parameters are outside of the ranges allowed by Section 2.
+    InvalidParams,
+    /// The requested output was longer than PBKDF2 can produce.
+    OutputTooLong,
+}
+
+/// Calculate HMAC-SHA256, as in RFC 2104, over several parts.
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; sha256::HASH_SIZE] {
+    let mut padded_key = [0u8; SHA256_BLOCK_SIZE];
+    if key.len() > SHA256_BLOCK_SIZE {
+        padded_key[..sha256::HASH_SIZE].copy_from_slice(&sha256::hash(key));
+    } else {
+        padded_key[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = sha256::Hasher::new();
+    inner.update(&padded_key.map(|x| x ^ 0x36));
+    for part in parts {
+        inner.update(part);
+    }
+    let mut outer = sha256::Hasher::new();
+    outer.update(&padded_key.map(|x| x ^ 0x5c));
+    outer.update(&inner.finalize());
+    outer.finalize()
+}
+
+/// PBKDF2-HMAC-SHA256, with a single iteration, which is all scrypt needs.
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], out: &mut [u8]) {
+    for (i, chunk) in out.chunks_mut(sha256::HASH_SIZE).enumerate() {
+        let t = hmac_sha256(password, &[salt, &(i as u32 + 1).to_be_bytes()]);
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+}
+
+/// The Salsa20/8 core, as described in Section 3.
+fn salsa20_8(b: &mut [u32; SALSA_WORDS]) {
+    let mut x = *b;
+    macro_rules! quarter {
+        ($a:expr, $b:expr, $c:expr, $d:expr) => {
+            x[$b] ^= x[$a].wrapping_add(x[$d]).rotate_left(7);
+            x[$c] ^= x[$b].wrapping_add(x[$a]).rotate_left(9);
+            x[$d] ^= x[$c].wrapping_add(x[$b]).rotate_left(13);
+            x[$a] ^= x[$d].wrapping_add(x[$c]).rotate_left(18);
+        };
+    }
+    for _ in 0..4 {
+        // Operate on columns
+        quarter!(0, 4, 8, 12);
+        quarter!(5, 9, 13, 1);
+        quarter!(10, 14, 2, 6);
+        quarter!(15, 3, 7, 11);
+        // Operate on rows
+        quarter!(0, 1, 2, 3);
+        quarter!(5, 6, 7, 4);
+        quarter!(10, 11, 8, 9);
+        quarter!(15, 12, 13, 14);
+    }
+    for (b_i, x_i) in b.iter_mut().zip(x.iter()) {
+        *b_i = b_i.wrapping_add(*x_i);
+    }
+}
+
+/// scryptBlockMix, as described in Section 4, reading `input`, and writing `output`.
+///
+/// Both of these consist of 2 * r blocks of Salsa20 words.
+fn block_mix(input: &[u32], output: &mut [u32]) {
+    let blocks = input.len() / SALSA_WORDS;
+    let r = blocks / 2;
+    let mut x: [u32; SALSA_WORDS] = input[input.len() - SALSA_WORDS..].try_into().unwrap();
+    for (i, block) in input.chunks_exact(SALSA_WORDS).enumerate() {
+        for (x_j, b_j) in x.iter_mut().zip(block.iter()) {
+            *x_j ^= b_j;
+        }
+        salsa20_8(&mut x);
+        // Even blocks go in the first half of the output, and odd blocks in the second.
+        let position = (i / 2) + (i % 2) * r;
+        output[position * SALSA_WORDS..(position + 1) * SALSA_WORDS].copy_from_slice(&x);
+    }
+}
+
+/// scryptROMix, as described in Section 5, modifying a block of 128 * r bytes.
+///
+/// `v` is scratch space for N such blocks.
+fn ro_mix(b: &mut [u32], n: usize, v: &mut [u32]) {
+    let len = b.len();
+    let mut y = vec![0u32; len];
+    for v_i in v.chunks_exact_mut(len) {
+        v_i.copy_from_slice(b);
+        block_mix(v_i, &mut y);
+        b.copy_from_slice(&y);
+    }
+    for _ in 0..n {
+        // Integerify takes the first 64 bits of the last block, in little endian.
+        let last = &b[len - SALSA_WORDS..];
+        let j = (u64::from(last[0]) | (u64::from(last[1]) << 32)) as usize & (n - 1);
+        for (b_k, v_k) in b.iter_mut().zip(v[j * len..(j + 1) * len].iter()) {
+            *b_k ^= v_k;
+        }
+        block_mix(b, &mut y);
+        b.copy_from_slice(&y);
+    }
+}
+
+/// Derive key material from a passphrase with scrypt, filling `out`.
+///
+/// This follows Section 6 of RFC 7914, and allocates `N * r * 128` bytes.
+pub fn scrypt(
+    passphrase: &[u8],
+    salt: &[u8],
+    params: Params,
+    out: &mut [u8],
+) -> Result<(), ScryptError> {
+    let r = params.r as usize;
+    let p = params.p as usize;
+    // N must be less than 2^(128 * r / 8), and r * p less than 2^30.
+    if params.log_n == 0
+        || params.log_n >= 63
+        || r == 0
+        || p == 0
+        || u64::from(params.log_n) >= 16 * params.r as u64
+        || params.r as u64 * params.p as u64 >= 1 << 30
+    {
+        return Err(ScryptError::InvalidParams);
+    }
+    if out.len() as u64 > (u64::from(u32::MAX)) * sha256::HASH_SIZE as u64 {
+        return Err(ScryptError::OutputTooLong);
+    }
+    let n = 1usize
+        .checked_shl(params.log_n.into())
+        .ok_or(ScryptError::InvalidParams)?;
+    let block_words = 32 * r;
+    let v_words = n
+        .checked_mul(block_words)
+        .ok_or(ScryptError::InvalidParams)?;
+
+    let b_len = (128 * r).checked_mul(p).ok_or(ScryptError::InvalidParams)?;
+    let mut b_bytes = vec![0u8; b_len];
+    pbkdf2_sha256(passphrase, salt, &mut b_bytes);
+
+    let mut b: Vec<u32> = b_bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    let mut v = vec![0u32; v_words];
+    for block in b.chunks_exact_mut(block_words) {
+        ro_mix(block, n, &mut v);
+    }
+    for (chunk, word) in b_bytes.chunks_exact_mut(4).zip(b.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+
+    pbkdf2_sha256(passphrase, &b_bytes, out);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pbkdf2_sha256_vector() {
+        // This is the first test vector from Section 11
+        let mut out = [0; 64];
+        pbkdf2_sha256(b"passwd", b"salt", &mut out);
+        assert_eq!(
+            hex::encode(out),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+        );
+    }
+
+    #[test]
+    fn test_salsa20_8_vector() {
+        // This is the test vector from Section 8
+        let input = hex::decode(
+            "7e879a214f3ec9867ca940e641718f26baee555b8c61c1b50df846116dcd3b1dee24f319df9b3d8514121e4b5ac5aa3276021d2909c74829edebc68db8b8c25e",
+        )
+        .unwrap();
+        let mut b = [0u32; SALSA_WORDS];
+        for (b_i, chunk) in b.iter_mut().zip(input.chunks_exact(4)) {
+            *b_i = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        salsa20_8(&mut b);
+        let output: Vec<u8> = b.iter().flat_map(|x| x.to_le_bytes()).collect();
+        assert_eq!(
+            hex::encode(output),
+            "a41f859c6608cc993b81cacb020cef05044b2181a2fd337dfd7b1c6396682f29b4393168e3c9e6bcfe6bc5b7a06d96bae424cc102c91745c24ad673dc7618f81"
+        );
+    }
+
+    #[test]
+    fn test_scrypt_vectors() {
+        // These are the first two test vectors from Section 12
+        let mut out = [0; 64];
+        let params = Params {
+            log_n: 4,
+            r: 1,
+            p: 1,
+        };
+        scrypt(b"", b"", params, &mut out).unwrap();
+        assert_eq!(
+            hex::encode(out),
+            "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"
+        );
+        let params = Params {
+            log_n: 10,
+            r: 8,
+            p: 16,
+        };
+        scrypt(b"password", b"NaCl", params, &mut out).unwrap();
+        assert_eq!(
+            hex::encode(out),
+            "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b3731622eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"
+        );
+    }
+
+    #[test]
+    fn test_invalid_params_are_rejected() {
+        let mut out = [0; 32];
+        for (log_n, r, p) in [
+            (0, 8, 1),
+            (4, 0, 1),
+            (4, 8, 0),
+            (16, 1, 1),
+            (4, 1 << 15, 1 << 15),
+        ] {
+            let params = Params { log_n, r, p };
+            assert_eq!(
+                scrypt(b"password", b"salt", params, &mut out),
+                Err(ScryptError::InvalidParams)
+            );
+        }
+    }
+
+    #[test]
+    fn test_minisign_params() {
+        // These are minisign's limits, for its secret keys
+        assert_eq!(
+            Params::from_limits(1 << 25, 1 << 30),
+            Params {
+                log_n: 20,
+                r: 8,
+                p: 1
+            }
+        );
+        // When memory is plentiful, the operation limit decides instead
+        assert_eq!(
+            Params::from_limits(1 << 20, 1 << 30),
+            Params {
+                log_n: 15,
+                r: 8,
+                p: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! This module checks that a build of eddo computes what it should.
+//!
+//! The arithmetic has different code paths depending on the architecture, and on
+//! features like `pure`, `radix51`, and `fiat`. These checks run known answer tests, from
+//! RFC 8032, and our own generated vectors, along with consistency checks, so that
+//! a build for an unusual target can be validated without running the test suite.
+use alloc::string::ToString;
+use core::convert::TryInto;
+
+use crate::{
+    curve25519::{
+        point::{CompressedEdwardsY, EdwardsPoint, B},
+        scalar::Scalar,
+    },
+    prehash, sha512,
+    vectors::{run_corpus, Rfc8032Vector},
+    ExpandedPrivateKey, PrivateKey, PublicKey, Signature,
+};
+
+/// Tests 1, 2, 3, and SHA(abc), from Section 7.1 of RFC 8032, in the `sign.input` format.
+const RFC_8032_CORPUS: &str = "\
+9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a:d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a::e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b:
+4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c:3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c:72:92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c0072:
+c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025:fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025:af82:6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40aaf82:
+833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf:ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf:ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f:dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b58909351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f:
+";
+
+/// How many generated vectors the self test checks.
+pub const GENERATED_VECTORS: u32 = 256;
+
+/// The SHA-512 hash of the first `GENERATED_VECTORS` generated vectors.
+///
+/// Each vector is formatted as a line of the `sign.input` format, ending with a newline,
+/// which is what `eddo selftest --generate 256` prints.
+const GENERATED_VECTORS_HASH: &str =
+    "c14271da2bd279858135a44639b098ffeddfccd6863acedea8fac17af9a4522c7c479b03d2af2a9c86f503ddaebb63457eee7991704c55b88aa0b77cbd2f9375";
+
+/// Represents a single check made by the self test.
+#[derive(Clone, Copy)]
+pub struct Check {
+    /// A short description of what this checks.
+    pub name: &'static str,
+    run: fn() -> bool,
+}
+
+impl Check {
+    /// Run this check, returning whether or not it passed.
+    pub fn run(&self) -> bool {
+        (self.run)()
+    }
+}
+
+/// Every check the self test makes, in the order they should be run.
+pub const CHECKS: &[Check] = &[
+    Check {
+        name: "RFC 8032 Ed25519 vectors",
+        run: check_ed25519_vectors,
+    },
+    Check {
+        name: "RFC 8032 Ed25519ctx vector",
+        run: check_ed25519ctx_vector,
+    },
+    Check {
+        name: "RFC 8032 Ed25519ph vector",
+        run: check_ed25519ph_vector,
+    },
+    Check {
+        name: "generated vectors",
+        run: check_generated_vectors,
+    },
+    Check {
+        name: "sign and verify round trips",
+        run: check_round_trips,
+    },
+    Check {
+        name: "point decompression edge cases",
+        run: check_decompression,
+    },
+    Check {
+        name: "scalar and point arithmetic",
+        run: check_arithmetic,
+    },
+];
+
+/// Represents the first check which failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfTestError {
+    /// The name of the failing check.
+    pub check: &'static str,
+}
+
+/// Run every check, stopping at the first failure.
+///
+/// This returns the number of checks which passed.
+pub fn run() -> Result<usize, SelfTestError> {
+    for check in CHECKS {
+        if !check.run() {
+            return Err(SelfTestError { check: check.name });
+        }
+    }
+    Ok(CHECKS.len())
+}
+
+fn decode<const N: usize>(input: &str) -> [u8; N] {
+    let mut out = [0; N];
+    hex::decode_to_slice(input, &mut out).unwrap();
+    out
+}
+
+fn check_ed25519_vectors() -> bool {
+    run_corpus(RFC_8032_CORPUS) == Ok(4)
+}
+
+fn check_ed25519ctx_vector() -> bool {
+    // This is the first test vector from Section 7.2
+    let private = PrivateKey {
+        bytes: decode("0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6"),
+    };
+    let public = PublicKey {
+        bytes: decode("dfc9425e4f968f7f0c29f0259cf5f9aed6851c2bb4ad8bfb860cfee0ab248292"),
+    };
+    let expected = Signature {
+        bytes: decode("55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d"),
+    };
+    let message = decode::<16>("f726936d19c800494e3fdaff20b276a8");
+    private.public_key() == public
+        && private.sign_with_context(&message, b"foo") == Some(expected)
+        && public.verify_with_context(&message, b"foo", expected)
+        && !public.verify_with_context(&message, b"bar", expected)
+}
+
+fn check_ed25519ph_vector() -> bool {
+    // This is the test vector from Section 7.3
+    let private = PrivateKey {
+        bytes: decode("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42"),
+    };
+    let public = private.public_key();
+    let expected = Signature {
+        bytes: decode("98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406"),
+    };
+    let digest = prehash::digest(b"abc");
+    private.sign_prehashed(&digest, &[]) == Some(expected)
+        && public.verify_prehashed(&digest, &[], expected)
+        && !public.verify(b"abc", expected)
+}
+
+fn check_generated_vectors() -> bool {
+    let mut hasher = sha512::Hasher::new();
+    for index in 0..GENERATED_VECTORS {
+        let vector = Rfc8032Vector::generate(index);
+        if vector.check().is_err() {
+            return false;
+        }
+        hasher.update(vector.to_string().as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize()) == GENERATED_VECTORS_HASH
+}
+
+fn check_round_trips() -> bool {
+    let message = [0xA5; 320];
+    (0..32u8).all(|i| {
+        let private = PrivateKey { bytes: [i; 32] };
+        let public = private.public_key();
+        let message = &message[..10 * i as usize];
+        let sig = private.sign(message);
+        let expanded = ExpandedPrivateKey::<sha512::Hasher>::from_private_key(&private);
+        let mut flipped = sig;
+        flipped.bytes[i as usize] ^= 1;
+        public.verify(message, sig)
+            && expanded.public_key() == public
+            && expanded.sign(message) == sig
+            && !public.verify(message, flipped)
+            && !public.verify(b"another message", sig)
+    })
+}
+
+fn check_decompression() -> bool {
+    let decompress = |hex: &str| CompressedEdwardsY { bytes: decode(hex) }.decompress();
+    // The base point, and the identity, which has y = 1
+    let base = decompress("5866666666666666666666666666666666666666666666666666666666666666");
+    let identity = decompress("0100000000000000000000000000000000000000000000000000000000000000");
+    // A point of order 8
+    let small = decompress("c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a");
+    base == Some(B)
+        && identity == Some(EdwardsPoint::identity())
+        && matches!(small, Some(p) if p.is_small_order() && !p.is_torsion_free())
+        // The identity, with the sign bit set, which would be -0
+        && decompress("0100000000000000000000000000000000000000000000000000000000000080").is_none()
+        // y = p, and y = p + 1, which aren't canonical
+        && decompress("edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f").is_none()
+        && decompress("eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f").is_none()
+        // y = 2, which has no matching x
+        && decompress("0200000000000000000000000000000000000000000000000000000000000000").is_none()
+        && (1..16u64).all(|i| {
+            let p = B * Scalar::from(i);
+            p.compress().decompress() == Some(p) && (-p).compress().decompress() == Some(-p)
+        })
+}
+
+fn check_arithmetic() -> bool {
+    let a = Scalar::from_bytes_mod_order(sha512::hash(b"a")[..32].try_into().unwrap());
+    let b = Scalar::from_bytes_mod_order(sha512::hash(b"b")[..32].try_into().unwrap());
+    let l = decode("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+    let mut l_minus_1 = l;
+    l_minus_1[0] -= 1;
+    B * a + B * b == B * (a + b)
+        && B * (a * b) == (B * a) * b
+        && (B * -Scalar::from(1) + B).is_identity()
+        && Scalar::from_canonical_bytes(l).is_none()
+        && Scalar::from_canonical_bytes(l_minus_1).is_some()
+        && (B * Scalar::from_bytes_mod_order(l)).is_identity()
+        && B.is_torsion_free()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_all_checks_pass() {
+        for check in CHECKS {
+            assert!(check.run(), "{} failed", check.name);
+        }
+        assert_eq!(run(), Ok(CHECKS.len()));
+    }
+}
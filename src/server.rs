@@ -0,0 +1,1169 @@
+use eddo::envelope::Envelope;
+use eddo::keyfile;
+use eddo::metered::{MeterError, MeteredSigner, Quota};
+use eddo::policy::Policy;
+use eddo::transparency::{self, LogEntry, TransparencyLog};
+use eddo::{PrivateKey, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+
+extern crate hex;
+extern crate serde;
+extern crate serde_json;
+extern crate structopt;
+
+/// A small HTTP server, signing and verifying messages over a JSON API.
+///
+/// The endpoints are:
+///
+/// - `GET /public`, returning the public key of the server, and of its audit log,
+/// - `POST /sign`, taking a hex encoded `message`, and returning a `signature`,
+///   along with the same signature in a CBOR `envelope`,
+/// - `POST /verify`, taking a `public` key, `message`, and a `signature` or `envelope`,
+///   returning `valid`,
+/// - `POST /verify-policy`, taking a list of `keys`, a `threshold`, a `message`, and
+///   a list of `signatures` or envelopes, returning `valid` if enough keys signed.
+///
+/// When a list of clients is given, signing requests must be authenticated, with an
+/// `X-Eddo-Client` header holding the client's public key, `X-Eddo-Timestamp` and
+/// `X-Eddo-Nonce` headers, and an `X-Eddo-Signature` header, with that key's signature
+/// over the request, including the timestamp, nonce, and host. Stale timestamps, and
+/// repeated nonces, are rejected, so captured requests can't be replayed.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "eddo-server")]
+struct Args {
+    /// A path to the private key file used for signing
+    #[structopt(short = "k", long = "key", parse(from_os_str))]
+    key_file: PathBuf,
+    /// A file holding the passphrase of an encrypted key file, on its first line
+    ///
+    /// Without this, the passphrase is read from $EDDO_PASSPHRASE.
+    #[structopt(long = "passphrase-file", parse(from_os_str))]
+    passphrase_file: Option<PathBuf>,
+    /// The address to listen on
+    #[structopt(short = "a", long = "address", default_value = "127.0.0.1:8025")]
+    address: String,
+    /// A file listing the hex encoded public keys of clients allowed to sign
+    #[structopt(short = "c", long = "clients", parse(from_os_str))]
+    clients_file: Option<PathBuf>,
+    /// The maximum number of signatures each client can request per minute
+    #[structopt(short = "r", long = "rate-limit")]
+    rate_limit: Option<u32>,
+    /// A file to append a signed record of every signature issued to
+    #[structopt(short = "l", long = "audit-log", parse(from_os_str))]
+    audit_log: Option<PathBuf>,
+    /// The host name authenticated clients must address their requests to
+    #[structopt(long = "host")]
+    host: Option<String>,
+    /// The maximum number of connections to serve at once
+    #[structopt(long = "max-connections", default_value = "64")]
+    max_connections: usize,
+}
+
+/// The largest request body we're willing to accept.
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// The longest request line, or header line, we're willing to accept.
+const MAX_LINE_SIZE: u64 = 8 * 1024;
+
+/// The most headers we're willing to accept in a request.
+const MAX_HEADERS: usize = 64;
+
+/// The most connections we serve at once, by default.
+const MAX_CONNECTIONS: usize = 64;
+
+/// How long a client has to send its entire request, however slowly it trickles in.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The environment variable holding the passphrase for a key file, as with `eddo`
+const PASSPHRASE_ENV: &str = "EDDO_PASSPHRASE";
+
+/// Read the passphrase for an encrypted key file, from a file, or the environment.
+///
+/// We never prompt, since the server usually runs without a terminal.
+fn read_passphrase(passphrase_path: Option<&Path>) -> io::Result<Option<String>> {
+    match passphrase_path {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)?;
+            Ok(Some(contents.lines().next().unwrap_or("").to_string()))
+        }
+        None => Ok(std::env::var(PASSPHRASE_ENV).ok()),
+    }
+}
+
+/// Parse a private key file, in any of the formats produced by `eddo generate`.
+fn parse_private_key(contents: &str, passphrase: Option<&str>) -> io::Result<PrivateKey> {
+    keyfile::parse_private_key(contents, passphrase).map_err(|e| {
+        let message = match e {
+            keyfile::KeyFileError::PassphraseRequired => {
+                format!("{}, use --passphrase-file or ${}", e, PASSPHRASE_ENV)
+            }
+            e => e.to_string(),
+        };
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    })
+}
+
+/// Read a private key file, decrypting it if necessary.
+fn load_private_key(key_path: &Path, passphrase_path: Option<&Path>) -> io::Result<PrivateKey> {
+    let contents = std::fs::read_to_string(key_path)?;
+    parse_private_key(&contents, read_passphrase(passphrase_path)?.as_deref())
+}
+
+/// Read the list of clients allowed to make signing requests.
+///
+/// This file contains one hex encoded public key per line, with lines starting
+/// with `#` being ignored.
+fn load_clients(clients_path: &Path) -> io::Result<HashSet<[u8; 32]>> {
+    let reader = BufReader::new(File::open(clients_path)?);
+    let mut clients = HashSet::new();
+    for maybe_line in reader.lines() {
+        let line = maybe_line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let client = decode_hex(line).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "invalid client public key")
+        })?;
+        clients.insert(client);
+    }
+    Ok(clients)
+}
+
+/// This is prepended to the other parts of a request, before clients sign it.
+const REQUEST_DOMAIN: &[u8] = b"eddo-server request v2\n";
+
+/// How far, in seconds, a request's timestamp can be from our clock.
+///
+/// Nonces are remembered for this long on either side, which is enough to reject
+/// any replay, since older requests are rejected as stale.
+const MAX_CLOCK_SKEW: u64 = 300;
+
+/// The number of bytes in the nonce of a request.
+const NONCE_SIZE: usize = 16;
+
+/// The parts of a request a client signs to authenticate it, besides the body.
+struct RequestHeader<'a> {
+    method: &'a str,
+    path: &'a str,
+    host: &'a str,
+    timestamp: u64,
+    nonce: [u8; NONCE_SIZE],
+}
+
+/// Calculate the bytes a client needs to sign, in order to authenticate a request.
+fn request_message(header: &RequestHeader, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(REQUEST_DOMAIN.len() + body.len() + 128);
+    out.extend_from_slice(REQUEST_DOMAIN);
+    out.extend_from_slice(
+        format!(
+            "{} {}\n{}\n{}\n{}\n",
+            header.method,
+            header.path,
+            header.host,
+            header.timestamp,
+            hex::encode(header.nonce)
+        )
+        .as_bytes(),
+    );
+    out.extend_from_slice(body);
+    out
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A nonce, along with the client which used it.
+type ClientNonce = ([u8; 32], [u8; NONCE_SIZE]);
+
+/// Remembers the nonces of recent requests, to reject replays.
+struct ReplayGuard {
+    /// For each client and nonce, the timestamp of the request which used it
+    seen: Mutex<HashMap<ClientNonce, u64>>,
+}
+
+impl ReplayGuard {
+    fn new() -> Self {
+        ReplayGuard {
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether a timestamp is close enough to the current time.
+    fn fresh(timestamp: u64, now: u64) -> bool {
+        timestamp.abs_diff(now) <= MAX_CLOCK_SKEW
+    }
+
+    /// Record the nonce of a request, returning false if it was already used.
+    fn first_use(
+        &self,
+        client: [u8; 32],
+        nonce: [u8; NONCE_SIZE],
+        timestamp: u64,
+        now: u64,
+    ) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        // Requests this old get rejected as stale, so we don't need their nonces
+        seen.retain(|_, &mut seen_at| Self::fresh(seen_at, now));
+        seen.insert((client, nonce), timestamp).is_none()
+    }
+}
+
+/// The client key used for accounting, when clients aren't authenticated.
+const ANONYMOUS_CLIENT: [u8; 32] = [0; 32];
+
+/// A single line in the audit log.
+#[derive(Serialize, Deserialize)]
+struct AuditEntry {
+    sequence: u64,
+    client: String,
+    signature: String,
+    previous: String,
+    entry_signature: String,
+}
+
+impl AuditEntry {
+    fn new(entry: &LogEntry) -> Self {
+        AuditEntry {
+            sequence: entry.sequence,
+            client: hex::encode(entry.client),
+            signature: hex::encode(entry.signature.bytes),
+            previous: hex::encode(entry.previous),
+            entry_signature: hex::encode(entry.entry_signature.bytes),
+        }
+    }
+
+    fn decode(&self) -> Option<LogEntry> {
+        Some(LogEntry {
+            sequence: self.sequence,
+            client: decode_hex(&self.client)?,
+            signature: Signature {
+                bytes: decode_hex(&self.signature)?,
+            },
+            previous: decode_hex(&self.previous)?,
+            entry_signature: Signature {
+                bytes: decode_hex(&self.entry_signature)?,
+            },
+        })
+    }
+}
+
+/// Read the entries of an audit log, checking that they form a valid chain.
+fn read_audit_log(path: &Path, log_public: &PublicKey) -> io::Result<Vec<LogEntry>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut entries = Vec::new();
+    for maybe_line in BufReader::new(File::open(path)?).lines() {
+        let entry: AuditEntry = serde_json::from_str(&maybe_line?)?;
+        entries.push(
+            entry
+                .decode()
+                .ok_or_else(|| invalid("invalid audit log entry".into()))?,
+        );
+    }
+    transparency::verify_log(log_public, &entries)
+        .map_err(|e| invalid(format!("invalid audit log: {:?}", e)))?;
+    Ok(entries)
+}
+
+/// An append-only file, recording every signature the server issues.
+struct AuditLog {
+    file: File,
+    log: TransparencyLog,
+}
+
+impl AuditLog {
+    /// Open an audit log, continuing the chain of any entries already present.
+    ///
+    /// We refuse to continue a log whose existing entries don't verify.
+    fn open(path: &Path, private: &PrivateKey) -> io::Result<Self> {
+        let mut log = TransparencyLog::new(private);
+        if path.exists() {
+            let log_public = transparency::log_key(private).public_key();
+            if let Some(last) = read_audit_log(path, &log_public)?.last() {
+                log = TransparencyLog::resume(private, last);
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog { file, log })
+    }
+
+    /// Append a new entry, recording that a signature was issued to a client.
+    ///
+    /// The entry is only counted once it's been written out.
+    fn record(&mut self, client: &[u8; 32], signature: Signature) -> io::Result<()> {
+        let mut log = self.log.clone();
+        let entry = AuditEntry::new(&log.append(client, signature));
+        writeln!(self.file, "{}", serde_json::to_string(&entry)?)?;
+        self.file.sync_data()?;
+        self.log = log;
+        Ok(())
+    }
+}
+
+/// The state shared between every connection.
+struct State {
+    signer: MeteredSigner,
+    public: PublicKey,
+    /// If present, only these clients are allowed to sign messages
+    clients: Option<HashSet<[u8; 32]>>,
+    audit_log: Option<Mutex<AuditLog>>,
+    replay_guard: ReplayGuard,
+    /// If present, authenticated requests must be addressed to this host
+    host: Option<String>,
+    max_connections: usize,
+    request_timeout: Duration,
+}
+
+impl State {
+    /// Create the state for a server, giving each client a quota of signatures.
+    fn new(private: PrivateKey, quota: Option<Quota>) -> Self {
+        let public = private.public_key();
+        State {
+            signer: MeteredSigner::new(private, quota),
+            public,
+            clients: None,
+            audit_log: None,
+            replay_guard: ReplayGuard::new(),
+            host: None,
+            max_connections: MAX_CONNECTIONS,
+            request_timeout: REQUEST_TIMEOUT,
+        }
+    }
+
+    /// The public key of the audit log.
+    fn audit_public(&self) -> PublicKey {
+        transparency::log_key(self.signer.private_key()).public_key()
+    }
+}
+
+/// Represents an HTTP request, with only the information we care about.
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Get the value of a header, given its lowercase name.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|s| s.as_str())
+    }
+}
+
+#[derive(Deserialize)]
+struct SignRequest {
+    message: String,
+}
+
+#[derive(Serialize)]
+struct SignResponse {
+    signature: String,
+    envelope: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyRequest {
+    public: String,
+    message: String,
+    signature: Option<String>,
+    envelope: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VerifyPolicyRequest {
+    keys: Vec<String>,
+    threshold: usize,
+    message: String,
+    signatures: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct VerifyResponse {
+    valid: bool,
+}
+
+#[derive(Serialize)]
+struct PublicResponse {
+    public: String,
+    audit: String,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// A response, with a status code, and a JSON body.
+type Response = (u16, String);
+
+fn json_response<T: Serialize>(status: u16, value: &T) -> Response {
+    // Serializing our simple response structs can't fail
+    (status, serde_json::to_string(value).unwrap())
+}
+
+fn error_response(status: u16, error: &str) -> Response {
+    json_response(
+        status,
+        &ErrorResponse {
+            error: error.into(),
+        },
+    )
+}
+
+fn decode_hex<const N: usize>(input: &str) -> Option<[u8; N]> {
+    let mut bytes = [0; N];
+    hex::decode_to_slice(input, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Figure out which client made a request, checking their signature if necessary.
+///
+/// If the server doesn't restrict clients, every request is anonymous.
+fn authenticate(state: &State, request: &Request) -> Result<[u8; 32], Response> {
+    let clients = match &state.clients {
+        None => return Ok(ANONYMOUS_CLIENT),
+        Some(clients) => clients,
+    };
+    let client = request
+        .header("x-eddo-client")
+        .and_then(decode_hex)
+        .ok_or_else(|| error_response(401, "missing client"))?;
+    let signature = request
+        .header("x-eddo-signature")
+        .and_then(decode_hex)
+        .map(|bytes| Signature { bytes })
+        .ok_or_else(|| error_response(401, "missing signature"))?;
+    let timestamp = request
+        .header("x-eddo-timestamp")
+        .and_then(|timestamp| timestamp.parse().ok())
+        .ok_or_else(|| error_response(401, "missing timestamp"))?;
+    let nonce = request
+        .header("x-eddo-nonce")
+        .and_then(decode_hex)
+        .ok_or_else(|| error_response(401, "missing nonce"))?;
+    let host = request.header("host").unwrap_or("");
+    if !clients.contains(&client) {
+        return Err(error_response(403, "unknown client"));
+    }
+    if state
+        .host
+        .as_deref()
+        .is_some_and(|expected| expected != host)
+    {
+        return Err(error_response(421, "wrong host"));
+    }
+    let now = unix_now();
+    if !ReplayGuard::fresh(timestamp, now) {
+        return Err(error_response(401, "stale timestamp"));
+    }
+    let header = RequestHeader {
+        method: &request.method,
+        path: &request.path,
+        host,
+        timestamp,
+        nonce,
+    };
+    let message = request_message(&header, &request.body);
+    if !(PublicKey { bytes: client }).verify(&message, signature) {
+        return Err(error_response(401, "invalid signature"));
+    }
+    // Only a valid signature gets to use up a nonce
+    if !state.replay_guard.first_use(client, nonce, timestamp, now) {
+        return Err(error_response(401, "replayed request"));
+    }
+    Ok(client)
+}
+
+fn handle_sign(state: &State, request: &Request) -> Response {
+    let client = match authenticate(state, request) {
+        Ok(client) => client,
+        Err(response) => return response,
+    };
+    let request: SignRequest = match serde_json::from_slice(&request.body) {
+        Ok(request) => request,
+        Err(_) => return error_response(400, "malformed request"),
+    };
+    let message = match hex::decode(request.message) {
+        Ok(message) => message,
+        Err(_) => return error_response(400, "malformed message"),
+    };
+    let signature = match state.signer.sign(&client, &message) {
+        Ok(signature) => signature,
+        Err(MeterError::RateLimited(_)) => return error_response(429, "rate limit exceeded"),
+    };
+    // We refuse to hand out signatures we haven't been able to record
+    if let Some(audit_log) = &state.audit_log {
+        if let Err(e) = audit_log.lock().unwrap().record(&client, signature) {
+            eprintln!("failed to write audit log: {}", e);
+            return error_response(500, "failed to write audit log");
+        }
+    }
+    let mut envelope = [0; eddo::envelope::MAX_ENVELOPE_SIZE];
+    // Our buffer is always large enough
+    let size = Envelope::new(&state.public, signature, None)
+        .encode(&mut envelope)
+        .unwrap();
+    json_response(
+        200,
+        &SignResponse {
+            signature: hex::encode(signature.bytes),
+            envelope: hex::encode(&envelope[..size]),
+        },
+    )
+}
+
+/// Decode a signature, given either as raw hex, or as a hex encoded envelope.
+///
+/// The envelope's key id isn't checked here, since a policy checks each signature
+/// against every key anyway.
+fn decode_signature(input: &str) -> Option<Signature> {
+    let bytes = hex::decode(input).ok()?;
+    match bytes.as_slice().try_into() {
+        Ok(bytes) => Some(Signature { bytes }),
+        Err(_) => Envelope::decode(&bytes).ok().map(|e| e.signature),
+    }
+}
+
+fn handle_verify(body: &[u8]) -> Response {
+    let request: VerifyRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(_) => return error_response(400, "malformed request"),
+    };
+    let (public, message) = match (decode_hex(&request.public), hex::decode(request.message)) {
+        (Some(public), Ok(message)) => (PublicKey { bytes: public }, message),
+        _ => return error_response(400, "malformed request"),
+    };
+    let valid = match (request.signature, request.envelope) {
+        (Some(signature), None) => match decode_hex(&signature) {
+            Some(bytes) => public.verify(&message, Signature { bytes }),
+            None => return error_response(400, "malformed signature"),
+        },
+        (None, Some(envelope)) => {
+            match hex::decode(envelope)
+                .ok()
+                .and_then(|e| Envelope::decode(&e).ok())
+            {
+                // This also checks that the envelope names the right key
+                Some(envelope) => envelope.verify(&public, &message),
+                None => return error_response(400, "malformed envelope"),
+            }
+        }
+        _ => return error_response(400, "expected one of signature or envelope"),
+    };
+    json_response(200, &VerifyResponse { valid })
+}
+
+fn handle_verify_policy(body: &[u8]) -> Response {
+    let request: VerifyPolicyRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(_) => return error_response(400, "malformed request"),
+    };
+    let keys: Option<Vec<PublicKey>> = request
+        .keys
+        .iter()
+        .map(|key| decode_hex(key).map(|bytes| PublicKey { bytes }))
+        .collect();
+    let signatures: Option<Vec<Signature>> = request
+        .signatures
+        .iter()
+        .map(|signature| decode_signature(signature))
+        .collect();
+    let (keys, signatures, message) = match (keys, signatures, hex::decode(request.message)) {
+        (Some(keys), Some(signatures), Ok(message)) => (keys, signatures, message),
+        _ => return error_response(400, "malformed request"),
+    };
+    let policy = match Policy::new(keys, request.threshold) {
+        Ok(policy) => policy,
+        Err(e) => return error_response(400, &format!("invalid policy: {:?}", e)),
+    };
+    json_response(
+        200,
+        &VerifyResponse {
+            valid: policy.verify(&message, &signatures),
+        },
+    )
+}
+
+/// Handle a single request, producing the response to send back.
+fn handle(state: &State, request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/public") => json_response(
+            200,
+            &PublicResponse {
+                public: hex::encode(state.public.bytes),
+                audit: hex::encode(state.audit_public().bytes),
+            },
+        ),
+        ("POST", "/sign") => handle_sign(state, request),
+        ("POST", "/verify") => handle_verify(&request.body),
+        ("POST", "/verify-policy") => handle_verify_policy(&request.body),
+        _ => error_response(404, "not found"),
+    }
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        421 => "Misdirected Request",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// A stream whose reads fail once a deadline has passed.
+///
+/// A read timeout alone isn't enough, since a client could send a byte just
+/// before each timeout, and keep a connection open forever.
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self
+            .deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "request took too long"))?;
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Read a single line, refusing lines longer than MAX_LINE_SIZE.
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    let read = reader.take(MAX_LINE_SIZE).read_line(&mut line)?;
+    if read as u64 == MAX_LINE_SIZE && !line.ends_with('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+    }
+    Ok(line)
+}
+
+/// Read an HTTP request from a stream, and write back the response.
+///
+/// We only support the small subset of HTTP/1.1 our clients need: a single
+/// request per connection, with the body delimited by `Content-Length`.
+fn serve_connection(state: &State, stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(state.request_timeout))?;
+    let mut reader = BufReader::new(DeadlineStream {
+        stream: stream.try_clone()?,
+        deadline: Instant::now() + state.request_timeout,
+    });
+    let request_line = read_line(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let path = parts.next().unwrap_or("").to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let header = read_line(&mut reader)?;
+        if header.is_empty() {
+            break;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() >= MAX_HEADERS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many headers",
+            ));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    let content_length = headers
+        .get("content-length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+
+    let response = if content_length > MAX_BODY_SIZE {
+        error_response(413, "request too large")
+    } else {
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        let request = Request {
+            method,
+            path,
+            headers,
+            body,
+        };
+        handle(state, &request)
+    };
+    write_response(stream, response)
+}
+
+fn write_response(mut stream: TcpStream, (status, body): Response) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        status_text(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Counts a connection as active, until dropped.
+struct ActiveConnection(Arc<AtomicUsize>);
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Accept connections forever, handling each of them on a separate thread.
+///
+/// At most `max_connections` get served at once, with any more being turned away.
+fn serve(listener: TcpListener, state: Arc<State>) {
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let count = active.fetch_add(1, Ordering::SeqCst);
+        let guard = ActiveConnection(Arc::clone(&active));
+        if count >= state.max_connections {
+            // Don't let a client which doesn't read block us from accepting others
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+            let _ = write_response(stream, error_response(503, "too many connections"));
+            continue;
+        }
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            let _guard = guard;
+            if let Err(e) = serve_connection(&state, stream) {
+                eprintln!("failed to serve connection: {}", e);
+            }
+        });
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::from_args();
+    let private = load_private_key(&args.key_file, args.passphrase_file.as_deref())?;
+    let quota = args.rate_limit.map(|limit| Quota {
+        limit,
+        window: Duration::from_secs(60),
+    });
+    let audit_log = match args.audit_log {
+        Some(path) => Some(Mutex::new(AuditLog::open(&path, &private)?)),
+        None => None,
+    };
+    let mut state = State::new(private, quota);
+    state.audit_log = audit_log;
+    if let Some(clients_file) = args.clients_file {
+        state.clients = Some(load_clients(&clients_file)?);
+    }
+    state.host = args.host;
+    state.max_connections = args.max_connections;
+    let listener = TcpListener::bind(&args.address)?;
+    eprintln!("listening on {}", listener.local_addr()?);
+    serve(listener, Arc::new(state));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn start(state: State) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, Arc::new(state)));
+        address
+    }
+
+    fn start_server() -> (SocketAddr, PublicKey) {
+        let state = State::new(PrivateKey { bytes: [1; 32] }, None);
+        let public = state.public;
+        (start(state), public)
+    }
+
+    fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        request_with_headers(address, method, path, "", body)
+    }
+
+    fn request_with_headers(
+        address: SocketAddr,
+        method: &str,
+        path: &str,
+        headers: &str,
+        body: &str,
+    ) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            headers,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap().to_string();
+        (status, body)
+    }
+
+    #[test]
+    fn test_public_endpoint() {
+        let (address, public) = start_server();
+        let (status, body) = request(address, "GET", "/public", "");
+        assert_eq!(status, 200);
+        assert!(body.contains(&hex::encode(public.bytes)));
+    }
+
+    #[test]
+    fn test_sign_then_verify() {
+        let (address, public) = start_server();
+        let (status, body) = request(address, "POST", "/sign", r#"{"message":"cafe"}"#);
+        assert_eq!(status, 200);
+        let signature: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let signature = signature["signature"].as_str().unwrap();
+
+        let verify_body = |message: &str| {
+            format!(
+                r#"{{"public":"{}","message":"{}","signature":"{}"}}"#,
+                hex::encode(public.bytes),
+                message,
+                signature
+            )
+        };
+        let (status, body) = request(address, "POST", "/verify", &verify_body("cafe"));
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"valid":true}"#);
+        let (status, body) = request(address, "POST", "/verify", &verify_body("beef"));
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"valid":false}"#);
+    }
+
+    #[test]
+    fn test_malformed_requests() {
+        let (address, _) = start_server();
+        let (status, _) = request(address, "POST", "/sign", "not json");
+        assert_eq!(status, 400);
+        let (status, _) = request(address, "POST", "/sign", r#"{"message":"xyz"}"#);
+        assert_eq!(status, 400);
+        let (status, _) = request(address, "GET", "/nowhere", "");
+        assert_eq!(status, 404);
+    }
+
+    /// The headers authenticating a request to sign a body, made at a given time.
+    fn auth_headers(client: &PrivateKey, body: &str, timestamp: u64, nonce: u8) -> String {
+        let header = RequestHeader {
+            method: "POST",
+            path: "/sign",
+            host: "localhost",
+            timestamp,
+            nonce: [nonce; NONCE_SIZE],
+        };
+        let signature = client.sign(&request_message(&header, body.as_bytes()));
+        format!(
+            "X-Eddo-Client: {}\r\nX-Eddo-Timestamp: {}\r\nX-Eddo-Nonce: {}\r\nX-Eddo-Signature: {}\r\n",
+            hex::encode(client.public_key().bytes),
+            timestamp,
+            hex::encode(header.nonce),
+            hex::encode(signature.bytes)
+        )
+    }
+
+    fn signed_request(
+        address: SocketAddr,
+        client: &PrivateKey,
+        body: &str,
+        nonce: u8,
+    ) -> (u16, String) {
+        let headers = auth_headers(client, body, unix_now(), nonce);
+        request_with_headers(address, "POST", "/sign", &headers, body)
+    }
+
+    fn pinned_state(client: &PrivateKey) -> State {
+        let mut state = State::new(PrivateKey { bytes: [1; 32] }, None);
+        state.clients = Some(vec![client.public_key().bytes].into_iter().collect());
+        state
+    }
+
+    #[test]
+    fn test_pinned_clients() {
+        let client = PrivateKey { bytes: [2; 32] };
+        let stranger = PrivateKey { bytes: [3; 32] };
+        let address = start(pinned_state(&client));
+
+        let body = r#"{"message":"cafe"}"#;
+        assert_eq!(request(address, "POST", "/sign", body).0, 401);
+        assert_eq!(signed_request(address, &stranger, body, 0).0, 403);
+        assert_eq!(signed_request(address, &client, body, 0).0, 200);
+
+        // A signature over a different body shouldn't be accepted
+        let headers = auth_headers(&client, "{}", unix_now(), 1);
+        let (status, _) = request_with_headers(address, "POST", "/sign", &headers, body);
+        assert_eq!(status, 401);
+    }
+
+    #[test]
+    fn test_replayed_requests_are_rejected() {
+        let client = PrivateKey { bytes: [2; 32] };
+        let address = start(pinned_state(&client));
+        let body = r#"{"message":"cafe"}"#;
+
+        let headers = auth_headers(&client, body, unix_now(), 0);
+        let (status, _) = request_with_headers(address, "POST", "/sign", &headers, body);
+        assert_eq!(status, 200);
+        let (status, body_out) = request_with_headers(address, "POST", "/sign", &headers, body);
+        assert_eq!(status, 401);
+        assert!(body_out.contains("replayed"));
+        // A fresh nonce is fine
+        assert_eq!(signed_request(address, &client, body, 1).0, 200);
+
+        for timestamp in [
+            unix_now() - MAX_CLOCK_SKEW - 60,
+            unix_now() + MAX_CLOCK_SKEW + 60,
+        ] {
+            let headers = auth_headers(&client, body, timestamp, 2);
+            let (status, body_out) = request_with_headers(address, "POST", "/sign", &headers, body);
+            assert_eq!(status, 401);
+            assert!(body_out.contains("stale"));
+        }
+    }
+
+    #[test]
+    fn test_signature_binds_host() {
+        let client = PrivateKey { bytes: [2; 32] };
+        let mut state = pinned_state(&client);
+        state.host = Some("signer.example".into());
+        let address = start(state);
+        let body = r#"{"message":"cafe"}"#;
+        // Our requests are addressed to localhost
+        assert_eq!(signed_request(address, &client, body, 0).0, 421);
+
+        let mut state = pinned_state(&client);
+        state.host = Some("localhost".into());
+        let address = start(state);
+        assert_eq!(signed_request(address, &client, body, 0).0, 200);
+    }
+
+    #[test]
+    fn test_slow_clients_time_out() {
+        let mut state = State::new(PrivateKey { bytes: [1; 32] }, None);
+        state.request_timeout = Duration::from_millis(200);
+        let address = start(state);
+        let mut stream = TcpStream::connect(address).unwrap();
+        let start = Instant::now();
+        // Trickle in a request, more slowly than the deadline allows
+        let mut closed = false;
+        for byte in b"GET /public HTTP/1.1\r\n".iter() {
+            if stream.write_all(&[*byte]).is_err() {
+                closed = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+            if start.elapsed() > Duration::from_secs(2) {
+                break;
+            }
+        }
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        assert!(closed || response.is_empty());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let mut state = State::new(PrivateKey { bytes: [1; 32] }, None);
+        state.max_connections = 1;
+        let address = start(state);
+        // This connection stays open, without sending anything
+        let idle = TcpStream::connect(address).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        // We're turned away before sending anything, so there's nothing left unread
+        let mut response = String::new();
+        TcpStream::connect(address)
+            .unwrap()
+            .read_to_string(&mut response)
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 503"));
+        drop(idle);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(request(address, "GET", "/public", "").0, 200);
+    }
+
+    #[test]
+    fn test_envelopes_and_policies() {
+        let (address, public) = start_server();
+        let (_, body) = request(address, "POST", "/sign", r#"{"message":"cafe"}"#);
+        let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let envelope = response["envelope"].as_str().unwrap();
+
+        let verify_envelope = |public: &PublicKey| {
+            format!(
+                r#"{{"public":"{}","message":"cafe","envelope":"{}"}}"#,
+                hex::encode(public.bytes),
+                envelope
+            )
+        };
+        let (status, body) = request(address, "POST", "/verify", &verify_envelope(&public));
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"valid":true}"#);
+        // The envelope names the key which made it
+        let other = PrivateKey { bytes: [9; 32] }.public_key();
+        let (_, body) = request(address, "POST", "/verify", &verify_envelope(&other));
+        assert_eq!(body, r#"{"valid":false}"#);
+
+        let signers: Vec<PrivateKey> = (2..5).map(|i| PrivateKey { bytes: [i; 32] }).collect();
+        let keys: Vec<String> = signers
+            .iter()
+            .map(|k| format!(r#""{}""#, hex::encode(k.public_key().bytes)))
+            .collect();
+        let policy_body = |threshold: usize, count: usize| {
+            let signatures: Vec<String> = signers[..count]
+                .iter()
+                .map(|k| format!(r#""{}""#, hex::encode(k.sign(b"\xca\xfe").bytes)))
+                .collect();
+            format!(
+                r#"{{"keys":[{}],"threshold":{},"message":"cafe","signatures":[{}]}}"#,
+                keys.join(","),
+                threshold,
+                signatures.join(",")
+            )
+        };
+        let (status, body) = request(address, "POST", "/verify-policy", &policy_body(2, 2));
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"valid":true}"#);
+        let (_, body) = request(address, "POST", "/verify-policy", &policy_body(2, 1));
+        assert_eq!(body, r#"{"valid":false}"#);
+        let (status, _) = request(address, "POST", "/verify-policy", &policy_body(4, 3));
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let quota = Quota {
+            limit: 2,
+            window: Duration::from_secs(60),
+        };
+        let address = start(State::new(PrivateKey { bytes: [1; 32] }, Some(quota)));
+        let body = r#"{"message":"cafe"}"#;
+        assert_eq!(request(address, "POST", "/sign", body).0, 200);
+        assert_eq!(request(address, "POST", "/sign", body).0, 200);
+        assert_eq!(request(address, "POST", "/sign", body).0, 429);
+    }
+
+    #[test]
+    fn test_load_key_files() {
+        let private = PrivateKey { bytes: [1; 32] };
+        let plain = keyfile::KeyFile::new(private.clone(), Some(1), Some("server".into())).unwrap();
+        for armored in [false, true] {
+            let contents = plain.format(armored);
+            assert!(contents.starts_with(keyfile::KEY_FILE_HEADER));
+            assert_eq!(parse_private_key(&contents, None).unwrap(), private);
+        }
+        let legacy = keyfile::format_private_key(&private);
+        assert_eq!(parse_private_key(&legacy, None).unwrap(), private);
+
+        let cheap = eddo::scrypt::Params {
+            log_n: 4,
+            r: 8,
+            p: 1,
+        };
+        let encrypted = keyfile::KeyFile::encrypted(
+            &mut rand::rngs::OsRng,
+            private.clone(),
+            Some(1),
+            None,
+            "hunter2",
+            cheap,
+        )
+        .unwrap()
+        .format(false);
+        let err = parse_private_key(&encrypted, None).unwrap_err();
+        assert!(err.to_string().contains("--passphrase-file"));
+        assert!(parse_private_key(&encrypted, Some("hunter3")).is_err());
+        assert_eq!(
+            parse_private_key(&encrypted, Some("hunter2")).unwrap(),
+            private
+        );
+    }
+
+    fn sign_with_audit_log(path: &Path, messages: &[&str]) {
+        let private = PrivateKey { bytes: [1; 32] };
+        let mut state = State::new(private.clone(), None);
+        state.audit_log = Some(Mutex::new(AuditLog::open(path, &private).unwrap()));
+        let address = start(state);
+        for message in messages {
+            let body = format!(r#"{{"message":"{}"}}"#, message);
+            assert_eq!(request(address, "POST", "/sign", &body).0, 200);
+        }
+    }
+
+    #[test]
+    fn test_audit_log_chains_entries() {
+        let path = std::env::temp_dir().join(format!("eddo-audit-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let public = PrivateKey { bytes: [1; 32] }.public_key();
+        let audit_public = transparency::log_public_key(&public).unwrap();
+
+        sign_with_audit_log(&path, &["00", "01", "02"]);
+        let entries = read_audit_log(&path, &audit_public).unwrap();
+        assert_eq!(entries.len(), 3);
+        for (entry, message) in entries.iter().zip(&[[0u8], [1], [2]]) {
+            assert!(public.verify(message, entry.signature));
+        }
+
+        // Reopening the log continues the same chain
+        sign_with_audit_log(&path, &["03"]);
+        let entries = read_audit_log(&path, &audit_public).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(transparency::verify_log(&audit_public, &entries), Ok(()));
+
+        // We refuse to continue a log which has been tampered with
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let without_second: Vec<&str> = contents
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, line)| line)
+            .collect();
+        std::fs::write(&path, without_second.join("\n")).unwrap();
+        let private = PrivateKey { bytes: [1; 32] };
+        let err = AuditLog::open(&path, &private).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
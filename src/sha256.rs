@@ -0,0 +1,281 @@
+//! This module implements the SHA-256 hash function.
+//!
+//! This isn't needed for Ed25519 itself, but some protocols pair Ed25519 signatures
+//! with SHA-256 digests, and this avoids pulling in another dependency for them.
+//!
+//! Like our SHA-512, this tries to follow RFC 6234 (https://datatracker.ietf.org/doc/html/rfc6234).
+
+use core::{convert::TryInto, mem::size_of};
+
+/// This is the number of bytes in our 256 bit hash.
+pub const HASH_SIZE: usize = 32;
+
+/// BLOCK_SIZE is the number of bytes needed to make a 512 bit block
+///
+/// This block structure is described in Section 4:
+/// https://datatracker.ietf.org/doc/html/rfc6234#section-4
+const BLOCK_SIZE: usize = 64;
+
+// Utility functions, as in Section 5.1:
+// https://datatracker.ietf.org/doc/html/rfc6234#section-5.1
+
+#[inline]
+fn ch(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) ^ (!x & z)
+}
+
+#[inline]
+fn maj(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) ^ (x & z) ^ (y & z)
+}
+
+#[inline]
+fn bsig0(x: u32) -> u32 {
+    x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22)
+}
+
+#[inline]
+fn bsig1(x: u32) -> u32 {
+    x.rotate_right(6) ^ x.rotate_right(11) ^ x.rotate_right(25)
+}
+
+#[inline]
+fn ssig0(x: u32) -> u32 {
+    x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3)
+}
+
+#[inline]
+fn ssig1(x: u32) -> u32 {
+    x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10)
+}
+
+/// The table of constants used in SHA-256 (and SHA-224).
+///
+/// This table is at the end of Section 5.1:
+/// https://datatracker.ietf.org/doc/html/rfc6234#section-5.1
+#[rustfmt::skip]
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The initial hash value for SHA-256, from Section 6.1:
+/// https://datatracker.ietf.org/doc/html/rfc6234#section-6.1
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Represents a "hash value", as described in Section 6:
+/// https://datatracker.ietf.org/doc/html/rfc6234#section-6
+///
+/// This can be thought of as the ongoing state of our hash function,
+/// which gets modified using our message blocks.
+struct HashValue {
+    data: [u32; 8],
+    schedule: [u32; 64],
+}
+
+impl HashValue {
+    fn initial() -> HashValue {
+        HashValue {
+            data: IV,
+            schedule: [0; 64],
+        }
+    }
+
+    /// Update the current hash value, as per Section 6.2:
+    /// https://datatracker.ietf.org/doc/html/rfc6234#section-6.2
+    fn update(&mut self, block: &[u8; BLOCK_SIZE]) {
+        // The following titles are quoted from the algorithm in Section 6.2:
+
+        // 1. Prepare the message schedule W:
+        let w = &mut self.schedule;
+        for (t, chunk) in block.chunks_exact(4).enumerate() {
+            w[t] = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        for t in 16..=63 {
+            w[t] = ssig1(w[t - 2])
+                .wrapping_add(w[t - 7])
+                .wrapping_add(ssig0(w[t - 15]))
+                .wrapping_add(w[t - 16]);
+        }
+
+        // 2. Initialize the working variables:
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.data;
+
+        // 3. Perform the main hash computation:
+        for t in 0..=63 {
+            let t1 = h
+                .wrapping_add(bsig1(e))
+                .wrapping_add(ch(e, f, g))
+                .wrapping_add(K[t])
+                .wrapping_add(w[t]);
+            let t2 = bsig0(a).wrapping_add(maj(a, b, c));
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        // 4. Compute the intermediate hash value H(i)
+        for (x, y) in self.data.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+
+    // This calculates the final result from a hash value, as per the end of Section 6.2:
+    // https://datatracker.ietf.org/doc/html/rfc6234#section-6.2
+    fn result(&self) -> [u8; HASH_SIZE] {
+        let mut out = [0; HASH_SIZE];
+        for (chunk, x) in out.chunks_exact_mut(size_of::<u32>()).zip(self.data.iter()) {
+            chunk.copy_from_slice(&x.to_be_bytes());
+        }
+        out
+    }
+}
+
+/// An incremental SHA-256 hasher, for messages which arrive in pieces.
+///
+/// Like the SHA-512 hasher, this only ever buffers a single block of input.
+pub struct Hasher {
+    hash_value: HashValue,
+    buffer: [u8; BLOCK_SIZE],
+    buffered: usize,
+    length: u64,
+}
+
+impl Hasher {
+    /// Create a new hasher, with no input.
+    pub fn new() -> Self {
+        Hasher {
+            hash_value: HashValue::initial(),
+            buffer: [0; BLOCK_SIZE],
+            buffered: 0,
+            length: 0,
+        }
+    }
+
+    /// Feed more of the message into this hasher.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        if self.buffered > 0 {
+            let to_copy = data.len().min(BLOCK_SIZE - self.buffered);
+            self.buffer[self.buffered..self.buffered + to_copy].copy_from_slice(&data[..to_copy]);
+            self.buffered += to_copy;
+            data = &data[to_copy..];
+            if self.buffered < BLOCK_SIZE {
+                return;
+            }
+            self.hash_value.update(&self.buffer);
+            self.buffered = 0;
+        }
+        let mut blocks = data.chunks_exact(BLOCK_SIZE);
+        for block in &mut blocks {
+            self.hash_value.update(block.try_into().unwrap());
+        }
+        let remainder = blocks.remainder();
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffered = remainder.len();
+    }
+
+    /// Finish hashing, producing the hash of all the input so far.
+    pub fn finalize(mut self) -> [u8; HASH_SIZE] {
+        // Now, we need to handle padding, as per Section 4.1:
+        // https://datatracker.ietf.org/doc/html/rfc6234#section-4.1
+        let remainder_len = self.buffered;
+
+        let mut scratch_block = [0; BLOCK_SIZE];
+        scratch_block[..remainder_len].copy_from_slice(&self.buffer[..remainder_len]);
+
+        // a. "1" is appended
+        scratch_block[remainder_len] = 0b1000_0000;
+
+        // b. K "0"s are appended where K is the smallest, non-negative solution
+        // to the equation
+        //     ( L + 1 + K ) mod 512 = 448
+        let l_plus_1 = remainder_len + 1;
+        let desired_size = BLOCK_SIZE - size_of::<u64>();
+        if l_plus_1 > desired_size {
+            self.hash_value.update(&scratch_block);
+            scratch_block.fill(0);
+        }
+
+        // c. Then append the 64-bit block that is L in binary representation.
+        let l = 8 * self.length;
+        scratch_block[BLOCK_SIZE - size_of::<u64>()..].copy_from_slice(&l.to_be_bytes());
+
+        self.hash_value.update(&scratch_block);
+
+        self.hash_value.result()
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// This calculates the SHA-256 hash of some arbitrary input, producing 256 bits of output.
+///
+/// This implements the function as defined in RFC 6234:
+/// https://datatracker.ietf.org/doc/html/rfc6234
+pub fn hash(message: &[u8]) -> [u8; HASH_SIZE] {
+    let mut hasher = Hasher::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vectors() {
+        let long = [b'a'; 200];
+        let vectors: [(&[u8], &str); 4] = [
+            (
+                b"abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                b"",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            // This needs two padding blocks
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+            (
+                &long,
+                "c2a908d98f5df987ade41b5fce213067efbcc21ef2240212a41e54b5e7c28ae5",
+            ),
+        ];
+        for (message, expected) in vectors.iter() {
+            assert_eq!(hex::encode(hash(message)), *expected);
+        }
+    }
+
+    #[test]
+    fn test_incremental_hashing_matches() {
+        let message: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        for &split in &[1, 31, 63, 64, 65, 500] {
+            let mut hasher = Hasher::new();
+            for chunk in message.chunks(split) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), hash(&message));
+        }
+    }
+}
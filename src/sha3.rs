@@ -0,0 +1,329 @@
+//! This module implements the SHA-3 family of hash functions, from FIPS 202:
+//! https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+//!
+//! This includes SHA3-256 and SHA3-512, as well as SHAKE256, an extendable output
+//! function, for protocols needing an arbitrary amount of output. SHA3-512 can
+//! also replace SHA-512 in signatures, see `ExpandedPrivateKey`.
+//!
+//! All of these are built from the same sponge, over the Keccak-f[1600] permutation.
+
+/// The number of bytes in a SHA3-256 hash.
+pub const SHA3_256_SIZE: usize = 32;
+
+/// The number of bytes in a SHA3-512 hash.
+pub const SHA3_512_SIZE: usize = 64;
+
+/// The round constants for the iota step, as in Section 3.2.5.
+const RC: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// The rotation offsets for the rho step, in the order the pi step visits lanes.
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+/// The order in which the pi step moves lanes, starting from lane 1.
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// The Keccak-f[1600] permutation, as in Section 3.3.
+///
+/// The state is stored as 25 lanes, with lane (x, y) at index x + 5 * y.
+fn keccak_f(a: &mut [u64; 25]) {
+    for rc in RC.iter() {
+        // Theta
+        let mut c = [0u64; 5];
+        for (x, c_x) in c.iter_mut().enumerate() {
+            *c_x = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+        // Rho and pi
+        let mut last = a[1];
+        for (&j, &rho) in PI.iter().zip(RHO.iter()) {
+            let next = a[j];
+            a[j] = last.rotate_left(rho);
+            last = next;
+        }
+        // Chi
+        for y in 0..5 {
+            let mut row = [0u64; 5];
+            row.copy_from_slice(&a[5 * y..5 * y + 5]);
+            for x in 0..5 {
+                a[x + 5 * y] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+        // Iota
+        a[0] ^= rc;
+    }
+}
+
+/// The sponge construction from Section 4, with byte oriented input and output.
+#[derive(Clone)]
+struct Sponge {
+    state: [u64; 25],
+    /// The number of bytes absorbed or squeezed per permutation.
+    rate: usize,
+    /// Our position in the current block.
+    position: usize,
+}
+
+impl Sponge {
+    fn new(rate: usize) -> Self {
+        Sponge {
+            state: [0; 25],
+            rate,
+            position: 0,
+        }
+    }
+
+    fn xor_byte(&mut self, i: usize, byte: u8) {
+        self.state[i / 8] ^= u64::from(byte) << (8 * (i % 8));
+    }
+
+    fn absorb(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.xor_byte(self.position, byte);
+            self.position += 1;
+            if self.position == self.rate {
+                keccak_f(&mut self.state);
+                self.position = 0;
+            }
+        }
+    }
+
+    /// Pad the input, after appending the domain separation bits, and switch to squeezing.
+    ///
+    /// The suffix contains those bits, followed by the first bit of the padding,
+    /// as in Appendix B.2.
+    fn pad(&mut self, suffix: u8) {
+        self.xor_byte(self.position, suffix);
+        self.xor_byte(self.rate - 1, 0x80);
+        keccak_f(&mut self.state);
+        self.position = 0;
+    }
+
+    fn squeeze(&mut self, out: &mut [u8]) {
+        for x in out {
+            if self.position == self.rate {
+                keccak_f(&mut self.state);
+                self.position = 0;
+            }
+            *x = (self.state[self.position / 8] >> (8 * (self.position % 8))) as u8;
+            self.position += 1;
+        }
+    }
+}
+
+/// The suffix for the SHA-3 hash functions, as in Section 6.1.
+const SHA3_SUFFIX: u8 = 0x06;
+
+/// The suffix for the SHAKE functions, as in Section 6.2.
+const SHAKE_SUFFIX: u8 = 0x1f;
+
+/// An incremental SHA3-256 hasher.
+#[derive(Clone)]
+pub struct Sha3_256(Sponge);
+
+impl Sha3_256 {
+    /// Create a new hasher, with no input.
+    pub fn new() -> Self {
+        Sha3_256(Sponge::new(200 - 2 * SHA3_256_SIZE))
+    }
+
+    /// Feed more of the message into this hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.absorb(data);
+    }
+
+    /// Finish hashing, producing the hash of all the input so far.
+    pub fn finalize(mut self) -> [u8; SHA3_256_SIZE] {
+        let mut out = [0; SHA3_256_SIZE];
+        self.0.pad(SHA3_SUFFIX);
+        self.0.squeeze(&mut out);
+        out
+    }
+}
+
+impl Default for Sha3_256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An incremental SHA3-512 hasher.
+#[derive(Clone)]
+pub struct Sha3_512(Sponge);
+
+impl Sha3_512 {
+    /// Create a new hasher, with no input.
+    pub fn new() -> Self {
+        Sha3_512(Sponge::new(200 - 2 * SHA3_512_SIZE))
+    }
+
+    /// Feed more of the message into this hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.absorb(data);
+    }
+
+    /// Finish hashing, producing the hash of all the input so far.
+    pub fn finalize(mut self) -> [u8; SHA3_512_SIZE] {
+        let mut out = [0; SHA3_512_SIZE];
+        self.0.pad(SHA3_SUFFIX);
+        self.0.squeeze(&mut out);
+        out
+    }
+}
+
+impl Default for Sha3_512 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An incremental SHAKE256 hasher, producing as much output as needed.
+#[derive(Clone)]
+pub struct Shake256(Sponge);
+
+impl Shake256 {
+    /// Create a new hasher, with no input.
+    pub fn new() -> Self {
+        // SHAKE256 has the same capacity as SHA3-256.
+        Shake256(Sponge::new(200 - 2 * SHA3_256_SIZE))
+    }
+
+    /// Feed more of the message into this hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.absorb(data);
+    }
+
+    /// Finish absorbing input, returning a reader for the output.
+    pub fn finalize_xof(mut self) -> Shake256Reader {
+        self.0.pad(SHAKE_SUFFIX);
+        Shake256Reader(self.0)
+    }
+}
+
+impl Default for Shake256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The output of SHAKE256, which can be read in as many pieces as needed.
+#[derive(Clone)]
+pub struct Shake256Reader(Sponge);
+
+impl Shake256Reader {
+    /// Fill a buffer with the next bytes of output.
+    pub fn read(&mut self, out: &mut [u8]) {
+        self.0.squeeze(out);
+    }
+}
+
+/// This calculates the SHA3-256 hash of some arbitrary input.
+pub fn sha3_256(message: &[u8]) -> [u8; SHA3_256_SIZE] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+/// This calculates the SHA3-512 hash of some arbitrary input.
+pub fn sha3_512(message: &[u8]) -> [u8; SHA3_512_SIZE] {
+    let mut hasher = Sha3_512::new();
+    hasher.update(message);
+    hasher.finalize()
+}
+
+/// This fills a buffer with the SHAKE256 output for some arbitrary input.
+pub fn shake256(message: &[u8], out: &mut [u8]) {
+    let mut hasher = Shake256::new();
+    hasher.update(message);
+    hasher.finalize_xof().read(out);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn long_message() -> [u8; 300] {
+        let mut message = [0; 300];
+        for (i, x) in message.iter_mut().enumerate() {
+            *x = (i % 251) as u8;
+        }
+        message
+    }
+
+    #[test]
+    fn test_sha3_vectors() {
+        assert_eq!(
+            hex::encode(sha3_256(b"")),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+        assert_eq!(
+            hex::encode(sha3_512(b"abc")),
+            "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"
+        );
+        // This spans several blocks, for both rates.
+        let message = long_message();
+        assert_eq!(
+            hex::encode(sha3_256(&message)),
+            "4be64d77dff18f218eeb40368f86ed78e6d4f2381c71675ab5ada46aa4fee621"
+        );
+        assert_eq!(
+            hex::encode(sha3_512(&message)),
+            "f41046d91092e2fc5dc1d9cc47a02d71f2809a4fa5a4bbacf35e10459ac853cc366c6579ca3705a59ac76ccbf0d835f2e56518c441c9d677c6801e19527b3327"
+        );
+    }
+
+    #[test]
+    fn test_shake256_vectors() {
+        let mut out = [0; 64];
+        shake256(b"", &mut out);
+        assert_eq!(
+            hex::encode(out),
+            "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762fd75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be"
+        );
+        // Reading in uneven pieces, past the end of a block, gives the same output.
+        let expected = "177c5689012e0ac1f06bea70d7946bf8dfc291cf53ed38086e0b6c81815e3800364a1a05690e3bab822c4959c3f11f90e6e4d2502487538281d90ed5fe2451f23c771dec6dcafdaccbeeba16ffffc057bb5a72a12d0cd2d05affdf892a28f22dfed62545a0fbccb7d779d9b0672442f08592da092d2489a5d5ea6836fd2145de5426343a03bb1cc45c14f03ad0b0136c3bea919d9ad6cc4c7c9c2c5b7f3f11e138dda3db9f7feb6c4c08df8a8590dbe1c1256d92134db25fb404467d974e601b20a2c34dca5d3b46";
+        let mut hasher = Shake256::new();
+        let message = long_message();
+        hasher.update(&message[..100]);
+        hasher.update(&message[100..]);
+        let mut reader = hasher.finalize_xof();
+        let mut out = [0; 200];
+        reader.read(&mut out[..7]);
+        reader.read(&mut out[7..150]);
+        reader.read(&mut out[150..]);
+        assert_eq!(hex::encode(out), expected);
+    }
+}
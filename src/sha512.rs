@@ -1,13 +1,37 @@
 //! This module exists to implement the SHA-512 hash function, which is necessary
 //! for Ed25519 signatures.
 //!
+//! SHA-384 and SHA-512/256 are also provided, since they only differ from SHA-512
+//! in their initial hash value, and in truncating the output.
+//!
 //! This file tries to follow RFC 6234 (https://datatracker.ietf.org/doc/html/rfc6234).
+//!
+//! The compression function has accelerated versions on aarch64 and x86_64,
+//! selected at runtime, unless the `pure` feature is enabled.
+
+use core::{convert::TryInto, mem::size_of};
 
-use std::{convert::TryInto, mem::size_of};
+// The aarch64 backend is also tested on other targets, by emulating its instructions.
+#[cfg(all(any(target_arch = "aarch64", test), not(feature = "pure")))]
+mod aarch64;
+mod multibuffer;
+#[cfg(all(target_arch = "x86_64", not(feature = "pure")))]
+mod x86_64;
 
-// This is the number of bytes in our 512 bit hash.
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+/// This is the number of bytes in our 512 bit hash.
 pub const HASH_SIZE: usize = 64;
 
+/// The number of bytes in a SHA-384 hash.
+pub const SHA384_SIZE: usize = 48;
+
+/// The number of bytes in a SHA-512/256 hash.
+pub const SHA512_256_SIZE: usize = 32;
+
 /// BLOCK_SIZE is the number of bytes needed to make a 1024 bit block
 ///
 /// This block structure is described in Section 4:
@@ -75,11 +99,51 @@ const K: [u64; 80] = [
     0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
 ];
 
+/// The initial hash value for SHA-512, from Section 6.3:
+/// https://datatracker.ietf.org/doc/html/rfc6234#section-6.3
+const SHA512_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The initial hash value for SHA-384, also from Section 6.3:
+/// https://datatracker.ietf.org/doc/html/rfc6234#section-6.3
+const SHA384_IV: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+/// The initial hash value for SHA-512/256, from Section 5.3.6.2 of FIPS 180-4:
+/// https://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.180-4.pdf
+const SHA512_256_IV: [u64; 8] = [
+    0x22312194fc2bf72c,
+    0x9f555fa3c84c64c2,
+    0x2393b86b6f53b151,
+    0x963877195940eabd,
+    0x96283ee2a88effe3,
+    0xbe5e1e2553863992,
+    0x2b0199fc2c85b8aa,
+    0x0eb72ddc81c52ca2,
+];
+
 /// This is used to avoid allocating new space for the message schedule for each block.
 ///
 /// This is a struct of our invention, and is used to carry out part 1 of the algorithm
 /// in Section 6.3:
 /// https://datatracker.ietf.org/doc/html/rfc6234#section-6.3
+#[derive(Clone)]
 struct MessageSchedule {
     words: [u64; 80],
 }
@@ -116,6 +180,7 @@ impl MessageSchedule {
 ///
 /// This can be thought of as the ongoing state of our hash function,
 /// which gets modified using our message blocks.
+#[derive(Clone)]
 struct HashValue {
     data: [u64; 8],
     schedule: MessageSchedule,
@@ -124,25 +189,37 @@ struct HashValue {
 impl HashValue {
     /// Create an initial hash value, as per Section 6.3:
     /// https://datatracker.ietf.org/doc/html/rfc6234#section-6.3
-    fn initial() -> HashValue {
+    ///
+    /// Only the initial data differs between SHA-512, and its truncated variants.
+    fn initial(iv: [u64; 8]) -> HashValue {
         HashValue {
-            data: [
-                0x6a09e667f3bcc908,
-                0xbb67ae8584caa73b,
-                0x3c6ef372fe94f82b,
-                0xa54ff53a5f1d36f1,
-                0x510e527fade682d1,
-                0x9b05688c2b3e6c1f,
-                0x1f83d9abfb41bd6b,
-                0x5be0cd19137e2179,
-            ],
+            data: iv,
             schedule: MessageSchedule::new(),
         }
     }
 
+    /// Update the current hash value with a new block.
+    ///
+    /// This uses an accelerated backend, if the CPU supports one.
+    fn update(&mut self, block: &[u8; BLOCK_SIZE]) {
+        #[cfg(all(target_arch = "aarch64", not(feature = "pure")))]
+        if aarch64::available() {
+            // Safe, since we've just checked that the CPU has the right instructions.
+            unsafe { aarch64::compress(&mut self.data, block) };
+            return;
+        }
+        #[cfg(all(target_arch = "x86_64", not(feature = "pure")))]
+        if x86_64::available() {
+            // Safe, since we've just checked that the CPU has the right instructions.
+            unsafe { x86_64::compress(&mut self.data, block) };
+            return;
+        }
+        self.update_portable(block);
+    }
+
     /// Update the current hash value, as per Section 6.3:
     /// https://datatracker.ietf.org/doc/html/rfc6234#section-6.3
-    fn update(&mut self, block: &[u8; BLOCK_SIZE]) {
+    fn update_portable(&mut self, block: &[u8; BLOCK_SIZE]) {
         // The following titles are quoted from the algorithm in Section 6.3:
 
         // 1. Prepare the message schedule W:
@@ -199,56 +276,298 @@ impl HashValue {
     }
 }
 
+/// An incremental SHA-512 hasher, for messages which arrive in pieces.
+///
+/// This only ever holds a single block of buffered input, so hashing uses
+/// a constant amount of memory, no matter how long the message is.
+#[derive(Clone)]
+pub struct Hasher {
+    hash_value: HashValue,
+    buffer: [u8; BLOCK_SIZE],
+    buffered: usize,
+    length: u128,
+}
+
+impl Hasher {
+    /// Create a new hasher, with no input.
+    pub fn new() -> Self {
+        Hasher::with_iv(SHA512_IV)
+    }
+
+    /// Create a new hasher for SHA-384, with no input.
+    ///
+    /// The hash is the first `SHA384_SIZE` bytes of what `finalize` returns.
+    pub fn sha384() -> Self {
+        Hasher::with_iv(SHA384_IV)
+    }
+
+    fn with_iv(iv: [u64; 8]) -> Self {
+        Hasher {
+            hash_value: HashValue::initial(iv),
+            buffer: [0; BLOCK_SIZE],
+            buffered: 0,
+            length: 0,
+        }
+    }
+
+    /// Feed more of the message into this hasher.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u128;
+        if self.buffered > 0 {
+            let to_copy = data.len().min(BLOCK_SIZE - self.buffered);
+            self.buffer[self.buffered..self.buffered + to_copy].copy_from_slice(&data[..to_copy]);
+            self.buffered += to_copy;
+            data = &data[to_copy..];
+            if self.buffered < BLOCK_SIZE {
+                return;
+            }
+            self.hash_value.update(&self.buffer);
+            self.buffered = 0;
+        }
+        let mut blocks = data.chunks_exact(BLOCK_SIZE);
+        for block in &mut blocks {
+            self.hash_value.update(block.try_into().unwrap());
+        }
+        let remainder = blocks.remainder();
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffered = remainder.len();
+    }
+
+    /// Finish hashing, producing the hash of all the input so far.
+    pub fn finalize(mut self) -> [u8; HASH_SIZE] {
+        // Now, we need to handle padding, as per Section 4.2:
+        // https://datatracker.ietf.org/doc/html/rfc6234#section-4.2
+        let remainder_len = self.buffered;
+
+        // This buffer is used to contain whatever remaining blocks we feed into the hasher
+        let mut scratch_block = [0; BLOCK_SIZE];
+        scratch_block[..remainder_len].copy_from_slice(&self.buffer[..remainder_len]);
+
+        // a. "1" is appended
+        scratch_block[remainder_len] = 0b1000_0000;
+
+        // b. K "0"s are appended where K is the smallest, non-negative solution
+        // to the equation
+        //     ( L + 1 + K ) mod 1024 = 896
+
+        // Here, the 1 we add includes the zero bits we've already added.
+        let l_plus_1 = remainder_len + 1;
+        let desired_size = BLOCK_SIZE - size_of::<u128>();
+        // In this case, we have two extra blocks, one of which is already ready
+        if l_plus_1 > desired_size {
+            self.hash_value.update(&scratch_block);
+            scratch_block.fill(0);
+        }
+
+        // c. Then append the 128-bit block that is L in binary representation.
+        let l = 8 * self.length;
+        scratch_block[BLOCK_SIZE - size_of::<u128>()..].copy_from_slice(&l.to_be_bytes());
+
+        self.hash_value.update(&scratch_block);
+
+        self.hash_value.result()
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// With these, `Hasher` implements `digest::Digest`, as SHA-512, for use with other crates.
+#[cfg(feature = "digest")]
+impl digest::HashMarker for Hasher {}
+
+#[cfg(feature = "digest")]
+impl digest::OutputSizeUser for Hasher {
+    type OutputSize = digest::consts::U64;
+}
+
+#[cfg(feature = "digest")]
+impl digest::Update for Hasher {
+    fn update(&mut self, data: &[u8]) {
+        Hasher::update(self, data)
+    }
+}
+
+#[cfg(feature = "digest")]
+impl digest::FixedOutput for Hasher {
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        out.copy_from_slice(&self.finalize())
+    }
+}
+
+/// An adapter hashing everything written through it, before passing it along.
+///
+/// This lets a stream be hashed with `io::copy`, while also being written elsewhere,
+/// like to a file. To only hash the data, use `io::sink()` as the inner writer.
+#[cfg(feature = "std")]
+pub struct Sha512Writer<W> {
+    inner: W,
+    hasher: Hasher,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Sha512Writer<W> {
+    /// Create a new adapter, passing data along to an inner writer.
+    pub fn new(inner: W) -> Self {
+        Sha512Writer {
+            inner,
+            hasher: Hasher::new(),
+        }
+    }
+
+    /// Finish hashing, returning the inner writer, and the hash of everything written.
+    pub fn finalize(self) -> (W, [u8; HASH_SIZE]) {
+        (self.inner, self.hasher.finalize())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for Sha512Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Only the bytes the inner writer accepted count as written.
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
 /// This calculates the SHA-512 hash of some arbitrary input, producing 512 bits of output.
 ///
 /// This implements the function as defined in RFC 6234:
 /// https://datatracker.ietf.org/doc/html/rfc6234
 pub fn hash(message: &[u8]) -> [u8; HASH_SIZE] {
-    let mut hash_value = HashValue::initial();
-
-    let mut blocks = message.chunks_exact(BLOCK_SIZE);
-    for block in &mut blocks {
-        hash_value.update(block.try_into().unwrap());
-    }
+    let mut hasher = Hasher::new();
+    hasher.update(message);
+    hasher.finalize()
+}
 
-    let remainder = blocks.remainder();
-    let remainder_len = remainder.len();
+/// This calculates the SHA-384 hash of some arbitrary input.
+///
+/// This is SHA-512, with a different initial hash value, truncated to 384 bits.
+pub fn sha384(message: &[u8]) -> [u8; SHA384_SIZE] {
+    let mut hasher = Hasher::sha384();
+    hasher.update(message);
+    hasher.finalize()[..SHA384_SIZE].try_into().unwrap()
+}
 
-    // Now, we need to handle padding, as per Section 4.2:
-    // https://datatracker.ietf.org/doc/html/rfc6234#section-4.2
+/// This calculates the SHA-512/256 hash of some arbitrary input.
+///
+/// This is SHA-512, with a different initial hash value, truncated to 256 bits.
+/// Unlike SHA-256, this uses 64 bit operations, and is faster on most machines.
+pub fn sha512_256(message: &[u8]) -> [u8; SHA512_256_SIZE] {
+    let mut hasher = Hasher::with_iv(SHA512_256_IV);
+    hasher.update(message);
+    hasher.finalize()[..SHA512_256_SIZE].try_into().unwrap()
+}
 
-    // This buffer is used to contain whatever remaining blocks we feed into the hasher
-    let mut scratch_block = [0; BLOCK_SIZE];
-    scratch_block[..remainder_len].copy_from_slice(remainder);
+/// The number of blocks a message of a given length takes up, once padded.
+fn padded_block_count(len: usize) -> usize {
+    (len + 1 + size_of::<u128>()).div_ceil(BLOCK_SIZE)
+}
 
-    // a. "1" is appended
-    scratch_block[remainder_len] = 0b1000_0000;
+/// Get a given block of a message, after padding, as per Section 4.2:
+/// https://datatracker.ietf.org/doc/html/rfc6234#section-4.2
+///
+/// The message is the concatenation of some parts, of total length `len`, which
+/// lets callers hash things like R || A || M without copying them together first.
+fn padded_block(parts: &[&[u8]], len: usize, index: usize) -> [u8; BLOCK_SIZE] {
+    let mut block = [0; BLOCK_SIZE];
+    let start = index * BLOCK_SIZE;
+    let mut offset = 0;
+    for part in parts {
+        let from = offset.max(start);
+        let to = (offset + part.len()).min(start + BLOCK_SIZE);
+        if from < to {
+            block[from - start..to - start].copy_from_slice(&part[from - offset..to - offset]);
+        }
+        offset += part.len();
+    }
+    if (start..start + BLOCK_SIZE).contains(&len) {
+        block[len - start] = 0b1000_0000;
+    }
+    if index + 1 == padded_block_count(len) {
+        let l = 8 * len as u128;
+        block[BLOCK_SIZE - size_of::<u128>()..].copy_from_slice(&l.to_be_bytes());
+    }
+    block
+}
 
-    // b. K "0"s are appended where K is the smallest, non-negative solution
-    // to the equation
-    //     ( L + 1 + K ) mod 1024 = 896
+/// This calculates the SHA-512 hashes of four independent messages at once.
+///
+/// This gives the same results as calling `hash` on each message, but interleaves
+/// the work, using SIMD instructions, which is faster when hashing many short messages.
+pub fn hash4(messages: [&[u8]; 4]) -> [[u8; HASH_SIZE]; 4] {
+    hash4_parts([
+        &messages[..1],
+        &messages[1..2],
+        &messages[2..3],
+        &messages[3..],
+    ])
+}
 
-    // Here, the 1 we add includes the zero bits we've already added.
-    let l_plus_1 = remainder_len + 1;
-    let desired_size = BLOCK_SIZE - size_of::<u128>();
-    // In this case, we have two extra blocks, one of which is already ready
-    if l_plus_1 > desired_size {
-        hash_value.update(&scratch_block);
-        scratch_block.fill(0);
+/// Like `hash4`, but with each message made of several parts, hashed one after the other.
+pub fn hash4_parts(messages: [&[&[u8]]; 4]) -> [[u8; HASH_SIZE]; 4] {
+    let lens = messages.map(|parts| parts.iter().map(|part| part.len()).sum::<usize>());
+    let block_counts = lens.map(padded_block_count);
+    let total_blocks = block_counts.iter().copied().max().unwrap_or(0);
+    let mut states = [SHA512_IV; 4];
+    for i in 0..total_blocks {
+        let blocks = [0, 1, 2, 3].map(|lane| padded_block(messages[lane], lens[lane], i));
+        let previous = states;
+        multibuffer::compress4(
+            &mut states,
+            [&blocks[0], &blocks[1], &blocks[2], &blocks[3]],
+        );
+        // Messages which have already finished just get garbage, which we undo.
+        for lane in 0..4 {
+            if i >= block_counts[lane] {
+                states[lane] = previous[lane];
+            }
+        }
     }
+    states.map(|data| HashValue::initial(data).result())
+}
 
-    // c. Then append the 128-bit block that is L in binary representation.
-    let l = 8 * (message.len() as u128);
-    scratch_block[BLOCK_SIZE - size_of::<u128>()..].copy_from_slice(&l.to_be_bytes());
-
-    hash_value.update(&scratch_block);
+/// This calculates the SHA-512 hashes of many messages, four at a time.
+///
+/// See `hash4`, which this uses for the bulk of the work.
+#[cfg(feature = "alloc")]
+pub fn hash_many(messages: &[&[u8]]) -> Vec<[u8; HASH_SIZE]> {
+    let mut out = Vec::with_capacity(messages.len());
+    for chunk in messages.chunks(4) {
+        let mut group: [&[&[u8]]; 4] = [&[]; 4];
+        for (parts, message) in group.iter_mut().zip(chunk.chunks(1)) {
+            *parts = message;
+        }
+        out.extend_from_slice(&hash4_parts(group)[..chunk.len()]);
+    }
+    out
+}
 
-    hash_value.result()
+/// Like `hash_many`, but with each message made of several parts, hashed one after the other.
+#[cfg(feature = "alloc")]
+pub fn hash_many_parts(messages: &[&[&[u8]]]) -> Vec<[u8; HASH_SIZE]> {
+    let mut out = Vec::with_capacity(messages.len());
+    for chunk in messages.chunks(4) {
+        let mut group: [&[&[u8]]; 4] = [&[]; 4];
+        group[..chunk.len()].copy_from_slice(chunk);
+        out.extend_from_slice(&hash4_parts(group)[..chunk.len()]);
+    }
+    out
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(not(feature = "pure"))]
+    use proptest::prelude::*;
 
     #[test]
     fn test_vectors() {
@@ -289,4 +608,174 @@ mod test {
         ).unwrap();
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_incremental_hashing_matches() {
+        let message: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        for &split in &[1, 63, 127, 128, 129, 500] {
+            let mut hasher = Hasher::new();
+            for chunk in message.chunks(split) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), hash(&message));
+        }
+    }
+
+    #[test]
+    fn test_incremental_sha384_matches() {
+        let message: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut hasher = Hasher::sha384();
+        for chunk in message.chunks(100) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize()[..SHA384_SIZE], sha384(&message));
+    }
+
+    #[test]
+    fn test_truncated_variant_vectors() {
+        let long = [b'a'; 200];
+        let vectors: [(&[u8], &str, &str); 3] = [
+            (
+                b"abc",
+                "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+                "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23",
+            ),
+            (
+                b"",
+                "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+                "c672b8d1ef56ed28ab87c3622c5114069bdd3ad7b8f9737498d0c01ecef0967a",
+            ),
+            (
+                &long,
+                "0691b6e978614b67d60557b2a2cddd53406508522efa21c624dbbfa8ab6e726d5c586b489c7c09f24109a64c10211d48",
+                "19b1e37317d7fd3d7651f397005e31f154ef4912d1345743d2d5889aaca28996",
+            ),
+        ];
+        for (message, expected_384, expected_256) in vectors.iter() {
+            assert_eq!(hex::encode(sha384(message)), *expected_384);
+            assert_eq!(hex::encode(sha512_256(message)), *expected_256);
+        }
+    }
+
+    #[cfg(not(feature = "pure"))]
+    fn portable_compress(state: [u64; 8], block: &[u8]) -> [u64; 8] {
+        let mut value = HashValue::initial(state);
+        value.update_portable(block.try_into().unwrap());
+        value.data
+    }
+
+    // These check that the accelerated backends match the portable one.
+    #[cfg(not(feature = "pure"))]
+    proptest! {
+        #[test]
+        fn test_aarch64_matches_portable(
+            mut state in any::<[u64; 8]>(),
+            block in prop::collection::vec(any::<u8>(), BLOCK_SIZE)
+        ) {
+            let expected = portable_compress(state, &block);
+            // Outside of aarch64, this runs with the instructions emulated.
+            #[cfg(target_arch = "aarch64")]
+            prop_assume!(aarch64::available());
+            unsafe { aarch64::compress(&mut state, block[..].try_into().unwrap()) };
+            assert_eq!(state, expected);
+        }
+    }
+
+    #[cfg(all(target_arch = "x86_64", not(feature = "pure")))]
+    proptest! {
+        #[test]
+        fn test_x86_64_matches_portable(
+            mut state in any::<[u64; 8]>(),
+            block in prop::collection::vec(any::<u8>(), BLOCK_SIZE)
+        ) {
+            let expected = portable_compress(state, &block);
+            prop_assume!(x86_64::available());
+            unsafe { x86_64::compress(&mut state, block[..].try_into().unwrap()) };
+            assert_eq!(state, expected);
+        }
+    }
+
+    #[test]
+    fn test_hash4_matches_hash() {
+        let mut message = [0u8; 600];
+        for (i, x) in message.iter_mut().enumerate() {
+            *x = i as u8;
+        }
+        // These lengths cover the edge cases of padding, and messages of different lengths.
+        let lengths = [0, 1, 111, 112, 127, 128, 129, 239, 240, 256, 300, 600];
+        for window in lengths.windows(4) {
+            let messages = [
+                &message[..window[0]],
+                &message[..window[1]],
+                &message[..window[2]],
+                &message[..window[3]],
+            ];
+            let hashes = hash4(messages);
+            for (m, h) in messages.iter().zip(hashes.iter()) {
+                assert_eq!(*h, hash(m));
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_hash_many_matches_hash() {
+        let messages: Vec<Vec<u8>> = (0..11).map(|i| vec![i as u8; 50 * i]).collect();
+        let slices: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        let hashes = hash_many(&slices);
+        assert_eq!(hashes.len(), messages.len());
+        for (m, h) in messages.iter().zip(hashes.iter()) {
+            assert_eq!(*h, hash(m));
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_hash_many_parts_matches_hash() {
+        let message: Vec<u8> = (0..400).map(|i| i as u8).collect();
+        // Split points which put block boundaries inside, and between, parts
+        let splits = [
+            (0, 0),
+            (0, 128),
+            (32, 64),
+            (100, 300),
+            (127, 129),
+            (400, 400),
+        ];
+        let parts: Vec<[&[u8]; 3]> = splits
+            .iter()
+            .map(|&(a, b)| [&message[..a], &message[a..b], &message[b..]])
+            .collect();
+        let inputs: Vec<&[&[u8]]> = parts.iter().map(|p| &p[..]).collect();
+        for h in hash_many_parts(&inputs) {
+            assert_eq!(h, hash(&message));
+        }
+        assert_eq!(hash_many_parts(&[&[]]), vec![hash(b"")]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_writer_matches_hash() {
+        let message = [7u8; 1000];
+        let mut writer = Sha512Writer::new(Vec::new());
+        io::copy(&mut &message[..], &mut writer).unwrap();
+        let (written, digest) = writer.finalize();
+        assert_eq!(written, message);
+        assert_eq!(digest, hash(&message));
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn test_digest_traits_match_hash() {
+        use digest::Digest;
+
+        for message in [&b""[..], b"abc", &[7; 1000]] {
+            assert_eq!(Hasher::digest(message)[..], hash(message)[..]);
+            let mut hasher = Hasher::new();
+            for chunk in message.chunks(100) {
+                Digest::update(&mut hasher, chunk);
+            }
+            assert_eq!(hasher.finalize()[..], hash(message)[..]);
+        }
+    }
 }
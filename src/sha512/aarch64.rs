@@ -0,0 +1,165 @@
+//! SHA-512 compression using the SHA-512 instructions from ARMv8.2.
+//!
+//! SHA512H and SHA512H2 each perform half of two rounds, and SHA512SU0 and SHA512SU1
+//! extend the message schedule two words at a time. The state is kept as four
+//! vectors, holding (a, b), (c, d), (e, f), and (g, h), whose roles rotate every two
+//! rounds. This follows the same structure as ARM's reference code.
+//!
+//! On other targets, this module is only compiled for tests, with the instructions
+//! emulated, so that the structure can be checked against our portable code.
+use core::convert::TryInto;
+
+#[cfg(target_arch = "aarch64")]
+use core::arch::aarch64::{
+    uint64x2_t, vaddq_u64, vextq_u64, vld1q_u64, vsha512h2q_u64, vsha512hq_u64, vsha512su0q_u64,
+    vsha512su1q_u64, vst1q_u64,
+};
+
+#[cfg(not(target_arch = "aarch64"))]
+use self::emulated::*;
+use super::{BLOCK_SIZE, K};
+
+/// Check whether or not the CPU supports the SHA-512 instructions.
+#[cfg(target_arch = "aarch64")]
+pub fn available() -> bool {
+    // Rust groups the SHA-512 and SHA-3 extensions under this name.
+    #[cfg(feature = "std")]
+    {
+        std::arch::is_aarch64_feature_detected!("sha3")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(target_feature = "sha3")
+    }
+}
+
+// Two rounds, starting at round t, with the roles of the state vectors given explicitly.
+macro_rules! rounds {
+    ($s:expr, $t:expr, $ab:ident, $cd:ident, $ef:ident, $gh:ident) => {
+        let initial_sum = vaddq_u64($s, vld1q_u64(K[$t..].as_ptr()));
+        let sum = vaddq_u64(vextq_u64(initial_sum, initial_sum, 1), $gh);
+        let intermed = vsha512hq_u64(sum, vextq_u64($ef, $gh, 1), vextq_u64($cd, $ef, 1));
+        $gh = vsha512h2q_u64(intermed, $cd, $ab);
+        $cd = vaddq_u64($cd, intermed);
+    };
+}
+
+/// Update a hash value with a new block, like `HashValue::update`.
+///
+/// # Safety
+///
+/// The CPU must support the SHA-512 instructions, as checked by `available`.
+#[cfg_attr(target_arch = "aarch64", target_feature(enable = "sha3"))]
+pub unsafe fn compress(state: &mut [u64; 8], block: &[u8; BLOCK_SIZE]) {
+    let mut w = [0u64; 16];
+    for (w_t, chunk) in w.iter_mut().zip(block.chunks_exact(8)) {
+        *w_t = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    let mut s = [vld1q_u64(w.as_ptr()); 8];
+    for (i, s_i) in s.iter_mut().enumerate() {
+        *s_i = vld1q_u64(w[2 * i..].as_ptr());
+    }
+
+    let mut ab = vld1q_u64(state[0..].as_ptr());
+    let mut cd = vld1q_u64(state[2..].as_ptr());
+    let mut ef = vld1q_u64(state[4..].as_ptr());
+    let mut gh = vld1q_u64(state[6..].as_ptr());
+    let (ab_orig, cd_orig, ef_orig, gh_orig) = (ab, cd, ef, gh);
+
+    for t in (0..80).step_by(16) {
+        for i in 0..8 {
+            if t >= 16 {
+                // This produces words t + 2i and t + 2i + 1 of the schedule.
+                s[i] = vsha512su1q_u64(
+                    vsha512su0q_u64(s[i], s[(i + 1) % 8]),
+                    s[(i + 7) % 8],
+                    vextq_u64(s[(i + 4) % 8], s[(i + 5) % 8], 1),
+                );
+            }
+            let r = t + 2 * i;
+            match i % 4 {
+                0 => {
+                    rounds!(s[i], r, ab, cd, ef, gh);
+                }
+                1 => {
+                    rounds!(s[i], r, gh, ab, cd, ef);
+                }
+                2 => {
+                    rounds!(s[i], r, ef, gh, ab, cd);
+                }
+                _ => {
+                    rounds!(s[i], r, cd, ef, gh, ab);
+                }
+            }
+        }
+    }
+
+    vst1q_u64(state[0..].as_mut_ptr(), vaddq_u64(ab, ab_orig));
+    vst1q_u64(state[2..].as_mut_ptr(), vaddq_u64(cd, cd_orig));
+    vst1q_u64(state[4..].as_mut_ptr(), vaddq_u64(ef, ef_orig));
+    vst1q_u64(state[6..].as_mut_ptr(), vaddq_u64(gh, gh_orig));
+}
+
+/// Portable versions of the instructions we use, with lane 0 holding the low 64 bits.
+///
+/// These follow the pseudocode in the Arm Architecture Reference Manual.
+#[cfg(not(target_arch = "aarch64"))]
+#[allow(non_camel_case_types)]
+mod emulated {
+    use super::super::{bsig0, bsig1, ssig0, ssig1};
+
+    pub type uint64x2_t = [u64; 2];
+
+    pub unsafe fn vld1q_u64(ptr: *const u64) -> uint64x2_t {
+        [*ptr, *ptr.add(1)]
+    }
+
+    pub unsafe fn vst1q_u64(ptr: *mut u64, a: uint64x2_t) {
+        *ptr = a[0];
+        *ptr.add(1) = a[1];
+    }
+
+    pub unsafe fn vaddq_u64(a: uint64x2_t, b: uint64x2_t) -> uint64x2_t {
+        [a[0].wrapping_add(b[0]), a[1].wrapping_add(b[1])]
+    }
+
+    pub unsafe fn vextq_u64(a: uint64x2_t, b: uint64x2_t, n: i32) -> uint64x2_t {
+        assert_eq!(n, 1);
+        [a[1], b[0]]
+    }
+
+    pub unsafe fn vsha512hq_u64(w: uint64x2_t, x: uint64x2_t, y: uint64x2_t) -> uint64x2_t {
+        let hi = ((y[1] & x[0]) ^ (!y[1] & x[1]))
+            .wrapping_add(bsig1(y[1]))
+            .wrapping_add(w[1]);
+        let tmp = hi.wrapping_add(y[0]);
+        let lo = ((tmp & y[1]) ^ (!tmp & x[0]))
+            .wrapping_add(bsig1(tmp))
+            .wrapping_add(w[0]);
+        [lo, hi]
+    }
+
+    pub unsafe fn vsha512h2q_u64(w: uint64x2_t, x: uint64x2_t, y: uint64x2_t) -> uint64x2_t {
+        let hi = ((x[0] & y[1]) ^ (x[0] & y[0]) ^ (y[1] & y[0]))
+            .wrapping_add(bsig0(y[0]))
+            .wrapping_add(w[1]);
+        let lo = ((hi & y[0]) ^ (hi & y[1]) ^ (y[1] & y[0]))
+            .wrapping_add(bsig0(hi))
+            .wrapping_add(w[0]);
+        [lo, hi]
+    }
+
+    pub unsafe fn vsha512su0q_u64(w: uint64x2_t, x: uint64x2_t) -> uint64x2_t {
+        [
+            w[0].wrapping_add(ssig0(w[1])),
+            w[1].wrapping_add(ssig0(x[0])),
+        ]
+    }
+
+    pub unsafe fn vsha512su1q_u64(w: uint64x2_t, x: uint64x2_t, y: uint64x2_t) -> uint64x2_t {
+        [
+            w[0].wrapping_add(ssig1(x[0])).wrapping_add(y[0]),
+            w[1].wrapping_add(ssig1(x[1])).wrapping_add(y[1]),
+        ]
+    }
+}
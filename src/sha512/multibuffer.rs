@@ -0,0 +1,158 @@
+//! A 4-way interleaved SHA-512 compression function.
+//!
+//! This compresses one block from each of four independent messages at once.
+//! The working variables hold one 64 bit word per message, and every operation is
+//! applied to all four lanes of an AVX2 register at once. Without AVX2, we fall back
+//! to compressing each block on its own.
+use super::{HashValue, BLOCK_SIZE};
+
+const LANES: usize = 4;
+
+#[cfg(all(target_arch = "x86_64", not(feature = "pure")))]
+mod avx2 {
+    use core::arch::x86_64::*;
+    use core::convert::TryInto;
+
+    use super::super::K;
+    use super::{BLOCK_SIZE, LANES};
+
+    #[inline(always)]
+    unsafe fn add(x: __m256i, y: __m256i) -> __m256i {
+        _mm256_add_epi64(x, y)
+    }
+
+    #[inline(always)]
+    unsafe fn xor(x: __m256i, y: __m256i) -> __m256i {
+        _mm256_xor_si256(x, y)
+    }
+
+    // AVX2 has no 64 bit rotation, so we need to combine two shifts.
+    macro_rules! rotr {
+        ($x:expr, $n:literal) => {
+            _mm256_or_si256(_mm256_srli_epi64($x, $n), _mm256_slli_epi64($x, 64 - $n))
+        };
+    }
+
+    #[inline(always)]
+    unsafe fn bsig0(x: __m256i) -> __m256i {
+        xor(xor(rotr!(x, 28), rotr!(x, 34)), rotr!(x, 39))
+    }
+
+    #[inline(always)]
+    unsafe fn bsig1(x: __m256i) -> __m256i {
+        xor(xor(rotr!(x, 14), rotr!(x, 18)), rotr!(x, 41))
+    }
+
+    #[inline(always)]
+    unsafe fn ssig0(x: __m256i) -> __m256i {
+        xor(xor(rotr!(x, 1), rotr!(x, 8)), _mm256_srli_epi64(x, 7))
+    }
+
+    #[inline(always)]
+    unsafe fn ssig1(x: __m256i) -> __m256i {
+        xor(xor(rotr!(x, 19), rotr!(x, 61)), _mm256_srli_epi64(x, 6))
+    }
+
+    #[inline(always)]
+    unsafe fn ch(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        xor(_mm256_and_si256(x, y), _mm256_andnot_si256(x, z))
+    }
+
+    #[inline(always)]
+    unsafe fn maj(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        // This is equivalent to (x & y) ^ (x & z) ^ (y & z), with one less operation.
+        _mm256_or_si256(
+            _mm256_and_si256(x, y),
+            _mm256_and_si256(z, _mm256_or_si256(x, y)),
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn gather(words: [u64; LANES]) -> __m256i {
+        _mm256_set_epi64x(
+            words[3] as i64,
+            words[2] as i64,
+            words[1] as i64,
+            words[0] as i64,
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn scatter(x: __m256i) -> [u64; LANES] {
+        let mut out = [0u64; LANES];
+        _mm256_storeu_si256(out.as_mut_ptr() as *mut __m256i, x);
+        out
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn compress4(states: &mut [[u64; 8]; LANES], blocks: [&[u8; BLOCK_SIZE]; LANES]) {
+        let word = |lane: usize, t: usize| {
+            u64::from_be_bytes(blocks[lane][8 * t..8 * t + 8].try_into().unwrap())
+        };
+        let mut w = [_mm256_setzero_si256(); 16];
+        for (t, w_t) in w.iter_mut().enumerate() {
+            *w_t = gather([word(0, t), word(1, t), word(2, t), word(3, t)]);
+        }
+        let mut v = [_mm256_setzero_si256(); 8];
+        for (i, v_i) in v.iter_mut().enumerate() {
+            *v_i = gather([states[0][i], states[1][i], states[2][i], states[3][i]]);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = v;
+        for t in 0..80 {
+            if t >= 16 {
+                // We only keep the last 16 words of the schedule, overwriting the oldest.
+                w[t % 16] = add(
+                    add(ssig1(w[(t - 2) % 16]), w[(t - 7) % 16]),
+                    add(ssig0(w[(t - 15) % 16]), w[t % 16]),
+                );
+            }
+            let k_w = add(_mm256_set1_epi64x(K[t] as i64), w[t % 16]);
+            let t1 = add(add(h, bsig1(e)), add(ch(e, f, g), k_w));
+            let t2 = add(bsig0(a), maj(a, b, c));
+            h = g;
+            g = f;
+            f = e;
+            e = add(d, t1);
+            d = c;
+            c = b;
+            b = a;
+            a = add(t1, t2);
+        }
+
+        for (i, (x, v_i)) in [a, b, c, d, e, f, g, h].iter().zip(v.iter()).enumerate() {
+            let lanes = scatter(add(*x, *v_i));
+            for (lane, state) in states.iter_mut().enumerate() {
+                state[i] = lanes[lane];
+            }
+        }
+    }
+}
+
+/// Update four hash values, each with a new block.
+pub fn compress4(states: &mut [[u64; 8]; LANES], blocks: [&[u8; BLOCK_SIZE]; LANES]) {
+    #[cfg(all(target_arch = "x86_64", not(feature = "pure")))]
+    if avx2_available() {
+        // Safe, since we've just checked that the CPU supports AVX2.
+        unsafe { avx2::compress4(states, blocks) };
+        return;
+    }
+    // Without SIMD, this is no faster than compressing each block on its own.
+    for (state, block) in states.iter_mut().zip(blocks.iter()) {
+        let mut value = HashValue::initial(*state);
+        value.update(block);
+        *state = value.data;
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "pure")))]
+fn avx2_available() -> bool {
+    #[cfg(feature = "std")]
+    {
+        std::is_x86_feature_detected!("avx2")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(target_feature = "avx2")
+    }
+}
@@ -0,0 +1,72 @@
+//! An unrolled SHA-512 compression function, for x86_64 CPUs with BMI2.
+//!
+//! Unrolling eight rounds at a time lets the working variables rotate through
+//! their roles by renaming, instead of shuffling them after every round, and keeping
+//! only 16 words of the message schedule lets everything stay in registers. With BMI2,
+//! the many rotations compile to `rorx`, which doesn't touch flags, and schedules better.
+use core::convert::TryInto;
+
+use super::{bsig0, bsig1, ch, maj, ssig0, ssig1, BLOCK_SIZE, K};
+
+/// Check whether or not the CPU supports the instructions this backend needs.
+pub fn available() -> bool {
+    #[cfg(feature = "std")]
+    {
+        std::is_x86_feature_detected!("bmi2")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(target_feature = "bmi2")
+    }
+}
+
+// One round of Section 6.3, with the roles of the working variables given explicitly.
+macro_rules! round {
+    ($a:ident, $b:ident, $c:ident, $d:ident, $e:ident, $f:ident, $g:ident, $h:ident, $k:expr, $w:expr) => {
+        let t1 = $h
+            .wrapping_add(bsig1($e))
+            .wrapping_add(ch($e, $f, $g))
+            .wrapping_add($k)
+            .wrapping_add($w);
+        let t2 = bsig0($a).wrapping_add(maj($a, $b, $c));
+        $d = $d.wrapping_add(t1);
+        $h = t1.wrapping_add(t2);
+    };
+}
+
+/// Update a hash value with a new block, like `HashValue::update`.
+///
+/// # Safety
+///
+/// The CPU must support BMI2, as checked by `available`.
+#[target_feature(enable = "bmi2")]
+pub unsafe fn compress(state: &mut [u64; 8], block: &[u8; BLOCK_SIZE]) {
+    let mut w = [0u64; 16];
+    for (w_t, chunk) in w.iter_mut().zip(block.chunks_exact(8)) {
+        *w_t = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for t in (0..80).step_by(8) {
+        if t >= 16 {
+            // We only keep the last 16 words of the schedule, overwriting the oldest.
+            for i in t..t + 8 {
+                w[i % 16] = ssig1(w[(i - 2) % 16])
+                    .wrapping_add(w[(i - 7) % 16])
+                    .wrapping_add(ssig0(w[(i - 15) % 16]))
+                    .wrapping_add(w[i % 16]);
+            }
+        }
+        let j = t % 16;
+        round!(a, b, c, d, e, f, g, h, K[t], w[j]);
+        round!(h, a, b, c, d, e, f, g, K[t + 1], w[j + 1]);
+        round!(g, h, a, b, c, d, e, f, K[t + 2], w[j + 2]);
+        round!(f, g, h, a, b, c, d, e, K[t + 3], w[j + 3]);
+        round!(e, f, g, h, a, b, c, d, K[t + 4], w[j + 4]);
+        round!(d, e, f, g, h, a, b, c, K[t + 5], w[j + 5]);
+        round!(c, d, e, f, g, h, a, b, K[t + 6], w[j + 6]);
+        round!(b, c, d, e, f, g, h, a, K[t + 7], w[j + 7]);
+    }
+    for (x, y) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *x = x.wrapping_add(y);
+    }
+}
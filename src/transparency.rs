@@ -0,0 +1,223 @@
+//! This module defines an append-only log, recording the signatures a signer issues.
+//!
+//! Each entry records a signature, and the client it was issued to, and is signed in
+//! turn, along with the signature of the previous entry. This chains the entries
+//! together, so that they can't be removed, or reordered, without breaking the chain.
+//!
+//! Entries are signed with a key blinded from the signer's key, see `PrivateKey::blind`,
+//! so that clients can't trick the signer into producing something that looks like
+//! an entry of its log.
+use crate::{BlindedPrivateKey, PrivateKey, PublicKey, Signature, SIGNATURE_SIZE};
+
+/// The blinding factor used to derive the log key from the signer's key.
+const LOG_BLINDING_FACTOR: &[u8; 32] = b"eddo-server audit log signing v1";
+
+/// This is prepended to each entry, before signing it.
+const ENTRY_DOMAIN: &[u8] = b"eddo-server audit log entry v1";
+
+/// The number of bytes signed for each entry.
+const ENTRY_MESSAGE_SIZE: usize = ENTRY_DOMAIN.len() + 8 + SIGNATURE_SIZE + 32 + SIGNATURE_SIZE;
+
+/// Represents the reasons a sequence of entries might not form a valid log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogError {
+    /// The entry at this index had the wrong sequence number.
+    WrongSequence(usize),
+    /// The entry at this index didn't follow the previous one.
+    BrokenChain(usize),
+    /// The entry at this index wasn't signed by the log key.
+    BadSignature(usize),
+}
+
+/// Represents a single entry in the log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogEntry {
+    /// The position of this entry, starting at 0.
+    pub sequence: u64,
+    /// The signature of the previous entry, or zeros for the first one.
+    pub previous: [u8; SIGNATURE_SIZE],
+    /// The client the signature was issued to.
+    pub client: [u8; 32],
+    /// The signature which was issued.
+    pub signature: Signature,
+    /// The log key's signature over this entry.
+    pub entry_signature: Signature,
+}
+
+impl LogEntry {
+    /// Calculate the bytes the log key signs for this entry.
+    fn message(&self) -> [u8; ENTRY_MESSAGE_SIZE] {
+        let mut out = [0; ENTRY_MESSAGE_SIZE];
+        let parts: [&[u8]; 5] = [
+            ENTRY_DOMAIN,
+            &self.sequence.to_be_bytes(),
+            &self.previous,
+            &self.client,
+            &self.signature.bytes,
+        ];
+        let mut at = 0;
+        for part in parts {
+            out[at..at + part.len()].copy_from_slice(part);
+            at += part.len();
+        }
+        out
+    }
+}
+
+/// The key used to sign a signer's log, derived from its private key.
+pub fn log_key(private: &PrivateKey) -> BlindedPrivateKey {
+    private.blind(LOG_BLINDING_FACTOR)
+}
+
+/// The public key of a signer's log, derived from the signer's public key.
+///
+/// This returns `None` if the public key isn't a valid point.
+pub fn log_public_key(public: &PublicKey) -> Option<PublicKey> {
+    public.blind(LOG_BLINDING_FACTOR)
+}
+
+/// Represents the end of a log, where new entries get appended.
+///
+/// This only produces entries; storing them is up to the caller, which should do
+/// so before handing out the signature they record.
+#[derive(Debug, Clone)]
+pub struct TransparencyLog {
+    key: BlindedPrivateKey,
+    sequence: u64,
+    previous: [u8; SIGNATURE_SIZE],
+}
+
+impl TransparencyLog {
+    /// Start a new, empty, log for a signer.
+    pub fn new(private: &PrivateKey) -> Self {
+        TransparencyLog {
+            key: log_key(private),
+            sequence: 0,
+            previous: [0; SIGNATURE_SIZE],
+        }
+    }
+
+    /// Continue a log, after the last entry already stored.
+    pub fn resume(private: &PrivateKey, last: &LogEntry) -> Self {
+        TransparencyLog {
+            key: log_key(private),
+            sequence: last.sequence + 1,
+            previous: last.entry_signature.bytes,
+        }
+    }
+
+    /// Create the next entry, recording that a signature was issued to a client.
+    pub fn append(&mut self, client: &[u8; 32], signature: Signature) -> LogEntry {
+        let mut entry = LogEntry {
+            sequence: self.sequence,
+            previous: self.previous,
+            client: *client,
+            signature,
+            entry_signature: Signature {
+                bytes: [0; SIGNATURE_SIZE],
+            },
+        };
+        entry.entry_signature = self.key.sign(&entry.message());
+        self.sequence += 1;
+        self.previous = entry.entry_signature.bytes;
+        entry
+    }
+}
+
+/// Check that entries form a complete log, starting from the first entry.
+///
+/// The key is the log's public key, as returned by `log_public_key`.
+pub fn verify_log<'a>(
+    log_public: &PublicKey,
+    entries: impl IntoIterator<Item = &'a LogEntry>,
+) -> Result<(), LogError> {
+    let mut previous = [0; SIGNATURE_SIZE];
+    for (i, entry) in entries.into_iter().enumerate() {
+        if entry.sequence != i as u64 {
+            return Err(LogError::WrongSequence(i));
+        }
+        if entry.previous != previous {
+            return Err(LogError::BrokenChain(i));
+        }
+        if !log_public.verify(&entry.message(), entry.entry_signature) {
+            return Err(LogError::BadSignature(i));
+        }
+        previous = entry.entry_signature.bytes;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn log(count: u8) -> (PublicKey, Vec<LogEntry>) {
+        let private = PrivateKey { bytes: [1; 32] };
+        let mut log = TransparencyLog::new(&private);
+        let entries = (0..count)
+            .map(|i| log.append(&[i; 32], private.sign(&[i])))
+            .collect();
+        (log_public_key(&private.public_key()).unwrap(), entries)
+    }
+
+    #[test]
+    fn test_log_verifies() {
+        let (public, entries) = log(4);
+        assert_eq!(verify_log(&public, &entries), Ok(()));
+        assert_eq!(verify_log(&public, &entries[..0]), Ok(()));
+    }
+
+    #[test]
+    fn test_log_key_matches() {
+        let private = PrivateKey { bytes: [1; 32] };
+        assert_eq!(
+            log_key(&private).public_key(),
+            log_public_key(&private.public_key()).unwrap()
+        );
+        // The log key isn't the signer's key
+        assert_ne!(log_key(&private).public_key(), private.public_key());
+    }
+
+    #[test]
+    fn test_resume_continues_chain() {
+        let private = PrivateKey { bytes: [1; 32] };
+        let (public, mut entries) = log(2);
+        let mut log = TransparencyLog::resume(&private, &entries[1]);
+        entries.push(log.append(&[9; 32], private.sign(b"more")));
+        assert_eq!(verify_log(&public, &entries), Ok(()));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let (public, entries) = log(3);
+
+        let mut removed = entries.clone();
+        removed.remove(1);
+        assert_eq!(
+            verify_log(&public, &removed),
+            Err(LogError::WrongSequence(1))
+        );
+
+        let mut reordered = entries.clone();
+        reordered.swap(1, 2);
+        reordered[1].sequence = 1;
+        reordered[2].sequence = 2;
+        assert_eq!(
+            verify_log(&public, &reordered),
+            Err(LogError::BrokenChain(1))
+        );
+
+        let mut modified = entries.clone();
+        modified[2].client = [7; 32];
+        assert_eq!(
+            verify_log(&public, &modified),
+            Err(LogError::BadSignature(2))
+        );
+
+        let (other, _) = {
+            let private = PrivateKey { bytes: [2; 32] };
+            (log_public_key(&private.public_key()).unwrap(), ())
+        };
+        assert_eq!(verify_log(&other, &entries), Err(LogError::BadSignature(0)));
+    }
+}
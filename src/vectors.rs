@@ -0,0 +1,215 @@
+//! This module converts between our types and RFC 8032 test vectors.
+//!
+//! The vectors use the colon separated format of the `sign.input` corpus, which
+//! the test vectors in section 7.1 of RFC 8032 were taken from:
+//!
+//! secret || public : public : message : signature || message :
+//!
+//! with every field hex encoded. This lets the whole corpus be run against eddo.
+//!
+//! We can also generate vectors deterministically, which lets the output of
+//! different builds, on different architectures, be compared against each other.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{sha512, PrivateKey, PublicKey, Signature};
+
+/// Represents the reasons a test vector might fail to parse, or to check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VectorError {
+    /// The line didn't have the four fields we expect.
+    WrongFieldCount,
+    /// A field wasn't valid hex, or had the wrong length.
+    BadField,
+    /// The public key in the vector didn't match the secret key.
+    PublicKeyMismatch,
+    /// Signing the message didn't produce the signature in the vector.
+    SignatureMismatch,
+    /// The signature in the vector didn't verify.
+    VerificationFailed,
+}
+
+/// Represents a single RFC 8032 test vector.
+#[derive(Debug, Clone)]
+pub struct Rfc8032Vector {
+    pub secret: PrivateKey,
+    pub public: PublicKey,
+    pub message: Vec<u8>,
+    pub signature: Signature,
+}
+
+impl Rfc8032Vector {
+    /// Parse a vector from a single line of the `sign.input` format.
+    pub fn parse(line: &str) -> Result<Self, VectorError> {
+        let fields: Vec<&str> = line.trim().split(':').collect();
+        // The trailing colon leaves an empty field at the end
+        if fields.len() != 5 || !fields[4].is_empty() {
+            return Err(VectorError::WrongFieldCount);
+        }
+        let keys = hex::decode(fields[0]).map_err(|_| VectorError::BadField)?;
+        let message = hex::decode(fields[2]).map_err(|_| VectorError::BadField)?;
+        let signed = hex::decode(fields[3]).map_err(|_| VectorError::BadField)?;
+        if keys.len() != 64 || signed.len() != 64 + message.len() || signed[64..] != message {
+            return Err(VectorError::BadField);
+        }
+        let mut out = Rfc8032Vector {
+            secret: PrivateKey { bytes: [0; 32] },
+            public: PublicKey { bytes: [0; 32] },
+            message,
+            signature: Signature { bytes: [0; 64] },
+        };
+        hex::decode_to_slice(fields[1], &mut out.public.bytes)
+            .map_err(|_| VectorError::BadField)?;
+        if keys[32..] != out.public.bytes {
+            return Err(VectorError::BadField);
+        }
+        out.secret.bytes.copy_from_slice(&keys[..32]);
+        out.signature.bytes.copy_from_slice(&signed[..64]);
+        Ok(out)
+    }
+
+    /// Generate a vector deterministically from an index, by signing with eddo.
+    ///
+    /// The secret key and message are derived from the index using SHA-512, and
+    /// the message is `index % 256` bytes long.
+    pub fn generate(index: u32) -> Self {
+        let derive = |label: &[u8], block: u32| {
+            let mut hasher = sha512::Hasher::new();
+            hasher.update(label);
+            hasher.update(&index.to_le_bytes());
+            hasher.update(&block.to_le_bytes());
+            hasher.finalize()
+        };
+        let mut secret = PrivateKey { bytes: [0; 32] };
+        secret
+            .bytes
+            .copy_from_slice(&derive(b"eddo vector secret", 0)[..32]);
+        let len = (index % 256) as usize;
+        let mut message = Vec::with_capacity(len);
+        let mut block = 0;
+        while message.len() < len {
+            let bytes = derive(b"eddo vector message", block);
+            let take = (len - message.len()).min(bytes.len());
+            message.extend_from_slice(&bytes[..take]);
+            block += 1;
+        }
+        Rfc8032Vector {
+            public: secret.public_key(),
+            signature: secret.sign(&message),
+            secret,
+            message,
+        }
+    }
+
+    /// Check that eddo reproduces this vector.
+    ///
+    /// This derives the public key, signs the message, and verifies the signature.
+    pub fn check(&self) -> Result<(), VectorError> {
+        if self.secret.public_key() != self.public {
+            return Err(VectorError::PublicKeyMismatch);
+        }
+        if self.secret.sign(&self.message) != self.signature {
+            return Err(VectorError::SignatureMismatch);
+        }
+        if !self.public.verify(&self.message, self.signature) {
+            return Err(VectorError::VerificationFailed);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Rfc8032Vector {
+    /// Format this vector as a line of the `sign.input` format, without a newline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let public = hex::encode(self.public.bytes);
+        let message = hex::encode(&self.message);
+        write!(
+            f,
+            "{}{}:{}:{}:{}{}:",
+            hex::encode(self.secret.bytes),
+            public,
+            public,
+            message,
+            hex::encode(self.signature.bytes),
+            message
+        )
+    }
+}
+
+/// Represents the first vector in a corpus which failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorpusError {
+    /// The line of the vector, starting from 1.
+    pub line: usize,
+    pub error: VectorError,
+}
+
+/// Parse and check every vector in a corpus, with one vector per line.
+///
+/// Empty lines are skipped. This returns the number of vectors checked.
+pub fn run_corpus(corpus: &str) -> Result<usize, CorpusError> {
+    let mut count = 0;
+    for (i, line) in corpus.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        Rfc8032Vector::parse(line)
+            .and_then(|vector| vector.check())
+            .map_err(|error| CorpusError { line: i + 1, error })?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // These are the first two lines of sign.input, which are tests 1 and 2 of RFC 8032
+    const CORPUS: &str = "\
+9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a:d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a::e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b:
+4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c:3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c:72:92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c0072:
+";
+
+    #[test]
+    fn test_corpus_runs() {
+        assert_eq!(run_corpus(CORPUS), Ok(2));
+    }
+
+    #[test]
+    fn test_format_roundtrips() {
+        for line in CORPUS.lines() {
+            assert_eq!(Rfc8032Vector::parse(line).unwrap().to_string(), line);
+        }
+    }
+
+    #[test]
+    fn test_generated_vectors_check() {
+        for index in [0, 1, 63, 64, 65, 255, 256].iter() {
+            let vector = Rfc8032Vector::generate(*index);
+            assert_eq!(vector.message.len(), *index as usize % 256);
+            assert_eq!(vector.check(), Ok(()));
+            let line = vector.to_string();
+            assert_eq!(Rfc8032Vector::parse(&line).unwrap().to_string(), line);
+        }
+        assert_ne!(
+            Rfc8032Vector::generate(1).secret.bytes,
+            Rfc8032Vector::generate(257).secret.bytes
+        );
+    }
+
+    #[test]
+    fn test_bad_vectors_are_reported() {
+        let mut bad = CORPUS.to_string();
+        // Flip the last bit of the second signature's s, before the message
+        bad = bad.replace("bb0c0072:", "bb0c0172:");
+        let error = run_corpus(&bad).unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.error, VectorError::SignatureMismatch);
+        assert_eq!(
+            Rfc8032Vector::parse("00:11").unwrap_err(),
+            VectorError::WrongFieldCount
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Checks that signing and verifying never allocate.
+//!
+//! This installs a global allocator counting allocations, which is why it lives in
+//! its own test binary, with a single test, so that nothing runs concurrently.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use eddo::PrivateKey;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_during(op: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    op();
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+#[test]
+fn test_sign_and_verify_do_not_allocate() {
+    let private = PrivateKey { bytes: [2; 32] };
+    let message = [0xAB; 10_000];
+    let public = private.public_key();
+    let sig = private.sign(&message);
+    assert_eq!(
+        allocations_during(|| {
+            black_box(private.public_key());
+        }),
+        0
+    );
+    assert_eq!(
+        allocations_during(|| {
+            black_box(private.sign(&message));
+        }),
+        0
+    );
+    assert_eq!(
+        allocations_during(|| assert!(public.verify(&message, sig))),
+        0
+    );
+    let digest = eddo::prehash::digest(&message);
+    assert_eq!(
+        allocations_during(|| {
+            let sig = private.sign_prehashed(&digest, b"ctx").unwrap();
+            assert!(public.verify_prehashed(&digest, b"ctx", sig));
+        }),
+        0
+    );
+}
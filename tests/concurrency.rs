@@ -0,0 +1,63 @@
+//! Checks that keys and signatures can be shared between threads.
+//!
+//! Nothing in the library keeps state between calls, e.g. a cache, so sharing keys
+//! across threads only needs them to be `Send` and `Sync`. The stress test makes sure
+//! that verifying from many threads at once agrees with verifying from one thread.
+//! The matching benchmark is the `concurrent_verification` group in `benches/eddo.rs`.
+
+use std::thread;
+
+#[cfg(feature = "hazmat")]
+use eddo::hazmat::{EdwardsPoint, Scalar};
+use eddo::{PrivateKey, PublicKey, Signature};
+
+const THREADS: usize = 16;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_types_are_send_and_sync() {
+    assert_send_sync::<PrivateKey>();
+    assert_send_sync::<PublicKey>();
+    assert_send_sync::<Signature>();
+}
+
+#[cfg(feature = "hazmat")]
+#[test]
+fn test_hazmat_types_are_send_and_sync() {
+    assert_send_sync::<EdwardsPoint>();
+    assert_send_sync::<Scalar>();
+}
+
+#[test]
+fn test_concurrent_verification_agrees() {
+    let private = PrivateKey { bytes: [1; 32] };
+    let public = private.public_key();
+    let messages: Vec<[u8; 4]> = (0..64u32).map(|i| i.to_le_bytes()).collect();
+    let mut signatures: Vec<Signature> = messages.iter().map(|m| private.sign(m)).collect();
+    // Corrupt every third signature, so that both outcomes get exercised
+    for sig in signatures.iter_mut().step_by(3) {
+        sig.bytes[0] ^= 1;
+    }
+    let expected: Vec<bool> = messages
+        .iter()
+        .zip(&signatures)
+        .map(|(m, sig)| public.verify(m, *sig))
+        .collect();
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                s.spawn(|| {
+                    messages
+                        .iter()
+                        .zip(&signatures)
+                        .map(|(m, sig)| public.verify(m, *sig))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    });
+}
@@ -0,0 +1,231 @@
+//! A statistical check that operations on secrets take the same time whatever the secret is.
+//!
+//! This follows dudect, from "Dude, is my code constant time?": https://eprint.iacr.org/2016/1123
+//! Each operation is timed many times, with inputs from two classes, interleaved at random:
+//! a fixed secret, which is zero, and random secrets. Welch's t-test then compares the
+//! two distributions of timings, both in full, and cropped at a few percentiles, to discard
+//! the long tail of interruptions. A large |t| means the timings depend on the secret, like
+//! they would with an accidental early return, or a branch on a secret bit.
+//!
+//! Timings only make sense with optimizations, so run this with:
+//!
+//!     cargo test --release --features dudect --test constant_time
+//!
+//! Setting `EDDO_DUDECT_SAMPLES` changes how many timings are taken per operation.
+#![cfg(feature = "dudect")]
+
+use std::{
+    convert::{TryFrom, TryInto},
+    env,
+    hint::black_box,
+    time::Instant,
+};
+
+use eddo::{
+    hazmat::{CompressedEdwardsY, Scalar, Z25519},
+    PrivateKey,
+};
+use rand::{rngs::OsRng, RngCore};
+
+const DEFAULT_SAMPLES: usize = 20_000;
+
+/// dudect takes |t| above 4.5 as evidence of a leak, we leave more room for noisy machines.
+const THRESHOLD: f64 = 10.0;
+
+/// The percentiles at which timings are cropped, in addition to not cropping at all.
+const CROPS: [f64; 5] = [0.5, 0.75, 0.9, 0.95, 0.99];
+
+/// The running mean and variance of one class of timings, using Welford's method.
+#[derive(Default)]
+struct Moments {
+    count: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Moments {
+    fn push(&mut self, x: f64) {
+        self.count += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.count;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        self.m2 / (self.count - 1.0)
+    }
+}
+
+/// Welch's t statistic between the two classes, using only timings below a limit.
+fn welch(timings: &[(bool, f64)], limit: f64) -> f64 {
+    let mut fixed = Moments::default();
+    let mut random = Moments::default();
+    for &(is_fixed, time) in timings.iter().filter(|(_, time)| *time < limit) {
+        if is_fixed {
+            fixed.push(time);
+        } else {
+            random.push(time);
+        }
+    }
+    if fixed.count < 2.0 || random.count < 2.0 {
+        return 0.0;
+    }
+    let error = (fixed.variance() / fixed.count + random.variance() / random.count).sqrt();
+    if error == 0.0 {
+        return 0.0;
+    }
+    (fixed.mean - random.mean) / error
+}
+
+fn samples() -> usize {
+    env::var("EDDO_DUDECT_SAMPLES")
+        .ok()
+        .map(|x| x.parse().expect("EDDO_DUDECT_SAMPLES should be a number"))
+        .unwrap_or(DEFAULT_SAMPLES)
+}
+
+/// Measure the largest |t| between timings of an operation, on a fixed, and on random inputs.
+///
+/// Each timing runs the operation `repeat` times, so that fast operations take long
+/// enough to measure.
+fn leakage<T: Copy, R>(
+    fixed: T,
+    random: impl Fn([u8; 32]) -> T,
+    repeat: usize,
+    op: impl Fn(T) -> R,
+) -> f64 {
+    let samples = samples();
+    let mut bytes = vec![0; 33 * samples];
+    OsRng.fill_bytes(&mut bytes);
+    // Generate every input first, so that only the operation gets timed
+    let inputs: Vec<(bool, T)> = bytes
+        .chunks_exact(33)
+        .map(|chunk| {
+            let is_fixed = chunk[0] & 1 == 0;
+            let input = if is_fixed {
+                fixed
+            } else {
+                random(chunk[1..].try_into().unwrap())
+            };
+            (is_fixed, input)
+        })
+        .collect();
+    // Warm up caches, and let the clock speed settle
+    for &(_, input) in inputs.iter().take(samples / 10) {
+        black_box(op(black_box(input)));
+    }
+    let timings: Vec<(bool, f64)> = inputs
+        .iter()
+        .map(|&(is_fixed, input)| {
+            let start = Instant::now();
+            for _ in 0..repeat {
+                black_box(op(black_box(input)));
+            }
+            (is_fixed, start.elapsed().as_nanos() as f64)
+        })
+        .collect();
+    let mut sorted: Vec<f64> = timings.iter().map(|(_, time)| *time).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    CROPS
+        .iter()
+        .map(|p| sorted[(p * sorted.len() as f64) as usize])
+        .chain(Some(f64::INFINITY))
+        .map(|limit| welch(&timings, limit).abs())
+        .fold(0.0, f64::max)
+}
+
+fn scalar(bytes: [u8; 32]) -> Scalar {
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+fn field(mut bytes: [u8; 32]) -> Z25519 {
+    // Clearing the top bit leaves only a negligible chance of being ≥ P
+    bytes[31] &= 0x7F;
+    Z25519::try_from(&bytes[..]).unwrap_or_else(|_| panic!("random field element should be < P"))
+}
+
+fn measure() -> Vec<(&'static str, f64)> {
+    let base = CompressedEdwardsY {
+        bytes: [
+            0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+            0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+            0x66, 0x66, 0x66, 0x66,
+        ],
+    }
+    .decompress()
+    .unwrap();
+    let other_scalar = scalar([0x42; 32]);
+    let other_field = field([0x42; 32]);
+    let zero_scalar = Scalar::from(0);
+    let zero_field = Z25519::from(0);
+    let message = [0xAB; 64];
+    vec![
+        (
+            "scalar multiplication",
+            leakage(zero_scalar, scalar, 100, |x| x * other_scalar),
+        ),
+        (
+            "scalar reduction",
+            leakage([0; 32], |x| x, 100, Scalar::from_bytes_mod_order),
+        ),
+        (
+            "scalar inversion",
+            leakage(zero_scalar, scalar, 1, Scalar::invert),
+        ),
+        (
+            "field multiplication",
+            leakage(zero_field, field, 100, |x| x * other_field),
+        ),
+        (
+            "field inversion",
+            leakage(zero_field, field, 1, Z25519::inverse),
+        ),
+        (
+            "point multiplication",
+            leakage(zero_scalar, scalar, 1, |x| base * x),
+        ),
+        (
+            "signing",
+            leakage(
+                [0; 32],
+                |x| x,
+                1,
+                |bytes| PrivateKey { bytes }.sign(&message),
+            ),
+        ),
+    ]
+}
+
+#[test]
+fn test_constant_time() {
+    if cfg!(debug_assertions) {
+        eprintln!("skipping constant time check: timings need --release");
+        return;
+    }
+    let mut leaks = Vec::new();
+    for (name, t) in measure() {
+        eprintln!("{}: max |t| = {:.2}", name, t);
+        if t > THRESHOLD {
+            leaks.push(format!("{} has |t| = {:.2}", name, t));
+        }
+    }
+    assert!(leaks.is_empty(), "possible timing leaks: {:?}", leaks);
+}
+
+#[test]
+fn test_early_return_detected() {
+    if cfg!(debug_assertions) {
+        eprintln!("skipping constant time check: timings need --release");
+        return;
+    }
+    // An inversion which returns early on zero, which the test above should catch
+    let leaky_invert = |x: Scalar| {
+        if x.to_bytes() == [0; 32] {
+            x
+        } else {
+            x.invert()
+        }
+    };
+    let t = leakage(Scalar::from(0), scalar, 1, leaky_invert);
+    assert!(t > THRESHOLD, "early return went unnoticed, |t| = {:.2}", t);
+}
@@ -0,0 +1,129 @@
+//! A coarse check that signing and verification haven't gotten much slower.
+//!
+//! This compares the median latency of a few operations against the baselines
+//! recorded in `benches/baselines.txt`, failing if any operation got slower by more
+//! than a factor, 3 by default, or the value of `EDDO_PERF_FACTOR`.
+//!
+//! Timings only make sense with optimizations, so run this with:
+//!
+//!     cargo test --release --features perf-gate --test perf
+//!
+//! Setting `EDDO_PERF_RECORD=1` writes the measured latencies as the new baselines.
+#![cfg(feature = "perf-gate")]
+
+use std::{
+    env, fs,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use eddo::PrivateKey;
+
+const BASELINES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/baselines.txt");
+
+const DEFAULT_FACTOR: f64 = 3.0;
+
+const ITERATIONS: usize = 101;
+
+/// Measure the median duration of an operation.
+fn median(mut op: impl FnMut()) -> Duration {
+    let mut times: Vec<Duration> = (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            op();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[ITERATIONS / 2]
+}
+
+fn measure() -> Vec<(&'static str, Duration)> {
+    let private = PrivateKey { bytes: [7; 32] };
+    let public = private.public_key();
+    let message = vec![0xAB; 1024];
+    let signature = private.sign(&message);
+    vec![
+        (
+            "public_key",
+            median(|| {
+                black_box(private.public_key());
+            }),
+        ),
+        (
+            "sign",
+            median(|| {
+                black_box(private.sign(&message));
+            }),
+        ),
+        (
+            "verify",
+            median(|| assert!(public.verify(&message, signature))),
+        ),
+    ]
+}
+
+fn read_baselines() -> Vec<(String, u128)> {
+    let contents = fs::read_to_string(BASELINES).expect("failed to read baselines");
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next().unwrap().to_string();
+            let nanos = parts.next().and_then(|x| x.parse().ok());
+            (name, nanos.expect("malformed baseline"))
+        })
+        .collect()
+}
+
+fn write_baselines(measured: &[(&str, Duration)]) {
+    let mut contents = String::from(
+        "# Median latencies in nanoseconds, checked by tests/perf.rs, in release mode.\n\
+         # Regenerate these with EDDO_PERF_RECORD=1 after intentional changes.\n",
+    );
+    for (name, time) in measured {
+        contents.push_str(&format!("{} {}\n", name, time.as_nanos()));
+    }
+    fs::write(BASELINES, contents).expect("failed to write baselines");
+}
+
+#[test]
+fn test_no_performance_regression() {
+    if cfg!(debug_assertions) {
+        eprintln!("skipping performance check: timings need --release");
+        return;
+    }
+    let measured = measure();
+    if env::var("EDDO_PERF_RECORD").is_ok() {
+        write_baselines(&measured);
+        return;
+    }
+    let factor = env::var("EDDO_PERF_FACTOR")
+        .ok()
+        .map(|x| {
+            x.parse::<f64>()
+                .expect("EDDO_PERF_FACTOR should be a number")
+        })
+        .unwrap_or(DEFAULT_FACTOR);
+    let baselines = read_baselines();
+    let mut regressions = Vec::new();
+    for (name, time) in &measured {
+        let baseline = baselines
+            .iter()
+            .find(|(x, _)| x == name)
+            .unwrap_or_else(|| panic!("no baseline for {}", name))
+            .1;
+        let limit = (baseline as f64 * factor) as u128;
+        eprintln!("{}: {}ns, baseline {}ns", name, time.as_nanos(), baseline);
+        if time.as_nanos() > limit {
+            regressions.push(format!(
+                "{} took {}ns, over {}ns",
+                name,
+                time.as_nanos(),
+                limit
+            ));
+        }
+    }
+    assert!(regressions.is_empty(), "regressions: {:?}", regressions);
+}
@@ -0,0 +1,110 @@
+//! Runs Ed25519 test vectors in the format of Project Wycheproof against `PublicKey::verify`.
+//!
+//! Every `.json` file in `tests/wycheproof` is run. The upstream vectors, from
+//! https://github.com/C2SP/wycheproof, go in `eddsa_test.json`, as is, and CI fetches
+//! them, setting `EDDO_REQUIRE_WYCHEPROOF` so that the test fails without them. Other
+//! files are extra vectors, whose names say where they come from, like
+//! `handbuilt_openssl_eddsa_test.json`, which we built ourselves, and checked against
+//! OpenSSL. Groups for other curves, like edwards448, are skipped.
+//!
+//! A "valid" vector must verify, and an "invalid" one must not. An "acceptable" vector
+//! is one where implementations legitimately differ, so either result is fine.
+#![cfg(feature = "std")]
+
+use std::{fs, path::Path};
+
+use eddo::{PublicKey, Signature, PUBLIC_KEY_SIZE, SIGNATURE_SIZE};
+use serde_json::Value;
+
+/// The name of the file holding the upstream vectors.
+const UPSTREAM_FILE: &str = "eddsa_test.json";
+
+/// Verify a signature given as raw bytes, treating the wrong lengths as invalid.
+fn verify(public: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    if public.len() != PUBLIC_KEY_SIZE || signature.len() != SIGNATURE_SIZE {
+        return false;
+    }
+    let mut public_key = PublicKey { bytes: [0; 32] };
+    public_key.bytes.copy_from_slice(public);
+    let mut sig = Signature { bytes: [0; 64] };
+    sig.bytes.copy_from_slice(signature);
+    public_key.verify(message, sig)
+}
+
+fn hex_field(value: &Value, field: &str) -> Vec<u8> {
+    hex::decode(value[field].as_str().expect(field)).expect(field)
+}
+
+/// Run every Ed25519 vector in a file, returning how many ran, and a description of each failure.
+fn run_file(path: &Path) -> (usize, Vec<String>) {
+    let contents = fs::read_to_string(path).unwrap();
+    let file: Value = serde_json::from_str(&contents).unwrap();
+    assert_eq!(file["algorithm"], "EDDSA", "{}", path.display());
+    let mut total = 0;
+    let mut count = 0;
+    let mut failures = Vec::new();
+    for group in file["testGroups"].as_array().unwrap() {
+        let tests = group["tests"].as_array().unwrap();
+        total += tests.len();
+        // Older versions of the vectors call the public key "key"
+        let key = match &group["publicKey"] {
+            Value::Null => &group["key"],
+            key => key,
+        };
+        if key["curve"] != "edwards25519" {
+            continue;
+        }
+        let public = hex_field(key, "pk");
+        for test in tests {
+            count += 1;
+            let valid = verify(&public, &hex_field(test, "msg"), &hex_field(test, "sig"));
+            let expected = match test["result"].as_str().unwrap() {
+                "valid" => true,
+                "invalid" => false,
+                "acceptable" => continue,
+                other => panic!("unknown result: {}", other),
+            };
+            if valid != expected {
+                failures.push(format!(
+                    "{} #{} ({}): expected {}",
+                    path.file_name().unwrap().to_string_lossy(),
+                    test["tcId"],
+                    test["comment"].as_str().unwrap_or_default(),
+                    test["result"].as_str().unwrap()
+                ));
+            }
+        }
+    }
+    if let Some(expected) = file["numberOfTests"].as_u64() {
+        assert_eq!(total as u64, expected, "{}", path.display());
+    }
+    (count, failures)
+}
+
+#[test]
+fn test_wycheproof_vectors() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/wycheproof");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect();
+    paths.sort();
+    let upstream = paths.iter().any(|path| path.ends_with(UPSTREAM_FILE));
+    if std::env::var_os("EDDO_REQUIRE_WYCHEPROOF").is_some() {
+        assert!(upstream, "missing tests/wycheproof/{}", UPSTREAM_FILE);
+    } else if !upstream {
+        eprintln!(
+            "tests/wycheproof/{} is missing, only running extra vectors",
+            UPSTREAM_FILE
+        );
+    }
+    assert!(!paths.is_empty());
+    let mut failures = Vec::new();
+    for path in &paths {
+        let (count, mut file_failures) = run_file(path);
+        assert!(count > 0, "no Ed25519 vectors in {}", path.display());
+        failures.append(&mut file_failures);
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}